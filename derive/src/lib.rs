@@ -0,0 +1,108 @@
+//! `#[derive(FromRow)]` for `sqlite_wasm_reader`
+//!
+//! Use it through the `derive` feature of `sqlite_wasm_reader`, which
+//! re-exports the macro next to the `FromRow` trait:
+//!
+//! ```ignore
+//! use sqlite_wasm_reader::FromRow;
+//!
+//! #[derive(FromRow)]
+//! struct User {
+//!     id: i64,
+//!     #[sqlite(rename = "full_name")]
+//!     name: String,
+//!     email: Option<String>,
+//! }
+//! ```
+//!
+//! Named fields are read from the column of the same name, or the one given
+//! by `#[sqlite(rename = "...")]`. Fields of tuple structs are read by position.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    ext::IdentExt, parse_macro_input, parse_quote, Data, DeriveInput, Field, Fields,
+    GenericParam, Lifetime, LifetimeParam, LitStr,
+};
+
+/// Implement `FromRow` by reading each field from a column
+#[proc_macro_derive(FromRow, attributes(sqlite))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(name, "FromRow can only be derived for structs")),
+    };
+
+    let body = match fields {
+        Fields::Named(named) => {
+            let assignments = named
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().expect("named field");
+                    let column = column_name(field)?.unwrap_or_else(|| ident.unraw().to_string());
+                    Ok(quote! { #ident: row.get_as(#column)? })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! { #name { #(#assignments),* } }
+        }
+        Fields::Unnamed(unnamed) => {
+            for field in &unnamed.unnamed {
+                if column_name(field)?.is_some() {
+                    return Err(syn::Error::new_spanned(field, "tuple struct fields are read by position and cannot be renamed"));
+                }
+            }
+            let values = (0..unnamed.unnamed.len()).map(|i| quote! { row.get_as(#i)? });
+            quote! { #name(#(#values),*) }
+        }
+        Fields::Unit => quote! { #name },
+    };
+
+    // Borrowed fields such as `&'a str` borrow from the row, so reuse the
+    // struct's lifetime when it has one
+    let mut generics = input.generics.clone();
+    let row_lifetime = match generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'__row", Span::call_site());
+            generics.params.insert(0, GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())));
+            lifetime
+        }
+    };
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::sqlite_wasm_reader::FromValue<#row_lifetime>));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::sqlite_wasm_reader::FromRow<#row_lifetime> for #name #ty_generics #where_clause {
+            fn from_row(row: &#row_lifetime ::sqlite_wasm_reader::Row) -> ::sqlite_wasm_reader::Result<Self> {
+                ::core::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+/// The column named by `#[sqlite(rename = "...")]`, if any
+fn column_name(field: &Field) -> syn::Result<Option<String>> {
+    let mut column = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("sqlite")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                column = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported sqlite attribute, expected `rename`"))
+            }
+        })?;
+    }
+    Ok(column)
+}
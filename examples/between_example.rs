@@ -77,7 +77,7 @@ fn main() -> Result<(), Error> {
                     Value::Integer(int_val) => {
                         let query = SelectQuery::new(table_name)
                             .with_where(Expr::between(
-                                column_name.clone(),
+                                column_name,
                                 Value::Integer(int_val - 5),
                                 Value::Integer(int_val + 5)
                             ))
@@ -98,7 +98,7 @@ fn main() -> Result<(), Error> {
                     Value::Real(real_val) => {
                         let query = SelectQuery::new(table_name)
                             .with_where(Expr::between(
-                                column_name.clone(),
+                                column_name,
                                 Value::Real(real_val - 5.0),
                                 Value::Real(real_val + 5.0)
                             ))
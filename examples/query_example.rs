@@ -43,7 +43,7 @@ fn main() -> Result<(), Error> {
     // Fetch first column name for demonstration
     let first_row = db.execute_query(&SelectQuery::new(table_name).with_limit(1))?;
     if let Some(row) = first_row.first() {
-        if let Some(first_col) = row.keys().next() {
+        if let Some(first_col) = row.columns().first() {
             let builder_query = SelectQuery::new(table_name)
                 .select_columns(vec![first_col.clone()])
                 .with_where(Expr::is_not_null(first_col.clone()))
@@ -83,7 +83,7 @@ fn main() -> Result<(), Error> {
     let query = format!("SELECT * FROM {} LIMIT 1", table_name);
     if let Ok(rows) = SelectQuery::parse(&query).and_then(|q| db.execute_query(&q)) {
         if !rows.is_empty() {
-            let columns: Vec<String> = rows[0].keys().cloned().collect();
+            let columns: Vec<String> = rows.columns().to_vec();
             if columns.len() >= 2 {
                 let col1 = &columns[0];
                 let col2 = &columns[1];
@@ -134,7 +134,7 @@ fn main() -> Result<(), Error> {
     let query = format!("SELECT * FROM {} LIMIT 1", table_name);
     if let Ok(rows) = SelectQuery::parse(&query).and_then(|q| db.execute_query(&q)) {
         if !rows.is_empty() {
-            let columns: Vec<String> = rows[0].keys().cloned().collect();
+            let columns: Vec<String> = rows.columns().to_vec();
             if !columns.is_empty() {
                 let order_column = &columns[0];
                 let query = format!("SELECT * FROM {} ORDER BY {} DESC", table_name, order_column);
@@ -173,7 +173,7 @@ fn main() -> Result<(), Error> {
     let query = format!("SELECT * FROM {} LIMIT 10", table_name);
     if let Ok(rows) = SelectQuery::parse(&query).and_then(|q| db.execute_query(&q)) {
         if !rows.is_empty() {
-            let columns: Vec<String> = rows[0].keys().cloned().collect();
+            let columns: Vec<String> = rows.columns().to_vec();
             if columns.len() >= 2 {
                 let col1 = &columns[0];
                 let col2 = &columns[1];
@@ -77,11 +77,7 @@ fn display_table_data(rows: &[sqlite_wasm_reader::Row]) {
     
     // Print column headers from the first row
     if let Some(first_row) = rows.first() {
-        let columns: Vec<&String> = first_row.keys().collect();
-        println!("  Columns: {}", columns.iter()
-            .map(|c| c.as_str())
-            .collect::<Vec<_>>()
-            .join(", "));
+        println!("  Columns: {}", first_row.columns().join(", "));
     }
     
     // Print each row
@@ -0,0 +1,1001 @@
+//! Aggregate functions and GROUP BY evaluated over the rows of a query, and
+//! how a query reads the rows it groups
+
+use crate::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    database::{collect_expr_columns, collect_scalar_columns, parse_numeric_text, Database, IndexInfo, RowIter, RowSource, TableInfo},
+    eval::{self, value_text, Collation, ColumnType, ColumnTypes, Context, Operands, RowOperands},
+    format::TextEncoding,
+    logging::log_debug,
+    query::{Aggregate, AggregateFunction, ResultColumn, ScalarExpr, SelectQuery},
+    Error, Result, Row, RowIndex, Value,
+};
+use alloc::{borrow::Cow, sync::Arc};
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, format, string::{String, ToString}, vec, vec::Vec};
+
+/// How the rows of an aggregate query turn into its output rows, resolved
+/// against the columns of the rows it reads
+///
+/// The rows it produces hold the output columns followed by hidden columns
+/// that only HAVING and ORDER BY read, such as an aggregate that is not selected.
+pub(crate) struct AggregatePlan {
+    /// Names of the output columns, then of the hidden columns
+    columns: Arc<[String]>,
+    /// Names of the output columns alone
+    visible_columns: Arc<[String]>,
+    outputs: Vec<Output>,
+    /// Distinct aggregate calls the outputs read, each with one accumulator per group
+    calls: Vec<Call>,
+    /// GROUP BY keys and how they compare TEXT
+    keys: Vec<(Operand, Collation)>,
+    /// Whether the query has a GROUP BY clause, so that no rows make no groups
+    grouped: bool,
+    /// Whether the SELECT list reads bare columns at all
+    has_bare_columns: bool,
+    /// Names of the columns of the input rows
+    inputs: Arc<[String]>,
+    /// Types of the columns of the input rows
+    context: Context,
+    /// Types of the columns of the rows the plan produces, for HAVING and ORDER BY
+    group_context: Context,
+}
+
+/// How an output column is computed
+enum Output {
+    /// Bare column, read from the row the last MIN or MAX picked or else the first row
+    Column(usize),
+    Literal(Value),
+    /// Result of the call at this position in the plan's calls
+    Aggregate(usize),
+    /// Expression over aggregates and bare columns
+    Computed(ScalarExpr),
+}
+
+/// Argument of an aggregate function or a GROUP BY key
+enum Operand {
+    Column(usize),
+    Literal(Value),
+    /// Expression over the columns of the input row
+    Expr(ScalarExpr),
+}
+
+impl Operand {
+    fn value<'a>(&'a self, row: &'a Row, context: &Context) -> Result<Cow<'a, Value>> {
+        Ok(match self {
+            Operand::Column(index) => Cow::Borrowed(&row.values()[*index]),
+            Operand::Literal(value) => Cow::Borrowed(value),
+            Operand::Expr(expr) => Cow::Owned(eval::evaluate(expr, &RowOperands { row, context }, context.encoding)?.into_owned()),
+        })
+    }
+}
+
+/// Aggregate function call with its arguments resolved
+struct Call {
+    /// The call as the query wrote it, which expressions over the group refer to
+    aggregate: Aggregate,
+    function: AggregateFunction,
+    /// Arguments, empty for `COUNT(*)`
+    args: Vec<Operand>,
+    distinct: bool,
+    /// Collation MIN, MAX and DISTINCT compare TEXT with
+    collation: Collation,
+}
+
+/// Running state of the aggregates of one group
+pub(crate) struct Group {
+    /// One per call of the plan, in order
+    accumulators: Vec<Accumulator>,
+    /// Values of the row bare columns are read from
+    bare_row: Option<Vec<Value>>,
+}
+
+/// Running state of one aggregate function call
+struct Accumulator {
+    /// Argument values aggregated so far, for DISTINCT, as grouped by the
+    /// call's collation
+    seen: Option<BTreeSet<Value>>,
+    state: State,
+}
+
+enum State {
+    Count(i64),
+    Sum(Sum),
+    /// Smallest or largest value so far, for MIN and MAX
+    Best(Option<Value>),
+    Concat(Option<String>),
+}
+
+/// Running sum of SUM, AVG and TOTAL, kept as an integer until a value that
+/// is not one arrives, then as a compensated floating point sum like SQLite's
+#[derive(Default)]
+struct Sum {
+    /// Non-NULL values added
+    count: i64,
+    integer: i64,
+    /// Whether the sum is held in `real` and `error` instead of `integer`
+    approximate: bool,
+    /// Whether the integer sum overflowed, with no REAL value added since
+    overflow: bool,
+    real: f64,
+    error: f64,
+}
+
+impl Sum {
+    fn add(&mut self, value: &Value) {
+        self.count += 1;
+        let value = match value {
+            Value::Text(text) => parse_numeric_text(text).unwrap_or_else(|| Value::Real(real_prefix(text.as_bytes()))),
+            Value::Blob(bytes) => Value::Real(real_prefix(bytes)),
+            value => value.clone(),
+        };
+        match (value, self.approximate) {
+            (Value::Integer(v), false) => match self.integer.checked_add(v) {
+                Some(sum) => self.integer = sum,
+                None => {
+                    self.overflow = true;
+                    self.start_approximating();
+                    self.add_integer(v);
+                }
+            },
+            (Value::Integer(v), true) => self.add_integer(v),
+            (value, approximate) => {
+                if !approximate {
+                    self.start_approximating();
+                }
+                self.overflow = false;
+                self.add_real(value.as_real().unwrap_or(0.0));
+            }
+        }
+    }
+
+    fn start_approximating(&mut self) {
+        self.approximate = true;
+        let (big, small) = split_integer(self.integer);
+        self.real = big;
+        self.error = small;
+    }
+
+    /// Kahan-Babuska-Neumaier summation step
+    fn add_real(&mut self, v: f64) {
+        let sum = self.real + v;
+        if self.real.abs() > v.abs() {
+            self.error += (self.real - sum) + v;
+        } else {
+            self.error += (v - sum) + self.real;
+        }
+        self.real = sum;
+    }
+
+    fn add_integer(&mut self, v: i64) {
+        let (big, small) = split_integer(v);
+        self.add_real(big);
+        if small != 0.0 {
+            self.add_real(small);
+        }
+    }
+
+    /// The approximate sum, or the integer sum as a REAL
+    fn real_total(&self) -> f64 {
+        if !self.approximate {
+            self.integer as f64
+        } else if self.error.is_finite() {
+            self.real + self.error
+        } else {
+            self.real
+        }
+    }
+}
+
+/// Split an integer too large for a double's mantissa into two exactly representable parts
+fn split_integer(v: i64) -> (f64, f64) {
+    const LIMIT: i64 = 1 << 52;
+    if v <= -LIMIT || v >= LIMIT {
+        let small = v % 16384;
+        ((v - small) as f64, small as f64)
+    } else {
+        (v as f64, 0.0)
+    }
+}
+
+/// Value of the longest prefix of `bytes` that reads as a number, or 0.0,
+/// as SQLite converts text that is not a well-formed number to REAL
+fn real_prefix(bytes: &[u8]) -> f64 {
+    let start = bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let bytes = &bytes[start..];
+    let len = bytes
+        .iter()
+        .take_while(|&&b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E'))
+        .count();
+    // The prefix is ASCII, so it is valid UTF-8
+    let text = core::str::from_utf8(&bytes[..len]).unwrap_or_default();
+    (1..=text.len()).rev().find_map(|end| text[..end].parse::<f64>().ok()).unwrap_or(0.0)
+}
+
+impl Accumulator {
+    fn new(call: &Call) -> Self {
+        let state = match call.function {
+            AggregateFunction::Count => State::Count(0),
+            AggregateFunction::Sum | AggregateFunction::Avg | AggregateFunction::Total => State::Sum(Sum::default()),
+            AggregateFunction::Min | AggregateFunction::Max => State::Best(None),
+            AggregateFunction::GroupConcat => State::Concat(None),
+        };
+        Accumulator { seen: call.distinct.then(BTreeSet::new), state }
+    }
+
+    /// Add a row, returning whether MIN or MAX picked it, in which case the row
+    /// supplies the bare columns
+    fn step(&mut self, call: &Call, row: &Row, context: &Context) -> Result<bool> {
+        let value = match call.args.first() {
+            Some(arg) => arg.value(row, context)?,
+            // COUNT(*)
+            None => {
+                if let State::Count(count) = &mut self.state {
+                    *count += 1;
+                }
+                return Ok(false);
+            }
+        };
+        let value = value.as_ref();
+        if value.is_null() {
+            return Ok(matches!(self.state, State::Best(None)));
+        }
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(call.collation.group_value(value)) {
+                return Ok(false);
+            }
+        }
+
+        match &mut self.state {
+            State::Count(count) => *count += 1,
+            State::Sum(sum) => sum.add(value),
+            State::Best(best) => {
+                let better = match best {
+                    None => true,
+                    Some(best) => {
+                        let ordering = call.collation.compare(value, best, context.encoding);
+                        if call.function == AggregateFunction::Min { ordering.is_lt() } else { ordering.is_gt() }
+                    }
+                };
+                if !better {
+                    return Ok(false);
+                }
+                *best = Some(value.clone());
+                return Ok(true);
+            }
+            State::Concat(text) => {
+                let value = value_text(value);
+                match text {
+                    None => *text = Some(value),
+                    Some(text) => {
+                        match call.args.get(1) {
+                            Some(separator) => text.push_str(&value_text(&*separator.value(row, context)?)),
+                            None => text.push(','),
+                        }
+                        text.push_str(&value);
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn finish(self, function: AggregateFunction) -> Result<Value> {
+        Ok(match (function, self.state) {
+            (_, State::Count(count)) => Value::Integer(count),
+            (_, State::Sum(sum)) if sum.count == 0 && function != AggregateFunction::Total => Value::Null,
+            (AggregateFunction::Sum, State::Sum(sum)) => {
+                if sum.overflow {
+                    return Err(Error::IntegerOverflow);
+                }
+                if sum.approximate { Value::Real(sum.real_total()) } else { Value::Integer(sum.integer) }
+            }
+            (AggregateFunction::Avg, State::Sum(sum)) => Value::Real(sum.real_total() / sum.count as f64),
+            (_, State::Sum(sum)) => Value::Real(sum.real_total()),
+            (_, State::Best(best)) => best.unwrap_or(Value::Null),
+            (_, State::Concat(text)) => text.map(Value::Text).unwrap_or(Value::Null),
+        })
+    }
+}
+
+impl AggregatePlan {
+    /// Plan `query` over rows with the given columns, typed by `context`,
+    /// where `*` stands for the first `declared` of them
+    pub(crate) fn new(query: &SelectQuery, columns: &[String], declared: usize, context: &Context) -> Result<Self> {
+        let column_index = |name: &String| name.index_in(columns).ok_or_else(|| Error::ColumnNotFound(name.clone()));
+        let types = ColumnTypes { columns, context };
+        let operand = |expr: &ScalarExpr| -> Result<Operand> {
+            Ok(match expr {
+                ScalarExpr::Column(name) => Operand::Column(column_index(name)?),
+                ScalarExpr::Literal(value) => Operand::Literal(value.clone()),
+                expr if expr.contains_aggregate() => {
+                    return Err(Error::QueryError("Aggregate functions cannot be nested".to_string()));
+                }
+                expr => {
+                    check_columns(expr, columns)?;
+                    Operand::Expr(expr.clone())
+                }
+            })
+        };
+        let mut calls: Vec<Call> = Vec::new();
+        let mut call = |aggregate: &Aggregate| -> Result<usize> {
+            if let Some(index) = calls.iter().position(|call| call.aggregate == *aggregate) {
+                return Ok(index);
+            }
+            calls.push(Call {
+                aggregate: aggregate.clone(),
+                function: aggregate.function,
+                args: aggregate.args.iter().map(operand).collect::<Result<Vec<_>>>()?,
+                distinct: aggregate.distinct,
+                collation: aggregate.args.first().map_or(Collation::Binary, |arg| eval::collation_of(arg, &types)),
+            });
+            Ok(calls.len() - 1)
+        };
+        let mut output = |expr: &ScalarExpr| -> Result<Output> {
+            Ok(match expr {
+                ScalarExpr::Column(name) => Output::Column(column_index(name)?),
+                ScalarExpr::Literal(value) => Output::Literal(value.clone()),
+                ScalarExpr::Aggregate(aggregate) => Output::Aggregate(call(aggregate)?),
+                expr => {
+                    let mut aggregates = Vec::new();
+                    collect_aggregates(expr, &mut aggregates);
+                    for aggregate in aggregates {
+                        call(aggregate)?;
+                    }
+                    check_columns(expr, columns)?;
+                    Output::Computed(expr.clone())
+                }
+            })
+        };
+
+        // Grouped queries may select plain columns, and `*`
+        let selected: Vec<ResultColumn> = match (&query.result_columns, &query.columns) {
+            (Some(result_columns), _) => result_columns.clone(),
+            (None, Some(names)) => names
+                .iter()
+                .flat_map(|name| match name.as_str() {
+                    "*" => &columns[..declared],
+                    _ => core::slice::from_ref(name),
+                })
+                .map(|name| ResultColumn::new(name.clone(), ScalarExpr::Column(name.clone())))
+                .collect(),
+            (None, None) => columns[..declared]
+                .iter()
+                .map(|name| ResultColumn::new(name.clone(), ScalarExpr::Column(name.clone())))
+                .collect(),
+        };
+        let mut names: Vec<String> = selected.iter().map(|column| column.name.clone()).collect();
+        let mut outputs = selected.iter().map(|column| output(&column.expr)).collect::<Result<Vec<_>>>()?;
+        let visible_columns: Arc<[String]> = names.clone().into();
+
+        // A GROUP BY name that is not a table column refers to an output column
+        let mut keys = Vec::new();
+        for key in query.group_by.iter().flatten() {
+            let key = match key {
+                ScalarExpr::Column(name) if name.index_in(columns).is_none() => name
+                    .index_in(&visible_columns)
+                    .map(|index| &selected[index].expr)
+                    .ok_or_else(|| Error::ColumnNotFound(name.clone()))?,
+                key => key,
+            };
+            if key.contains_aggregate() {
+                return Err(Error::QueryError("Aggregate functions are not allowed in the GROUP BY clause".to_string()));
+            }
+            keys.push((operand(key)?, eval::collation_of(key, &types)));
+        }
+
+        // HAVING and ORDER BY may read table columns, aggregates and
+        // expressions over them that are not selected
+        let mut referenced: Vec<&str> = Vec::new();
+        if let Some(having) = &query.having {
+            collect_expr_columns(having, &mut referenced);
+        }
+        if let Some(order_by) = &query.order_by {
+            referenced.push(&order_by.column);
+        }
+        for name in referenced {
+            if name.index_in(&names).is_some() {
+                continue;
+            }
+            let hidden = match name.index_in(columns) {
+                Some(index) => Output::Column(index),
+                None => match SelectQuery::parse_expression_text(name) {
+                    Some(mut expr) => {
+                        query.expand_aliases(&mut expr, &|name| name.index_in(columns).is_some());
+                        output(&expr)?
+                    }
+                    None => return Err(Error::ColumnNotFound(name.to_string())),
+                },
+            };
+            names.push(name.to_string());
+            outputs.push(hidden);
+        }
+
+        // Output columns keep the type of the table column they hold, and
+        // expressions the collation they compare with
+        let group_types = outputs
+            .iter()
+            .map(|output| match output {
+                Output::Column(index) => context.column_type(*index),
+                Output::Computed(expr) => ColumnType { collation: eval::collation_of(expr, &types), ..ColumnType::UNTYPED },
+                _ => ColumnType::UNTYPED,
+            })
+            .collect();
+        Ok(AggregatePlan {
+            columns: names.into(),
+            visible_columns,
+            has_bare_columns: outputs.iter().any(|output| match output {
+                Output::Column(_) => true,
+                Output::Computed(expr) => reads_bare_columns(expr),
+                _ => false,
+            }),
+            outputs,
+            calls,
+            keys,
+            grouped: query.group_by.is_some(),
+            inputs: columns.into(),
+            context: context.clone(),
+            group_context: context.with_columns(group_types),
+        })
+    }
+
+    /// Names of the output columns followed by the hidden ones
+    pub(crate) fn columns(&self) -> &Arc<[String]> {
+        &self.columns
+    }
+
+    /// Names of the output columns, which lead every row the plan produces
+    pub(crate) fn visible_columns(&self) -> &Arc<[String]> {
+        &self.visible_columns
+    }
+
+    /// Types of the columns of the rows the plan produces
+    pub(crate) fn group_context(&self) -> &Context {
+        &self.group_context
+    }
+
+    /// The columns the GROUP BY keys read, unless a key is not a column
+    pub(crate) fn key_columns(&self) -> Option<Vec<usize>> {
+        self.keys
+            .iter()
+            .map(|(key, _)| match key {
+                Operand::Column(index) => Some(*index),
+                Operand::Literal(_) | Operand::Expr(_) => None,
+            })
+            .collect()
+    }
+
+    /// Every column the plan reads from its input rows
+    pub(crate) fn input_columns(&self) -> Vec<usize> {
+        let mut used = Vec::new();
+        let add_expr = |expr: &ScalarExpr, used: &mut Vec<usize>| {
+            let mut names = Vec::new();
+            collect_scalar_columns(expr, &mut names);
+            used.extend(names.iter().filter_map(|name| name.index_in(&self.inputs)));
+        };
+        let add = |operand: &Operand, used: &mut Vec<usize>| match operand {
+            Operand::Column(index) => used.push(*index),
+            Operand::Literal(_) => {}
+            Operand::Expr(expr) => add_expr(expr, used),
+        };
+        for output in &self.outputs {
+            match output {
+                Output::Column(index) => used.push(*index),
+                Output::Computed(expr) => add_expr(expr, &mut used),
+                Output::Literal(_) | Output::Aggregate(_) => {}
+            }
+        }
+        for call in &self.calls {
+            call.args.iter().for_each(|arg| add(arg, &mut used));
+        }
+        self.keys.iter().for_each(|(key, _)| add(key, &mut used));
+        used
+    }
+
+    /// The group a row belongs to
+    fn key(&self, row: &Row) -> Result<Vec<Value>> {
+        self.keys
+            .iter()
+            .map(|(key, collation)| Ok(collation.group_value(&*key.value(row, &self.context)?)))
+            .collect()
+    }
+
+    fn new_group(&self) -> Group {
+        Group { accumulators: self.calls.iter().map(Accumulator::new).collect(), bare_row: None }
+    }
+
+    /// Add a row to every aggregate of its group
+    fn step(&self, group: &mut Group, row: &Row) -> Result<()> {
+        // As in SQLite, bare columns come from the first row of the group, or
+        // from the rows the last MIN or MAX of the SELECT list picks
+        let mut load = group.bare_row.is_none();
+        for (accumulator, call) in group.accumulators.iter_mut().zip(&self.calls) {
+            let picked = accumulator.step(call, row, &self.context)?;
+            if matches!(call.function, AggregateFunction::Min | AggregateFunction::Max) {
+                load = picked;
+            }
+        }
+        if load && self.has_bare_columns {
+            group.bare_row = Some(row.values().to_vec());
+        }
+        Ok(())
+    }
+
+    /// The row of a group, with bare columns NULL if it read no row
+    fn finish(&self, group: Group) -> Result<Row> {
+        let results = group
+            .accumulators
+            .into_iter()
+            .zip(&self.calls)
+            .map(|(accumulator, call)| accumulator.finish(call.function))
+            .collect::<Result<Vec<_>>>()?;
+        let operands = GroupOperands { plan: self, bare_row: group.bare_row.as_deref(), results: &results };
+        let values = self
+            .outputs
+            .iter()
+            .map(|output| match output {
+                Output::Column(index) => Ok(group.bare_row.as_ref().map_or(Value::Null, |row| row[*index].clone())),
+                Output::Literal(value) => Ok(value.clone()),
+                Output::Aggregate(index) => Ok(results[*index].clone()),
+                Output::Computed(expr) => Ok(eval::evaluate(expr, &operands, self.context.encoding)?.into_owned()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Row::new(self.columns.clone(), values))
+    }
+}
+
+/// The values an expression over a group reads: bare columns from the row
+/// they come from, and the results of the group's aggregate calls
+struct GroupOperands<'a> {
+    plan: &'a AggregatePlan,
+    bare_row: Option<&'a [Value]>,
+    results: &'a [Value],
+}
+
+impl Operands for GroupOperands<'_> {
+    fn column(&self, name: &str) -> Option<(&Value, ColumnType)> {
+        static NULL: Value = Value::Null;
+        let index = name.index_in(&self.plan.inputs)?;
+        let value = self.bare_row.map_or(&NULL, |row| &row[index]);
+        Some((value, self.plan.context.column_type(index)))
+    }
+
+    fn context(&self) -> &Context {
+        &self.plan.context
+    }
+
+    fn aggregate(&self, aggregate: &Aggregate) -> Option<&Value> {
+        let index = self.plan.calls.iter().position(|call| call.aggregate == *aggregate)?;
+        self.results.get(index)
+    }
+}
+
+/// Fail unless every column `expr` reads is one of `columns`
+fn check_columns(expr: &ScalarExpr, columns: &[String]) -> Result<()> {
+    let mut names = Vec::new();
+    collect_scalar_columns(expr, &mut names);
+    match names.into_iter().find(|name| name.index_in(columns).is_none()) {
+        Some(name) => Err(Error::ColumnNotFound(name.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// The aggregate calls in an expression
+fn collect_aggregates<'a>(expr: &'a ScalarExpr, aggregates: &mut Vec<&'a Aggregate>) {
+    match expr {
+        ScalarExpr::Aggregate(aggregate) => aggregates.push(aggregate),
+        expr => expr.children().into_iter().for_each(|child| collect_aggregates(child, aggregates)),
+    }
+}
+
+/// Whether an expression reads columns outside of aggregate calls
+fn reads_bare_columns(expr: &ScalarExpr) -> bool {
+    match expr {
+        ScalarExpr::Column(_) => true,
+        ScalarExpr::Aggregate(_) => false,
+        expr => expr.children().into_iter().any(reads_bare_columns),
+    }
+}
+
+/// GROUP BY key hashed by value, where keys hold INTEGER in place of whole REAL values
+#[derive(PartialEq, Eq)]
+pub(crate) struct HashKey(Vec<Value>);
+
+impl HashKey {
+    /// Key of values compared as the BINARY collation compares them, such
+    /// as the columns of an equality join
+    pub(crate) fn binary(values: &[Value]) -> Self {
+        HashKey(values.iter().map(|value| Collation::Binary.group_value(value)).collect())
+    }
+}
+
+impl Hash for HashKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for value in &self.0 {
+            match value {
+                Value::Null => state.write_u8(0),
+                Value::Integer(v) => {
+                    state.write_u8(1);
+                    v.hash(state);
+                }
+                Value::Real(v) => {
+                    state.write_u8(2);
+                    v.to_bits().hash(state);
+                }
+                Value::Text(text) => {
+                    state.write_u8(3);
+                    text.hash(state);
+                }
+                Value::Blob(bytes) => {
+                    state.write_u8(4);
+                    bytes.hash(state);
+                }
+            }
+        }
+    }
+}
+
+/// GROUP BY key ordered as SQLite orders the keys of an index
+struct SortKey {
+    values: Vec<Value>,
+    encoding: TextEncoding,
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.encoding.compare_keys(&self.values, &other.values)
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for SortKey {}
+
+/// Aggregation into a hash table of groups, for rows in any order
+pub(crate) struct HashAggregator {
+    groups: HashMap<HashKey, Group>,
+    /// Most groups the table may hold
+    max_groups: Option<usize>,
+}
+
+impl HashAggregator {
+    pub(crate) fn new(max_groups: Option<usize>) -> Self {
+        HashAggregator { groups: HashMap::new(), max_groups }
+    }
+
+    /// Add a row to its group, returning false instead if that group would be
+    /// one more than the table may hold
+    pub(crate) fn push(&mut self, plan: &AggregatePlan, row: &Row) -> Result<bool> {
+        let key = HashKey(plan.key(row)?);
+        let group = match self.groups.get_mut(&key) {
+            Some(group) => group,
+            None => {
+                if self.max_groups.is_some_and(|max| self.groups.len() >= max) {
+                    return Ok(false);
+                }
+                self.groups.entry(key).or_insert_with(|| plan.new_group())
+            }
+        };
+        plan.step(group, row)?;
+        Ok(true)
+    }
+
+    /// The rows of the groups in key order; without GROUP BY there is always
+    /// exactly one, even over no rows
+    pub(crate) fn finish(&mut self, plan: &AggregatePlan) -> Result<Vec<Row>> {
+        let mut groups: Vec<(Vec<Value>, Group)> = self.groups.drain().map(|(key, group)| (key.0, group)).collect();
+        if groups.is_empty() && !plan.grouped {
+            groups.push((Vec::new(), plan.new_group()));
+        }
+        groups.sort_by(|(a, _), (b, _)| plan.context.encoding.compare_keys(a, b));
+        groups.into_iter().map(|(_, group)| plan.finish(group)).collect()
+    }
+}
+
+/// Aggregation of rows that arrive in GROUP BY key order, finishing each
+/// group as soon as the key changes
+#[derive(Default)]
+pub(crate) struct OrderedAggregator {
+    current: Option<(Vec<Value>, Group)>,
+}
+
+impl OrderedAggregator {
+    /// Add a row, returning the row of the previous group if this one starts a new group
+    pub(crate) fn push(&mut self, plan: &AggregatePlan, row: &Row) -> Result<Option<Row>> {
+        let key = plan.key(row)?;
+        if let Some((current, group)) = &mut self.current {
+            if *current == key {
+                plan.step(group, row)?;
+                return Ok(None);
+            }
+        }
+        let mut group = plan.new_group();
+        plan.step(&mut group, row)?;
+        self.current
+            .replace((key, group))
+            .map(|(_, previous)| plan.finish(previous))
+            .transpose()
+    }
+
+    /// The row of the last group, once every row was added
+    pub(crate) fn finish(&mut self, plan: &AggregatePlan) -> Result<Option<Row>> {
+        match self.current.take() {
+            Some((_, group)) => plan.finish(group).map(Some),
+            None if !plan.grouped => plan.finish(plan.new_group()).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Aggregation in repeated passes over the rows, for more groups than fit in
+/// memory at once
+///
+/// Each pass keeps the groups with the lowest keys above those returned by
+/// earlier passes, at most `max_groups` of them, and drops the highest one
+/// whenever another group needs room. Its groups are complete once the pass
+/// ends, and the next pass starts from the lowest key it dropped. Every pass
+/// reads all the rows again, so at most `max_passes` of them are made.
+pub(crate) struct PassAggregator {
+    groups: BTreeMap<SortKey, Group>,
+    max_groups: usize,
+    /// Passes ended so far, and how many may be made
+    passes: usize,
+    max_passes: usize,
+    /// Largest key returned by earlier passes
+    after: Option<Vec<Value>>,
+    /// Smallest key this pass dropped, above which it ignores rows
+    bound: Option<Vec<Value>>,
+    /// Whether the last pass dropped groups, so another one is needed
+    more: bool,
+}
+
+impl PassAggregator {
+    pub(crate) fn new(max_groups: usize, max_passes: usize) -> Self {
+        PassAggregator {
+            groups: BTreeMap::new(),
+            max_groups: max_groups.max(1),
+            passes: 0,
+            max_passes: max_passes.max(1),
+            after: None,
+            bound: None,
+            more: true,
+        }
+    }
+
+    /// Add a row to its group, if the group belongs to this pass
+    pub(crate) fn push(&mut self, plan: &AggregatePlan, row: &Row) -> Result<()> {
+        let values = plan.key(row)?;
+        let encoding = plan.context.encoding;
+        if self.after.as_ref().is_some_and(|after| encoding.compare_keys(&values, after).is_le())
+            || self.bound.as_ref().is_some_and(|bound| encoding.compare_keys(&values, bound).is_ge())
+        {
+            return Ok(());
+        }
+
+        let key = SortKey { values, encoding };
+        if !self.groups.contains_key(&key) && self.groups.len() >= self.max_groups {
+            // Make room by dropping the highest group, unless this one is higher still
+            match self.groups.last_key_value() {
+                Some((highest, _)) if key > *highest => {
+                    self.bound = Some(key.values);
+                    return Ok(());
+                }
+                _ => {
+                    if let Some((highest, _)) = self.groups.pop_last() {
+                        self.bound = Some(highest.values);
+                    }
+                }
+            }
+        }
+        let group = self.groups.entry(key).or_insert_with(|| plan.new_group());
+        plan.step(group, row)
+    }
+
+    /// End the pass, returning the rows of its groups in key order; fails if
+    /// groups remain once the last pass allowed ends
+    pub(crate) fn finish_pass(&mut self, plan: &AggregatePlan) -> Result<Vec<Row>> {
+        let groups = core::mem::take(&mut self.groups);
+        if let Some((highest, _)) = groups.last_key_value() {
+            self.after = Some(highest.values.clone());
+        }
+        self.more = self.bound.take().is_some();
+        self.passes += 1;
+        if self.more && self.passes >= self.max_passes {
+            return Err(Error::LimitExceeded { limit: "max_group_passes", max: self.max_passes });
+        }
+        groups.into_values().map(|group| plan.finish(group)).collect()
+    }
+
+    /// Whether groups remain for another pass
+    pub(crate) fn has_more(&self) -> bool {
+        self.more
+    }
+}
+
+/// Aggregation of the rows matching the WHERE clause into groups
+pub(crate) struct Grouping {
+    pub(crate) plan: AggregatePlan,
+    strategy: GroupStrategy,
+    /// Rows of finished groups, not yet checked against HAVING
+    pending: alloc::vec::IntoIter<Row>,
+    /// Whether every group is finished
+    finished: bool,
+}
+
+/// How a [`Grouping`] keeps its groups
+pub(crate) enum GroupStrategy {
+    /// Every group in a hash table, finished once all rows were read
+    Hash(HashAggregator),
+    /// One group at a time, for rows read in GROUP BY key order
+    Ordered(OrderedAggregator),
+    /// Repeated reads of the rows, for more groups than the hash table may hold
+    Passes(PassAggregator),
+}
+
+impl Grouping {
+    pub(crate) fn new(plan: AggregatePlan, strategy: GroupStrategy) -> Box<Self> {
+        Box::new(Grouping { plan, strategy, pending: Vec::new().into_iter(), finished: false })
+    }
+
+    /// Aggregate rows until a group that satisfies HAVING is finished
+    pub(crate) fn next_row(&mut self, rows: &mut RowIter<'_>) -> Result<Option<Row>> {
+        loop {
+            for row in self.pending.by_ref() {
+                match &rows.query.having {
+                    Some(having) if !rows.query.evaluate_expr_in(&row, having, self.plan.group_context())? => {}
+                    _ => return Ok(Some(row)),
+                }
+            }
+            if self.finished {
+                return Ok(None);
+            }
+
+            let Grouping { plan, strategy, pending, finished } = self;
+            match rows.next_match()? {
+                Some(row) => match strategy {
+                    GroupStrategy::Hash(aggregator) => {
+                        if !aggregator.push(plan, &row)? {
+                            // Start over, holding only as many groups at a time as the table could
+                            log_debug(&format!(
+                                "Query on {} has more than {} groups, aggregating in passes",
+                                rows.table_info.name, rows.db.max_hash_groups
+                            ));
+                            *strategy = GroupStrategy::Passes(PassAggregator::new(rows.db.max_hash_groups, rows.db.max_group_passes));
+                            rows.restart()?;
+                        }
+                    }
+                    GroupStrategy::Ordered(aggregator) => {
+                        if let Some(row) = aggregator.push(plan, &row)? {
+                            *pending = vec![row].into_iter();
+                        }
+                    }
+                    GroupStrategy::Passes(aggregator) => aggregator.push(plan, &row)?,
+                },
+                None => match strategy {
+                    GroupStrategy::Hash(aggregator) => {
+                        *pending = aggregator.finish(plan)?.into_iter();
+                        *finished = true;
+                    }
+                    GroupStrategy::Ordered(aggregator) => {
+                        *pending = aggregator.finish(plan)?.into_iter().collect::<Vec<_>>().into_iter();
+                        *finished = true;
+                    }
+                    GroupStrategy::Passes(aggregator) => {
+                        *pending = aggregator.finish_pass(plan)?.into_iter();
+                        if aggregator.has_more() {
+                            rows.restart()?;
+                        } else {
+                            *finished = true;
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Choose where an aggregate query reads its rows from and how it groups them
+///
+/// Rows that arrive in GROUP BY key order, from the table b-tree for the
+/// rowid or from an index on the GROUP BY columns, are grouped as they
+/// stream past; others go into a hash table.
+pub(crate) fn group_source(
+    db: &mut Database,
+    query: &SelectQuery,
+    plan: AggregatePlan,
+    table_info: &TableInfo,
+    rowid_columns: &[usize],
+) -> Result<(RowSource, Option<Box<Grouping>>)> {
+    if let (Some(result_columns), None, None, None) = (&query.result_columns, &query.where_expr, &query.group_by, &query.having) {
+        if let Some(values) = db.aggregate_from_btree(result_columns, table_info)? {
+            let row = Row::new(plan.columns().clone(), values);
+            return Ok((RowSource::Buffered(vec![row].into_iter()), None));
+        }
+    }
+
+    let rowid_column = table_info.rowid_alias.as_deref();
+    let mut keys = match (&query.group_by, plan.key_columns()) {
+        (Some(_), Some(keys)) => keys,
+        _ => Vec::new(),
+    };
+    keys.sort_unstable();
+    keys.dedup();
+
+    // Rowid tables are read in rowid order, whether scanned or sought
+    if !keys.is_empty() && !table_info.without_rowid && keys.iter().all(|key| rowid_columns.contains(key)) {
+        log_debug(&format!("Grouping rows of {} in rowid order", table_info.name));
+        let source = db.plan_row_source(query, table_info, rowid_column)?;
+        return Ok((source, Some(Grouping::new(plan, GroupStrategy::Ordered(OrderedAggregator::default())))));
+    }
+
+    if !keys.is_empty() && query.where_expr.is_none() && !table_info.without_rowid {
+        if let Some(index) = group_index(table_info, &keys) {
+            log_debug(&format!("Grouping rows of {} in the order of index {}", table_info.name, index.name));
+
+            // Read the rows from the index alone when it holds every column the query reads
+            let held: Vec<(usize, usize)> = index
+                .columns
+                .iter()
+                .enumerate()
+                .filter_map(|(position, part)| {
+                    let column = part.column_name()?.index_in(&table_info.columns)?;
+                    Some((column, position))
+                })
+                .collect();
+            let covers = plan
+                .input_columns()
+                .iter()
+                .all(|column| rowid_columns.contains(column) || held.iter().any(|(held, _)| held == column));
+
+            let root_page = db.read_page(index.root_page)?;
+            let source = RowSource::IndexOrder {
+                index: index.name.clone(),
+                cursor: db.cursor(root_page),
+                covering: covers.then_some(held),
+            };
+            return Ok((source, Some(Grouping::new(plan, GroupStrategy::Ordered(OrderedAggregator::default())))));
+        }
+    }
+
+    let source = db.plan_row_source(query, table_info, rowid_column)?;
+    let aggregator = HashAggregator::new(Some(db.max_hash_groups));
+    Ok((source, Some(Grouping::new(plan, GroupStrategy::Hash(aggregator)))))
+}
+
+/// Find a full index whose leading key parts are the GROUP BY columns
+/// `keys`, in any order and compared as the grouping compares them, so that
+/// the rows of each group are next to each other in it
+fn group_index<'a>(table_info: &'a TableInfo, keys: &[usize]) -> Option<&'a IndexInfo> {
+    table_info.indexes.iter().find(|index| {
+        if index.is_partial() || index.columns.len() < keys.len() {
+            return false;
+        }
+        let mut columns = Vec::new();
+        for part in &index.columns[..keys.len()] {
+            let column = match part.column_name().and_then(|name| name.index_in(&table_info.columns)) {
+                Some(column) => column,
+                None => return false,
+            };
+            let collation = table_info.column_info[column].collation.as_deref().unwrap_or("BINARY");
+            if !part.collation.as_deref().unwrap_or("BINARY").eq_ignore_ascii_case(collation) {
+                return false;
+            }
+            columns.push(column);
+        }
+        columns.sort_unstable();
+        columns == keys
+    })
+}
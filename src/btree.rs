@@ -1,9 +1,10 @@
 //! B-tree traversal functionality
 
-use crate::{Error, Result, page::Page, logging::log_warn, logging::log_debug, value::Value};
+use crate::{Error, Result, collections::HashSet, format::{FileHeader, PageType, TextEncoding}, page::Page, logging::log_debug, value::Value};
+use byteorder::{BigEndian, ByteOrder};
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
-use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec, format};
 
 /// Cell in a B-tree page
 #[derive(Debug)]
@@ -17,22 +18,19 @@ pub struct Cell {
     pub payload: Vec<u8>,
 }
 
-/// An entry in an index B-tree
-#[derive(Debug)]
-struct IndexCell {
-    /// The indexed value(s)
-    pub key: Vec<Value>,
-    /// The rowid of the corresponding row
-    pub rowid: i64,
-}
+/// Source of pages for b-tree traversal
+///
+/// Cursors read b-tree pages through this trait, and follow overflow chains
+/// for payloads that do not fit on a single page.
+pub trait PageReader {
+    /// Read and parse a b-tree page
+    fn read_page(&mut self, page_number: u32) -> Result<Page>;
 
-/// An entry in an interior index page
-#[derive(Debug)]
-struct InteriorIndexCell {
-    /// The page number of the left child.
-    pub left_child: u32,
-    /// The indexed value(s)
-    pub key: Vec<Value>,
+    /// Read the raw contents of an overflow page
+    fn read_overflow_page(&mut self, page_number: u32) -> Result<Vec<u8>>;
+
+    /// Database file header, used to compute the local/overflow payload split
+    fn header(&self) -> &FileHeader;
 }
 
 /// B-tree cursor for traversing pages
@@ -40,34 +38,59 @@ pub struct BTreeCursor {
     /// Stack of pages being traversed
     /// Each entry contains: (page, current_cell_index)
     page_stack: Vec<(Page, usize)>,
-    /// Track visited pages to prevent infinite loops
-    visited_pages: Vec<u32>,
-    /// Safety counter to prevent infinite loops
-    iteration_count: usize,
+    /// Pages entered by this traversal, to detect cycles in corrupt files
+    visited_pages: HashSet<u32>,
+    /// Most pages this traversal may visit, if limited
+    max_pages: Option<usize>,
 }
 
 impl BTreeCursor {
     /// Create a new cursor starting at the given page
     pub fn new(root_page: Page) -> Self {
-        let page_number = root_page.page_number;
+        let mut visited_pages = HashSet::new();
+        visited_pages.insert(root_page.page_number);
         BTreeCursor {
             page_stack: vec![(root_page, 0)],
-            visited_pages: vec![page_number],
-            iteration_count: 0,
+            visited_pages,
+            max_pages: None,
+        }
+    }
+    
+    /// Limit the number of pages this cursor may visit
+    ///
+    /// Traversals that would enter more pages fail with [`Error::LimitExceeded`].
+    pub fn with_max_pages(mut self, max_pages: Option<usize>) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+    
+    /// Record that the traversal enters `page_number`
+    ///
+    /// Every page of a well-formed b-tree is reached exactly once, so seeing a
+    /// page again means the file links pages into a cycle.
+    fn visit(&mut self, page_number: u32) -> Result<()> {
+        if !self.visited_pages.insert(page_number) {
+            return Err(Error::InvalidFormat(format!(
+                "B-tree cycle detected at page {}", page_number
+            )));
         }
+        if let Some(max) = self.max_pages {
+            if self.visited_pages.len() > max {
+                return Err(Error::LimitExceeded { limit: "max_pages_per_traversal", max });
+            }
+        }
+        Ok(())
     }
     
     /// Find a cell with the specified key (ROWID) in the B-tree
-    pub fn find_cell<F>(&mut self, key: i64, mut read_page: F) -> Result<Option<Cell>>
-    where
-        F: FnMut(u32) -> Result<Page>,
-    {
+    pub fn find_cell<R: PageReader + ?Sized>(&mut self, key: i64, reader: &mut R) -> Result<Option<Cell>> {
         if self.page_stack.is_empty() {
             return Ok(None);
         }
 
         let root_page_num = self.page_stack[0].0.page_number;
-        let mut current_page = read_page(root_page_num)?;
+        let mut current_page = reader.read_page(root_page_num)?;
+        let mut path = vec![root_page_num];
 
         loop {
             if current_page.page_type.is_leaf() {
@@ -81,12 +104,12 @@ impl BTreeCursor {
                     let mid = low + (high - low) / 2;
                     let cell_offset = cell_pointers[mid];
                     let cell_data = current_page.cell_content(cell_offset)?;
-                    let cell = parse_leaf_table_cell(&cell_data)?;
+                    let rowid = leaf_table_cell_rowid(cell_data)?;
 
-                    match cell.key.cmp(&key) {
-                        std::cmp::Ordering::Equal => return Ok(Some(cell)),
-                        std::cmp::Ordering::Less => low = mid + 1,
-                        std::cmp::Ordering::Greater => high = mid,
+                    match rowid.cmp(&key) {
+                        core::cmp::Ordering::Equal => return Ok(Some(parse_leaf_table_cell(cell_data, reader)?)),
+                        core::cmp::Ordering::Less => low = mid + 1,
+                        core::cmp::Ordering::Greater => high = mid,
                     }
                 }
 
@@ -102,7 +125,7 @@ impl BTreeCursor {
 
                 for &cell_offset in cell_pointers.iter() {
                     let cell_data = current_page.cell_content(cell_offset)?;
-                    let cell = parse_interior_table_cell(&cell_data)?;
+                    let cell = parse_interior_table_cell(cell_data)?;
                     if key <= cell.key {
                         next_page_num = cell.left_child.unwrap();
                         break;
@@ -110,22 +133,18 @@ impl BTreeCursor {
                 }
 
                 // Descend to the child page.
-                current_page = read_page(next_page_num)?;
+                descend_to(&mut path, next_page_num)?;
+                current_page = reader.read_page(next_page_num)?;
             }
         }
     }
     
     /// Move to the next cell in the B-tree using in-order traversal
-    pub fn next_cell<F>(&mut self, mut read_page: F) -> Result<Option<Cell>>
-    where
-        F: FnMut(u32) -> Result<Page>,
-    {
-        // Safety check: prevent infinite loops
-        self.iteration_count += 1;
-        if self.iteration_count > 100_000 {
-            return Err(Error::InvalidFormat("B-tree traversal exceeded safety limit".into()));
-        }
-        
+    ///
+    /// Cells that cannot be read, such as those with a broken overflow chain,
+    /// and child pages that cannot be loaded fail the traversal with an error
+    /// instead of being skipped.
+    pub fn next_cell<R: PageReader + ?Sized>(&mut self, reader: &mut R) -> Result<Option<Cell>> {
         loop {
             if self.page_stack.is_empty() {
                 return Ok(None);
@@ -143,47 +162,20 @@ impl BTreeCursor {
                 }
                 
                 // Get the current cell from leaf page
-                let is_first_page = page.page_number == 1;
-                let cell_pointers = match page.cell_pointers(is_first_page) {
-                    Ok(pointers) => pointers,
-                    Err(e) => {
-                        log_warn(&format!("Failed to get cell pointers for page {}: {}", page.page_number, e));
-                        // Skip this page and continue with parent
-                        self.page_stack.pop();
-                        continue;
-                    }
-                };
-                
+                let cell_pointers = page.cell_pointers(page.page_number == 1)?;
                 if *cell_index >= cell_pointers.len() {
                     // Pop this page and continue with parent
                     self.page_stack.pop();
                     continue;
                 }
                 
-                let cell_offset = cell_pointers[*cell_index];
-                let cell_data = match page.cell_content(cell_offset) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        log_warn(&format!("Failed to get cell content at offset {} on page {}: {}", cell_offset, page.page_number, e));
-                        // Skip this cell and move to next
-                        *cell_index += 1;
-                        continue;
-                    }
-                };
+                let cell_data = page.cell_content(cell_pointers[*cell_index])?;
                 
                 // Move to next cell in current page
                 *cell_index += 1;
                 
                 // Parse and return the leaf cell
-                let cell = match parse_leaf_table_cell(cell_data) {
-                    Ok(cell) => cell,
-                    Err(e) => {
-                        log_debug(&format!("Failed to parse leaf cell on page {}: {}", page.page_number, e));
-                        // Skip this cell and continue to next iteration
-                        continue;
-                    }
-                };
-                return Ok(Some(cell));
+                return parse_leaf_table_cell(cell_data, reader).map(Some);
             }
             
             // This is an interior page
@@ -191,17 +183,9 @@ impl BTreeCursor {
                 // We've processed all cells in this interior page
                 // Follow the right-most pointer if it exists
                 if let Some(right_ptr) = page.right_pointer {
-                    // Safety check: prevent revisiting the same page
-                    if self.visited_pages.contains(&right_ptr) {
-                        // We're about to revisit a page, this indicates a cycle
-                        // Pop this page and continue with parent instead
-                        self.page_stack.pop();
-                        continue;
-                    }
-                    
-                    let right_page = read_page(right_ptr)?;
-                    self.visited_pages.push(right_ptr);
-                    self.page_stack.push((right_page, 0));
+                    // Replace this page with its right-most child, it has nothing left to yield
+                    self.page_stack.pop();
+                    self.push_child(right_ptr, reader)?;
                     continue;
                 }
                 
@@ -211,169 +195,317 @@ impl BTreeCursor {
             }
             
             // Process the current cell in the interior page
-            let is_first_page = page.page_number == 1;
-            let cell_pointers = match page.cell_pointers(is_first_page) {
-                Ok(pointers) => pointers,
-                Err(e) => {
-                    log_warn(&format!("Failed to get cell pointers for interior page {}: {}", page.page_number, e));
-                    // Skip this page and continue with parent
-                    self.page_stack.pop();
-                    continue;
-                }
-            };
-            
+            let cell_pointers = page.cell_pointers(page.page_number == 1)?;
             if *cell_index >= cell_pointers.len() {
                 // Pop this page and continue with parent
                 self.page_stack.pop();
                 continue;
             }
             
-            let cell_offset = cell_pointers[*cell_index];
-            let cell_data = match page.cell_content(cell_offset) {
-                Ok(data) => data,
-                Err(e) => {
-                    log_warn(&format!("Failed to get cell content at offset {} on interior page {}: {}", cell_offset, page.page_number, e));
-                    // Skip this cell and move to next
-                    *cell_index += 1;
-                    continue;
-                }
-            };
-            
-            // Parse the interior cell
-            let cell = match parse_interior_table_cell(cell_data) {
-                Ok(cell) => cell,
-                Err(e) => {
-                    log_warn(&format!("Failed to parse interior cell on page {}: {}", page.page_number, e));
-                    // Skip this cell and move to next
-                    *cell_index += 1;
-                    continue;
-                }
-            };
+            let cell_data = page.cell_content(cell_pointers[*cell_index])?;
+            let cell = parse_interior_table_cell(cell_data)?;
 
             // Move to next cell in this interior page for the next iteration
             *cell_index += 1;
 
             // Descend to the left child of this interior cell
             if let Some(left_child) = cell.left_child {
-                // Prevent revisiting pages and potential infinite loops
-                if !self.visited_pages.contains(&left_child) {
-                    let child_page = match read_page(left_child) {
-                        Ok(p) => p,
-                        Err(e) => {
-                            log_warn(&format!("Failed to read child page {}: {}", left_child, e));
-                            continue;
-                        }
-                    };
-                    self.visited_pages.push(left_child);
-                    self.page_stack.push((child_page, 0));
-                }
+                self.push_child(left_child, reader)?;
             }
-
-            // Continue traversal with the newly pushed page (if any)
-            continue;
         }
     }
 
-    /// Find all rowids for a composite index key (exact match on all components).
-    pub fn find_rowids_by_key<F>(&mut self, key: &[&Value], mut read_page: F) -> Result<Vec<i64>>
-    where
-        F: FnMut(u32) -> Result<Page>,
-    {
-        log_debug(&format!("[BTreeCursor] Searching for composite key: {:?}", key));
-        if self.page_stack.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        let root_page_num = self.page_stack[0].0.page_number;
-        let mut current_page = read_page(root_page_num)?;
-
-        // Descend until we hit a leaf page in the index B-tree
+    /// Position the cursor on the first cell of a table b-tree whose rowid is
+    /// greater than or equal to `rowid`.
+    ///
+    /// Subsequent calls to [`BTreeCursor::next_cell`] walk forward from that
+    /// cell in rowid order.
+    pub fn seek_rowid<R: PageReader + ?Sized>(&mut self, rowid: i64, reader: &mut R) -> Result<()> {
+        let root_page_num = match self.page_stack.first() {
+            Some((page, _)) => page.page_number,
+            None => return Ok(()),
+        };
+        
+        self.page_stack.clear();
+        self.visited_pages.clear();
+        self.visit(root_page_num)?;
+        let mut current_page = reader.read_page(root_page_num)?;
+        
         loop {
-            if current_page.page_type.is_leaf() {
-                break;
-            }
-
             let cell_pointers = current_page.cell_pointers(current_page.page_number == 1)?;
-            // By default, follow the right-most child ( > all keys )
-            let mut next_page_num = current_page
-                .right_pointer
-                .ok_or_else(|| Error::InvalidFormat("Interior index page missing right pointer".into()))?;
-
-            // Iterate over cells to find the first key >= search key (lexicographically by component)
-            for &cell_offset in &cell_pointers {
-                let cell_data = current_page.cell_content(cell_offset)?;
-                let cell = parse_interior_index_cell(&cell_data)?;
-                let cell_key_refs: Vec<&Value> = cell.key.iter().collect();
-
-                let cmp_len = std::cmp::min(key.len(), cell_key_refs.len());
-                let ord = key[..cmp_len].cmp(&cell_key_refs[..cmp_len]);
-                if ord == std::cmp::Ordering::Less || ord == std::cmp::Ordering::Equal {
-                    next_page_num = cell.left_child;
-                    break;
+            
+            // Binary search for the first cell whose rowid is >= the search key
+            let mut low = 0;
+            let mut high = cell_pointers.len();
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let cell_data = current_page.cell_content(cell_pointers[mid])?;
+                let key = if current_page.page_type.is_leaf() {
+                    leaf_table_cell_rowid(cell_data)?
+                } else {
+                    parse_interior_table_cell(cell_data)?.key
+                };
+                
+                if key < rowid {
+                    low = mid + 1;
+                } else {
+                    high = mid;
                 }
             }
-
-            current_page = read_page(next_page_num)?;
+            
+            if current_page.page_type.is_leaf() {
+                self.page_stack.push((current_page, low));
+                return Ok(());
+            }
+            
+            // Interior keys are the largest rowid of their left subtree
+            let next_page_num = if low < cell_pointers.len() {
+                let cell_data = current_page.cell_content(cell_pointers[low])?;
+                let left_child = parse_interior_table_cell(cell_data)?.left_child;
+                // Resume with the next cell's subtree once this one is exhausted
+                self.page_stack.push((current_page, low + 1));
+                left_child.ok_or_else(|| Error::InvalidFormat("Interior cell missing left child".into()))?
+            } else {
+                // Nothing is left on this page after its right-most subtree
+                current_page.right_pointer.ok_or_else(|| {
+                    Error::InvalidFormat("Interior page missing right pointer".into())
+                })?
+            };
+            
+            self.visit(next_page_num)?;
+            current_page = reader.read_page(next_page_num)?;
         }
+    }
 
-        // We're now on a leaf page – gather all rowids whose key matches exactly
-        let mut rowids = Vec::new();
-        let cell_pointers = current_page.cell_pointers(current_page.page_number == 1)?;
-        
-        for &cell_offset in &cell_pointers {
-            let cell_data = current_page.cell_content(cell_offset)?;
-            let cell = parse_leaf_index_cell(&cell_data)?;
-
-            // Need at least as many components as the search key
-            if cell.key.len() < key.len() {
+    /// Move to the next entry of an index b-tree and return its record payload.
+    ///
+    /// Index b-trees (including WITHOUT ROWID tables) store entries on interior
+    /// pages as well as on leaves, so an in-order walk yields each interior cell
+    /// after its left subtree. On interior pages the per-page position counts in
+    /// half-steps: an even position `2 * i` descends into the left child of cell
+    /// `i`, and the following odd position yields the entry stored in cell `i`.
+    pub fn next_index_payload<R: PageReader + ?Sized>(&mut self, reader: &mut R) -> Result<Option<Vec<u8>>> {
+        loop {
+            let (page, position) = match self.page_stack.last_mut() {
+                Some(top) => top,
+                None => return Ok(None),
+            };
+            let cell_count = page.cell_count as usize;
+            
+            if page.page_type.is_leaf() {
+                if *position >= cell_count {
+                    self.page_stack.pop();
+                    continue;
+                }
+                
+                let cell_pointers = page.cell_pointers(page.page_number == 1)?;
+                let cell_data = page.cell_content(cell_pointers[*position])?;
+                *position += 1;
+                
+                let (payload_size, offset) = read_varint(cell_data)?;
+                return read_payload(&cell_data[offset..], payload_size, false, reader).map(Some);
+            }
+            
+            if *position > 2 * cell_count {
+                self.page_stack.pop();
+                continue;
+            }
+            
+            if *position == 2 * cell_count {
+                *position += 1;
+                let right_ptr = page.right_pointer.ok_or_else(|| {
+                    Error::InvalidFormat("Interior index page missing right pointer".into())
+                })?;
+                self.push_child(right_ptr, reader)?;
                 continue;
             }
             
-            // Exact component-wise equality for the prefix length of key
-            let matches = cell
-                .key
-                .iter()
-                .zip(key.iter())
-                .all(|(a, b)| a == *b);
+            let cell_pointers = page.cell_pointers(page.page_number == 1)?;
+            let cell_data = page.cell_content(cell_pointers[*position / 2])?;
+            let descend = *position % 2 == 0;
+            *position += 1;
+            
+            if cell_data.len() < 4 {
+                return Err(Error::InvalidFormat("Interior index cell data too short".into()));
+            }
             
-            log_debug(&format!(
-                "[BTreeCursor] Checking cell: key={:?} vs search={:?}, matches={}",
-                cell.key, key, matches
-            ));
+            if descend {
+                let left_child = BigEndian::read_u32(cell_data);
+                self.push_child(left_child, reader)?;
+                continue;
+            }
             
-            if matches {
-                log_debug(&format!("[BTreeCursor] MATCH FOUND! Adding rowid {}", cell.rowid));
-                rowids.push(cell.rowid);
+            let (payload_size, offset) = read_varint(&cell_data[4..])?;
+            return read_payload(&cell_data[4 + offset..], payload_size, false, reader).map(Some);
+        }
+    }
+    
+    /// Position the cursor on the first index entry whose leading columns are
+    /// greater than or equal to `key`.
+    ///
+    /// Subsequent calls to [`BTreeCursor::next_index_payload`] walk forward from
+    /// that entry in key order.
+    pub fn seek_index<R: PageReader + ?Sized>(&mut self, key: &[Value], reader: &mut R) -> Result<()> {
+        let root_page_num = match self.page_stack.first() {
+            Some((page, _)) => page.page_number,
+            None => return Ok(()),
+        };
+        
+        let encoding = reader.header().encoding();
+        self.page_stack.clear();
+        self.visited_pages.clear();
+        self.visit(root_page_num)?;
+        let mut current_page = reader.read_page(root_page_num)?;
+        
+        loop {
+            let is_leaf = current_page.page_type.is_leaf();
+            let cell_pointers = current_page.cell_pointers(current_page.page_number == 1)?;
+            
+            // Binary search for the first cell whose key is >= the search key
+            let mut low = 0;
+            let mut high = cell_pointers.len();
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let cell_data = current_page.cell_content(cell_pointers[mid])?;
+                let values = parse_index_cell_record(cell_data, is_leaf, reader)?;
+                
+                if compare_key_prefix(&values, key, encoding) == core::cmp::Ordering::Less {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            
+            if is_leaf {
+                self.page_stack.push((current_page, low));
+                return Ok(());
+            }
+            
+            // Resume at the entry stored in cell `low` once its left subtree is exhausted
+            let next_page_num = if low < cell_pointers.len() {
+                let cell_data = current_page.cell_content(cell_pointers[low])?;
+                if cell_data.len() < 4 {
+                    return Err(Error::InvalidFormat("Interior index cell data too short".into()));
+                }
+                BigEndian::read_u32(cell_data)
             } else {
-                // Check if we've passed the search key alphabetically
-                // Since the page is sorted, if the current cell key is greater than the search key,
-                // no further cells will match
-                if let (Some(search_first), Some(cell_first)) = (key.first(), cell.key.first()) {
-                    match cell_first.cmp(search_first) {
-                        std::cmp::Ordering::Greater => {
-                            // We've passed the search key, stop searching
-                            log_debug(&format!(
-                                "[BTreeCursor] Cell key {:?} > search key {:?}, stopping early scan on page {}",
-                                cell.key, key, current_page.page_number
-                            ));
-                            break;
-                        },
-                        std::cmp::Ordering::Less => {
-                            // Cell key is less than search key, continue searching
-                            log_debug(&format!(
-                                "[BTreeCursor] Cell key {:?} < search key {:?}, continuing search",
-                                cell.key, key
-                            ));
-                        },
-                        std::cmp::Ordering::Equal => {
-                            // First component matches but full key doesn't - could be composite key mismatch
-                            log_debug(&format!(
-                                "[BTreeCursor] Partial composite key mismatch – cell key {:?} vs search {:?}",
-                                cell.key, key
-                            ));
-                        }
-                    }
+                current_page.right_pointer.ok_or_else(|| {
+                    Error::InvalidFormat("Interior index page missing right pointer".into())
+                })?
+            };
+            
+            self.page_stack.push((current_page, 2 * low + 1));
+            self.visit(next_page_num)?;
+            current_page = reader.read_page(next_page_num)?;
+        }
+    }
+    
+    /// Position the cursor on the last entry of the b-tree, so the next call to
+    /// [`BTreeCursor::next_cell`] or [`BTreeCursor::next_index_payload`]
+    /// returns the largest rowid or key.
+    pub fn seek_last<R: PageReader + ?Sized>(&mut self, reader: &mut R) -> Result<()> {
+        let root_page_num = match self.page_stack.first() {
+            Some((page, _)) => page.page_number,
+            None => return Ok(()),
+        };
+        
+        self.page_stack.clear();
+        self.visited_pages.clear();
+        self.visit(root_page_num)?;
+        let mut current_page = reader.read_page(root_page_num)?;
+        
+        // The right-most subtree holds the largest keys of every interior page,
+        // and nothing is left to yield on an interior page after it
+        while !current_page.page_type.is_leaf() {
+            let next_page_num = current_page.right_pointer.ok_or_else(|| {
+                Error::InvalidFormat("Interior page missing right pointer".into())
+            })?;
+            self.visit(next_page_num)?;
+            current_page = reader.read_page(next_page_num)?;
+        }
+        
+        let last = (current_page.cell_count as usize).saturating_sub(1);
+        self.page_stack.push((current_page, last));
+        Ok(())
+    }
+    
+    /// Count the entries of the b-tree from reading page headers and child
+    /// pointers only, without parsing any cell payloads.
+    ///
+    /// Table b-trees hold their rows on the leaves, while index b-trees
+    /// (including WITHOUT ROWID tables) also hold an entry in every interior
+    /// cell. The cursor is exhausted afterwards.
+    pub fn count_entries<R: PageReader + ?Sized>(&mut self, reader: &mut R) -> Result<usize> {
+        let mut pending = match self.page_stack.first() {
+            Some((page, _)) => vec![page.page_number],
+            None => return Ok(0),
+        };
+        self.page_stack.clear();
+        
+        let mut count = 0;
+        while let Some(page_number) = pending.pop() {
+            let page = reader.read_page(page_number)?;
+            if page.page_type.is_leaf() {
+                count += page.cell_count as usize;
+                continue;
+            }
+            if page.page_type == PageType::InteriorIndex {
+                count += page.cell_count as usize;
+            }
+            
+            let mut children = Vec::with_capacity(page.cell_count as usize + 1);
+            for pointer in page.cell_pointers(page.page_number == 1)? {
+                let cell_data = page.cell_content(pointer)?;
+                if cell_data.len() < 4 {
+                    return Err(Error::InvalidFormat("Interior cell data too short".into()));
                 }
+                children.push(BigEndian::read_u32(cell_data));
+            }
+            children.push(page.right_pointer.ok_or_else(|| {
+                Error::InvalidFormat("Interior page missing right pointer".into())
+            })?);
+            for child in children {
+                self.visit(child)?;
+                pending.push(child);
+            }
+        }
+        Ok(count)
+    }
+    
+    /// Push a child page onto the traversal stack, refusing to revisit pages
+    fn push_child<R: PageReader + ?Sized>(&mut self, page_number: u32, reader: &mut R) -> Result<()> {
+        self.visit(page_number)?;
+        let page = reader.read_page(page_number)?;
+        self.page_stack.push((page, 0));
+        Ok(())
+    }
+
+    /// Find all rowids for a composite index key (exact match on all components).
+    ///
+    /// Seeks to the first entry whose leading columns equal `key` and walks
+    /// forward in key order, through interior cells and across sibling leaves,
+    /// until the key changes, so every duplicate is found.
+    pub fn find_rowids_by_key<R: PageReader + ?Sized>(&mut self, key: &[&Value], reader: &mut R) -> Result<Vec<i64>> {
+        log_debug(&format!("[BTreeCursor] Searching for composite key: {:?}", key));
+        if self.page_stack.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let encoding = reader.header().encoding();
+        let key: Vec<Value> = key.iter().map(|value| (*value).clone()).collect();
+        self.seek_index(&key, reader)?;
+
+        let mut rowids = Vec::new();
+        while let Some(payload) = self.next_index_payload(reader)? {
+            let mut values = crate::record::parse_record_encoded(&payload, encoding)?;
+            if compare_key_prefix(&values, &key, encoding) != core::cmp::Ordering::Equal {
+                break;
+            }
+
+            // In SQLite index cells, the ROWID is the last value in the payload
+            match values.pop() {
+                Some(Value::Integer(rowid)) if values.len() >= key.len() => rowids.push(rowid),
+                _ => return Err(Error::InvalidFormat("Index cell ROWID is not an integer".into())),
             }
         }
 
@@ -385,22 +517,31 @@ impl BTreeCursor {
 
 }
 
+/// Extend a root-to-leaf descent, failing if `page_number` is already on the path
+fn descend_to(path: &mut Vec<u32>, page_number: u32) -> Result<()> {
+    if path.contains(&page_number) {
+        return Err(Error::InvalidFormat(format!(
+            "B-tree cycle detected at page {}", page_number
+        )));
+    }
+    path.push(page_number);
+    Ok(())
+}
+
+/// Read the rowid of a leaf table cell without assembling its payload
+fn leaf_table_cell_rowid(data: &[u8]) -> Result<i64> {
+    let (_, offset) = read_varint(data)?;
+    let (rowid, _) = read_varint(&data[offset..])?;
+    Ok(rowid)
+}
+
 /// Parse a leaf table cell
-fn parse_leaf_table_cell(data: &[u8]) -> Result<Cell> {
+fn parse_leaf_table_cell<R: PageReader + ?Sized>(data: &[u8], reader: &mut R) -> Result<Cell> {
     let (payload_size, offset) = read_varint(data)?;
     let (rowid, offset2) = read_varint(&data[offset..])?;
     let offset = offset + offset2;
     
-    // Add bounds checking to prevent panic
-    let payload_end = offset + payload_size as usize;
-    if payload_end > data.len() {
-        return Err(Error::InvalidFormat(format!(
-            "Payload size {} exceeds available data (offset: {}, data_len: {})",
-            payload_size, offset, data.len()
-        )));
-    }
-    
-    let payload = data[offset..payload_end].to_vec();
+    let payload = read_payload(&data[offset..], payload_size, true, reader)?;
     
     Ok(Cell {
         left_child: None,
@@ -409,6 +550,60 @@ fn parse_leaf_table_cell(data: &[u8]) -> Result<Cell> {
     })
 }
 
+/// Assemble a cell payload, following the overflow chain when the payload
+/// does not fit on the b-tree page.
+///
+/// `data` starts at the first payload byte of the cell.
+fn read_payload<R: PageReader + ?Sized>(data: &[u8], payload_size: i64, table_leaf: bool, reader: &mut R) -> Result<Vec<u8>> {
+    if payload_size < 0 {
+        return Err(Error::InvalidFormat(format!("Negative payload size {}", payload_size)));
+    }
+    let payload_size = payload_size as usize;
+    let local_size = reader.header().local_payload_size(payload_size, table_leaf);
+    
+    // Add bounds checking to prevent panic
+    if local_size > data.len() {
+        return Err(Error::InvalidFormat(format!(
+            "Payload size {} exceeds available data (local: {}, data_len: {})",
+            payload_size, local_size, data.len()
+        )));
+    }
+    
+    if local_size == payload_size {
+        return Ok(data[..local_size].to_vec());
+    }
+    
+    // The local part is followed by the page number of the first overflow page
+    if local_size + 4 > data.len() {
+        return Err(Error::InvalidFormat("Cell is missing its overflow page pointer".into()));
+    }
+    
+    let mut payload = Vec::with_capacity(payload_size);
+    payload.extend_from_slice(&data[..local_size]);
+    let mut next_page = BigEndian::read_u32(&data[local_size..]);
+    let bytes_per_page = reader.header().usable_size() - 4;
+    
+    while payload.len() < payload_size {
+        if next_page == 0 {
+            return Err(Error::InvalidFormat(format!(
+                "Overflow chain ended after {} of {} payload bytes",
+                payload.len(), payload_size
+            )));
+        }
+        
+        let page = reader.read_overflow_page(next_page)?;
+        if page.len() < 4 + bytes_per_page {
+            return Err(Error::InvalidFormat(format!("Overflow page {} too small", next_page)));
+        }
+        
+        let take = (payload_size - payload.len()).min(bytes_per_page);
+        payload.extend_from_slice(&page[4..4 + take]);
+        next_page = BigEndian::read_u32(&page[..4]);
+    }
+    
+    Ok(payload)
+}
+
 /// Parse an interior table cell
 fn parse_interior_table_cell(data: &[u8]) -> Result<Cell> {
     // Check if we have enough data for the left child pointer
@@ -429,57 +624,28 @@ fn parse_interior_table_cell(data: &[u8]) -> Result<Cell> {
     })
 }
 
-/// Parse a leaf index cell
-fn parse_leaf_index_cell(data: &[u8]) -> Result<IndexCell> {
-    let (payload_size, offset) = read_varint(data)?;
-    let payload = &data[offset..offset + payload_size as usize];
-    let (header_size, mut header_offset) = read_varint(payload)?;
-    let mut values = Vec::new();
-    let mut content_offset = header_size as usize;
-
-    while header_offset < header_size as usize {
-        let (serial_type, bytes_read) = read_varint(&payload[header_offset..])?;
-        header_offset += bytes_read;
-        let (value, value_bytes) = crate::record::parse_value(serial_type, &payload[content_offset..])?;
-        values.push(value);
-        content_offset += value_bytes;
-    }
-
-    // In SQLite index leaf cells, the ROWID is the last value in the payload
-    // Extract it from the values array
-    if values.is_empty() {
-        return Err(Error::InvalidFormat("Index cell has no values".into()));
-    }
-    
-    let rowid = match values.pop().unwrap() {
-        Value::Integer(id) => id,
-        _ => return Err(Error::InvalidFormat("Index cell ROWID is not an integer".into())),
+/// Decode the full record stored in an index cell (leaf or interior)
+fn parse_index_cell_record<R: PageReader + ?Sized>(data: &[u8], is_leaf: bool, reader: &mut R) -> Result<Vec<Value>> {
+    let data = if is_leaf {
+        data
+    } else {
+        if data.len() < 4 {
+            return Err(Error::InvalidFormat("Interior index cell data too short".into()));
+        }
+        &data[4..]
     };
-
-    Ok(IndexCell { key: values, rowid })
+    
+    let (payload_size, offset) = read_varint(data)?;
+    let payload = read_payload(&data[offset..], payload_size, false, reader)?;
+    crate::record::parse_record_encoded(&payload, reader.header().encoding())
 }
 
-/// Parse an interior index cell
-fn parse_interior_index_cell(data: &[u8]) -> Result<InteriorIndexCell> {
-    let left_child = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
-    let (payload_size, offset) = read_varint(&data[4..])?;
-    let payload = &data[4 + offset..4 + offset + payload_size as usize];
-    let (header_size, mut header_offset) = read_varint(payload)?;
-    let mut values = Vec::new();
-    let mut content_offset = header_size as usize;
-
-    while header_offset < header_size as usize {
-        let (serial_type, bytes_read) = read_varint(&payload[header_offset..])?;
-        header_offset += bytes_read;
-        let (value, value_bytes) = crate::record::parse_value(serial_type, &payload[content_offset..])?;
-        values.push(value);
-        content_offset += value_bytes;
+/// Compare the leading columns of an index record against a search key
+fn compare_key_prefix(values: &[Value], key: &[Value], encoding: TextEncoding) -> core::cmp::Ordering {
+    match encoding.compare_keys(values, key) {
+        core::cmp::Ordering::Equal if values.len() < key.len() => core::cmp::Ordering::Less,
+        other => other,
     }
-
-    Ok(InteriorIndexCell {
-        left_child,
-        key: values,
-    })
 }
 
 /// Read a variable-length integer
@@ -1,52 +1,158 @@
 //! Main database interface
 
-use std::collections::HashMap;
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom};
+#[cfg(feature = "std")]
+use std::io::{BufReader, Read, Seek};
+#[cfg(feature = "std")]
 use std::path::Path;
 use lru::LruCache;
-use std::num::NonZeroUsize;
+use core::num::NonZeroUsize;
 use byteorder::{BigEndian, ByteOrder};
 
 use crate::{
-    btree::BTreeCursor,
+    collections::{BTreeSet, HashMap},
+    btree::{BTreeCursor, PageReader},
+    decode::FromRow,
     error::{Error, Result},
-    format::{FileHeader, SQLITE_HEADER_MAGIC},
+    format::{FileHeader, TextEncoding, SQLITE_HEADER_MAGIC},
     logging::{log_debug, log_error, log_warn},
+    options::{OpenOptions, ReaderLimits},
     page::Page,
-    query::{ComparisonOperator, Expr, SelectQuery},
-    record::parse_record,
+    aggregate::{self, AggregatePlan, GroupStrategy, Grouping, HashAggregator},
+    eval::{self, ColumnType, Context, RowOperands},
+    functions::Random,
+    join::{self, JoinPlan, JoinSource},
+    query::{AggregateFunction, ComparisonOperator, Expr, ResultColumn, ScalarExpr, SelectQuery},
+    record::parse_record_encoded,
+    row::{ResultSet, RowIndex},
+    source::{BytesSource, PageSource},
     value::Value,
+    wal::{Wal, WalCommit},
 };
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
-use alloc::{vec::Vec, string::String, format};
+use alloc::sync::Arc;
 
-/// A row of data from a table
-pub type Row = HashMap<String, Value>;
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec, vec::Vec, string::{String, ToString}, format};
+
+pub use crate::row::Row;
 
 /// SQLite database reader
 pub struct Database {
-    file: BufReader<File>,
+    /// Main database file contents
+    source: Box<dyn PageSource>,
     header: FileHeader,
     page_buffer: Vec<u8>,
+    /// Write-ahead log contents, when the database is in WAL mode
+    wal_source: Option<Box<dyn PageSource>>,
+    /// Page number -> offset of its newest committed frame in the WAL
+    wal_pages: HashMap<u32, u64>,
+    /// Commits available in the WAL
+    wal_commits: Vec<WalCommit>,
     /// Cache of table schemas and their indexes
     schema_cache: HashMap<String, TableInfo>,
     /// Cache of recently read pages (page_number -> Page)
     page_cache: LruCache<u32, Page>,
-    /// Interned column names to avoid string allocation during row creation
-    column_name_cache: HashMap<String, String>,
+    /// Resource limits applied to traversals, queries and schema loading
+    limits: ReaderLimits,
+    /// Most groups a GROUP BY query holds in memory at once
+    pub(crate) max_hash_groups: usize,
+    /// Most passes a GROUP BY query makes over its rows past `max_hash_groups` groups
+    pub(crate) max_group_passes: usize,
+    /// Source of `random()` for the queries run on this database
+    random: Random,
 }
 
 impl Database {
     /// Open a SQLite database file
+    ///
+    /// If the database is in WAL mode, committed frames from `<path>-wal` are
+    /// read as well.
+    #[cfg(feature = "std")]
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Database> {
-        let file = File::open(path)?;
-        let mut file_buffered = BufReader::new(file);
-        
+        Self::open_with_options(path, &OpenOptions::new())
+    }
+    
+    /// Open a SQLite database file with custom limits and caching
+    #[cfg(feature = "std")]
+    pub fn open_with_options<P: AsRef<Path>>(path: P, options: &OpenOptions) -> Result<Database> {
+        let path = path.as_ref();
+        let file = BufReader::new(File::open(path)?);
+        Self::open_source(Box::new(file), Self::open_wal_file(path)?, options)
+    }
+    
+    /// Open a WAL-mode database as it was at an earlier commit
+    ///
+    /// `frame_index` must be the index of a commit frame, as listed by
+    /// [`Database::wal_commits`]. Frames written after it are ignored.
+    #[cfg(feature = "std")]
+    pub fn open_at_wal_commit<P: AsRef<Path>>(path: P, frame_index: usize) -> Result<Database> {
+        Self::open_with_options(path, &OpenOptions::new().wal_commit(frame_index))
+    }
+    
+    /// Open a database held in memory, e.g. a `Vec<u8>`, `&'static [u8]` or `bytes::Bytes`
+    pub fn from_bytes<B: AsRef<[u8]> + 'static>(bytes: B) -> Result<Database> {
+        Self::from_source(BytesSource(bytes))
+    }
+    
+    /// Open a database from any `Read + Seek` implementation
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: Read + Seek + 'static>(reader: R) -> Result<Database> {
+        Self::from_source(crate::source::ReadSeekSource(reader))
+    }
+    
+    /// Open a database from a custom page source
+    pub fn from_source<S: PageSource + 'static>(source: S) -> Result<Database> {
+        Self::from_source_with_options(source, &OpenOptions::new())
+    }
+    
+    /// Open a database from a custom page source with custom limits and caching
+    pub fn from_source_with_options<S: PageSource + 'static>(source: S, options: &OpenOptions) -> Result<Database> {
+        Self::open_source(Box::new(source), None, options)
+    }
+    
+    /// Open a WAL-mode database from custom page sources for the main file and its WAL
+    pub fn from_source_with_wal<S, W>(source: S, wal: W) -> Result<Database>
+    where
+        S: PageSource + 'static,
+        W: PageSource + 'static,
+    {
+        Self::from_source_with_wal_and_options(source, wal, &OpenOptions::new())
+    }
+    
+    /// Open a WAL-mode database from custom page sources with custom limits and
+    /// caching, optionally as it was at the commit chosen with [`OpenOptions::wal_commit`]
+    pub fn from_source_with_wal_and_options<S, W>(source: S, wal: W, options: &OpenOptions) -> Result<Database>
+    where
+        S: PageSource + 'static,
+        W: PageSource + 'static,
+    {
+        Self::open_source(Box::new(source), Some(Box::new(wal)), options)
+    }
+    
+    /// Open `<path>-wal` if it exists
+    #[cfg(feature = "std")]
+    fn open_wal_file(path: &Path) -> Result<Option<Box<dyn PageSource>>> {
+        let mut wal_path = path.as_os_str().to_owned();
+        wal_path.push("-wal");
+        
+        match File::open(&wal_path) {
+            Ok(file) => Ok(Some(Box::new(BufReader::new(file)))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Open a database, reading the WAL up to the commit selected in `options` (or the latest commit)
+    fn open_source(
+        mut source: Box<dyn PageSource>,
+        wal_source: Option<Box<dyn PageSource>>,
+        options: &OpenOptions,
+    ) -> Result<Database> {
         // Read and validate header
         let mut header_bytes = [0u8; 100];
-        file_buffered.read_exact(&mut header_bytes)?;
+        source.read_at(0, &mut header_bytes)?;
         
         // Check magic string
         if &header_bytes[0..16] != SQLITE_HEADER_MAGIC {
@@ -55,34 +161,135 @@ impl Database {
         
         let header = Self::parse_header(&header_bytes)?;
         let page_size = header.page_size as usize;
-        let max_cache_size = 5000;
+        let cache_size = NonZeroUsize::new(options.page_cache_size).unwrap_or(NonZeroUsize::MIN);
 
         let mut db = Database { 
-            file: file_buffered,
+            source,
             header,
             page_buffer: vec![0; page_size],
+            wal_source: None,
+            wal_pages: HashMap::new(),
+            wal_commits: Vec::new(),
             schema_cache: HashMap::new(),
-            page_cache: LruCache::new(NonZeroUsize::new(max_cache_size).unwrap()),
-            column_name_cache: HashMap::new(),
+            page_cache: LruCache::new(cache_size),
+            limits: options.limits.clone(),
+            max_hash_groups: options.max_hash_groups,
+            max_group_passes: options.max_group_passes,
+            random: Random::new(options.random_seed),
         };
         
+        // Databases in WAL mode keep their newest committed pages in the -wal file
+        let commit = options.wal_commit;
+        if db.header.read_version == 2 || db.header.write_version == 2 {
+            db.load_wal(wal_source, commit)?;
+        } else if commit.is_some() {
+            return Err(Error::InvalidFormat("Database is not in WAL mode".into()));
+        }
+        
         // Load schema information
         db.load_schema()?;
         
         Ok(db)
     }
     
+    /// Validate a WAL source, returning `None` if it is missing or unusable
+    fn read_wal(&self, wal_source: Option<Box<dyn PageSource>>) -> Result<Option<(Box<dyn PageSource>, Wal)>> {
+        let mut wal_source = match wal_source {
+            Some(source) => source,
+            None => {
+                log_debug("Database is in WAL mode but has no -wal file");
+                return Ok(None);
+            }
+        };
+        
+        // A checkpointed WAL may be empty or only contain a header
+        if wal_source.size()? < crate::wal::WAL_HEADER_SIZE as u64 {
+            return Ok(None);
+        }
+        
+        let wal = match Wal::parse(wal_source.as_mut()) {
+            Ok(wal) => wal,
+            Err(e) => {
+                log_warn(&format!("Ignoring invalid WAL file: {}", e));
+                return Ok(None);
+            }
+        };
+        
+        if wal.header.page_size != self.header.page_size {
+            log_warn(&format!(
+                "Ignoring WAL file with page size {} (database page size is {})",
+                wal.header.page_size, self.header.page_size
+            ));
+            return Ok(None);
+        }
+        
+        Ok(Some((wal_source, wal)))
+    }
+    
+    /// Merge the WAL into page reads, as of the commit frame `commit` or the latest commit
+    fn load_wal(&mut self, wal_source: Option<Box<dyn PageSource>>, commit: Option<usize>) -> Result<()> {
+        let (wal_source, wal) = match self.read_wal(wal_source)? {
+            Some(found) => found,
+            None if commit.is_some() => {
+                return Err(Error::InvalidFormat("No valid WAL file to read a commit from".into()));
+            }
+            None => return Ok(()),
+        };
+        
+        let commits = wal.commits();
+        let snapshot = match commit {
+            Some(frame_index) => *commits
+                .iter()
+                .find(|c| c.frame_index == frame_index)
+                .ok_or_else(|| Error::InvalidFormat(format!("WAL frame {} is not a valid commit frame", frame_index)))?,
+            None => match commits.last() {
+                Some(latest) => *latest,
+                None => return Ok(()),
+            },
+        };
+        
+        self.wal_pages = wal.page_offsets_at(snapshot.frame_index);
+        self.wal_source = Some(wal_source);
+        self.wal_commits = commits;
+        log_debug(&format!(
+            "Loaded WAL up to frame {} covering {} pages",
+            snapshot.frame_index,
+            self.wal_pages.len()
+        ));
+        
+        // The header on page 1 may itself have been rewritten in the WAL
+        self.header.database_size = snapshot.database_size;
+        if self.wal_pages.contains_key(&1) {
+            self.read_page_data(1)?;
+            self.header = Self::parse_header(&self.page_buffer[..100])?;
+        }
+        self.header.database_size = snapshot.database_size;
+        self.page_cache.clear();
+        
+        Ok(())
+    }
+    
+    /// Commits found in the `-wal` file, oldest first
+    ///
+    /// Empty when the database is not in WAL mode or the WAL holds no commits.
+    /// Pass a commit's `frame_index` to [`Database::open_at_wal_commit`] to read
+    /// the database as it was at that point.
+    pub fn wal_commits(&self) -> &[WalCommit] {
+        &self.wal_commits
+    }
+    
     /// Load schema information for all tables and indexes
     fn load_schema(&mut self) -> Result<()> {
         let schema_objects = self.read_schema()?;
         
         let mut tables = HashMap::new();
+        let mut parsed_tables = HashMap::new();
 
         // First pass: process tables
         for (name, object) in &schema_objects {
             if object.type_name == "table" && !name.starts_with("sqlite_") {
-                let columns = match Self::parse_create_table_columns(&object.sql) {
-                    Ok(cols) => cols,
+                let parsed = match Self::parse_create_table_info(&object.sql) {
+                    Ok(parsed) => parsed,
                     Err(e) => {
                         log_warn(&format!("Failed to parse CREATE TABLE statement for table '{}': {}", name, e));
                         continue;
@@ -92,35 +299,77 @@ impl Database {
                 let table_info = TableInfo {
                     name: name.clone(),
                     root_page: object.root_page,
-                    columns,
+                    columns: parsed.columns.clone(),
                     indexes: Vec::new(),
                     sql: object.sql.clone(),
+                    without_rowid: parsed.without_rowid,
+                    primary_key: parsed.primary_key.clone(),
+                    key_parts: parsed.key_parts.clone(),
+                    rowid_alias: parsed.rowid_alias.clone(),
+                    column_info: parsed.column_info.clone(),
+                    constraints: parsed.constraints.clone(),
+                    foreign_keys: parsed.foreign_keys.clone(),
                 };
                 tables.insert(name.clone(), table_info);
+                parsed_tables.insert(name.clone(), parsed);
             }
         }
 
         // Second pass: process indexes
         for (name, object) in &schema_objects {
-            if object.type_name == "index" && !name.starts_with("sqlite_") {
-                match Self::parse_create_index_info(&object.sql) {
-                    Ok((table_name, columns)) => {
-                        if let Some(table_info) = tables.get_mut(&table_name) {
-                            let index_info = IndexInfo {
-                                name: name.clone(),
-                                table_name: table_name.clone(),
-                                columns,
-                                root_page: object.root_page,
-                            };
-                            table_info.indexes.push(index_info);
-                        } else {
-                            log_warn(&format!("Index '{}' references unknown table '{}'", name, table_name));
-                        }
+            if object.type_name != "index" {
+                continue;
+            }
+            
+            let index_info = if name.starts_with("sqlite_autoindex_") {
+                // Automatic indexes have no SQL; `sqlite_autoindex_<table>_<N>` is the
+                // N-th PRIMARY KEY or UNIQUE constraint of its table
+                let columns = name
+                    .rsplit_once('_')
+                    .and_then(|(_, n)| n.parse::<usize>().ok())
+                    .and_then(|n| parsed_tables.get(&object.table_name)?.autoindexes.get(n.checked_sub(1)?));
+                match columns {
+                    Some(columns) => IndexInfo {
+                        name: name.clone(),
+                        table_name: object.table_name.clone(),
+                        columns: columns.clone(),
+                        predicate: None,
+                        predicate_expr: None,
+                        root_page: object.root_page,
+                    },
+                    None => {
+                        log_warn(&format!("Could not match automatic index '{}' to a constraint of '{}'", name, object.table_name));
+                        continue;
                     }
+                }
+            } else if !name.starts_with("sqlite_") {
+                match Self::parse_create_index_info(name, object.root_page, &object.sql) {
+                    Ok(index_info) => index_info,
                     Err(e) => {
                         log_warn(&format!("Failed to parse CREATE INDEX for '{}': {}", name, e));
+                        continue;
+                    }
+                }
+            } else {
+                continue;
+            };
+            
+            match (tables.get_mut(&index_info.table_name), parsed_tables.get(&index_info.table_name)) {
+                (Some(table_info), Some(parsed)) => {
+                    let mut index_info = index_info;
+                    // Index keys without a COLLATE clause use the column's collation
+                    for part in &mut index_info.columns {
+                        if let (None, IndexKey::Column(col)) = (&part.collation, &part.key) {
+                            part.collation = parsed
+                                .column_info
+                                .iter()
+                                .find(|info| &info.name == col)
+                                .and_then(|info| info.collation.clone());
+                        }
                     }
+                    table_info.indexes.push(index_info);
                 }
+                _ => log_warn(&format!("Index '{}' references unknown table '{}'", name, index_info.table_name)),
             }
         }
 
@@ -128,77 +377,428 @@ impl Database {
         Ok(())
     }
 
-    /// Parse a CREATE TABLE statement to extract column names
-    fn parse_create_table_columns(sql: &str) -> Result<Vec<String>> {
+    /// Parse a CREATE TABLE statement to extract column names, whether the table
+    /// is declared `WITHOUT ROWID`, its PRIMARY KEY columns in key order, the
+    /// column aliasing the rowid and the columns of the automatic indexes behind
+    /// its PRIMARY KEY and UNIQUE constraints
+    fn parse_create_table_info(sql: &str) -> Result<ParsedTable> {
+        use sqlparser::ast::{ColumnDef, ColumnOption, TableConstraint};
+        use sqlparser::keywords::Keyword;
+        use sqlparser::tokenizer::Token;
+        
         let dialect = sqlparser::dialect::SQLiteDialect {};
-        let statements = sqlparser::parser::Parser::parse_sql(&dialect, sql)
+        let (sql, mut constraint_keys) = Self::split_constraint_keys(sql)?;
+        let statements = sqlparser::parser::Parser::parse_sql(&dialect, &sql)
             .map_err(|e| Error::SchemaError(format!("Failed to parse SQL: {}", e)))?;
 
         if statements.len() != 1 {
             return Err(Error::SchemaError("Expected a single CREATE TABLE statement".into()));
         }
 
-        if let sqlparser::ast::Statement::CreateTable(sqlparser::ast::CreateTable { name: _, columns, .. }) = &statements[0] {
-            let column_names = columns.iter().map(|col| col.name.value.clone()).collect();
-            Ok(column_names)
+        if let sqlparser::ast::Statement::CreateTable(create) = &statements[0] {
+            let column_names: Vec<String> = create.columns.iter().map(|col| col.name.value.clone()).collect();
+            
+            // A PRIMARY KEY on a single column whose declared type is exactly
+            // `INTEGER` aliases the rowid of a rowid table. For backwards
+            // compatibility SQLite makes an exception for the column constraint
+            // `INTEGER PRIMARY KEY DESC`, which is an ordinary key
+            let is_integer = |col: &ColumnDef| declared_type(col).eq_ignore_ascii_case("INTEGER");
+            // The sort order of a column constraint is kept as tokens following it
+            let is_descending = |col: &ColumnDef, option: usize| {
+                matches!(
+                    col.options.get(option + 1).map(|next| &next.option),
+                    Some(ColumnOption::DialectSpecific(tokens))
+                        if matches!(tokens.first(), Some(Token::Word(word)) if word.keyword == Keyword::DESC)
+                )
+            };
+            let mut rowid_alias = None;
+            if !create.without_rowid {
+                for col in &create.columns {
+                    for (i, opt) in col.options.iter().enumerate() {
+                        if !matches!(opt.option, ColumnOption::Unique { is_primary: true, .. }) {
+                            continue;
+                        }
+                        if is_integer(col) && !is_descending(col, i) {
+                            rowid_alias = Some(col.name.value.clone());
+                        }
+                    }
+                }
+                for constraint in &create.constraints {
+                    if let TableConstraint::PrimaryKey { columns, .. } = constraint {
+                        if let [column] = columns.as_slice() {
+                            let declared = create.columns.iter().find(|col| col.name.value.eq_ignore_ascii_case(&column.value));
+                            if let Some(col) = declared.filter(|col| is_integer(col)) {
+                                rowid_alias = Some(col.name.value.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            
+            // Every other PRIMARY KEY of a rowid table gets an automatic index
+            let is_rowid_alias = |columns: &[IndexColumn]| match (columns, &rowid_alias) {
+                ([column], Some(alias)) => column.column_name().is_some_and(|name| name.eq_ignore_ascii_case(alias)),
+                _ => false,
+            };
+            
+            // SQLite creates the automatic indexes in the order the constraints
+            // appear, skipping any whose columns repeat an earlier one
+            let mut autoindexes: Vec<Vec<IndexColumn>> = Vec::new();
+            let mut add_autoindex = |columns: Vec<IndexColumn>| {
+                if !autoindexes.contains(&columns) {
+                    autoindexes.push(columns);
+                }
+            };
+
+            // PRIMARY KEY is either a column option or a table constraint
+            let mut primary_key = Vec::new();
+            let mut key_parts = Vec::new();
+            for col in &create.columns {
+                for (i, opt) in col.options.iter().enumerate() {
+                    if let ColumnOption::Unique { is_primary, .. } = opt.option {
+                        // `PRIMARY KEY DESC` builds its automatic index in descending order
+                        let mut column = IndexColumn::column(col.name.value.clone());
+                        column.descending = is_primary && is_descending(col, i);
+                        let columns = vec![column];
+                        if is_primary {
+                            primary_key.push(col.name.value.clone());
+                            key_parts.extend(columns.iter().cloned());
+                            if is_rowid_alias(&columns) {
+                                continue;
+                            }
+                        }
+                        add_autoindex(columns);
+                    }
+                }
+            }
+            // The key parts of each PRIMARY KEY and UNIQUE constraint, with the
+            // sort orders and collations taken out of the statement
+            let mut constraint_keys = constraint_keys.drain(..);
+            let mut parts_of = |columns: &[sqlparser::ast::Ident]| {
+                constraint_keys
+                    .next()
+                    .filter(|parts| parts.len() == columns.len())
+                    .unwrap_or_else(|| columns.iter().map(|col| IndexColumn::column(col.value.clone())).collect())
+            };
+            for constraint in &create.constraints {
+                match constraint {
+                    TableConstraint::PrimaryKey { columns, .. } => {
+                        let columns = parts_of(columns);
+                        for part in &columns {
+                            let Some(col) = part.column_name() else { continue };
+                            if !primary_key.iter().any(|key| key == col) {
+                                primary_key.push(col.to_string());
+                                key_parts.push(part.clone());
+                            }
+                        }
+                        if !is_rowid_alias(&columns) {
+                            add_autoindex(columns);
+                        }
+                    }
+                    TableConstraint::Unique { columns, .. } => {
+                        let columns = parts_of(columns);
+                        add_autoindex(columns);
+                    }
+                    _ => {}
+                }
+            }
+
+            let (column_info, constraints, foreign_keys) = Self::parse_column_definitions(create, &primary_key);
+            
+            // Key parts without a COLLATE clause use the column's collation
+            for part in &mut key_parts {
+                if let (None, Some(col)) = (&part.collation, part.column_name()) {
+                    part.collation = column_info.iter().find(|info| info.name == col).and_then(|info| info.collation.clone());
+                }
+            }
+
+            Ok(ParsedTable {
+                columns: column_names,
+                without_rowid: create.without_rowid,
+                primary_key,
+                key_parts,
+                autoindexes,
+                rowid_alias,
+                column_info,
+                constraints,
+                foreign_keys,
+            })
         } else {
             Err(Error::SchemaError("Expected a CREATE TABLE statement".into()))
         }
     }
 
-    /// Parse a CREATE INDEX statement to extract the target table and column names
-    /// This routine uses simple string parsing instead of relying on the full SQL parser
-    /// because sqlparser's CreateIndex support is experimental and may break between versions.
-    /// It supports statements of the following forms (case-insensitive):
-    ///     CREATE [UNIQUE] INDEX idx_name ON table_name(col1, col2, ...);
-    ///     CREATE INDEX IF NOT EXISTS idx_name ON "table" ( `col1` , `col2` );
-    /// It returns the referenced table name and the list of column names in the order they
-    /// appear in the index definition.
-    fn parse_create_index_info(sql: &str) -> Result<(String, Vec<String>)> {
-        // To keep things reasonably robust without pulling in a full SQL parser, we
-        // locate the first " ON " keyword (case-insensitive) and then extract the
-        // substring up to the first opening parenthesis. Everything between ON and
-        // the parenthesis is considered the table name (it may include a schema
-        // prefix, e.g. "main.table").
-        let lowercase = sql.to_lowercase();
-        let on_pos = lowercase.find(" on ")
-            .ok_or_else(|| Error::SchemaError("CREATE INDEX statement missing 'ON'".into()))?;
-
-        // Slice AFTER the " on " (preserve original casing for table name parsing)
-        let after_on = &sql[on_pos + 4..];
-        let after_on_trim = after_on.trim_start();
-
-        // Parse table name (stop at whitespace or '(' )
-        let mut table_name = String::new();
-        for ch in after_on_trim.chars() {
-            if ch.is_whitespace() || ch == '(' {
-                break;
+    /// Take the sort orders and collations out of the column lists of PRIMARY KEY
+    /// and UNIQUE table constraints, which sqlparser only reads as plain names.
+    ///
+    /// Returns the statement without them and the key parts of each such
+    /// constraint, in the order the constraints appear.
+    fn split_constraint_keys(sql: &str) -> Result<(String, Vec<Vec<IndexColumn>>)> {
+        use sqlparser::keywords::Keyword;
+        use sqlparser::tokenizer::{Location, Token, Tokenizer};
+        
+        let dialect = sqlparser::dialect::SQLiteDialect {};
+        let tokens = Tokenizer::new(&dialect, sql)
+            .tokenize_with_location()
+            .map_err(|e| Error::SchemaError(format!("Failed to parse SQL: {}", e)))?;
+        let tokens: Vec<_> = tokens.iter().filter(|token| !matches!(token.token, Token::Whitespace(_))).collect();
+        
+        // Locations count lines and characters from 1
+        let line_starts: Vec<usize> = core::iter::once(0).chain(sql.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        let offset = |location: Location| {
+            let start = line_starts.get((location.line as usize).saturating_sub(1)).copied().unwrap_or(sql.len());
+            sql[start..]
+                .char_indices()
+                .map(|(i, _)| start + i)
+                .chain(core::iter::once(sql.len()))
+                .nth((location.column as usize).saturating_sub(1))
+                .unwrap_or(sql.len())
+        };
+        let keyword = |i: usize, keyword: Keyword| {
+            matches!(tokens.get(i).map(|token| &token.token), Some(Token::Word(word)) if word.keyword == keyword)
+        };
+        
+        let mut constraints = Vec::new();
+        let mut removed = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            // Column constraints are never followed by a column list
+            i = if keyword(i, Keyword::PRIMARY) && keyword(i + 1, Keyword::KEY) {
+                i + 2
+            } else if keyword(i, Keyword::UNIQUE) {
+                i + 1
+            } else {
+                i += 1;
+                continue;
+            };
+            if !matches!(tokens.get(i).map(|token| &token.token), Some(Token::LParen)) {
+                continue;
+            }
+            i += 1;
+            
+            // Each part is a column name, optionally followed by COLLATE and ASC or DESC
+            let mut parts = Vec::new();
+            while let Some(Token::Word(name)) = tokens.get(i).map(|token| &token.token) {
+                let mut part = IndexColumn::column(name.value.clone());
+                i += 1;
+                let options = i;
+                if keyword(i, Keyword::COLLATE) {
+                    if let Some(Token::Word(collation)) = tokens.get(i + 1).map(|token| &token.token) {
+                        part.collation = Some(collation.value.clone());
+                        i += 2;
+                    }
+                }
+                if keyword(i, Keyword::ASC) {
+                    i += 1;
+                } else if keyword(i, Keyword::DESC) {
+                    part.descending = true;
+                    i += 1;
+                }
+                if i > options {
+                    removed.push(offset(tokens[options].span.start)..offset(tokens[i - 1].span.end));
+                }
+                parts.push(part);
+                
+                if !matches!(tokens.get(i).map(|token| &token.token), Some(Token::Comma)) {
+                    break;
+                }
+                i += 1;
             }
-            table_name.push(ch);
+            constraints.push(parts);
         }
-        if table_name.is_empty() {
-            return Err(Error::SchemaError("Unable to parse table name from CREATE INDEX".into()));
+        
+        let mut stripped = String::with_capacity(sql.len());
+        let mut copied = 0;
+        for range in removed {
+            stripped.push_str(&sql[copied..range.start]);
+            copied = range.end;
         }
+        stripped.push_str(&sql[copied..]);
+        Ok((stripped, constraints))
+    }
 
-        // Locate the first '(' which starts the column list
-        let paren_start = after_on_trim.find('(')
-            .ok_or_else(|| Error::SchemaError("CREATE INDEX missing column list".into()))?;
-        let paren_end_rel = after_on_trim[paren_start + 1..]
-            .find(')')
-            .ok_or_else(|| Error::SchemaError("CREATE INDEX missing closing ')'".into()))?;
-        let paren_end = paren_start + 1 + paren_end_rel;
-        let cols_segment = &after_on_trim[paren_start + 1..paren_end];
-
-        let columns: Vec<String> = cols_segment
-            .split(',')
-            .map(|s| s.trim().trim_matches('`').trim_matches('"').to_string())
-            .filter(|s| !s.is_empty())
+    /// Collect the definition of each column of a CREATE TABLE statement, its
+    /// table constraints and its foreign keys, whether declared on a column or
+    /// as a table constraint
+    fn parse_column_definitions(
+        create: &sqlparser::ast::CreateTable,
+        primary_key: &[String],
+    ) -> (Vec<ColumnInfo>, Vec<TableConstraint>, Vec<ForeignKey>) {
+        use sqlparser::ast::{ColumnOption, Expr as SqlExpr, GeneratedExpressionMode, TableConstraint as SqlConstraint};
+        
+        let names = |columns: &[sqlparser::ast::Ident]| columns.iter().map(|col| col.value.clone()).collect::<Vec<_>>();
+        let table_name = |name: &sqlparser::ast::ObjectName| {
+            name.0.last().and_then(|part| part.as_ident()).map(|ident| ident.value.clone()).unwrap_or_default()
+        };
+        
+        let mut foreign_keys = Vec::new();
+        let mut columns: Vec<ColumnInfo> = create
+            .columns
+            .iter()
+            .map(|col| {
+                let declared_type = Some(declared_type(col)).filter(|ty| !ty.is_empty());
+                let in_primary_key = primary_key.iter().any(|key| key.eq_ignore_ascii_case(&col.name.value));
+                let mut info = ColumnInfo {
+                    name: col.name.value.clone(),
+                    affinity: Affinity::from_declared_type(declared_type.as_deref().unwrap_or("")),
+                    declared_type,
+                    // The PRIMARY KEY of a WITHOUT ROWID table is always NOT NULL
+                    not_null: in_primary_key && create.without_rowid,
+                    default: None,
+                    collation: None,
+                    primary_key: in_primary_key,
+                    unique: false,
+                    generated: None,
+                    default_value: None,
+                };
+                for opt in &col.options {
+                    match &opt.option {
+                        ColumnOption::NotNull => info.not_null = true,
+                        // The parser folds a COLLATE clause after DEFAULT into the default value
+                        ColumnOption::Default(SqlExpr::Collate { expr, collation }) => {
+                            info.set_default(expr);
+                            info.collation = Some(collation.to_string());
+                        }
+                        ColumnOption::Default(expr) => info.set_default(expr),
+                        ColumnOption::Collation(collation) => info.collation = Some(collation.to_string()),
+                        ColumnOption::Unique { is_primary: false, .. } => info.unique = true,
+                        ColumnOption::Generated { generation_expr: Some(expr), generation_expr_mode, .. } => {
+                            info.generated = Some(GeneratedColumn {
+                                expression: expr.to_string(),
+                                stored: *generation_expr_mode == Some(GeneratedExpressionMode::Stored),
+                            });
+                        }
+                        ColumnOption::ForeignKey { foreign_table, referred_columns, on_delete, on_update, .. } => {
+                            foreign_keys.push(ForeignKey {
+                                name: opt.name.as_ref().map(|name| name.value.clone()),
+                                columns: vec![col.name.value.clone()],
+                                table: table_name(foreign_table),
+                                referred_columns: names(referred_columns),
+                                on_delete: on_delete.as_ref().map(|action| action.to_string()),
+                                on_update: on_update.as_ref().map(|action| action.to_string()),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                info
+            })
             .collect();
+        
+        let mut constraints = Vec::new();
+        for constraint in &create.constraints {
+            match constraint {
+                SqlConstraint::PrimaryKey { name, columns, .. } => constraints.push(TableConstraint::PrimaryKey {
+                    name: name.as_ref().map(|name| name.value.clone()),
+                    columns: names(columns),
+                }),
+                SqlConstraint::Unique { name, columns: unique, .. } => {
+                    // A single-column UNIQUE constraint makes that column unique
+                    if let [column] = unique.as_slice() {
+                        if let Some(info) = columns.iter_mut().find(|info| info.name.eq_ignore_ascii_case(&column.value)) {
+                            info.unique = true;
+                        }
+                    }
+                    constraints.push(TableConstraint::Unique {
+                        name: name.as_ref().map(|name| name.value.clone()),
+                        columns: names(unique),
+                    });
+                }
+                SqlConstraint::Check { name, expr, .. } => constraints.push(TableConstraint::Check {
+                    name: name.as_ref().map(|name| name.value.clone()),
+                    expression: expr.to_string(),
+                }),
+                SqlConstraint::ForeignKey { name, columns, foreign_table, referred_columns, on_delete, on_update, .. } => {
+                    foreign_keys.push(ForeignKey {
+                        name: name.as_ref().map(|name| name.value.clone()),
+                        columns: names(columns),
+                        table: table_name(foreign_table),
+                        referred_columns: names(referred_columns),
+                        on_delete: on_delete.as_ref().map(|action| action.to_string()),
+                        on_update: on_update.as_ref().map(|action| action.to_string()),
+                    });
+                }
+                _ => {}
+            }
+        }
+        
+        (columns, constraints, foreign_keys)
+    }
 
+    /// Parse a CREATE INDEX statement into its table, key parts and partial-index predicate
+    fn parse_create_index_info(name: &str, root_page: u32, sql: &str) -> Result<IndexInfo> {
+        use sqlparser::ast::Expr as SqlExpr;
+        
+        let dialect = sqlparser::dialect::SQLiteDialect {};
+        let statements = sqlparser::parser::Parser::parse_sql(&dialect, sql)
+            .map_err(|e| Error::SchemaError(format!("Failed to parse SQL: {}", e)))?;
+
+        let create = match statements.as_slice() {
+            [sqlparser::ast::Statement::CreateIndex(create)] => create,
+            _ => return Err(Error::SchemaError("Expected a single CREATE INDEX statement".into())),
+        };
+        
+        // The table is always in the index's own schema, so drop any schema prefix
+        let table_name = create
+            .table_name
+            .0
+            .last()
+            .and_then(|part| part.as_ident())
+            .map(|ident| ident.value.clone())
+            .ok_or_else(|| Error::SchemaError("Unable to parse table name from CREATE INDEX".into()))?;
+        
+        let columns: Vec<IndexColumn> = create
+            .columns
+            .iter()
+            .map(|column| {
+                let mut expr = &column.column.expr;
+                let mut collation = None;
+                loop {
+                    match expr {
+                        SqlExpr::Nested(inner) => expr = inner,
+                        SqlExpr::Collate { expr: inner, collation: name } => {
+                            // The outermost COLLATE wins
+                            collation.get_or_insert_with(|| name.to_string());
+                            expr = inner;
+                        }
+                        _ => break,
+                    }
+                }
+                let key = match expr {
+                    SqlExpr::Identifier(ident) => IndexKey::Column(ident.value.clone()),
+                    other => IndexKey::Expression(other.to_string()),
+                };
+                IndexColumn {
+                    key,
+                    collation,
+                    descending: column.column.options.asc == Some(false),
+                }
+            })
+            .collect();
         if columns.is_empty() {
             return Err(Error::SchemaError("CREATE INDEX has no columns".into()));
         }
-        Ok((table_name, columns))
+        
+        // A predicate the planner cannot reason about keeps the index out of use
+        let predicate_expr = match &create.predicate {
+            Some(predicate) => match SelectQuery::parse_predicate(predicate) {
+                Ok(expr) => Some(expr),
+                Err(e) => {
+                    log_debug(&format!("Partial index '{}' will not be used: {}", name, e));
+                    None
+                }
+            },
+            None => None,
+        };
+        
+        Ok(IndexInfo {
+            name: name.to_string(),
+            table_name,
+            columns,
+            predicate: create.predicate.as_ref().map(|predicate| predicate.to_string()),
+            predicate_expr,
+            root_page,
+        })
     }
 
     /// Parse the file header
@@ -206,6 +806,11 @@ impl Database {
         let page_size = BigEndian::read_u16(&data[16..18]);
         let page_size = if page_size == 1 { 65536u32 } else { page_size as u32 };
         
+        let text_encoding = BigEndian::read_u32(&data[56..60]);
+        if text_encoding != 0 && TextEncoding::from_header_value(text_encoding).is_none() {
+            return Err(Error::InvalidFormat(format!("Unknown text encoding {}", text_encoding)));
+        }
+        
         Ok(FileHeader {
             page_size,
             write_version: data[18],
@@ -222,7 +827,7 @@ impl Database {
             schema_format: BigEndian::read_u32(&data[44..48]),
             default_cache_size: BigEndian::read_u32(&data[48..52]),
             largest_root_page: BigEndian::read_u32(&data[52..56]),
-            text_encoding: BigEndian::read_u32(&data[56..60]),
+            text_encoding,
             user_version: BigEndian::read_u32(&data[60..64]),
             incremental_vacuum: BigEndian::read_u32(&data[64..68]),
             application_id: BigEndian::read_u32(&data[68..72]),
@@ -232,21 +837,14 @@ impl Database {
     }
     
     /// Read a page with optimized caching for sequential access patterns
-    fn read_page(&mut self, page_number: u32) -> Result<Page> {
-        if page_number == 0 || page_number > self.header.database_size {
-            return Err(Error::InvalidPage(page_number));
-        }
-        
+    pub(crate) fn read_page(&mut self, page_number: u32) -> Result<Page> {
         // Check cache first
         if let Some(page) = self.page_cache.get(&page_number) {
             return Ok(page.clone());
         }
         
-        let offset = (page_number - 1) as usize * self.header.page_size as usize;
-        
         // Read page data and create page
-        self.file.seek(SeekFrom::Start(offset as u64))?;
-        self.file.read_exact(&mut self.page_buffer)?;
+        self.read_page_data(page_number)?;
         let page = Page::parse(page_number, &self.page_buffer, page_number == 1)?;
         
         // Cache the page
@@ -255,6 +853,21 @@ impl Database {
         Ok(page)
     }
     
+    /// Read the raw bytes of a page into the page buffer
+    fn read_page_data(&mut self, page_number: u32) -> Result<()> {
+        if page_number == 0 || page_number > self.header.database_size {
+            return Err(Error::InvalidPage(page_number));
+        }
+        
+        // Committed WAL frames take precedence over the main file
+        if let (Some(&offset), Some(wal_source)) = (self.wal_pages.get(&page_number), self.wal_source.as_mut()) {
+            return wal_source.read_at(offset, &mut self.page_buffer);
+        }
+        
+        let offset = (page_number - 1) as u64 * self.header.page_size as u64;
+        self.source.read_at(offset, &mut self.page_buffer)
+    }
+    
     /// List all tables in the database
     pub fn tables(&mut self) -> Result<Vec<String>> {
         let schema = self.read_schema()?;
@@ -284,32 +897,19 @@ impl Database {
             }
         };
         
-        // Only count cells, don't parse them
-        let mut cursor = BTreeCursor::new(root_page);
-        let mut row_count = 0;
-        
-        // Safety check: limit number of iterations to prevent infinite loops
-        let max_iterations = 1_000_000;
-        let mut iteration_count = 0;
-        
-        while let Some(cell) = cursor.next_cell(|page_num| self.read_page(page_num))? {
-            iteration_count += 1;
-            if iteration_count > max_iterations {
-                log_warn(&format!("Row counting exceeded safety limit, stopping at {} rows", row_count));
-                break;
-            }
-            
-            // Only count cells that have actual data (non-empty payloads)
-            // Empty payloads indicate deleted or empty rows
-            if !cell.payload.is_empty() {
-                row_count += 1;
-            }
-        }
-        
+        let row_count = self.count_rows(root_page)?;
         log_debug(&format!("Counted {} rows in table {}", row_count, table_name));
         Ok(row_count)
     }
     
+    /// Count the rows of the table b-tree rooted at `root_page` from its cell
+    /// counts, without reading any records
+    fn count_rows(&mut self, root_page: Page) -> Result<usize> {
+        let row_count = self.cursor(root_page).count_entries(self)?;
+        self.check_row_limit(row_count)?;
+        Ok(row_count)
+    }
+    
     /// Read the schema information
     fn read_schema(&mut self) -> Result<HashMap<String, SchemaObject>> {
         let mut schema = HashMap::new();
@@ -323,21 +923,19 @@ impl Database {
             }
         };
         
-        let mut cursor = BTreeCursor::new(root_page);
+        let mut cursor = self.cursor(root_page);
         
-        // Safety check: limit number of schema objects
-        // Even large databases rarely have more than a few thousand tables/indexes
-        let max_schema_objects = 10_000;
         let mut count = 0;
         
-        while let Some(cell) = cursor.next_cell(|page_num| self.read_page(page_num))? {
-            if count >= max_schema_objects {
-                log_warn(&format!("Truncating schema objects at {} (limit: {})", count, max_schema_objects));
-                break;
-            }
+        while let Some(cell) = cursor.next_cell(self)? {
             count += 1;
+            if let Some(max) = self.limits.max_schema_objects {
+                if count > max {
+                    return Err(Error::LimitExceeded { limit: "max_schema_objects", max });
+                }
+            }
             
-            let values = match parse_record(&cell.payload) {
+            let values = match parse_record_encoded(&cell.payload, self.header.encoding()) {
                 Ok(values) => values,
                 Err(e) => {
                     log_warn(&format!("Failed to parse schema record {}: {}", count, e));
@@ -346,13 +944,15 @@ impl Database {
             };
             
             if values.len() >= 5 {
-                if let (Some(type_name), Some(name), _, Some(root_page), Some(sql)) = (
+                if let (Some(type_name), Some(name), Some(root_page)) = (
                     values[0].as_text(),
                     values[1].as_text(),
-                    &values[2],
                     values[3].as_integer(),
-                    values[4].as_text(),
                 ) {
+                    // Automatic indexes are stored with NULL SQL
+                    let table_name = values[2].as_text().unwrap_or(name);
+                    let sql = values[4].as_text().unwrap_or_default();
+                    
                     // Safety check: limit SQL statement size
                     // Even complex CREATE TABLE statements are rarely > 1MB
                     if sql.len() > 1_000_000 {
@@ -363,6 +963,7 @@ impl Database {
                     schema.insert(name.to_string(), SchemaObject {
                         type_name: type_name.to_string(),
                         name: name.to_string(),
+                        table_name: table_name.to_string(),
                         root_page: root_page as u32,
                         sql: sql.to_string(),
                     });
@@ -387,520 +988,1014 @@ impl Database {
         Ok(table_info.columns.clone())
     }
 
-    /// Perform a streaming table scan that processes rows one at a time for better memory efficiency
-    fn read_table_rows_streaming<F>(&mut self, table_name: &str, limit: Option<usize>, mut row_processor: F) -> Result<usize>
+    /// Schema of a table: its columns with their declared types, affinities and
+    /// constraints, its table constraints, foreign keys and indexes
+    pub fn table_info(&self, table_name: &str) -> Result<&TableInfo> {
+        self.schema_cache.get(table_name)
+            .ok_or_else(|| Error::TableNotFound(table_name.to_string()))
+    }
+
+    /// Position a cursor on the index b-tree rooted at `root_page` at the start of `range`
+    fn seek_key_range(&mut self, root_page: u32, range: &KeyRange) -> Result<BTreeCursor> {
+        let root_page = self.read_page(root_page)?;
+        let mut cursor = self.cursor(root_page);
+        
+        let mut seek_key = range.prefix.clone();
+        if let Some((low, _)) = &range.lower {
+            seek_key.push(low.clone());
+        }
+        if !seek_key.is_empty() {
+            cursor.seek_index(&seek_key, self)?;
+        }
+        Ok(cursor)
+    }
+    
+    /// Create a cursor over the b-tree rooted at `root_page`, honouring the traversal limit
+    pub(crate) fn cursor(&self, root_page: Page) -> BTreeCursor {
+        BTreeCursor::new(root_page).with_max_pages(self.limits.max_pages_per_traversal)
+    }
+    
+    /// Fail once more than the configured number of rows were produced
+    fn check_row_limit(&self, rows: usize) -> Result<()> {
+        match self.limits.max_rows {
+            Some(max) if rows > max => Err(Error::LimitExceeded { limit: "max_rows", max }),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Resource limits this database was opened with
+    pub fn limits(&self) -> &ReaderLimits {
+        &self.limits
+    }
+    
+    /// Context for the expressions of a query on this database, before its
+    /// columns are typed
+    fn context(&self) -> Context {
+        Context::with_random(self.header.encoding(), self.random.clone())
+    }
+    
+    /// Execute a SELECT SQL query with index acceleration and table scan fallback
+    pub fn execute_query(&mut self, query: &SelectQuery) -> Result<ResultSet> {
+        let rows = self.query_iter(query)?;
+        let columns = rows.projection.columns.clone();
+        Ok(ResultSet::new(columns, rows.collect::<Result<Vec<_>>>()?))
+    }
+    
+    /// Run a SELECT statement and decode every row into `T`
+    ///
+    /// `T` is anything implementing [`FromRow`], such as a tuple or a struct
+    /// deriving it with the `derive` feature:
+    /// `db.query_as::<(i64, String)>("SELECT id, name FROM users")`.
+    pub fn query_as<T>(&mut self, sql: &str) -> Result<Vec<T>>
     where
-        F: FnMut(&Row) -> bool, // Return false to stop processing
+        T: for<'r> FromRow<'r>,
     {
-        log_debug(&format!("Performing streaming table scan for table: {}", table_name));
-        
-        // Get table info from cache
-        let table_info = self.schema_cache.get(table_name)
-            .ok_or_else(|| Error::TableNotFound(table_name.to_string()))?;
-        
-        let columns = table_info.columns.clone();
-        
-        // Pre-intern all column names to avoid allocations during row creation
-        for col in &columns {
-            if !self.column_name_cache.contains_key(col) {
-                self.column_name_cache.insert(col.clone(), col.clone());
+        let query = SelectQuery::parse(sql)?;
+        self.query_iter(&query)?.map(|row| T::from_row(&row?)).collect()
+    }
+    
+    /// Execute a SELECT query lazily, yielding one row at a time
+    ///
+    /// Cells are read from the b-tree on demand and WHERE and LIMIT are applied
+    /// as rows are produced, so memory use does not grow with the table size.
+    /// The one exception is ORDER BY, which has to buffer the matching rows
+    /// before the first one can be returned.
+    pub fn query_iter(&mut self, query: &SelectQuery) -> Result<RowIter<'_>> {
+        if !query.joins.is_empty() {
+            return self.join_iter(query);
+        }
+        let table_info = self.schema_cache.get(&query.table)
+            .ok_or_else(|| Error::TableNotFound(query.table.clone()))?
+            .clone();
+        let mut query = join::unqualify(query, &table_info.columns)?;
+        let rowid_column = table_info.rowid_alias.clone();
+        
+        // Rowid pseudo-columns the query refers to follow the declared columns,
+        // unless a declared column of the same name shadows them
+        let mut columns = table_info.columns.clone();
+        let mut rowid_columns: Vec<usize> = rowid_column
+            .as_deref()
+            .and_then(|name| columns.iter().position(|column| column == name))
+            .into_iter()
+            .collect();
+        if !table_info.without_rowid {
+            for name in rowid_pseudo_columns(&query) {
+                if name.index_in(&columns).is_none() {
+                    rowid_columns.push(columns.len());
+                    columns.push(name);
+                }
+            }
+        }
+        let context = self.context().with_columns(ColumnType::of_table(&table_info, columns.len()));
+        let table_columns: Arc<[String]> = columns.into();
+        query.where_expr = query.where_with_aliases(&|name| name.index_in(&table_columns).is_some())?;
+        if let Some(where_expr) = &mut query.where_expr {
+            // Unknown columns fail even where no row is read
+            let mut referenced = Vec::new();
+            collect_expr_columns(where_expr, &mut referenced);
+            if let Some(column) = referenced.into_iter().find(|column| column.index_in(&table_columns).is_none()) {
+                return Err(Error::ColumnNotFound(column.to_string()));
             }
+            apply_column_affinity(where_expr, &table_columns, &context);
         }
+        let query = &query;
         
-        // Detect INTEGER PRIMARY KEY column by parsing the SQL
-        let rowid_column = self.find_rowid_column(table_name)?;
+        if query.is_aggregate() {
+            return self.aggregate_iter(query, table_info, table_columns, rowid_columns, context);
+        }
         
-        let root_page = self.read_page(table_info.root_page)?;
-        let mut cursor = BTreeCursor::new(root_page);
+        let projection = Projection::resolve(query, &table_columns, table_info.columns.len())?;
+        
+        let source = match &query.order_by {
+            Some(order_by) => {
+                // Sort on full rows, since the ORDER BY column need not be selected
+                let key = query.order_key(&table_columns)?.unwrap_or_else(|| ScalarExpr::Column(order_by.column.clone()));
+                let with_pseudo_columns = table_columns.len() > table_info.columns.len();
+                let unordered = SelectQuery {
+                    columns: with_pseudo_columns.then(|| table_columns.to_vec()),
+                    result_columns: None,
+                    order_by: None,
+                    limit: None,
+                    ..query.clone()
+                };
+                let mut rows = self.query_iter(&unordered)?.collect::<Result<Vec<_>>>()?;
+                eval::sort_rows(&mut rows, &key, order_by.ascending, &context)?;
+                if let Some(limit) = query.limit {
+                    rows.truncate(limit);
+                }
+                RowSource::Buffered(rows.into_iter())
+            }
+            None => self.plan_row_source(query, &table_info, rowid_column.as_deref())?,
+        };
         
-        // Safety check: limit number of rows to prevent excessive memory usage
-        let max_rows = limit.unwrap_or(1_000_000);
-        let mut row_count = 0;
-        let mut processed_count = 0;
+        Ok(RowIter {
+            db: self,
+            query: query.clone(),
+            table_info,
+            table_columns,
+            context,
+            projection,
+            rowid_columns,
+            source,
+            grouping: None,
+            returned: 0,
+            done: false,
+        })
+    }
+    
+    /// Start an aggregate query, choosing how its rows are read and grouped
+    fn aggregate_iter(
+        &mut self,
+        query: &SelectQuery,
+        table_info: TableInfo,
+        table_columns: Arc<[String]>,
+        rowid_columns: Vec<usize>,
+        context: Context,
+    ) -> Result<RowIter<'_>> {
+        let plan = AggregatePlan::new(query, &table_columns, table_info.columns.len(), &context)?;
+        let visible = plan.visible_columns().clone();
+        let hidden = plan.columns().len() > visible.len();
+        let full_rows = Projection { columns: plan.columns().clone(), sources: None };
+        
+        let (source, grouping) = aggregate::group_source(self, query, plan, &table_info, &rowid_columns)?;
+        
+        // The groups keep their hidden columns, which ORDER BY may read, until projected
+        let groups = RowIter {
+            db: self,
+            query: SelectQuery { order_by: None, limit: None, ..query.clone() },
+            table_info,
+            table_columns,
+            context,
+            projection: full_rows,
+            rowid_columns,
+            source,
+            grouping,
+            returned: 0,
+            done: false,
+        };
+        let sources = hidden.then(|| (0..visible.len()).map(OutputColumn::Column).collect());
+        groups.ordered(query, Projection { columns: visible, sources })
+    }
+    
+    /// Start a query over joined tables
+    ///
+    /// Rows are joined by nested loops in the order the tables are listed,
+    /// each joined table looked up by its rowid or an index when the
+    /// conditions equate their keys with columns of the tables before it,
+    /// through a hash table of its rows built on first use for other
+    /// equalities, and by scanning it otherwise.
+    fn join_iter(&mut self, query: &SelectQuery) -> Result<RowIter<'_>> {
+        let mut tables = Vec::new();
+        for name in core::iter::once(&query.table).chain(query.joins.iter().map(|join| &join.table)) {
+            let table_info = self.schema_cache.get(name).ok_or_else(|| Error::TableNotFound(name.clone()))?;
+            tables.push(table_info.clone());
+        }
+        let JoinPlan { tables, columns, context, query: resolved, output } = JoinPlan::new(query, tables, &self.context())?;
+        let table_info = tables[0].info.clone();
+        let join = JoinSource::new(self, tables, resolved.clone(), columns.clone(), context.clone())?;
+        
+        let (projection, grouping, projected) = if resolved.is_aggregate() {
+            let plan = AggregatePlan::new(&resolved, &columns, columns.len(), &context)?;
+            let visible = plan.visible_columns().clone();
+            let hidden = plan.columns().len() > visible.len();
+            let full_rows = Projection { columns: plan.columns().clone(), sources: None };
+            let sources = hidden.then(|| (0..visible.len()).map(OutputColumn::Column).collect());
+            let aggregator = HashAggregator::new(Some(self.max_hash_groups));
+            let grouping = Grouping::new(plan, GroupStrategy::Hash(aggregator));
+            (full_rows, Some(grouping), Projection { columns: visible, sources })
+        } else {
+            let (names, sources) = output;
+            let full_rows = Projection { columns: columns.clone(), sources: None };
+            (full_rows, None, Projection { columns: names, sources: Some(sources) })
+        };
         
-        // Reuse row object to reduce allocations
-        let mut row = HashMap::with_capacity(columns.len());
+        let rows = RowIter {
+            db: self,
+            query: SelectQuery { order_by: None, limit: None, ..resolved.clone() },
+            table_info,
+            table_columns: columns,
+            context,
+            projection,
+            rowid_columns: Vec::new(),
+            source: RowSource::Join(Box::new(join)),
+            grouping,
+            returned: 0,
+            done: false,
+        };
+        rows.ordered(&resolved, projected)
+    }
+    
+    /// Answer an aggregate query over a whole table from the b-trees alone:
+    /// `COUNT(*)` from the cell counts, and a lone MIN or MAX of the rowid or
+    /// of the first column of an index from the first or last entry
+    pub(crate) fn aggregate_from_btree(&mut self, result_columns: &[ResultColumn], table_info: &TableInfo) -> Result<Option<Vec<Value>>> {
+        let counts_rows = |column: &ResultColumn| match &column.expr {
+            ScalarExpr::Aggregate(aggregate) => aggregate.function == AggregateFunction::Count && aggregate.args.is_empty(),
+            ScalarExpr::Literal(_) => true,
+            _ => false,
+        };
+        if result_columns.iter().all(counts_rows) {
+            let root_page = self.read_page(table_info.root_page)?;
+            let count = self.count_rows(root_page)?;
+            log_debug(&format!("Counted the rows of {} from its b-tree", table_info.name));
+            let values = result_columns
+                .iter()
+                .map(|column| match &column.expr {
+                    ScalarExpr::Literal(value) => value.clone(),
+                    _ => Value::Integer(count as i64),
+                })
+                .collect();
+            return Ok(Some(values));
+        }
         
-        while let Some(cell) = cursor.next_cell(|page_num| self.read_page(page_num))? {
-            if row_count >= max_rows {
-                if limit.is_none() {
-                    log_warn(&format!("Table scan truncated at {} rows (limit: {})", row_count, max_rows));
-                }
-                break;
-            }
-            
-            // Skip empty payloads (deleted rows)
-            if cell.payload.is_empty() {
-                continue;
-            }
-            
-            // Parse the row data
-            match parse_record(&cell.payload) {
-                Ok(values) => {
-                    // Clear and reuse the row HashMap
-                    row.clear();
-                    
-                    // Convert to a row with column names
-                    for (i, column_name) in columns.iter().enumerate() {
-                        if let Some(ref rowid_col) = rowid_column {
-                            if column_name == rowid_col {
-                                // This is the INTEGER PRIMARY KEY column - use rowid from cell
-                                row.insert(column_name.clone(), Value::Integer(cell.key));
-                                continue;
-                            }
-                        }
-                        
-                        let value = values.get(i).cloned().unwrap_or(Value::Null);
-                        row.insert(column_name.clone(), value);
-                    }
-                    
-                    // Process the row
-                    if !row_processor(&row) {
-                        break; // Stop processing if processor returns false
-                    }
-                    
-                    processed_count += 1;
-                    row_count += 1;
-                },
-                Err(e) => {
-                    log_warn(&format!("Failed to parse row {}: {}", row_count, e));
-                    // Continue with next row instead of failing
-                    continue;
-                }
-            }
-        }
+        let (min, column) = match result_columns {
+            [ResultColumn { expr: ScalarExpr::Aggregate(aggregate), .. }] => match (aggregate.function, aggregate.args.as_slice()) {
+                (AggregateFunction::Min, [ScalarExpr::Column(column)]) => (true, column.clone()),
+                (AggregateFunction::Max, [ScalarExpr::Column(column)]) => (false, column.clone()),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
         
-        log_debug(&format!("Streaming table scan completed: {} rows processed from {}", processed_count, table_name));
-        Ok(processed_count)
-    }
-
-    /// Perform a full table scan with batch processing for better performance
-    fn read_all_table_rows_batch(&mut self, table_name: &str, limit: Option<usize>) -> Result<Vec<Row>> {
-        // For small limits, use streaming to reduce memory usage
-        if let Some(limit_val) = limit {
-            if limit_val <= 10000 {
-                let mut rows = Vec::with_capacity(limit_val);
-                let mut count = 0;
-                
-                self.read_table_rows_streaming(table_name, limit, |row| {
-                    if count < limit_val {
-                        rows.push(row.clone());
-                        count += 1;
-                        true
-                    } else {
-                        false
-                    }
-                })?;
-                
-                return Ok(rows);
+        // The rowid and its alias are the keys of the table b-tree
+        let is_rowid = table_info.rowid_alias.as_deref().is_some_and(|alias| alias.eq_ignore_ascii_case(&column))
+            || (is_rowid_pseudo_column(&column) && column.index_in(&table_info.columns).is_none());
+        if is_rowid && !table_info.without_rowid {
+            let root_page = self.read_page(table_info.root_page)?;
+            let mut cursor = self.cursor(root_page);
+            if !min {
+                cursor.seek_last(self)?;
             }
+            let rowid = cursor.next_cell(self)?.map_or(Value::Null, |cell| Value::Integer(cell.key));
+            log_debug(&format!("Read the {} rowid of {} from its b-tree", if min { "first" } else { "last" }, table_info.name));
+            return Ok(Some(vec![rowid]));
         }
         
-        // Fall back to original batch processing for larger datasets
-        log_debug(&format!("Performing batch table scan for table: {}", table_name));
-        
-        // Get table info from cache
-        let table_info = self.schema_cache.get(table_name)
-            .ok_or_else(|| Error::TableNotFound(table_name.to_string()))?;
+        // Index entries are in the order MIN and MAX compare in when the index
+        // and the column both use BINARY, with NULLs first
+        let info = match table_info.column_info.iter().find(|info| info.name.eq_ignore_ascii_case(&column)) {
+            Some(info) if info.collation.as_deref().is_none_or(|c| c.eq_ignore_ascii_case("BINARY")) => info,
+            _ => return Ok(None),
+        };
+        let index = table_info.indexes.iter().find(|index| {
+            !index.is_partial()
+                && index.columns.first().is_some_and(|part| {
+                    part.is_seekable() && part.column_name().is_some_and(|name| name.eq_ignore_ascii_case(&info.name))
+                })
+        });
+        let index = match index {
+            Some(index) => index,
+            None => return Ok(None),
+        };
         
-        let columns = table_info.columns.clone();
+        let root_page = self.read_page(index.root_page)?;
+        let mut cursor = self.cursor(root_page);
+        if min {
+            // Every number, TEXT and BLOB sorts after this, and NULL before it
+            cursor.seek_index(&[Value::Real(f64::NEG_INFINITY)], self)?;
+        } else {
+            cursor.seek_last(self)?;
+        }
+        let value = match cursor.next_index_payload(self)? {
+            Some(payload) => parse_record_encoded(&payload, self.header.encoding())?.into_iter().next(),
+            None => None,
+        };
+        log_debug(&format!("Read the {} entry of index {}", if min { "first" } else { "last" }, index.name));
+        Ok(Some(vec![info.read_value(Some(value.unwrap_or(Value::Null)))]))
+    }
+    
+    /// Choose how the candidate rows for `query` are read
+    pub(crate) fn plan_row_source(
+        &mut self,
+        query: &SelectQuery,
+        table_info: &TableInfo,
+        rowid_column: Option<&str>,
+    ) -> Result<RowSource> {
+        // WITHOUT ROWID tables live in a clustered index b-tree keyed by their PRIMARY KEY
+        if table_info.without_rowid {
+            let ranges = query.where_expr.as_ref().and_then(|where_expr| {
+                collect_or_branches(where_expr)
+                    .into_iter()
+                    .map(|branch| primary_key_range(table_info, branch))
+                    .collect::<Option<Vec<_>>>()
+            });
+            
+            let ranges = match ranges {
+                Some(ranges) => {
+                    log_debug(&format!("Using PRIMARY KEY seeks for WITHOUT ROWID table {}", table_info.name));
+                    ranges
+                }
+                None => {
+                    log_debug(&format!("Scanning WITHOUT ROWID table {}", table_info.name));
+                    vec![KeyRange::default()]
+                }
+            };
+            
+            // Overlapping OR branches may reach the same row more than once
+            let seen = if ranges.len() > 1 { Some(BTreeSet::new()) } else { None };
+            return Ok(RowSource::KeyRanges {
+                storage_columns: table_info.storage_columns(),
+                ranges: ranges.into_iter(),
+                current: None,
+                seen,
+            });
+        }
         
-        // Pre-intern all column names to avoid allocations during row creation
-        for col in &columns {
-            if !self.column_name_cache.contains_key(col) {
-                self.column_name_cache.insert(col.clone(), col.clone());
+        // Try rowid and index seeks first if we have a WHERE clause
+        if let Some(where_expr) = &query.where_expr {
+            if let Some(source) = self.rowid_lookups(where_expr, table_info, rowid_column) {
+                log_debug(&format!("Using rowid seeks for query on table {}", table_info.name));
+                return Ok(source);
             }
         }
         
-        // Detect INTEGER PRIMARY KEY column by parsing the SQL
-        let rowid_column = self.find_rowid_column(table_name)?;
-        
+        // Fall back to table scan
+        log_debug(&format!("Using table scan fallback for query on table {}", table_info.name));
         let root_page = self.read_page(table_info.root_page)?;
-        let mut cursor = BTreeCursor::new(root_page);
-        
-        // Pre-allocate with estimated capacity to reduce reallocations
-        let estimated_rows = limit.unwrap_or(10000).min(100000);
-        let mut rows = Vec::with_capacity(estimated_rows);
+        Ok(RowSource::TableScan(self.cursor(root_page)))
+    }
+    
+    /// Read the rows that may match `where_expr` from the ranges of rowids and
+    /// of index entries holding them, returning None if some OR branch can
+    /// only be served by a table scan
+    ///
+    /// Each branch is served by conditions on the rowid or one of its aliases
+    /// when they pin down single rowids, then by an index with an equality
+    /// prefix, then by a rowid range, then by any usable index.
+    fn rowid_lookups(
+        &self,
+        where_expr: &Expr,
+        table_info: &TableInfo,
+        rowid_column: Option<&str>,
+    ) -> Option<RowSource> {
+        let encoding = self.header.encoding();
+        let is_rowid = |column: &str| {
+            rowid_column.is_some_and(|name| name.eq_ignore_ascii_case(column))
+                || (is_rowid_pseudo_column(column) && column.index_in(&table_info.columns).is_none())
+        };
         
-        // Batch processing parameters
-        const BATCH_SIZE: usize = 1000;
-        let mut batch_rows = Vec::with_capacity(BATCH_SIZE);
+        let mut spans = Vec::new();
+        let mut index_ranges = Vec::new();
+        for branch in collect_or_branches(where_expr) {
+            match (branch_rowid_spans(branch, is_rowid), find_best_index(table_info, branch, encoding)) {
+                (Some((rowid_spans, true)), _) => spans.extend(rowid_spans),
+                (_, Some((index, range))) if !range.prefix.is_empty() => {
+                    log_debug(&format!("Found usable index '{}' for query condition", index.name));
+                    index_ranges.push(IndexRange::new(index, range));
+                }
+                (Some((rowid_spans, false)), _) => spans.extend(rowid_spans),
+                (None, Some((index, range))) => {
+                    log_debug(&format!("Found usable index '{}' for query condition", index.name));
+                    index_ranges.push(IndexRange::new(index, range));
+                }
+                (None, None) => {
+                    // Rows of this branch can only be found by a table scan
+                    log_debug("No suitable index found for query conditions, will use table scan");
+                    return None;
+                }
+            }
+        }
         
-        // Safety check: limit number of rows to prevent excessive memory usage
-        let max_rows = limit.unwrap_or(1_000_000);
-        let mut row_count = 0;
+        // Merge overlapping and adjacent spans, so each row is read once and in rowid order
+        spans.sort_unstable();
+        let mut merged: Vec<(i64, i64)> = Vec::new();
+        for (low, high) in spans {
+            match merged.last_mut() {
+                Some(last) if low <= last.1.saturating_add(1) => last.1 = last.1.max(high),
+                _ => merged.push((low, high)),
+            }
+        }
         
-        while let Some(cell) = cursor.next_cell(|page_num| self.read_page(page_num))? {
-            if row_count >= max_rows {
-                if limit.is_none() {
-                    log_warn(&format!("Table scan truncated at {} rows (limit: {})", row_count, max_rows));
+        log_debug(&format!("Rowid lookup found {} spans and {} index ranges", merged.len(), index_ranges.len()));
+        Some(RowSource::rowid_lookups(merged, index_ranges))
+    }
+} // end impl Database
+
+impl PageReader for Database {
+    fn read_page(&mut self, page_number: u32) -> Result<Page> {
+        Database::read_page(self, page_number)
+    }
+
+    fn read_overflow_page(&mut self, page_number: u32) -> Result<Vec<u8>> {
+        // Overflow pages are read once per payload, so they bypass the page cache
+        self.read_page_data(page_number)?;
+        Ok(self.page_buffer.clone())
+    }
+
+    fn header(&self) -> &FileHeader {
+        &self.header
+    }
+}
+
+/// Lazy iterator over the rows of a query, created by [`Database::query_iter`]
+pub struct RowIter<'db> {
+    pub(crate) db: &'db mut Database,
+    pub(crate) query: SelectQuery,
+    pub(crate) table_info: TableInfo,
+    /// Declared columns of the table, shared by every candidate row
+    table_columns: Arc<[String]>,
+    /// Types of the columns of candidate rows
+    context: Context,
+    /// Columns of the rows this iterator yields
+    projection: Projection,
+    /// Columns filled from the cell key: the rowid alias and rowid pseudo-columns
+    rowid_columns: Vec<usize>,
+    source: RowSource,
+    /// Grouping of the rows into the rows of an aggregate query
+    grouping: Option<Box<Grouping>>,
+    /// Rows returned so far, for LIMIT
+    returned: usize,
+    done: bool,
+}
+
+/// Where a [`RowIter`] reads its candidate rows from
+pub(crate) enum RowSource {
+    /// Full scan of a rowid table b-tree
+    TableScan(BTreeCursor),
+    /// Rows of a rowid table found by rowid: inclusive rowid ranges, each read
+    /// by seeking to its first rowid and scanning forward past its last, then
+    /// the rows the entries of index ranges name, each looked up as the index
+    /// cursor reaches its entry
+    RowidLookups {
+        spans: alloc::vec::IntoIter<(i64, i64)>,
+        /// Last rowid of the span being scanned and its cursor
+        current: Option<Box<(i64, BTreeCursor)>>,
+        index_ranges: alloc::vec::IntoIter<IndexRange>,
+        /// Index range being walked and its cursor
+        current_range: Option<Box<(IndexRange, BTreeCursor)>>,
+        /// Rowids already returned, when spans and ranges may reach the same row
+        seen: Option<BTreeSet<i64>>,
+    },
+    /// PRIMARY KEY ranges of a WITHOUT ROWID table, scanned one after another
+    KeyRanges {
+        storage_columns: Vec<usize>,
+        ranges: alloc::vec::IntoIter<KeyRange>,
+        /// Range being scanned and its cursor
+        current: Option<Box<(KeyRange, BTreeCursor)>>,
+        /// PRIMARY KEYs already returned, when ranges may overlap
+        seen: Option<BTreeSet<Vec<Value>>>,
+    },
+    /// Every entry of a rowid table's index in key order, each row read from
+    /// the index record when the index covers the query, or else looked up
+    /// in the table by its rowid
+    IndexOrder {
+        index: String,
+        cursor: BTreeCursor,
+        /// Table column and record position of every column the index holds,
+        /// when it holds all those the query reads
+        covering: Option<Vec<(usize, usize)>>,
+    },
+    /// Rows already filtered, sorted and limited for ORDER BY, or computed by
+    /// an aggregate query
+    Buffered(alloc::vec::IntoIter<Row>),
+    /// Rows of several tables joined side by side
+    Join(Box<JoinSource>),
+}
+
+impl RowSource {
+    /// Read the rows of a rowid table in the rowid `spans`, then those the
+    /// entries in `index_ranges` name
+    pub(crate) fn rowid_lookups(spans: Vec<(i64, i64)>, index_ranges: Vec<IndexRange>) -> RowSource {
+        // Merged spans never overlap, but index ranges may reach their rows or each other's
+        let seen = if index_ranges.len() > usize::from(spans.is_empty()) { Some(BTreeSet::new()) } else { None };
+        RowSource::RowidLookups {
+            spans: spans.into_iter(),
+            current: None,
+            index_ranges: index_ranges.into_iter(),
+            current_range: None,
+            seen,
+        }
+    }
+    
+    /// Read the next row of the table, before WHERE filtering; `table_columns`
+    /// and `rowid_columns` describe the rows of a single table, which joined
+    /// rows bring along instead
+    pub(crate) fn next_row(
+        &mut self,
+        db: &mut Database,
+        table_info: &TableInfo,
+        table_columns: &Arc<[String]>,
+        rowid_columns: &[usize],
+    ) -> Result<Option<Row>> {
+        match self {
+            RowSource::TableScan(cursor) => {
+                while let Some(cell) = cursor.next_cell(db)? {
+                    // Skip empty payloads (deleted rows)
+                    if cell.payload.is_empty() {
+                        continue;
+                    }
+                
+                    match parse_record_encoded(&cell.payload, db.header.encoding()) {
+                        Ok(values) => {
+                            return Ok(Some(table_row(table_columns, &table_info.column_info, rowid_columns, cell.key, values)));
+                        }
+                        Err(e) => {
+                            // Continue with next row instead of failing
+                            log_warn(&format!("Failed to parse row {} of {}: {}", cell.key, table_info.name, e));
+                        }
+                    }
                 }
-                break;
+                Ok(None)
             }
+            RowSource::RowidLookups { spans, current, index_ranges, current_range, seen } => loop {
+                let found = match next_span_row(db, table_info, table_columns, rowid_columns, spans, current)? {
+                    Some(found) => found,
+                    None => match next_index_range_row(db, table_info, table_columns, rowid_columns, index_ranges, current_range)? {
+                        Some(found) => found,
+                        None => return Ok(None),
+                    },
+                };
+                let (rowid, row) = found;
+                if seen.as_mut().is_some_and(|seen| !seen.insert(rowid)) {
+                    continue;
+                }
+                return Ok(Some(row));
+            },
+            RowSource::KeyRanges { storage_columns, ranges, current, seen } => loop {
+                let (range, cursor) = match current {
+                    Some(current) => &mut **current,
+                    None => {
+                        let range = match ranges.next() {
+                            Some(range) => range,
+                            None => return Ok(None),
+                        };
+                        let cursor = db.seek_key_range(table_info.root_page, &range)?;
+                        &mut **current.insert(Box::new((range, cursor)))
+                    }
+                };
             
-            // Skip empty payloads (deleted rows)
-            if cell.payload.is_empty() {
-                continue;
-            }
+                let payload = match cursor.next_index_payload(db)? {
+                    Some(payload) => payload,
+                    None => {
+                        *current = None;
+                        continue;
+                    }
+                };
             
-            // Parse the row data
-            match parse_record(&cell.payload) {
-                Ok(values) => {
-                    // Convert to a row with column names using pre-allocated capacity
-                    let mut row = HashMap::with_capacity(columns.len());
-                    for (i, column_name) in columns.iter().enumerate() {
-                        if let Some(ref rowid_col) = rowid_column {
-                            if column_name == rowid_col {
-                                // This is the INTEGER PRIMARY KEY column - use rowid from cell
-                                row.insert(column_name.clone(), Value::Integer(cell.key));
-                                continue;
-                            }
+                let values = match parse_record_encoded(&payload, db.header.encoding()) {
+                    Ok(values) => values,
+                    Err(e) => {
+                        log_warn(&format!("Failed to parse WITHOUT ROWID record in {}: {}", table_info.name, e));
+                        continue;
+                    }
+                };
+            
+                match range.position(&values, db.header.encoding()) {
+                    core::cmp::Ordering::Less => continue,
+                    core::cmp::Ordering::Greater => {
+                        *current = None;
+                        continue;
+                    }
+                    core::cmp::Ordering::Equal => {}
+                }
+            
+                // Records store the PRIMARY KEY columns first
+                if let Some(seen) = seen {
+                    let key_len = table_info.primary_key.len().min(values.len());
+                    if !seen.insert(values[..key_len].to_vec()) {
+                        continue;
+                    }
+                }
+            
+                // Map the record back to declared column order
+                let row_values = storage_columns
+                    .iter()
+                    .zip(&table_info.column_info)
+                    .map(|(&i, info)| info.read_value(values.get(i).cloned()))
+                    .collect();
+                return Ok(Some(Row::new(table_columns.clone(), row_values)));
+            },
+            RowSource::IndexOrder { index, cursor, covering } => {
+                while let Some(payload) = cursor.next_index_payload(db)? {
+                    let values = match parse_record_encoded(&payload, db.header.encoding()) {
+                        Ok(values) => values,
+                        Err(e) => {
+                            log_warn(&format!("Failed to parse entry of index {}: {}", index, e));
+                            continue;
+                        }
+                    };
+                
+                    // Index records end with the rowid of their row
+                    let rowid = match values.last() {
+                        Some(Value::Integer(rowid)) => *rowid,
+                        _ => {
+                            log_warn(&format!("Entry of index {} has no rowid", index));
+                            continue;
+                        }
+                    };
+                
+                    if let Some(covering) = covering {
+                        let mut row_values = vec![Value::Null; table_columns.len()];
+                        for &(column, position) in covering.iter() {
+                            row_values[column] = table_info.column_info[column].read_value(values.get(position).cloned());
+                        }
+                        for &column in rowid_columns.iter() {
+                            row_values[column] = Value::Integer(rowid);
                         }
-                        
-                        let value = values.get(i).cloned().unwrap_or(Value::Null);
-                        row.insert(column_name.clone(), value);
+                        return Ok(Some(Row::new(table_columns.clone(), row_values)));
                     }
-                    batch_rows.push(row);
-                    row_count += 1;
-                    
-                    // Process batch when full
-                    if batch_rows.len() >= BATCH_SIZE {
-                        rows.extend(batch_rows.drain(..));
-                        
-                        // Early termination check for LIMIT queries
-                        if let Some(limit_val) = limit {
-                            if rows.len() >= limit_val {
-                                break;
-                            }
+                
+                    let root_page = db.read_page(table_info.root_page)?;
+                    let cell = match db.cursor(root_page).find_cell(rowid, db)? {
+                        Some(cell) => cell,
+                        None => {
+                            log_warn(&format!("Row {} of index {} is missing from {}", rowid, index, table_info.name));
+                            continue;
+                        }
+                    };
+                    match parse_record_encoded(&cell.payload, db.header.encoding()) {
+                        Ok(values) => {
+                            return Ok(Some(table_row(table_columns, &table_info.column_info, rowid_columns, cell.key, values)));
                         }
+                        Err(e) => log_warn(&format!("Failed to parse row {} of {}: {}", cell.key, table_info.name, e)),
                     }
-                },
-                Err(e) => {
-                    log_warn(&format!("Failed to parse row {}: {}", row_count, e));
-                    // Continue with next row instead of failing
-                    continue;
                 }
+                Ok(None)
             }
+            RowSource::Buffered(rows) => Ok(rows.next()),
+            RowSource::Join(join) => join.next_row(db),
         }
-        
-        // Process remaining rows in batch
-        if !batch_rows.is_empty() {
-            rows.extend(batch_rows);
-        }
-        
-        log_debug(&format!("Batch table scan completed: {} rows read from {}", rows.len(), table_name));
-        Ok(rows)
     }
+}
 
-    /// Perform a full table scan to read all rows with optimized memory management
-    fn read_all_table_rows_optimized(&mut self, table_name: &str, limit: Option<usize>) -> Result<Vec<Row>> {
-        // Use batch processing for better performance
-        self.read_all_table_rows_batch(table_name, limit)
+/// A range of the entries of a rowid table's index, naming rows of the table
+pub(crate) struct IndexRange {
+    index: String,
+    root_page: u32,
+    range: KeyRange,
+}
+
+impl IndexRange {
+    pub(crate) fn new(index: &IndexInfo, range: KeyRange) -> Self {
+        IndexRange { index: index.name.clone(), root_page: index.root_page, range }
     }
-    
-    /// Find the INTEGER PRIMARY KEY column name for a table (if any)
-    fn find_rowid_column(&self, table_name: &str) -> Result<Option<String>> {
-        // Get the table info from cache
-        let table_info = self.schema_cache.get(table_name)
-            .ok_or_else(|| Error::TableNotFound(table_name.to_string()))?;
+}
+
+/// Read the next row in the rowid spans, with its rowid
+fn next_span_row(
+    db: &mut Database,
+    table_info: &TableInfo,
+    table_columns: &Arc<[String]>,
+    rowid_columns: &[usize],
+    spans: &mut alloc::vec::IntoIter<(i64, i64)>,
+    current: &mut Option<Box<(i64, BTreeCursor)>>,
+) -> Result<Option<(i64, Row)>> {
+    loop {
+        let (high, cursor) = match current {
+            Some(current) => &mut **current,
+            None => {
+                let (low, high) = match spans.next() {
+                    Some(span) => span,
+                    None => return Ok(None),
+                };
+                let root_page = db.read_page(table_info.root_page)?;
+                let mut cursor = db.cursor(root_page);
+                cursor.seek_rowid(low, db)?;
+                &mut **current.insert(Box::new((high, cursor)))
+            }
+        };
+        let high = *high;
         
-        // Parse the CREATE TABLE statement to find INTEGER PRIMARY KEY
-        let sql = &table_info.sql;
-        
-        // Simple pattern matching for INTEGER PRIMARY KEY
-        // This is a simplified approach - in a full implementation, we'd use a proper SQL parser
-        // This is a basic implementation that handles common cases
-        let sql_lower = sql.to_lowercase();
-        
-        // Look for patterns like "columnname integer primary key"
-        for line in sql_lower.lines() {
-            let line = line.trim();
-            if line.contains("integer") && line.contains("primary") && line.contains("key") {
-                // Extract column name - look for the first word before "integer"
-                let words: Vec<&str> = line.split_whitespace().collect();
-                for i in 0..words.len() {
-                    if words[i] == "integer" && i > 0 {
-                        let column_name = words[i-1].trim_matches(',').trim_matches('(').trim_matches('"').trim_matches('`');
-                        return Ok(Some(column_name.to_string()));
-                    }
-                }
+        let cell = match cursor.next_cell(db)? {
+            Some(cell) if cell.key <= high => cell,
+            _ => {
+                *current = None;
+                continue;
             }
+        };
+        // Stop at the last rowid without reading on into the next page
+        if cell.key == high {
+            *current = None;
         }
         
-        Ok(None)
-    }
-    
-    /// Execute a SELECT SQL query with index acceleration and table scan fallback
-    pub fn execute_query(&mut self, query: &SelectQuery) -> Result<Vec<Row>> {
-        let table_name = &query.table;
+        // Skip empty payloads (deleted rows)
+        if cell.payload.is_empty() {
+            continue;
+        }
         
-        // Get table info once and reuse
-        let table_info = self.schema_cache.get(table_name)
-            .ok_or_else(|| Error::TableNotFound(table_name.clone()))?;
+        match parse_record_encoded(&cell.payload, db.header.encoding()) {
+            Ok(values) => {
+                return Ok(Some((cell.key, table_row(table_columns, &table_info.column_info, rowid_columns, cell.key, values))));
+            }
+            Err(e) => log_warn(&format!("Failed to parse row {} of {}: {}", cell.key, table_info.name, e)),
+        }
+    }
+}
+
+/// Read the next row an entry of the index ranges names, with its rowid,
+/// seeking to the start of each range and walking forward until the first
+/// entry past its end
+fn next_index_range_row(
+    db: &mut Database,
+    table_info: &TableInfo,
+    table_columns: &Arc<[String]>,
+    rowid_columns: &[usize],
+    index_ranges: &mut alloc::vec::IntoIter<IndexRange>,
+    current: &mut Option<Box<(IndexRange, BTreeCursor)>>,
+) -> Result<Option<(i64, Row)>> {
+    let encoding = db.header.encoding();
+    loop {
+        let (index_range, cursor) = match current {
+            Some(current) => &mut **current,
+            None => {
+                let Some(index_range) = index_ranges.next() else {
+                    return Ok(None);
+                };
+                let cursor = db.seek_key_range(index_range.root_page, &index_range.range)?;
+                &mut **current.insert(Box::new((index_range, cursor)))
+            }
+        };
         
-        let table_info_clone = table_info.clone();
+        let Some(payload) = cursor.next_index_payload(db)? else {
+            *current = None;
+            continue;
+        };
+        let values = parse_record_encoded(&payload, encoding)
+            .map_err(|e| Error::InvalidFormat(format!("Failed to parse entry of index {}: {}", index_range.index, e)))?;
         
-        // Try index-based search first if we have a WHERE clause
-        if let Some(where_expr) = &query.where_expr {
-            if let Some(index_rows) = self.try_index_lookup(query, where_expr, &table_info_clone)? {
-                log_debug(&format!("Using index acceleration for query on table {}", table_name));
-                // Index lookup succeeded - apply remaining operations
-                return self.apply_query_operations(index_rows, query);
+        match index_range.range.position(&values, encoding) {
+            core::cmp::Ordering::Less => continue,
+            core::cmp::Ordering::Greater => {
+                *current = None;
+                continue;
             }
+            core::cmp::Ordering::Equal => {}
         }
         
-        // Fall back to table scan
-        log_debug(&format!("Using table scan fallback for query on table {}", table_name));
-        
-        // Use fast path for simple queries without WHERE clauses
-        let rows = if query.where_expr.is_none() && query.order_by.is_none() {
-            // Fast path for simple SELECT * queries
-            log_debug("Using fast table scan path");
-            self.read_table_rows_fast(table_name, query.limit)?
-        } else {
-            // Use optimized table scan for complex queries
-            self.read_all_table_rows_optimized(table_name, query.limit)?
+        // Index records end with the rowid of their row
+        let Some(&Value::Integer(rowid)) = values.last() else {
+            return Err(Error::InvalidFormat(format!("Entry of index {} has no rowid", index_range.index)));
         };
-        
-        // Apply query operations (WHERE, ORDER BY, LIMIT)
-        self.apply_query_operations(rows, query)
+        let root_page = db.read_page(table_info.root_page)?;
+        let Some(cell) = db.cursor(root_page).find_cell(rowid, db)? else {
+            return Err(Error::InvalidFormat(format!("Row {} of index {} is missing from {}", rowid, index_range.index, table_info.name)));
+        };
+        match parse_record_encoded(&cell.payload, encoding) {
+            Ok(record) => return Ok(Some((rowid, table_row(table_columns, &table_info.column_info, rowid_columns, rowid, record)))),
+            Err(e) => log_warn(&format!("Failed to parse row {} of {}: {}", rowid, table_info.name, e)),
+        }
+    }
+}
+
+impl RowIter<'_> {
+    /// Names of the columns in each returned row, in projection order
+    pub fn columns(&self) -> &[String] {
+        &self.projection.columns
     }
     
-    /// Try to use index lookup for the query, returning Some(rows) if successful, None if no suitable index
-    fn try_index_lookup(&mut self, query: &SelectQuery, where_expr: &Expr, table_info: &TableInfo) -> Result<Option<Vec<Row>>> {
-        let table_name = &query.table;
-        let columns = &table_info.columns;
-        let or_branches = collect_or_branches(where_expr);
-        
-        // Process each OR branch to find usable indexes
-        let mut all_rowids = std::collections::HashSet::new();
-        let mut found_usable_index = false;
-        
-        for branch in or_branches.iter() {
-            if let Some((index, values)) = find_best_index(table_info, branch) {
-                found_usable_index = true;
-                log_debug(&format!("Found usable index '{}' for query condition", index.name));
-                
-                // Process this index branch directly
-                let index_root_page = self.read_page(index.root_page)?;
-                let mut cursor = BTreeCursor::new(index_root_page);
-                
-                // Convert Vec<&Value> to Vec<&Value> for find_rowids_by_key
-                let value_refs: Vec<&Value> = values.iter().map(|&v| v).collect();
-                let page_reader = |page_num: u32| self.read_page(page_num);
-                let rowids = cursor.find_rowids_by_key(&value_refs, page_reader)?;
-                all_rowids.extend(rowids);
+    /// Finish a query whose rows this iterator reads without ORDER BY or
+    /// LIMIT, holding every column ORDER BY may refer to: sort and limit them
+    /// as `query` asks, then project them onto its output columns
+    fn ordered(mut self, query: &SelectQuery, projection: Projection) -> Result<Self> {
+        if let Some(order_by) = &query.order_by {
+            // Group rows are typed by their plan, and name the ORDER BY term
+            // as a column; other rows are the candidate rows
+            let (key, context) = match &self.grouping {
+                Some(grouping) => (ScalarExpr::Column(order_by.column.clone()), grouping.plan.group_context().clone()),
+                None if query.is_aggregate() => (ScalarExpr::Column(order_by.column.clone()), self.context.with_columns(Vec::new())),
+                None => {
+                    let key = query.order_key(&self.projection.columns)?;
+                    (key.unwrap_or_else(|| ScalarExpr::Column(order_by.column.clone())), self.context.clone())
+                }
+            };
+            let mut rows = self.by_ref().collect::<Result<Vec<_>>>()?;
+            eval::sort_rows(&mut rows, &key, order_by.ascending, &context)?;
+            if let Some(limit) = query.limit {
+                rows.truncate(limit);
             }
+            self.source = RowSource::Buffered(rows.into_iter());
+            self.grouping = None;
+            self.returned = 0;
+            self.done = false;
         }
-        
-        // If no branches could use an index, return None to trigger table scan
-        if !found_usable_index {
-            log_debug("No suitable index found for query conditions, will use table scan");
-            return Ok(None);
-        }
-        
-        // Convert rowids to a vec for deterministic ordering
-        let mut all_rowids: Vec<_> = all_rowids.into_iter().collect();
-        all_rowids.sort(); // Ensure deterministic results
-        let mut rows = Vec::with_capacity(all_rowids.len());
-        
-        // Fetch each matching row by its ROWID using targeted lookups
-        for rowid in all_rowids {
-            if let Some(row) = self.read_row_by_rowid(table_name, rowid, columns)? {
-                rows.push(row);
+        self.query = query.clone();
+        self.projection = projection;
+        Ok(self)
+    }
+    
+    /// Read the next candidate row, before WHERE filtering
+    fn next_candidate(&mut self) -> Result<Option<Row>> {
+        let RowIter { db, table_info, table_columns, rowid_columns, source, .. } = self;
+        source.next_row(db, table_info, table_columns, rowid_columns)
+    }
+    
+    /// Read the next candidate row that matches the WHERE clause
+    pub(crate) fn next_match(&mut self) -> Result<Option<Row>> {
+        while let Some(row) = self.next_candidate()? {
+            // Buffered rows were filtered before sorting or aggregating
+            if matches!(self.source, RowSource::Buffered(_)) {
+                return Ok(Some(row));
+            }
+            match &self.query.where_expr {
+                Some(where_expr) if !self.query.evaluate_expr_in(&row, where_expr, &self.context)? => {}
+                _ => return Ok(Some(row)),
             }
         }
-        
-        log_debug(&format!("Index lookup found {} rows", rows.len()));
-        Ok(Some(rows))
+        Ok(None)
     }
     
-    /// Read a single row by its ROWID using targeted binary search
-    fn read_row_by_rowid(&mut self, table_name: &str, rowid: i64, columns: &[String]) -> Result<Option<Row>> {
-        // Get the table's root page from cache
-        let table_info = self.schema_cache.get(table_name)
-            .ok_or_else(|| Error::TableNotFound(table_name.to_string()))?;
-        
-        // Detect INTEGER PRIMARY KEY column
-        let rowid_column = self.find_rowid_column(table_name)?;
-        
-        let root_page = self.read_page(table_info.root_page)?;
-        let mut cursor = BTreeCursor::new(root_page);
-
-        // Try to find the cell with the matching ROWID using binary search
-        match cursor.find_cell(rowid, |page_num| self.read_page(page_num)) {
-            Ok(Some(cell)) => {
-                // Parse the row data
-                match parse_record(&cell.payload) {
-                    Ok(values) => {
-                        // Convert to a row with column names
-                        let mut row = HashMap::new();
-                        for (i, column_name) in columns.iter().enumerate() {
-                            if let Some(ref rowid_col) = rowid_column {
-                                if column_name == rowid_col {
-                                    // This is the INTEGER PRIMARY KEY column - use rowid from cell
-                                    row.insert(column_name.clone(), Value::Integer(cell.key));
-                                    continue;
-                                }
-                            }
-                            
-                            let value = values.get(i).cloned().unwrap_or(Value::Null);
-                            row.insert(column_name.clone(), value);
-                        }
-                        
-                        Ok(Some(row))
-                    },
-                    Err(_) => {
-                        // Return None instead of failing to allow processing to continue with other rows
-                        Ok(None)
-                    }
-                }
-            },
-            Ok(None) => {
-                Ok(None)
-            },
-            Err(e) => {
-                Err(e)
+    /// Read the next row of the query: the next row matching the WHERE clause,
+    /// or for aggregate queries the next group row that satisfies HAVING
+    fn next_row(&mut self) -> Result<Option<Row>> {
+        match self.grouping.take() {
+            Some(mut grouping) => {
+                let row = grouping.next_row(self);
+                self.grouping = Some(grouping);
+                row
             }
+            None => self.next_match(),
         }
     }
-
-    /// Fast table scan for simple queries without WHERE clauses
-    fn read_table_rows_fast(&mut self, table_name: &str, limit: Option<usize>) -> Result<Vec<Row>> {
-        log_debug(&format!("Performing fast table scan for table: {}", table_name));
-        
-        // Use the high-performance optimized version
-        self.read_table_rows_optimized_v2(table_name, limit)
+    
+    /// Read the candidate rows again from the start
+    pub(crate) fn restart(&mut self) -> Result<()> {
+        if let RowSource::Join(join) = &mut self.source {
+            return join.restart(self.db);
+        }
+        let rowid_column = self.table_info.rowid_alias.clone();
+        self.source = self.db.plan_row_source(&self.query, &self.table_info, rowid_column.as_deref())?;
+        Ok(())
     }
+}
 
-    /// Apply query operations (WHERE, ORDER BY, LIMIT) to a set of rows
-    fn apply_query_operations(&self, mut rows: Vec<Row>, query: &SelectQuery) -> Result<Vec<Row>> {
-        // Apply WHERE clause
-        if let Some(where_expr) = &query.where_expr {
-            rows.retain(|row| {
-                // Use the query's evaluate_expr method
-                query.evaluate_expr(row, where_expr)
-            });
-        }
-        
-        // Apply ORDER BY
-        if let Some(order_by) = &query.order_by {
-            rows.sort_by(|a, b| {
-                let val_a = a.get(&order_by.column).unwrap_or(&Value::Null);
-                let val_b = b.get(&order_by.column).unwrap_or(&Value::Null);
-                
-                let cmp = match (val_a, val_b) {
-                    (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
-                    (Value::Real(a), Value::Real(b)) => a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
-                    (Value::Text(a), Value::Text(b)) => a.cmp(b),
-                    (Value::Null, Value::Null) => std::cmp::Ordering::Equal,
-                    (Value::Null, _) => std::cmp::Ordering::Less,
-                    (_, Value::Null) => std::cmp::Ordering::Greater,
-                    _ => std::cmp::Ordering::Equal,
-                };
-                
-                if order_by.ascending { cmp } else { cmp.reverse() }
-            });
+impl Iterator for RowIter<'_> {
+    type Item = Result<Row>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.query.limit.is_some_and(|limit| self.returned >= limit) {
+            return None;
         }
         
-        // Apply LIMIT
-        if let Some(limit) = query.limit {
-            rows.truncate(limit);
-        }
+        let row = match self.next_row() {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
         
         // Apply column selection
-        if let Some(ref columns) = query.columns {
-            if !columns.is_empty() && columns != &vec!["*"] {
-                for row in &mut rows {
-                    row.retain(|col_name, _| columns.contains(col_name));
-                }
+        let row = match self.projection.apply(row, &self.context) {
+            Ok(row) => row,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
             }
-        }
+        };
         
-        Ok(rows)
+        self.returned += 1;
+        if let Err(e) = self.db.check_row_limit(self.returned) {
+            self.done = true;
+            return Some(Err(e));
+        }
+        Some(Ok(row))
     }
+}
 
-    /// High-performance table scan that minimizes allocations
-    fn read_table_rows_optimized_v2(&mut self, table_name: &str, limit: Option<usize>) -> Result<Vec<Row>> {
-        log_debug(&format!("Performing high-performance table scan for table: {}", table_name));
-        
-        // Get table info from cache
-        let table_info = self.schema_cache.get(table_name)
-            .ok_or_else(|| Error::TableNotFound(table_name.to_string()))?;
-        
-        let columns = table_info.columns.clone();
-        let root_page_num = table_info.root_page;
-        
-        // Pre-allocate with estimated capacity
-        let estimated_rows = limit.unwrap_or(100_000).min(100_000);
-        let mut rows = Vec::with_capacity(estimated_rows);
-        
-        // Pre-intern column names once
-        let interned_columns: Vec<String> = columns.iter().map(|col| {
-            if !self.column_name_cache.contains_key(col) {
-                self.column_name_cache.insert(col.clone(), col.clone());
+/// Build a row of a rowid table, taking the rowid alias column and rowid
+/// pseudo-columns from the cell key
+fn table_row(
+    columns: &Arc<[String]>,
+    column_info: &[ColumnInfo],
+    rowid_columns: &[usize],
+    rowid: i64,
+    values: Vec<Value>,
+) -> Row {
+    // Records written before ALTER TABLE ADD COLUMN end before the added columns
+    let mut values = values.into_iter();
+    let values = column_info.iter().map(|info| info.read_value(values.next())).collect();
+    let mut row = Row::new(columns.clone(), values);
+    for &index in rowid_columns {
+        row.values_mut()[index] = Value::Integer(rowid);
+    }
+    row
+}
+
+/// Output columns of a query, resolved against the table's columns
+struct Projection {
+    /// Names of the output columns
+    columns: Arc<[String]>,
+    /// How each output column is computed, unless every column is selected as-is
+    sources: Option<Vec<OutputColumn>>,
+}
+
+/// How an output column is computed from a full row
+pub(crate) enum OutputColumn {
+    /// The column at this position
+    Column(usize),
+    /// An expression over the row's columns
+    Computed(ScalarExpr),
+}
+
+impl Projection {
+    /// Resolve the SELECT list of `query`, where `*` stands for the first
+    /// `declared` table columns
+    fn resolve(query: &SelectQuery, table_columns: &Arc<[String]>, declared: usize) -> Result<Projection> {
+        if let Some(result_columns) = &query.result_columns {
+            let mut names = Vec::new();
+            let mut sources = Vec::new();
+            for result in result_columns {
+                let mut referenced = Vec::new();
+                collect_scalar_columns(&result.expr, &mut referenced);
+                if let Some(column) = referenced.into_iter().find(|column| column.index_in(table_columns).is_none()) {
+                    return Err(Error::ColumnNotFound(column.to_string()));
+                }
+                names.push(result.name.clone());
+                sources.push(match &result.expr {
+                    ScalarExpr::Column(column) => OutputColumn::Column(column.index_in(table_columns).unwrap_or_default()),
+                    expr => OutputColumn::Computed(expr.clone()),
+                });
             }
-            col.clone()
-        }).collect();
-        
-        // Read root page
-        let root_page = self.read_page(root_page_num)?;
-        let mut cursor = BTreeCursor::new(root_page);
-        let mut count = 0;
-        
-        // Safety limits
-        const MAX_ROWS: usize = 1_000_000;
-        const MAX_ITERATIONS: usize = 10_000_000;
-        
-        let row_limit = limit.unwrap_or(MAX_ROWS).min(MAX_ROWS);
+            return Ok(Projection { columns: names.into(), sources: Some(sources) });
+        }
         
-        // Pre-allocate reusable row HashMap
-        let mut reusable_row = HashMap::with_capacity(columns.len());
+        let star = [String::from("*")];
+        let selected = match &query.columns {
+            Some(selected) if !selected.is_empty() && selected.iter().any(|col| col != "*") => selected.as_slice(),
+            // Rowid pseudo-columns are not part of `*`
+            _ if table_columns.len() > declared => &star,
+            _ => return Ok(Projection { columns: table_columns.clone(), sources: None }),
+        };
         
-        for iteration in 0..MAX_ITERATIONS {
-            if count >= row_limit {
-                log_debug(&format!("Reached row limit: {}", row_limit));
-                break;
+        let mut names = Vec::new();
+        let mut sources = Vec::new();
+        for column in selected {
+            if column == "*" {
+                names.extend(table_columns[..declared].iter().cloned());
+                sources.extend((0..declared).map(OutputColumn::Column));
+                continue;
             }
-            
-            match cursor.next_cell(|page_num| self.read_page(page_num)) {
-                Ok(Some(cell)) => {
-                    // Parse record with minimal allocations using optimized parser
-                    if let Ok(values) = crate::record::parse_record_optimized(&cell.payload) {
-                        if values.len() <= columns.len() {
-                            reusable_row.clear();
-                            
-                            // Fill row with minimal string allocations
-                            for (i, col_name) in interned_columns.iter().enumerate() {
-                                let value = if i < values.len() {
-                                    values[i].clone()
-                                } else {
-                                    Value::Null
-                                };
-                                reusable_row.insert(col_name.clone(), value);
-                            }
-                            
-                            // Clone the completed row
-                            rows.push(reusable_row.clone());
-                            count += 1;
-                        }
-                    }
-                }
-                Ok(None) => {
-                    log_debug("High-performance table scan completed - no more cells");
-                    break;
-                }
-                Err(e) => {
-                    log_warn(&format!("Error reading cell in high-perf scan (iteration {}): {}", iteration, e));
-                    break;
-                }
+            let index = column
+                .index_in(table_columns)
+                .ok_or_else(|| Error::ColumnNotFound(column.clone()))?;
+            names.push(column.clone());
+            sources.push(OutputColumn::Column(index));
+        }
+        Ok(Projection { columns: names.into(), sources: Some(sources) })
+    }
+    
+    /// Turn a full table row, typed by `context`, into an output row
+    fn apply(&self, row: Row, context: &Context) -> Result<Row> {
+        match &self.sources {
+            Some(sources) => {
+                let operands = RowOperands { row: &row, context };
+                let values = sources
+                    .iter()
+                    .map(|source| match source {
+                        OutputColumn::Column(index) => Ok(row.values()[*index].clone()),
+                        OutputColumn::Computed(expr) => Ok(eval::evaluate(expr, &operands, context.encoding)?.into_owned()),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Row::new(self.columns.clone(), values))
             }
+            None => Ok(row),
         }
-        
-        log_debug(&format!("High-performance table scan completed: {} rows processed", count));
-        Ok(rows)
     }
-} // end impl Database
+}
 
 /// Collect all branches of an OR expression.
 /// Collect all branches of an OR expression.
@@ -919,46 +2014,333 @@ fn collect_or_branches(expr: &Expr) -> Vec<&Expr> {
     branches
 }
 
-/// Find the best index for a WHERE clause, supporting composite indexes.
-fn find_best_index<'a, 'b>(
+/// Find the best index for a WHERE clause and the range of its keys to scan.
+///
+/// Composite indexes match an equality prefix, optionally followed by bounds on
+/// the next column. Partial indexes are only chosen when the clause implies
+/// their predicate.
+fn find_best_index<'a>(
     table_info: &'a TableInfo,
-    expr: &'b Expr,
-) -> Option<(&'a IndexInfo, Vec<&'b Value>)> {
-    let mut conditions = HashMap::new();
-    collect_and_conditions(expr, &mut conditions);
+    expr: &Expr,
+    encoding: TextEncoding,
+) -> Option<(&'a IndexInfo, KeyRange)> {
+    let terms = collect_and_terms(expr);
 
-    let mut best_index: Option<(&'a IndexInfo, Vec<&'b Value>)> = None;
-    let mut max_len = 0;
+    let mut best_index: Option<(&'a IndexInfo, KeyRange)> = None;
+    let mut best_score = (0, 0);
 
     for index in &table_info.indexes {
-        let mut values = Vec::new();
-
-        // For composite indexes, we can use prefix matching
-        // We need consecutive columns starting from the first column
-        for col in &index.columns {
-            match conditions.get(col) {
-                Some(value) => {
-                    values.push(*value);
+        if index.is_partial() {
+            match &index.predicate_expr {
+                Some(predicate) if implies(&terms, predicate, encoding) => {}
+                _ => continue,
+            }
+        }
+        
+        // Seeks compare keys in ascending BINARY order, and queries only
+        // compare plain columns, so the usable key columns end at the first
+        // part that is anything else
+        let columns: Vec<&str> = index
+            .columns
+            .iter()
+            .map_while(|part| part.column_name().filter(|_| part.is_seekable()))
+            .collect();
+        
+        if let Some(range) = key_range(table_info, &columns, expr) {
+            // Prefer the longest equality prefix, then the most bounds
+            let bounds = usize::from(range.lower.is_some()) + usize::from(range.upper.is_some() || range.like_prefix.is_some());
+            let score = (range.prefix.len(), bounds);
+            if score > best_score {
+                best_index = Some((index, range));
+                best_score = score;
+            }
+        }
+    }
+
+    best_index
+}
+
+/// Collect the conditions of an AND expression tree
+pub(crate) fn collect_and_terms(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::And(left, right) => {
+            let mut terms = collect_and_terms(left);
+            terms.extend(collect_and_terms(right));
+            terms
+        }
+        _ => vec![expr],
+    }
+}
+
+/// Whether `column` names the rowid of a rowid table, unless a declared column shadows it
+pub(crate) fn is_rowid_pseudo_column(column: &str) -> bool {
+    ["rowid", "_rowid_", "oid"].iter().any(|name| name.eq_ignore_ascii_case(column))
+}
+
+/// Rowid pseudo-columns a query refers to in its SELECT list, WHERE clause or ORDER BY
+fn rowid_pseudo_columns(query: &SelectQuery) -> Vec<String> {
+    let mut referenced: Vec<&str> = Vec::new();
+    if let Some(columns) = &query.columns {
+        referenced.extend(columns.iter().map(String::as_str));
+    }
+    for column in query.result_columns.iter().flatten() {
+        collect_scalar_columns(&column.expr, &mut referenced);
+    }
+    for key in query.group_by.iter().flatten() {
+        collect_scalar_columns(key, &mut referenced);
+    }
+    for expr in [&query.where_expr, &query.having].into_iter().flatten() {
+        collect_expr_columns(expr, &mut referenced);
+    }
+    // ORDER BY may name an expression after its SQL text
+    let order_expr = query.order_by.as_ref().and_then(|order_by| SelectQuery::parse_expression_text(&order_by.column));
+    match &order_expr {
+        Some(expr) => collect_scalar_columns(expr, &mut referenced),
+        None => referenced.extend(query.order_by.as_ref().map(|order_by| order_by.column.as_str())),
+    }
+    
+    let mut names: Vec<String> = Vec::new();
+    for column in referenced {
+        if is_rowid_pseudo_column(column) && !names.iter().any(|name| name.eq_ignore_ascii_case(column)) {
+            names.push(column.to_string());
+        }
+    }
+    names
+}
+
+/// Collect the columns an expression refers to
+pub(crate) fn collect_expr_columns<'a>(expr: &'a Expr, columns: &mut Vec<&'a str>) {
+    match expr {
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            collect_expr_columns(left, columns);
+            collect_expr_columns(right, columns);
+        }
+        Expr::Not(inner) => collect_expr_columns(inner, columns),
+        Expr::Comparison { column, .. }
+        | Expr::In { column, .. }
+        | Expr::Between { column, .. }
+        | Expr::IsNull(column)
+        | Expr::IsNotNull(column) => columns.push(column),
+        Expr::ColumnComparison { left, right, .. } => {
+            columns.push(left);
+            columns.push(right);
+        }
+        Expr::Scalar(expr) => collect_scalar_columns(expr, columns),
+    }
+}
+
+/// Collect the columns a SELECT list expression refers to
+pub(crate) fn collect_scalar_columns<'a>(expr: &'a ScalarExpr, columns: &mut Vec<&'a str>) {
+    match expr {
+        ScalarExpr::Column(column) => columns.push(column),
+        expr => expr.children().into_iter().for_each(|child| collect_scalar_columns(child, columns)),
+    }
+}
+
+/// Rowids the conditions of an AND expression restrict the rows to, from the
+/// comparisons, IN lists and BETWEENs on columns for which `is_rowid` holds
+///
+/// Returns inclusive rowid spans and whether they are single rowids, or None
+/// if no condition bounds the rowid or one compares it with a non-numeric value.
+fn branch_rowid_spans(expr: &Expr, is_rowid: impl Fn(&str) -> bool) -> Option<(Vec<(i64, i64)>, bool)> {
+    use ComparisonOperator::*;
+    
+    // Bounds are widened so REAL values just past the i64 range stay exact
+    let mut low = i128::from(i64::MIN);
+    let mut high = i128::from(i64::MAX);
+    let mut points: Option<BTreeSet<i64>> = None;
+    let mut restricted = false;
+    
+    let restrict_points = |values: &[Value], points: &mut Option<BTreeSet<i64>>| -> Option<()> {
+        let mut set = BTreeSet::new();
+        for value in values {
+            match value {
+                Value::Integer(v) => {
+                    set.insert(*v);
                 }
-                None => {
-                    break;
+                // Only integral REALs can equal a rowid
+                Value::Real(v) => {
+                    if *v >= i64::MIN as f64 && *v < i64::MAX as f64 && (*v as i64) as f64 == *v {
+                        set.insert(*v as i64);
+                    }
                 }
+                // NULL matches nothing
+                Value::Null => {}
+                _ => return None,
             }
         }
-
-        // We can use an index if we have at least one matching column from the prefix
-        if !values.is_empty() {
-            // Prefer the index that covers the most columns (ties resolved arbitrarily)
-            if values.len() > max_len {
-                best_index = Some((index, values.clone()));
-                max_len = values.len();
+        *points = Some(match points.take() {
+            Some(existing) => existing.intersection(&set).copied().collect(),
+            None => set,
+        });
+        Some(())
+    };
+    
+    for term in collect_and_terms(expr) {
+        match term {
+            Expr::Comparison { column, operator, value } if is_rowid(column) => {
+                match operator {
+                    Equal => restrict_points(core::slice::from_ref(value), &mut points)?,
+                    GreaterThan => low = low.max(rowid_lower_bound(value, false)?),
+                    GreaterThanOrEqual => low = low.max(rowid_lower_bound(value, true)?),
+                    LessThan => high = high.min(rowid_upper_bound(value, false)?),
+                    LessThanOrEqual => high = high.min(rowid_upper_bound(value, true)?),
+                    NotEqual | Like => continue,
+                }
+                restricted = true;
+            }
+            Expr::In { column, values } if is_rowid(column) => {
+                restrict_points(values, &mut points)?;
+                restricted = true;
             }
+            Expr::Between { column, low: between_low, high: between_high } if is_rowid(column) => {
+                low = low.max(rowid_lower_bound(between_low, true)?);
+                high = high.min(rowid_upper_bound(between_high, true)?);
+                restricted = true;
+            }
+            _ => {}
+        }
+    }
+    
+    if !restricted {
+        return None;
+    }
+    match points {
+        Some(points) => {
+            let spans = points
+                .into_iter()
+                .filter(|&rowid| (low..=high).contains(&i128::from(rowid)))
+                .map(|rowid| (rowid, rowid))
+                .collect();
+            Some((spans, true))
         }
+        // Contradictory bounds select nothing, which is as cheap as single rowids
+        None if low > high => Some((Vec::new(), true)),
+        None => Some((vec![(low as i64, high as i64)], false)),
     }
+}
 
+/// Smallest integer that is greater than `value`, or equal to it if `inclusive`
+fn rowid_lower_bound(value: &Value, inclusive: bool) -> Option<i128> {
+    match value {
+        Value::Integer(v) => Some(i128::from(*v) + i128::from(!inclusive)),
+        Value::Real(v) if !v.is_nan() => Some(if inclusive { real_ceil(*v) } else { real_floor(*v).saturating_add(1) }),
+        _ => None,
+    }
+}
 
+/// Largest integer that is less than `value`, or equal to it if `inclusive`
+fn rowid_upper_bound(value: &Value, inclusive: bool) -> Option<i128> {
+    match value {
+        Value::Integer(v) => Some(i128::from(*v) - i128::from(!inclusive)),
+        Value::Real(v) if !v.is_nan() => Some(if inclusive { real_floor(*v) } else { real_ceil(*v).saturating_sub(1) }),
+        _ => None,
+    }
+}
 
-    best_index
+/// Largest integer not greater than `v`, saturating at the bounds of i128
+///
+/// `f64::floor` needs std, so this corrects the truncation toward zero instead.
+fn real_floor(v: f64) -> i128 {
+    let truncated = v as i128;
+    if (truncated as f64) > v { truncated.saturating_sub(1) } else { truncated }
+}
+
+/// Smallest integer not less than `v`, saturating at the bounds of i128
+fn real_ceil(v: f64) -> i128 {
+    let truncated = v as i128;
+    if (truncated as f64) < v { truncated.saturating_add(1) } else { truncated }
+}
+
+/// Whether every row satisfying all of `terms` also satisfies `predicate`.
+///
+/// This is a conservative check: it returns false whenever the implication
+/// cannot be shown from the individual conditions.
+fn implies(terms: &[&Expr], predicate: &Expr, encoding: TextEncoding) -> bool {
+    match predicate {
+        Expr::And(left, right) => implies(terms, left, encoding) && implies(terms, right, encoding),
+        Expr::Or(left, right) => implies(terms, left, encoding) || implies(terms, right, encoding),
+        Expr::Between { column, low, high } => {
+            let lower = Expr::Comparison { column: column.clone(), operator: ComparisonOperator::GreaterThanOrEqual, value: low.clone() };
+            let upper = Expr::Comparison { column: column.clone(), operator: ComparisonOperator::LessThanOrEqual, value: high.clone() };
+            implies(terms, &lower, encoding) && implies(terms, &upper, encoding)
+        }
+        _ => terms.iter().any(|term| *term == predicate || term_implies(term, predicate, encoding)),
+    }
+}
+
+/// Whether rows satisfying the single condition `term` all satisfy `predicate`
+fn term_implies(term: &Expr, predicate: &Expr, encoding: TextEncoding) -> bool {
+    use core::cmp::Ordering;
+    use ComparisonOperator::*;
+    
+    // Values are only compared within a storage class, where SQLite's
+    // comparison does not depend on column affinity
+    let compare = |a: &Value, b: &Value| -> Option<Ordering> {
+        let numeric = |v: &Value| matches!(v, Value::Integer(_) | Value::Real(_));
+        let text = |v: &Value| matches!(v, Value::Text(_));
+        if (numeric(a) && numeric(b)) || (text(a) && text(b)) {
+            Some(encoding.compare(a, b))
+        } else {
+            None
+        }
+    };
+    // Whether `value op bound` holds
+    let satisfies = |value: &Value, op: &ComparisonOperator, bound: &Value| -> bool {
+        match (compare(value, bound), op) {
+            (Some(ord), Equal) => ord == Ordering::Equal,
+            (Some(ord), NotEqual) => ord != Ordering::Equal,
+            (Some(ord), LessThan) => ord == Ordering::Less,
+            (Some(ord), LessThanOrEqual) => ord != Ordering::Greater,
+            (Some(ord), GreaterThan) => ord == Ordering::Greater,
+            (Some(ord), GreaterThanOrEqual) => ord != Ordering::Less,
+            _ => false,
+        }
+    };
+    // Values a term restricts its column to, when it is a finite set
+    let candidates = |term: &Expr| -> Option<Vec<Value>> {
+        match term {
+            Expr::Comparison { operator: Equal, value, .. } => Some(vec![value.clone()]),
+            Expr::In { values, .. } => Some(values.iter().filter(|v| !v.is_null()).cloned().collect()),
+            _ => None,
+        }
+    };
+    let term_column = match term {
+        Expr::Comparison { column, value, .. } if !value.is_null() => column,
+        Expr::In { column, .. } | Expr::Between { column, .. } | Expr::IsNotNull(column) => column,
+        _ => return false,
+    };
+    
+    match predicate {
+        // Any comparison with a value rules out NULL
+        Expr::IsNotNull(column) => column == term_column,
+        Expr::Comparison { column, operator, value } if column == term_column && operator != &Like => {
+            if let Some(values) = candidates(term) {
+                return values.iter().all(|v| satisfies(v, operator, value));
+            }
+            // Otherwise compare the term's bound with the predicate's
+            let (bound, inclusive, lower) = match (term, operator) {
+                (Expr::Comparison { operator: GreaterThan, value, .. }, GreaterThan | GreaterThanOrEqual) => (value, false, true),
+                (Expr::Comparison { operator: GreaterThanOrEqual, value, .. }, GreaterThan | GreaterThanOrEqual) => (value, true, true),
+                (Expr::Between { low, .. }, GreaterThan | GreaterThanOrEqual) => (low, true, true),
+                (Expr::Comparison { operator: LessThan, value, .. }, LessThan | LessThanOrEqual) => (value, false, false),
+                (Expr::Comparison { operator: LessThanOrEqual, value, .. }, LessThan | LessThanOrEqual) => (value, true, false),
+                (Expr::Between { high, .. }, LessThan | LessThanOrEqual) => (high, true, false),
+                _ => return false,
+            };
+            let predicate_inclusive = matches!(operator, GreaterThanOrEqual | LessThanOrEqual);
+            match compare(bound, value) {
+                Some(Ordering::Equal) => predicate_inclusive || !inclusive,
+                Some(Ordering::Greater) => lower,
+                Some(Ordering::Less) => !lower,
+                None => false,
+            }
+        }
+        Expr::In { column, values } if column == term_column => candidates(term).is_some_and(|candidates| {
+            candidates.iter().all(|c| values.iter().any(|v| satisfies(c, &Equal, v)))
+        }),
+        _ => false,
+    }
 }
 
 /// Collect all equality conditions from an AND expression tree.
@@ -985,6 +2367,9 @@ fn collect_and_conditions<'b>(expr: &'b Expr, conditions: &mut HashMap<String, &
                 }
             }
         },
+        Expr::ColumnComparison { .. } => {
+            // Skip comparisons between columns, whose values vary by row
+        },
         Expr::IsNull(_) => {
             // Skip IS NULL conditions
         },
@@ -997,10 +2382,183 @@ fn collect_and_conditions<'b>(expr: &'b Expr, conditions: &mut HashMap<String, &
         Expr::Between { .. } => {
             // Skip BETWEEN conditions for now (they're not equality conditions)
         }
+        Expr::Scalar(_) => {
+            // Skip general expressions, which no key range can serve
+        }
+    }
+}
+
+
+
+/// Range of keys in an index: an equality prefix on the leading key columns,
+/// optionally followed by bounds on the next key column
+#[derive(Debug, Default)]
+pub(crate) struct KeyRange {
+    prefix: Vec<Value>,
+    /// Lower bound and whether it is inclusive
+    lower: Option<(Value, bool)>,
+    /// Upper bound and whether it is inclusive
+    upper: Option<(Value, bool)>,
+    /// Text the next key column starts with, from `LIKE 'prefix%'`
+    like_prefix: Option<String>,
+}
+
+impl KeyRange {
+    /// The keys that start with `prefix`
+    pub(crate) fn prefix(prefix: Vec<Value>) -> Self {
+        KeyRange { prefix, ..KeyRange::default() }
+    }
+    
+    /// Where a record lies relative to the range: `Less` before it, `Equal`
+    /// inside it, `Greater` past its end
+    fn position(&self, values: &[Value], encoding: TextEncoding) -> core::cmp::Ordering {
+        use core::cmp::Ordering;
+        
+        match encoding.compare_keys(values, &self.prefix) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+        
+        let value = match values.get(self.prefix.len()) {
+            Some(value) => value,
+            None => return Ordering::Equal,
+        };
+        
+        // NULL never satisfies a bound, and sorts before every other value
+        let bounded = self.lower.is_some() || self.upper.is_some() || self.like_prefix.is_some();
+        if bounded && value.is_null() {
+            return Ordering::Less;
+        }
+        
+        if let Some((low, inclusive)) = &self.lower {
+            match encoding.compare(value, low) {
+                Ordering::Less => return Ordering::Less,
+                Ordering::Equal if !inclusive => return Ordering::Less,
+                _ => {}
+            }
+        }
+        if let Some((high, inclusive)) = &self.upper {
+            match encoding.compare(value, high) {
+                Ordering::Greater => return Ordering::Greater,
+                Ordering::Equal if !inclusive => return Ordering::Greater,
+                _ => {}
+            }
+        }
+        // Text starting with the prefix sorts together, right from the prefix itself
+        if let Some(prefix) = &self.like_prefix {
+            match value {
+                Value::Text(text) if text.starts_with(prefix.as_str()) => {}
+                _ => return Ordering::Greater,
+            }
+        }
+        
+        Ordering::Equal
+    }
+}
+
+/// Build the PRIMARY KEY range of a WITHOUT ROWID table implied by an AND-only
+/// expression, or None if the expression does not constrain the leading key column
+fn primary_key_range(table_info: &TableInfo, expr: &Expr) -> Option<KeyRange> {
+    key_range(table_info, &table_info.seekable_primary_key().collect::<Vec<_>>(), expr)
+}
+
+/// Give the literals compared with the columns of `columns` the affinity of
+/// the column, as SQLite converts them before comparing, so that lookups seek
+/// the values the comparisons match
+pub(crate) fn apply_column_affinity(expr: &mut Expr, columns: &[String], context: &Context) {
+    let affinity = |column: &str| column.index_in(columns).map(|index| context.column_type(index).affinity);
+    match expr {
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            apply_column_affinity(left, columns, context);
+            apply_column_affinity(right, columns, context);
+        }
+        Expr::Not(inner) => apply_column_affinity(inner, columns, context),
+        Expr::Comparison { operator: ComparisonOperator::Like, .. } => {}
+        Expr::Comparison { column, value, .. } => {
+            if let Some(affinity) = affinity(column) {
+                *value = affinity.apply(core::mem::replace(value, Value::Null));
+            }
+        }
+        Expr::In { column, values } => {
+            if let Some(affinity) = affinity(column) {
+                values.iter_mut().for_each(|value| *value = affinity.apply(core::mem::replace(value, Value::Null)));
+            }
+        }
+        Expr::Between { column, low, high } => {
+            if let Some(affinity) = affinity(column) {
+                *low = affinity.apply(core::mem::replace(low, Value::Null));
+                *high = affinity.apply(core::mem::replace(high, Value::Null));
+            }
+        }
+        Expr::ColumnComparison { .. } | Expr::IsNull(_) | Expr::IsNotNull(_) | Expr::Scalar(_) => {}
     }
 }
 
+/// Build the range of keys over `columns` of `table_info` implied by an
+/// AND-only expression, or None if the expression does not constrain the
+/// leading column
+fn key_range(table_info: &TableInfo, columns: &[&str], expr: &Expr) -> Option<KeyRange> {
+    let mut conditions = HashMap::new();
+    collect_and_conditions(expr, &mut conditions);
+    
+    let mut range = KeyRange::default();
+    for col in columns {
+        match conditions.get(*col) {
+            Some(value) => range.prefix.push((*value).clone()),
+            None => {
+                // Only TEXT columns hold nothing but text for a prefix to bound;
+                // numbers and blobs that LIKE reads as text sort elsewhere
+                let text = col
+                    .index_in(&table_info.columns)
+                    .and_then(|index| table_info.column_info.get(index))
+                    .is_some_and(|info| info.affinity == Affinity::Text);
+                collect_bounds(expr, col, text, &mut range);
+                break;
+            }
+        }
+    }
+    
+    if range.prefix.is_empty() && range.lower.is_none() && range.upper.is_none() {
+        None
+    } else {
+        Some(range)
+    }
+}
 
+/// Collect range bounds on `column` from an AND expression tree, including
+/// the prefix of LIKE patterns if the column has TEXT affinity
+fn collect_bounds(expr: &Expr, column: &str, text: bool, range: &mut KeyRange) {
+    match expr {
+        Expr::And(left, right) => {
+            collect_bounds(left, column, text, range);
+            collect_bounds(right, column, text, range);
+        }
+        Expr::Comparison { column: col, operator: ComparisonOperator::Like, value: Value::Text(pattern) } if col == column && text => {
+            // Only the text before the first wildcard narrows the range. LIKE
+            // ignores the case of ASCII letters, which BINARY keys do not, so
+            // the range ends before the first letter
+            let prefix: String = pattern.chars().take_while(|&c| c != '%' && c != '_' && !c.is_ascii_alphabetic()).collect();
+            if !prefix.is_empty() {
+                range.lower = Some((Value::Text(prefix.clone()), true));
+                range.like_prefix = Some(prefix);
+            }
+        }
+        Expr::Comparison { column: col, operator, value } if col == column && !value.is_null() => {
+            match operator {
+                ComparisonOperator::GreaterThan => range.lower = Some((value.clone(), false)),
+                ComparisonOperator::GreaterThanOrEqual => range.lower = Some((value.clone(), true)),
+                ComparisonOperator::LessThan => range.upper = Some((value.clone(), false)),
+                ComparisonOperator::LessThanOrEqual => range.upper = Some((value.clone(), true)),
+                _ => {}
+            }
+        }
+        Expr::Between { column: col, low, high } if col == column && !low.is_null() && !high.is_null() => {
+            range.lower = Some((low.clone(), true));
+            range.upper = Some((high.clone(), true));
+        }
+        _ => {}
+    }
+}
 
 /// Schema object information
 #[derive(Debug, Clone)]
@@ -1008,10 +2566,39 @@ pub struct SchemaObject {
     type_name: String,
     #[allow(dead_code)]
     name: String,
+    /// Table the object belongs to (the object's own name for tables)
+    table_name: String,
     root_page: u32,
     sql: String,
 }
 
+/// Declared type of a column, without the quotes SQLite also drops from a
+/// type written as a quoted identifier such as `"INTEGER"`
+fn declared_type(col: &sqlparser::ast::ColumnDef) -> String {
+    let declared = col.data_type.to_string();
+    let unquoted = [('"', '"'), ('[', ']'), ('`', '`')]
+        .into_iter()
+        .find_map(|(open, close)| declared.strip_prefix(open)?.strip_suffix(close));
+    match unquoted {
+        Some(unquoted) => unquoted.to_string(),
+        None => declared,
+    }
+}
+
+/// The parts of a CREATE TABLE statement the reader uses
+struct ParsedTable {
+    columns: Vec<String>,
+    without_rowid: bool,
+    primary_key: Vec<String>,
+    key_parts: Vec<IndexColumn>,
+    /// Columns of each automatic index, `sqlite_autoindex_<table>_<N>` being entry N - 1
+    autoindexes: Vec<Vec<IndexColumn>>,
+    rowid_alias: Option<String>,
+    column_info: Vec<ColumnInfo>,
+    constraints: Vec<TableConstraint>,
+    foreign_keys: Vec<ForeignKey>,
+}
+
 /// Table schema information
 #[derive(Debug, Clone)]
 pub struct TableInfo {
@@ -1020,6 +2607,283 @@ pub struct TableInfo {
     pub indexes: Vec<IndexInfo>,
     pub root_page: u32,
     pub sql: String,
+    /// Whether the table is declared `WITHOUT ROWID` and stored as an index b-tree
+    pub without_rowid: bool,
+    /// PRIMARY KEY column names in key order (empty if the table has none)
+    pub primary_key: Vec<String>,
+    /// Sort order and collation of each PRIMARY KEY column, which order the
+    /// b-tree of a WITHOUT ROWID table
+    key_parts: Vec<IndexColumn>,
+    /// Column that aliases the rowid, i.e. the INTEGER PRIMARY KEY, if any
+    pub rowid_alias: Option<String>,
+    /// Definition of each column, in the same order as `columns`
+    pub column_info: Vec<ColumnInfo>,
+    /// PRIMARY KEY, UNIQUE and CHECK constraints declared after the columns
+    pub constraints: Vec<TableConstraint>,
+    /// Foreign keys, from both column `REFERENCES` clauses and table constraints
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+impl TableInfo {
+    /// Leading PRIMARY KEY columns of a WITHOUT ROWID table that seeks can use.
+    ///
+    /// Keys past a `DESC` column or one with a collation other than BINARY are
+    /// not in the ascending BINARY order seeks assume, as for index key parts.
+    pub(crate) fn seekable_primary_key(&self) -> impl Iterator<Item = &str> {
+        self.key_parts.iter().take_while(|part| part.is_seekable()).filter_map(IndexColumn::column_name)
+    }
+    
+    /// Position of each declared column within the stored record.
+    ///
+    /// WITHOUT ROWID tables store their PRIMARY KEY columns first, followed by
+    /// the remaining columns in declaration order.
+    pub fn storage_columns(&self) -> Vec<usize> {
+        if !self.without_rowid {
+            return (0..self.columns.len()).collect();
+        }
+        
+        let mut positions = vec![0; self.columns.len()];
+        let mut next = 0;
+        for key_col in &self.primary_key {
+            if let Some(i) = self.columns.iter().position(|c| c == key_col) {
+                positions[i] = next;
+                next += 1;
+            }
+        }
+        for (i, col) in self.columns.iter().enumerate() {
+            if !self.primary_key.contains(col) {
+                positions[i] = next;
+                next += 1;
+            }
+        }
+        positions
+    }
+}
+
+/// Definition of a table column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    /// Declared type as normalized by the SQL parser (e.g. `VARCHAR(255)`), if any
+    pub declared_type: Option<String>,
+    /// Type affinity SQLite derives from the declared type
+    pub affinity: Affinity,
+    pub not_null: bool,
+    /// DEFAULT value as SQL text (e.g. `'none'`, `-1` or `1 + 2`)
+    pub default: Option<String>,
+    /// Collating sequence from a COLLATE clause
+    pub collation: Option<String>,
+    /// Whether the column is part of the PRIMARY KEY
+    pub primary_key: bool,
+    /// Whether a UNIQUE constraint covers exactly this column
+    pub unique: bool,
+    /// Expression of a generated column
+    pub generated: Option<GeneratedColumn>,
+    /// DEFAULT evaluated and converted to the column's affinity, if it is a literal
+    default_value: Option<Value>,
+}
+
+impl ColumnInfo {
+    /// Value of a constant DEFAULT, converted to the column's affinity
+    ///
+    /// Records written before the column was added with `ALTER TABLE ADD COLUMN`
+    /// end early and read this value for the column. Defaults that are not
+    /// literals, such as `CURRENT_TIMESTAMP`, have none.
+    pub fn default_value(&self) -> Option<&Value> {
+        self.default_value.as_ref()
+    }
+    
+    /// Value of the column as read from a record, or its default if the record
+    /// ends before the column
+    fn read_value(&self, value: Option<Value>) -> Value {
+        match value {
+            // SQLite stores whole REAL values as INTEGER to save space
+            Some(Value::Integer(v)) if self.affinity == Affinity::Real => Value::Real(v as f64),
+            Some(value) => value,
+            None => self.default_value.clone().unwrap_or(Value::Null),
+        }
+    }
+    
+    /// Record the DEFAULT clause of the column
+    fn set_default(&mut self, expr: &sqlparser::ast::Expr) {
+        use sqlparser::ast::{Expr as SqlExpr, UnaryOperator};
+        
+        // SQLite keeps a parenthesized default without its parentheses
+        let expr = match expr {
+            SqlExpr::Nested(inner) => inner,
+            _ => expr,
+        };
+        
+        // The parser would print a `0x` integer as a blob literal
+        let (negative, literal) = match expr {
+            SqlExpr::UnaryOp { op: UnaryOperator::Minus, expr } => (true, &**expr),
+            SqlExpr::UnaryOp { op: UnaryOperator::Plus, expr } => (false, &**expr),
+            _ => (false, expr),
+        };
+        let hex = match literal {
+            SqlExpr::Value(value) => SelectQuery::hex_integer_digits(value),
+            _ => None,
+        };
+        let Some(hex) = hex else {
+            self.default = Some(expr.to_string());
+            self.default_value = SelectQuery::parse_sql_value(expr).ok().map(|value| self.affinity.apply(value));
+            return;
+        };
+        let text = format!("{}0x{}", if negative { "-" } else { "" }, hex);
+        // SQLite reads a hex literal past 32 bits as the text of the DEFAULT clause
+        let value = match u32::from_str_radix(hex, 16) {
+            Ok(magnitude) if magnitude <= i32::MAX as u32 => SelectQuery::parse_sql_value(expr).ok(),
+            _ => Some(Value::Text(text.clone())),
+        };
+        self.default = Some(text);
+        self.default_value = value.map(|value| self.affinity.apply(value));
+    }
+    
+    /// Whether the column is hidden from `SELECT *` and `PRAGMA table_info`,
+    /// which for ordinary tables means it is generated
+    pub fn is_hidden(&self) -> bool {
+        self.generated.is_some()
+    }
+}
+
+/// Expression and storage of a `GENERATED ALWAYS AS` column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedColumn {
+    /// Generating expression as SQL text
+    pub expression: String,
+    /// Whether the value is `STORED` in the record rather than computed when read (`VIRTUAL`)
+    pub stored: bool,
+}
+
+/// Type affinity of a column, which decides how SQLite converts the values stored in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    /// Affinity of a declared column type, following the rules of section 3.1
+    /// of <https://www.sqlite.org/datatype3.html>
+    pub fn from_declared_type(declared_type: &str) -> Affinity {
+        let declared_type = declared_type.to_ascii_uppercase();
+        let contains = |pattern: &str| declared_type.contains(pattern);
+        if contains("INT") {
+            Affinity::Integer
+        } else if contains("CHAR") || contains("CLOB") || contains("TEXT") {
+            Affinity::Text
+        } else if contains("BLOB") || declared_type.is_empty() {
+            Affinity::Blob
+        } else if contains("REAL") || contains("FLOA") || contains("DOUB") {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+    
+    /// Convert a value as SQLite does when storing it in a column of this affinity
+    pub fn apply(self, value: Value) -> Value {
+        match (self, value) {
+            (Affinity::Blob, value) => value,
+            (Affinity::Text, Value::Integer(v)) => Value::Text(v.to_string()),
+            (Affinity::Text, Value::Real(v)) => Value::Text(format_real(v)),
+            (Affinity::Real, Value::Integer(v)) => Value::Real(v as f64),
+            (Affinity::Real, Value::Text(text)) => match parse_numeric_text(&text) {
+                Some(Value::Integer(v)) => Value::Real(v as f64),
+                Some(real) => real,
+                None => Value::Text(text),
+            },
+            (Affinity::Integer | Affinity::Numeric, Value::Text(text)) => match parse_numeric_text(&text) {
+                Some(number) => Affinity::Numeric.apply(number),
+                None => Value::Text(text),
+            },
+            // REALs that are exact integers are stored as INTEGER
+            (Affinity::Integer | Affinity::Numeric, Value::Real(v))
+                if (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&v) && (v as i64) as f64 == v =>
+            {
+                Value::Integer(v as i64)
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+/// Parse text that is a well-formed integer or real literal, ignoring surrounding spaces
+pub(crate) fn parse_numeric_text(text: &str) -> Option<Value> {
+    let text = text.trim();
+    let well_formed = !text.is_empty()
+        && text.bytes().any(|b| b.is_ascii_digit())
+        && text.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E'));
+    if !well_formed {
+        return None;
+    }
+    match text.parse::<i64>() {
+        Ok(v) => Some(Value::Integer(v)),
+        Err(_) => text.parse::<f64>().ok().map(Value::Real),
+    }
+}
+
+/// Render a REAL as text the way SQLite does: 15 significant digits, in
+/// exponent form for very large or small magnitudes, and always with a
+/// fractional part such as the `.0` of whole numbers
+pub(crate) fn format_real(v: f64) -> String {
+    if v.is_infinite() {
+        return String::from(if v > 0.0 { "Inf" } else { "-Inf" });
+    }
+    if v == 0.0 {
+        return String::from("0.0");
+    }
+    
+    // Round to 15 significant digits, e.g. "-1.23400000000000e5"
+    let formatted = format!("{:.14e}", v);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let digits = digits.trim_end_matches('0');
+    
+    let mut text = String::from(if v < 0.0 { "-" } else { "" });
+    if !(-4..15).contains(&exponent) {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        text.push_str(&format!("{}.{}e{}{:02}", &digits[..1], fraction, if exponent < 0 { '-' } else { '+' }, exponent.abs()));
+    } else if exponent < 0 {
+        text.push_str(&format!("0.{}{}", "0".repeat((-exponent - 1) as usize), digits));
+    } else {
+        let whole = exponent as usize + 1;
+        if digits.len() <= whole {
+            text.push_str(&format!("{}{}.0", digits, "0".repeat(whole - digits.len())));
+        } else {
+            text.push_str(&format!("{}.{}", &digits[..whole], &digits[whole..]));
+        }
+    }
+    text
+}
+
+/// Table constraint declared after the columns of a CREATE TABLE statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableConstraint {
+    PrimaryKey { name: Option<String>, columns: Vec<String> },
+    Unique { name: Option<String>, columns: Vec<String> },
+    /// CHECK constraint with its expression as SQL text
+    Check { name: Option<String>, expression: String },
+}
+
+/// Foreign key from columns of a table to a parent table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    /// Constraint name, if given with `CONSTRAINT name`
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    /// Parent table
+    pub table: String,
+    /// Parent columns; empty when the key refers to the parent's PRIMARY KEY
+    pub referred_columns: Vec<String>,
+    /// `ON DELETE` action, e.g. `CASCADE`
+    pub on_delete: Option<String>,
+    /// `ON UPDATE` action
+    pub on_update: Option<String>,
 }
 
 /// Index schema information
@@ -1027,6 +2891,157 @@ pub struct TableInfo {
 pub struct IndexInfo {
     pub name: String,
     pub table_name: String,
-    pub columns: Vec<String>,
+    /// Key parts in index order
+    pub columns: Vec<IndexColumn>,
+    /// WHERE clause of a partial index, as SQL
+    pub predicate: Option<String>,
+    /// The predicate as a query expression, if the planner can reason about it
+    predicate_expr: Option<Expr>,
     pub root_page: u32,
 }
+
+impl IndexInfo {
+    /// Whether the index holds an entry for every row of its table
+    pub fn is_partial(&self) -> bool {
+        self.predicate.is_some()
+    }
+}
+
+/// One key part of an index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexColumn {
+    pub key: IndexKey,
+    /// Collating sequence from the index or the column declaration; BINARY when None
+    pub collation: Option<String>,
+    /// Whether the part is declared `DESC`
+    pub descending: bool,
+}
+
+impl IndexColumn {
+    /// An ascending, BINARY key part on a plain column
+    pub fn column(name: impl Into<String>) -> Self {
+        IndexColumn { key: IndexKey::Column(name.into()), collation: None, descending: false }
+    }
+    
+    /// Name of the indexed column, or None for an expression
+    pub fn column_name(&self) -> Option<&str> {
+        match &self.key {
+            IndexKey::Column(name) => Some(name),
+            IndexKey::Expression(_) => None,
+        }
+    }
+    
+    /// Whether the keys of this part are in ascending BINARY order, as index seeks assume
+    pub(crate) fn is_seekable(&self) -> bool {
+        !self.descending && self.collation.as_deref().is_none_or(|c| c.eq_ignore_ascii_case("BINARY"))
+    }
+}
+
+/// What an index key part holds
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IndexKey {
+    /// The value of a table column
+    Column(String),
+    /// The value of an expression, as SQL text such as `lower(name)`
+    Expression(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rowid_alias() {
+        let alias = |sql: &str| Database::parse_create_table_info(sql).unwrap().rowid_alias;
+        assert_eq!(alias("CREATE TABLE t (a INTEGER, id INTEGER PRIMARY KEY, b INTEGER)").as_deref(), Some("id"));
+        assert_eq!(alias("CREATE TABLE t (id integer NOT NULL PRIMARY KEY ASC AUTOINCREMENT)").as_deref(), Some("id"));
+        assert_eq!(alias("CREATE TABLE t (a INTEGER, \"Id\" INTEGER, PRIMARY KEY (id))").as_deref(), Some("Id"));
+        assert_eq!(alias("CREATE TABLE t (id INTEGER PRIMARY KEY DESC, a)"), None);
+        assert_eq!(alias("CREATE TABLE t (id INT PRIMARY KEY, a)"), None);
+        assert_eq!(alias("CREATE TABLE t (id INTEGER(8) PRIMARY KEY, a)"), None);
+        assert_eq!(alias("CREATE TABLE t (a INTEGER, b INTEGER, PRIMARY KEY (a, b))"), None);
+        assert_eq!(alias("CREATE TABLE t (id INTEGER PRIMARY KEY, a) WITHOUT ROWID"), None);
+        assert_eq!(alias("CREATE TABLE t (id \"INTEGER\" PRIMARY KEY, a)").as_deref(), Some("id"));
+        assert_eq!(alias("CREATE TABLE t (id [integer] PRIMARY KEY, a)").as_deref(), Some("id"));
+        assert_eq!(alias("CREATE TABLE t (k INTEGER, v, PRIMARY KEY (k DESC))").as_deref(), Some("k"));
+        
+        // Only the DESC column constraint keeps its automatic index
+        let parsed = Database::parse_create_table_info("CREATE TABLE t (id INTEGER PRIMARY KEY DESC, a)").unwrap();
+        assert_eq!(parsed.autoindexes, [vec![IndexColumn { descending: true, ..IndexColumn::column("id") }]]);
+        assert!(Database::parse_create_table_info("CREATE TABLE t (id INTEGER PRIMARY KEY ASC, a)").unwrap().autoindexes.is_empty());
+    }
+
+    #[test]
+    fn test_constraint_key_parts() {
+        let parsed = Database::parse_create_table_info(
+            "CREATE TABLE t (a INT, b TEXT COLLATE NOCASE, c, PRIMARY KEY (b DESC, a), UNIQUE (c COLLATE rtrim ASC, \"a\")) WITHOUT ROWID",
+        )
+        .unwrap();
+        assert_eq!(parsed.primary_key, ["b", "a"]);
+        assert_eq!(
+            parsed.key_parts,
+            [
+                IndexColumn { collation: Some("NOCASE".into()), descending: true, ..IndexColumn::column("b") },
+                IndexColumn::column("a"),
+            ]
+        );
+        assert_eq!(parsed.autoindexes[1], [IndexColumn { collation: Some("rtrim".into()), ..IndexColumn::column("c") }, IndexColumn::column("a")]);
+        assert!(matches!(&parsed.constraints[0], TableConstraint::PrimaryKey { columns, .. } if columns == &["b", "a"]));
+        
+        // Sort orders in strings and column constraints are left alone
+        let parsed = Database::parse_create_table_info("CREATE TABLE t (k INT PRIMARY KEY DESC, v DEFAULT 'UNIQUE (x DESC)')").unwrap();
+        assert_eq!(parsed.key_parts, [IndexColumn { descending: true, ..IndexColumn::column("k") }]);
+        assert_eq!(parsed.column_info[1].default.as_deref(), Some("'UNIQUE (x DESC)'"));
+    }
+
+    #[test]
+    fn test_parse_create_index_info() {
+        let index = Database::parse_create_index_info(
+            "idx",
+            7,
+            "CREATE INDEX idx ON main.\"items\" (lower(name), category COLLATE NOCASE, (price) DESC) WHERE deleted = 0",
+        )
+        .unwrap();
+        assert_eq!(index.table_name, "items");
+        assert_eq!(index.root_page, 7);
+        assert_eq!(
+            index.columns,
+            [
+                IndexColumn { key: IndexKey::Expression("lower(name)".into()), collation: None, descending: false },
+                IndexColumn { key: IndexKey::Column("category".into()), collation: Some("NOCASE".into()), descending: false },
+                IndexColumn { key: IndexKey::Column("price".into()), collation: None, descending: true },
+            ]
+        );
+        assert_eq!(index.predicate.as_deref(), Some("deleted = 0"));
+        assert_eq!(index.predicate_expr, Some(Expr::eq("deleted", Value::Integer(0))));
+
+        // Predicates comparing columns are kept as SQL but never used
+        let index = Database::parse_create_index_info("idx", 2, "CREATE INDEX idx ON t (a) WHERE a = b").unwrap();
+        assert!(index.is_partial());
+        assert_eq!(index.predicate_expr, None);
+    }
+
+    #[test]
+    fn test_predicate_implication() {
+        let encoding = TextEncoding::Utf8;
+        let check = |query: Expr, predicate: Expr| implies(&collect_and_terms(&query), &predicate, encoding);
+
+        let live = Expr::eq("deleted", Value::Integer(0));
+        assert!(check(Expr::eq("a", Value::Integer(1)).and(live.clone()), live.clone()));
+        assert!(!check(Expr::eq("a", Value::Integer(1)), live.clone()));
+        assert!(!check(Expr::eq("deleted", Value::Integer(1)), live.clone()));
+
+        let cheap = Expr::lt("price", Value::Real(10.0)).and(Expr::is_not_null("price"));
+        assert!(check(Expr::eq("price", Value::Real(3.5)), cheap.clone()));
+        assert!(check(Expr::le("price", Value::Integer(9)), cheap.clone()));
+        assert!(check(Expr::lt("price", Value::Real(10.0)), cheap.clone()));
+        assert!(!check(Expr::le("price", Value::Real(10.0)), cheap.clone()));
+        assert!(!check(Expr::gt("price", Value::Real(1.0)), cheap.clone()));
+        assert!(!check(Expr::eq("price", Value::Text("3".into())), cheap));
+
+        assert!(check(Expr::in_values("kind", vec![Value::Integer(1), Value::Integer(2)]), Expr::ge("kind", Value::Integer(1))));
+        assert!(check(Expr::between("ts", Value::Integer(5), Value::Integer(8)), Expr::between("ts", Value::Integer(0), Value::Integer(10))));
+        assert!(check(Expr::eq("kind", Value::Integer(2)), Expr::eq("kind", Value::Integer(1)).or(Expr::eq("kind", Value::Integer(2)))));
+        assert!(!check(Expr::ne("kind", Value::Integer(2)), Expr::gt("kind", Value::Integer(2))));
+    }
+}
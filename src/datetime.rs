@@ -0,0 +1,193 @@
+//! Calendar dates and times stored as ISO-8601 text
+//!
+//! SQLite has no date type; its date and time functions read and write text
+//! such as `2024-02-29`, `13:45:00.250` and `2024-02-29 13:45:00`. These
+//! types parse that text without pulling in a date library.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// A calendar date, as in `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// A time of day, as in `HH:MM[:SS[.fff]]`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+/// A date and time of day, as in `YYYY-MM-DD HH:MM:SS` or `YYYY-MM-DDTHH:MM:SS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+}
+
+/// Text that is not a valid date or time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseDateTimeError;
+
+impl fmt::Display for ParseDateTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid date or time")
+    }
+}
+
+impl Date {
+    /// Create a date, checking that the day exists in that month
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Date> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+}
+
+impl Time {
+    /// Create a time of day, checking each field's range
+    pub fn new(hour: u8, minute: u8, second: u8, nanosecond: u32) -> Option<Time> {
+        if hour > 23 || minute > 59 || second > 59 || nanosecond > 999_999_999 {
+            return None;
+        }
+        Some(Time { hour, minute, second, nanosecond })
+    }
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+/// Parse exactly `digits` ASCII digits
+fn number(text: &str, digits: usize) -> Result<u32, ParseDateTimeError> {
+    if text.len() != digits || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ParseDateTimeError);
+    }
+    text.parse().map_err(|_| ParseDateTimeError)
+}
+
+impl FromStr for Date {
+    type Err = ParseDateTimeError;
+
+    fn from_str(text: &str) -> Result<Date, ParseDateTimeError> {
+        let mut parts = text.split('-');
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(year), Some(month), Some(day), None) => (year, month, day),
+            _ => return Err(ParseDateTimeError),
+        };
+        let year = number(year, 4)? as i32;
+        Date::new(year, number(month, 2)? as u8, number(day, 2)? as u8).ok_or(ParseDateTimeError)
+    }
+}
+
+impl FromStr for Time {
+    type Err = ParseDateTimeError;
+
+    fn from_str(text: &str) -> Result<Time, ParseDateTimeError> {
+        let text = text.strip_suffix('Z').unwrap_or(text);
+        let (clock, fraction) = match text.split_once('.') {
+            Some((clock, fraction)) => (clock, Some(fraction)),
+            None => (text, None),
+        };
+
+        let mut parts = clock.split(':');
+        let (hour, minute, second) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(hour), Some(minute), second, None) => (hour, minute, second),
+            _ => return Err(ParseDateTimeError),
+        };
+        let second = match second {
+            Some(second) => number(second, 2)?,
+            None if fraction.is_none() => 0,
+            None => return Err(ParseDateTimeError),
+        };
+
+        let nanosecond = match fraction {
+            Some(fraction) if (1..=9).contains(&fraction.len()) => {
+                number(fraction, fraction.len())? * 10u32.pow(9 - fraction.len() as u32)
+            }
+            Some(_) => return Err(ParseDateTimeError),
+            None => 0,
+        };
+
+        Time::new(number(hour, 2)? as u8, number(minute, 2)? as u8, second as u8, nanosecond)
+            .ok_or(ParseDateTimeError)
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = ParseDateTimeError;
+
+    /// Parse a date and time; a date on its own means midnight
+    fn from_str(text: &str) -> Result<DateTime, ParseDateTimeError> {
+        match text.split_once([' ', 'T']) {
+            Some((date, time)) => Ok(DateTime { date: date.parse()?, time: time.parse()? }),
+            None => Ok(DateTime { date: text.parse()?, time: Time::default() }),
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        if self.nanosecond.is_multiple_of(1_000_000) {
+            if self.nanosecond != 0 {
+                write!(f, ".{:03}", self.nanosecond / 1_000_000)?;
+            }
+        } else {
+            write!(f, ".{:09}", self.nanosecond)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.date, self.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_parse_dates_and_times() {
+        assert_eq!("2024-02-29".parse(), Ok(Date { year: 2024, month: 2, day: 29 }));
+        assert_eq!("2023-02-29".parse::<Date>(), Err(ParseDateTimeError));
+        assert_eq!("2024-1-05".parse::<Date>(), Err(ParseDateTimeError));
+
+        assert_eq!("13:45".parse(), Ok(Time { hour: 13, minute: 45, second: 0, nanosecond: 0 }));
+        assert_eq!("13:45:07.25".parse(), Ok(Time { hour: 13, minute: 45, second: 7, nanosecond: 250_000_000 }));
+        assert_eq!("24:00:00".parse::<Time>(), Err(ParseDateTimeError));
+
+        let expected = DateTime {
+            date: Date { year: 2024, month: 2, day: 29 },
+            time: Time { hour: 13, minute: 45, second: 7, nanosecond: 0 },
+        };
+        assert_eq!("2024-02-29 13:45:07".parse(), Ok(expected));
+        assert_eq!("2024-02-29T13:45:07Z".parse(), Ok(expected));
+        assert_eq!(expected.to_string(), "2024-02-29 13:45:07");
+        assert_eq!("2024-02-29".parse::<DateTime>().unwrap().to_string(), "2024-02-29 00:00:00");
+        assert_eq!("2024-02-29 13:45:07.5".parse::<DateTime>().unwrap().to_string(), "2024-02-29 13:45:07.500");
+    }
+}
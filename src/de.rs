@@ -0,0 +1,290 @@
+//! serde support for query results (requires the `serde` feature)
+//!
+//! [`from_row`] deserializes a [`Row`] into any `Deserialize` type. Structs
+//! and maps read columns by name; tuples, tuple structs and sequences read
+//! them in projection order. [`from_value`] does the same for a single
+//! [`Value`]: INTEGER, REAL, TEXT and BLOB map to `i64`, `f64`, strings and
+//! bytes, and NULL maps to `None` or `()`.
+//!
+//! `Value`, `Row` and `ResultSet` also implement `Serialize`: values as the
+//! matching primitive, rows as a map from column name to value and result
+//! sets as a sequence of rows.
+
+use crate::{Error, Result, Row, RowEntries, Value};
+use core::fmt;
+use serde::de::{
+    self, value::{BorrowedStrDeserializer, SeqDeserializer}, DeserializeSeed, Deserializer, IntoDeserializer,
+    MapAccess, SeqAccess, Visitor,
+};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde::Deserialize;
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::ToString, vec::Vec};
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Deserialize(msg.to_string())
+    }
+}
+
+/// Deserialize a single value
+pub fn from_value<'de, T: Deserialize<'de>>(value: &'de Value) -> Result<T> {
+    T::deserialize(ValueDeserializer::new(value))
+}
+
+/// Deserialize a row, reading struct fields by column name
+pub fn from_row<'de, T: Deserialize<'de>>(row: &'de Row) -> Result<T> {
+    T::deserialize(RowDeserializer::new(row))
+}
+
+/// Deserialize every row of a result set
+pub fn from_rows<'de, T: Deserialize<'de>>(rows: &'de [Row]) -> Result<Vec<T>> {
+    rows.iter().map(from_row).collect()
+}
+
+/// A `Deserializer` reading from a borrowed [`Value`]
+#[derive(Debug, Clone, Copy)]
+pub struct ValueDeserializer<'de> {
+    value: &'de Value,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    /// Deserializer for `value`
+    pub fn new(value: &'de Value) -> Self {
+        ValueDeserializer { value }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Real(f) => visitor.visit_f64(*f),
+            Value::Text(s) => visitor.visit_borrowed_str(s),
+            Value::Blob(b) => visitor.visit_borrowed_bytes(b),
+        }
+    }
+
+    /// SQLite stores booleans as integers
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Integer(i) => visitor.visit_bool(*i != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Text(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// BLOBs can also be read as a sequence of bytes, e.g. into `Vec<u8>`
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Blob(b) => visitor.visit_seq(SeqDeserializer::new(b.iter().copied())),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are stored as their name
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            Value::Text(s) => visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// A `Deserializer` reading from a borrowed [`Row`]
+#[derive(Debug, Clone, Copy)]
+pub struct RowDeserializer<'de> {
+    row: &'de Row,
+}
+
+impl<'de> RowDeserializer<'de> {
+    /// Deserializer for `row`
+    pub fn new(row: &'de Row) -> Self {
+        RowDeserializer { row }
+    }
+
+    /// Read the columns in order, failing if any are left over
+    fn visit_columns<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut access = RowAccess::new(self.row);
+        let value = visitor.visit_seq(&mut access)?;
+        match access.entries.len() {
+            0 => Ok(value),
+            left => Err(de::Error::custom(format!(
+                "row has {} columns but only {} were read",
+                self.row.len(),
+                self.row.len() - left
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(RowAccess::new(self.row))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.visit_columns(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.visit_columns(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.visit_columns(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Walks the columns of a row, as map entries or as sequence elements
+struct RowAccess<'de> {
+    entries: RowEntries<'de>,
+    pending: Option<(&'de str, &'de Value)>,
+}
+
+impl<'de> RowAccess<'de> {
+    fn new(row: &'de Row) -> Self {
+        RowAccess { entries: row.iter(), pending: None }
+    }
+}
+
+/// Deserialize one column, naming it in any error
+fn column<'de, T: DeserializeSeed<'de>>(seed: T, name: &str, value: &'de Value) -> Result<T::Value> {
+    seed.deserialize(ValueDeserializer::new(value)).map_err(|e| match e {
+        Error::Deserialize(msg) => Error::Deserialize(format!("column {}: {}", name, msg)),
+        e => e,
+    })
+}
+
+impl<'de> MapAccess<'de> for RowAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((name, value)) => {
+                self.pending = Some((name, value));
+                seed.deserialize(BorrowedStrDeserializer::new(name)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (name, value) = self
+            .pending
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("value requested before its column name"))?;
+        column(seed, name, value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+impl<'de> SeqAccess<'de> for RowAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.entries.next() {
+            Some((name, value)) => column(seed, name, value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Real(f) => serializer.serialize_f64(*f),
+            Value::Text(s) => serializer.serialize_str(s),
+            Value::Blob(b) => serializer.serialize_bytes(b),
+        }
+    }
+}
+
+impl Serialize for Row {
+    /// A map from column name to value, in projection order
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (name, value) in self {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for crate::ResultSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for row in self.rows() {
+            seq.serialize_element(row)?;
+        }
+        seq.end()
+    }
+}
@@ -0,0 +1,269 @@
+//! Typed decoding of values and rows
+//!
+//! [`FromValue`] converts a single [`Value`] into a Rust type and [`FromRow`]
+//! converts a whole [`Row`], e.g. into a tuple or (with the `derive` feature)
+//! a struct whose fields are named after the columns.
+
+use crate::{
+    datetime::{Date, DateTime, Time},
+    Result, Row, Value,
+};
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::{String, ToString}, vec::Vec, format};
+
+/// A value that could not be converted to the requested Rust type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromValueError {
+    /// The Rust type that was requested
+    pub expected: &'static str,
+    /// Description of the value that was found instead
+    pub found: String,
+}
+
+impl FromValueError {
+    /// The value's storage class cannot be read as `expected`
+    pub fn mismatch(expected: &'static str, value: &Value) -> Self {
+        FromValueError { expected, found: value.type_name().to_string() }
+    }
+
+    /// The value has a usable storage class, but its contents do not fit `expected`
+    pub fn invalid(expected: &'static str, value: &Value) -> Self {
+        FromValueError { expected, found: format!("{} {}", value.type_name(), value) }
+    }
+}
+
+impl fmt::Display for FromValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot convert {} to {}", self.found, self.expected)
+    }
+}
+
+/// Conversion from a column value
+///
+/// The lifetime lets types such as `&str` and `&[u8]` borrow from the row.
+pub trait FromValue<'a>: Sized {
+    /// Convert `value`, failing if it has the wrong type or is out of range
+    fn from_value(value: &'a Value) -> core::result::Result<Self, FromValueError>;
+}
+
+/// Conversion from a whole row
+///
+/// Tuples read their elements by position. Structs can derive this trait
+/// with the `derive` feature to read each field from the column of the same name.
+pub trait FromRow<'r>: Sized {
+    /// Convert `row`, failing with [`Error::ColumnNotFound`] or
+    /// [`Error::TypeMismatch`] when a column is missing or has the wrong type
+    ///
+    /// [`Error::ColumnNotFound`]: crate::Error::ColumnNotFound
+    /// [`Error::TypeMismatch`]: crate::Error::TypeMismatch
+    fn from_row(row: &'r Row) -> Result<Self>;
+}
+
+macro_rules! from_integer {
+    ($($ty:ty),*) => {$(
+        impl FromValue<'_> for $ty {
+            fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+                match value {
+                    Value::Integer(i) => <$ty>::try_from(*i)
+                        .map_err(|_| FromValueError::invalid(stringify!($ty), value)),
+                    _ => Err(FromValueError::mismatch(stringify!($ty), value)),
+                }
+            }
+        }
+    )*};
+}
+
+from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromValue<'_> for f64 {
+    fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+        match value {
+            Value::Real(f) => Ok(*f),
+            Value::Integer(i) => Ok(*i as f64),
+            _ => Err(FromValueError::mismatch("f64", value)),
+        }
+    }
+}
+
+impl FromValue<'_> for f32 {
+    fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+        match value {
+            Value::Real(f) => Ok(*f as f32),
+            Value::Integer(i) => Ok(*i as f32),
+            _ => Err(FromValueError::mismatch("f32", value)),
+        }
+    }
+}
+
+impl FromValue<'_> for bool {
+    /// SQLite stores booleans as integers; any non-zero value is true
+    fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+        value.as_bool().ok_or_else(|| FromValueError::mismatch("bool", value))
+    }
+}
+
+impl FromValue<'_> for String {
+    fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+        match value {
+            Value::Text(text) => Ok(text.clone()),
+            _ => Err(FromValueError::mismatch("String", value)),
+        }
+    }
+}
+
+impl<'a> FromValue<'a> for &'a str {
+    fn from_value(value: &'a Value) -> core::result::Result<Self, FromValueError> {
+        value.as_text().ok_or_else(|| FromValueError::mismatch("&str", value))
+    }
+}
+
+impl FromValue<'_> for Vec<u8> {
+    fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+        match value {
+            Value::Blob(bytes) => Ok(bytes.clone()),
+            _ => Err(FromValueError::mismatch("Vec<u8>", value)),
+        }
+    }
+}
+
+impl<'a> FromValue<'a> for &'a [u8] {
+    fn from_value(value: &'a Value) -> core::result::Result<Self, FromValueError> {
+        value.as_blob().ok_or_else(|| FromValueError::mismatch("&[u8]", value))
+    }
+}
+
+impl FromValue<'_> for Value {
+    fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+        Ok(value.clone())
+    }
+}
+
+impl<'a> FromValue<'a> for &'a Value {
+    fn from_value(value: &'a Value) -> core::result::Result<Self, FromValueError> {
+        Ok(value)
+    }
+}
+
+impl<'a, T: FromValue<'a>> FromValue<'a> for Option<T> {
+    /// NULL reads as `None`; anything else must convert to `T`
+    fn from_value(value: &'a Value) -> core::result::Result<Self, FromValueError> {
+        match value {
+            Value::Null => Ok(None),
+            _ => T::from_value(value).map(Some),
+        }
+    }
+}
+
+macro_rules! from_iso_text {
+    ($($ty:ident),*) => {$(
+        impl FromValue<'_> for $ty {
+            fn from_value(value: &Value) -> core::result::Result<Self, FromValueError> {
+                match value {
+                    Value::Text(text) => text
+                        .parse()
+                        .map_err(|_| FromValueError::invalid(stringify!($ty), value)),
+                    _ => Err(FromValueError::mismatch(stringify!($ty), value)),
+                }
+            }
+        }
+    )*};
+}
+
+from_iso_text!(Date, Time, DateTime);
+
+impl FromRow<'_> for Row {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(row.clone())
+    }
+}
+
+macro_rules! tuple_from_row {
+    ($($name:ident $index:tt),+) => {
+        impl<'r, $($name: FromValue<'r>),+> FromRow<'r> for ($($name,)+) {
+            fn from_row(row: &'r Row) -> Result<Self> {
+                Ok(($(row.get_as::<$name, usize>($index)?,)+))
+            }
+        }
+    };
+}
+
+tuple_from_row!(A 0);
+tuple_from_row!(A 0, B 1);
+tuple_from_row!(A 0, B 1, C 2);
+tuple_from_row!(A 0, B 1, C 2, D 3);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple_from_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use alloc::sync::Arc;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    #[test]
+    fn test_from_value() {
+        assert_eq!(i64::from_value(&Value::Integer(-5)), Ok(-5));
+        assert_eq!(u8::from_value(&Value::Integer(255)), Ok(255));
+        assert_eq!(
+            u8::from_value(&Value::Integer(256)),
+            Err(FromValueError { expected: "u8", found: "INTEGER 256".into() })
+        );
+        assert_eq!(
+            i32::from_value(&Value::Text("1".into())),
+            Err(FromValueError { expected: "i32", found: "TEXT".into() })
+        );
+        assert_eq!(f64::from_value(&Value::Integer(2)), Ok(2.0));
+        assert_eq!(bool::from_value(&Value::Integer(0)), Ok(false));
+        assert_eq!(bool::from_value(&Value::Integer(2)), Ok(true));
+
+        let text = Value::Text("hello".into());
+        assert_eq!(<&str>::from_value(&text), Ok("hello"));
+        assert_eq!(String::from_value(&text), Ok("hello".to_string()));
+        assert!(Vec::<u8>::from_value(&text).is_err());
+        assert_eq!(<&[u8]>::from_value(&Value::Blob(vec![1, 2])), Ok(&[1u8, 2][..]));
+
+        assert_eq!(Option::<i64>::from_value(&Value::Null), Ok(None));
+        assert_eq!(Option::<i64>::from_value(&Value::Integer(1)), Ok(Some(1)));
+        assert!(i64::from_value(&Value::Null).is_err());
+
+        let date = Value::Text("2024-02-29".into());
+        assert_eq!(Date::from_value(&date), Ok(Date { year: 2024, month: 2, day: 29 }));
+        assert_eq!(DateTime::from_value(&date).unwrap().to_string(), "2024-02-29 00:00:00");
+        assert_eq!(
+            Date::from_value(&Value::Text("2024-02-30".into())),
+            Err(FromValueError { expected: "Date", found: "TEXT 2024-02-30".into() })
+        );
+    }
+
+    #[test]
+    fn test_from_row() {
+        let columns: Arc<[String]> = vec!["id".to_string(), "name".to_string(), "score".to_string()].into();
+        let row = Row::new(columns, vec![Value::Integer(7), Value::Text("ada".into()), Value::Null]);
+
+        let (id, name, score) = <(i64, &str, Option<f64>)>::from_row(&row).unwrap();
+        assert_eq!((id, name, score), (7, "ada", None));
+        assert_eq!(row.get_as::<String, _>("name").unwrap(), "ada");
+
+        match <(i64, i64)>::from_row(&row) {
+            Err(Error::TypeMismatch { column, expected, found }) => {
+                assert_eq!((column.as_str(), expected, found.as_str()), ("name", "i64", "TEXT"));
+            }
+            other => panic!("expected a type mismatch, got {:?}", other),
+        }
+        assert!(matches!(
+            <(i64, String, Option<f64>, i64)>::from_row(&row),
+            Err(Error::ColumnNotFound(column)) if column == "3"
+        ));
+        assert!(matches!(row.get_as::<i64, _>("missing"), Err(Error::ColumnNotFound(_))));
+    }
+}
@@ -2,7 +2,7 @@
 
 use thiserror::Error;
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+#[cfg(not(feature = "std"))]
 use alloc::string::String;
 
 /// Result type alias for operations that can fail with our Error type
@@ -14,6 +14,7 @@ pub enum Error {
     /// Error related to database schema
     #[error("Schema error: {0}")]
     SchemaError(String),
+    #[cfg(feature = "std")]
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     
@@ -46,22 +47,38 @@ pub enum Error {
     
     #[error("Column not found: {0}")]
     ColumnNotFound(String),
+    
+    /// A column value could not be converted to the requested Rust type
+    #[error("Cannot read column {column} as {expected}: found {found}")]
+    TypeMismatch { column: String, expected: &'static str, found: String },
+    
+    /// A row or value could not be deserialized with serde
+    #[cfg(feature = "serde")]
+    #[error("Deserialization error: {0}")]
+    Deserialize(String),
+    
+    /// A configured [`ReaderLimits`](crate::ReaderLimits) value, or the
+    /// [`OpenOptions::max_group_passes`](crate::OpenOptions::max_group_passes)
+    /// of a GROUP BY query, was reached
+    #[error("Reader limit exceeded: {limit} (maximum {max})")]
+    LimitExceeded { limit: &'static str, max: usize },
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "std")]
     use std::io;
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, string::ToString};
 
     #[test]
     fn test_error_creation() {
-        let io_error = Error::Io(io::Error::new(io::ErrorKind::NotFound, "File not found"));
         let format_error = Error::InvalidFormat("Invalid SQLite format".to_string());
         let page_error = Error::InvalidPage(42);
         let table_error = Error::TableNotFound("users".to_string());
         let record_error = Error::InvalidRecord;
 
-        assert!(matches!(io_error, Error::Io(_)));
         assert!(matches!(format_error, Error::InvalidFormat(_)));
         assert!(matches!(page_error, Error::InvalidPage(42)));
         assert!(matches!(table_error, Error::TableNotFound(_)));
@@ -79,10 +96,20 @@ mod tests {
         assert_eq!(page_error.to_string(), "Invalid page number: 123");
         assert_eq!(table_error.to_string(), "Table not found: test_table");
         assert_eq!(record_error.to_string(), "Invalid record format");
+        
+        let type_error = Error::TypeMismatch { column: "age".to_string(), expected: "i64", found: "TEXT".to_string() };
+        assert_eq!(type_error.to_string(), "Cannot read column age as i64: found TEXT");
+        
+        let limit_error = Error::LimitExceeded { limit: "max_rows", max: 10 };
+        assert_eq!(limit_error.to_string(), "Reader limit exceeded: max_rows (maximum 10)");
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_io_error_conversion() {
+        let io_error = Error::Io(io::Error::new(io::ErrorKind::NotFound, "File not found"));
+        assert!(matches!(io_error, Error::Io(_)));
+        
         let io_error = io::Error::new(io::ErrorKind::NotFound, "File not found");
         let sqlite_error: Error = io_error.into();
         
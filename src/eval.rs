@@ -0,0 +1,768 @@
+//! Evaluation of expressions over rows, with SQLite's type affinity,
+//! collating sequences and three-valued logic
+
+use crate::{
+    database::{format_real, Affinity, TableInfo},
+    format::TextEncoding,
+    functions::{self, Random},
+    query::{Aggregate, BinaryOperator, ComparisonOperator, Expr, ScalarExpr, UnaryOperator},
+    Error, Result, Row, RowIndex, Value,
+};
+use alloc::{borrow::Cow, sync::Arc};
+use core::cmp::Ordering;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, string::{String, ToString}, vec::Vec};
+
+/// Collating sequence TEXT values are compared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Collation {
+    Binary,
+    /// Ignores the case of ASCII letters
+    NoCase,
+    /// Ignores trailing spaces
+    Rtrim,
+}
+
+impl Collation {
+    /// The collation named in a schema, with unknown names compared as BINARY
+    pub(crate) fn from_name(name: Option<&str>) -> Self {
+        name.and_then(Collation::parse).unwrap_or(Collation::Binary)
+    }
+
+    /// One of the collations SQLite has built in, by name
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            name if name.eq_ignore_ascii_case("BINARY") => Some(Collation::Binary),
+            name if name.eq_ignore_ascii_case("NOCASE") => Some(Collation::NoCase),
+            name if name.eq_ignore_ascii_case("RTRIM") => Some(Collation::Rtrim),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Collation::Binary => "BINARY",
+            Collation::NoCase => "NOCASE",
+            Collation::Rtrim => "RTRIM",
+        }
+    }
+
+    /// A value that is equal to any other in the same group, and ordered the
+    /// way the collation orders them
+    pub(crate) fn group_value(self, value: &Value) -> Value {
+        match (value, self) {
+            // SQLite compares INTEGER and REAL by value, so 1 and 1.0 form one group
+            (Value::Real(v), _) if *v >= i64::MIN as f64 && *v < i64::MAX as f64 && (*v as i64) as f64 == *v => {
+                Value::Integer(*v as i64)
+            }
+            (Value::Text(text), Collation::NoCase) => Value::Text(text.to_ascii_lowercase()),
+            (Value::Text(text), Collation::Rtrim) => Value::Text(text.trim_end_matches(' ').to_string()),
+            _ => value.clone(),
+        }
+    }
+
+    /// Order two values as SQLite does: NULL, then numbers by value, then
+    /// TEXT under this collation as stored in `encoding`, then BLOBs
+    pub(crate) fn compare(self, a: &Value, b: &Value, encoding: TextEncoding) -> Ordering {
+        match (a, b) {
+            (Value::Integer(a), Value::Real(b)) => compare_integer_real(*a, *b),
+            (Value::Real(a), Value::Integer(b)) => compare_integer_real(*b, *a).reverse(),
+            (Value::Text(x), Value::Text(y)) => match self {
+                Collation::Binary => encoding.compare(a, b),
+                Collation::NoCase => encoding.compare(
+                    &Value::Text(x.to_ascii_lowercase()),
+                    &Value::Text(y.to_ascii_lowercase()),
+                ),
+                Collation::Rtrim => encoding.compare(
+                    &Value::Text(x.trim_end_matches(' ').to_string()),
+                    &Value::Text(y.trim_end_matches(' ').to_string()),
+                ),
+            },
+            _ => encoding.compare(a, b),
+        }
+    }
+}
+
+/// Compare an INTEGER with a REAL exactly, even beyond the 53 bits a REAL
+/// holds exactly
+fn compare_integer_real(a: i64, b: f64) -> Ordering {
+    if b.is_nan() {
+        return Ordering::Greater;
+    }
+    if b < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+    if b >= i64::MAX as f64 {
+        return Ordering::Less;
+    }
+    let whole = b as i64;
+    a.cmp(&whole).then_with(|| {
+        let fraction = b - whole as f64;
+        if fraction > 0.0 {
+            Ordering::Less
+        } else if fraction < 0.0 {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    })
+}
+
+/// How comparisons treat the values of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ColumnType {
+    pub(crate) affinity: Affinity,
+    pub(crate) collation: Collation,
+}
+
+impl ColumnType {
+    /// Type of a column without a declared type, or of a computed value
+    pub(crate) const UNTYPED: ColumnType = ColumnType { affinity: Affinity::Blob, collation: Collation::Binary };
+
+    /// Types of the columns of a table's rows: its declared columns, then
+    /// rowid pseudo-columns up to `count` columns in all
+    pub(crate) fn of_table(info: &TableInfo, count: usize) -> Vec<ColumnType> {
+        (0..count)
+            .map(|index| match info.column_info.get(index) {
+                Some(column) => ColumnType {
+                    affinity: column.affinity,
+                    collation: Collation::from_name(column.collation.as_deref()),
+                },
+                None => ColumnType { affinity: Affinity::Integer, collation: Collation::Binary },
+            })
+            .collect()
+    }
+}
+
+/// What evaluating expressions over the rows of a query needs to know
+#[derive(Debug, Clone)]
+pub(crate) struct Context {
+    /// Encoding TEXT is stored in, which orders it under BINARY
+    pub(crate) encoding: TextEncoding,
+    /// Type of each column of the rows, by position; columns past the end are untyped
+    columns: Arc<[ColumnType]>,
+    /// Source of `random()`, shared by the contexts derived from this one
+    random: Random,
+}
+
+impl Context {
+    /// Context for rows whose columns are all untyped
+    pub(crate) fn new(encoding: TextEncoding) -> Self {
+        Self::with_random(encoding, Random::new(None))
+    }
+
+    /// Context for untyped rows that draws `random()` from `random`
+    pub(crate) fn with_random(encoding: TextEncoding, random: Random) -> Self {
+        Context { encoding, columns: Arc::from(Vec::new()), random }
+    }
+
+    /// This context for rows whose columns have the types `columns`
+    pub(crate) fn with_columns(&self, columns: Vec<ColumnType>) -> Self {
+        Context { encoding: self.encoding, columns: columns.into(), random: self.random.clone() }
+    }
+
+    /// The next value of `random()`
+    pub(crate) fn random(&self) -> i64 {
+        self.random.draw()
+    }
+
+    pub(crate) fn column_type(&self, index: usize) -> ColumnType {
+        self.columns.get(index).copied().unwrap_or(ColumnType::UNTYPED)
+    }
+}
+
+/// The values an expression reads
+pub(crate) trait Operands {
+    /// Value and type of the column `name`, or None if there is no such column
+    fn column(&self, name: &str) -> Option<(&Value, ColumnType)>;
+
+    /// Context the expression is evaluated in
+    fn context(&self) -> &Context;
+
+    /// Value of an aggregate call, where the expression is evaluated over a group
+    fn aggregate(&self, _aggregate: &Aggregate) -> Option<&Value> {
+        None
+    }
+}
+
+/// The columns of a row, typed by a context
+pub(crate) struct RowOperands<'a> {
+    pub(crate) row: &'a Row,
+    pub(crate) context: &'a Context,
+}
+
+impl Operands for RowOperands<'_> {
+    fn column(&self, name: &str) -> Option<(&Value, ColumnType)> {
+        let index = name.index_in(self.row.columns())?;
+        Some((&self.row.values()[index], self.context.column_type(index)))
+    }
+
+    fn context(&self) -> &Context {
+        self.context
+    }
+}
+
+/// The types of columns without their values, for what an expression's
+/// collation and affinity depend on before any row is read
+pub(crate) struct ColumnTypes<'a> {
+    pub(crate) columns: &'a [String],
+    pub(crate) context: &'a Context,
+}
+
+impl Operands for ColumnTypes<'_> {
+    fn column(&self, name: &str) -> Option<(&Value, ColumnType)> {
+        static NULL: Value = Value::Null;
+        let index = name.index_in(self.columns)?;
+        Some((&NULL, self.context.column_type(index)))
+    }
+
+    fn context(&self) -> &Context {
+        self.context
+    }
+}
+
+/// Evaluate an expression
+pub(crate) fn evaluate<'a>(expr: &'a ScalarExpr, operands: &'a dyn Operands, encoding: TextEncoding) -> Result<Cow<'a, Value>> {
+    let value = |expr: &'a ScalarExpr| evaluate(expr, operands, encoding);
+    Ok(match expr {
+        ScalarExpr::Column(name) => match operands.column(name) {
+            Some((value, _)) => Cow::Borrowed(value),
+            None => return Err(Error::ColumnNotFound(name.clone())),
+        },
+        ScalarExpr::Literal(literal) => Cow::Borrowed(literal),
+        ScalarExpr::Aggregate(aggregate) => match operands.aggregate(aggregate) {
+            Some(value) => Cow::Borrowed(value),
+            None => return Err(Error::QueryError("misuse of aggregate function".to_string())),
+        },
+        ScalarExpr::Unary { operator, expr } => Cow::Owned(unary(*operator, &*value(expr)?)),
+        ScalarExpr::Binary { left, operator: operator @ (BinaryOperator::And | BinaryOperator::Or), right } => {
+            // FALSE AND anything is FALSE and TRUE OR anything is TRUE, even NULL
+            let decisive = *operator == BinaryOperator::Or;
+            let result = match truth(&*value(left)?) {
+                Some(left) if left == decisive => Some(decisive),
+                left => match (left, truth(&*value(right)?)) {
+                    (_, Some(right)) if right == decisive => Some(decisive),
+                    (Some(_), Some(_)) => Some(!decisive),
+                    _ => None,
+                },
+            };
+            Cow::Owned(result.map_or(Value::Null, |result| Value::Integer(i64::from(result))))
+        }
+        ScalarExpr::Binary { left, operator, right } => Cow::Owned(binary(*operator, &*value(left)?, &*value(right)?)),
+        ScalarExpr::Compare { left, operator, right } => {
+            let ordering = compare_expressions(left, &*value(left)?, right, &*value(right)?, operator, operands, encoding);
+            Cow::Owned(ordering.map_or(Value::Null, |holds| Value::Integer(i64::from(holds))))
+        }
+        ScalarExpr::Is { left, right, negated } => {
+            let (l, r) = (value(left)?, value(right)?);
+            let equal = match (l.is_null(), r.is_null()) {
+                (true, true) => true,
+                (false, false) => {
+                    compare_expressions(left, &l, right, &r, &ComparisonOperator::Equal, operands, encoding) == Some(true)
+                }
+                _ => false,
+            };
+            Cow::Owned(Value::Integer(i64::from(equal != *negated)))
+        }
+        ScalarExpr::Case { operand, branches, otherwise } => {
+            let operand = operand.as_deref().map(|operand| Ok::<_, Error>((operand, value(operand)?))).transpose()?;
+            for (when, then) in branches {
+                let chosen = match &operand {
+                    Some((operand, operand_value)) => {
+                        let when_value = value(when)?;
+                        compare_expressions(operand, operand_value, when, &when_value, &ComparisonOperator::Equal, operands, encoding)
+                            == Some(true)
+                    }
+                    None => truth(&*value(when)?) == Some(true),
+                };
+                if chosen {
+                    return value(then);
+                }
+            }
+            match otherwise {
+                Some(otherwise) => value(otherwise)?,
+                None => Cow::Owned(Value::Null),
+            }
+        }
+        ScalarExpr::Cast { expr, affinity } => Cow::Owned(cast(value(expr)?.into_owned(), *affinity)),
+        ScalarExpr::Collate { expr, .. } => value(expr)?,
+        ScalarExpr::Function { function, args } => functions::call(*function, args, operands, encoding)?,
+    })
+}
+
+/// Whether a condition holds for a row: None when it is NULL, as when it
+/// compares NULL with anything
+pub(crate) fn condition(expr: &Expr, operands: &dyn Operands, encoding: TextEncoding) -> Result<Option<bool>> {
+    let column = |name: &String| operands.column(name).ok_or_else(|| Error::ColumnNotFound(name.clone()));
+    Ok(match expr {
+        Expr::Comparison { column: name, operator, value } => {
+            let (column_value, column_type) = column(name)?;
+            compare_typed(column_value, column_type, value, ColumnType::UNTYPED, column_type.collation, operator, encoding)
+        }
+        Expr::ColumnComparison { left, operator, right } => {
+            let ((left, left_type), (right, right_type)) = (column(left)?, column(right)?);
+            compare_typed(left, left_type, right, right_type, left_type.collation, operator, encoding)
+        }
+        Expr::And(left, right) => match condition(left, operands, encoding)? {
+            Some(false) => Some(false),
+            left => match (left, condition(right, operands, encoding)?) {
+                (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+        },
+        Expr::Or(left, right) => match condition(left, operands, encoding)? {
+            Some(true) => Some(true),
+            left => match (left, condition(right, operands, encoding)?) {
+                (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+        },
+        Expr::Not(expr) => condition(expr, operands, encoding)?.map(|holds| !holds),
+        Expr::IsNull(name) => Some(column(name)?.0.is_null()),
+        Expr::IsNotNull(name) => Some(!column(name)?.0.is_null()),
+        Expr::In { column: name, values } => {
+            let (column_value, column_type) = column(name)?;
+            // Not found is unknown rather than false when the list holds NULL
+            let mut found = Some(false);
+            for value in values {
+                let equal = ComparisonOperator::Equal;
+                match compare_typed(column_value, column_type, value, ColumnType::UNTYPED, column_type.collation, &equal, encoding) {
+                    Some(true) => return Ok(Some(true)),
+                    Some(false) => {}
+                    None => found = None,
+                }
+            }
+            found
+        }
+        Expr::Between { column, low, high } => {
+            let lower = Expr::Comparison { column: column.clone(), operator: ComparisonOperator::GreaterThanOrEqual, value: low.clone() };
+            let upper = Expr::Comparison { column: column.clone(), operator: ComparisonOperator::LessThanOrEqual, value: high.clone() };
+            condition(&Expr::And(Box::new(lower), Box::new(upper)), operands, encoding)?
+        }
+        Expr::Scalar(expr) => truth(&*evaluate(expr, operands, encoding)?),
+    })
+}
+
+/// Whether a value counts as true, as a condition: None for NULL, and for
+/// TEXT and BLOB whether the number they start with is not zero
+pub(crate) fn truth(value: &Value) -> Option<bool> {
+    match value {
+        Value::Null => None,
+        Value::Integer(v) => Some(*v != 0),
+        Value::Real(v) => Some(*v != 0.0),
+        Value::Text(text) => Some(number_prefix(text.as_bytes()) != Value::Integer(0)),
+        Value::Blob(bytes) => Some(number_prefix(bytes) != Value::Integer(0)),
+    }
+}
+
+/// Evaluate `left op right` for two expressions, with the affinity and
+/// collation each of them brings
+fn compare_expressions(
+    left: &ScalarExpr,
+    left_value: &Value,
+    right: &ScalarExpr,
+    right_value: &Value,
+    operator: &ComparisonOperator,
+    operands: &dyn Operands,
+    encoding: TextEncoding,
+) -> Option<bool> {
+    // An explicit COLLATE wins, then the collation of a column, the left one first
+    let collation = explicit_collation(left)
+        .or_else(|| explicit_collation(right))
+        .or_else(|| column_collation(left, operands))
+        .or_else(|| column_collation(right, operands))
+        .unwrap_or(Collation::Binary);
+    let left_type = ColumnType { affinity: expr_affinity(left, operands), collation };
+    let right_type = ColumnType { affinity: expr_affinity(right, operands), collation };
+    compare_typed(left_value, left_type, right_value, right_type, collation, operator, encoding)
+}
+
+/// Evaluate `left op right`, converting the operands as their affinities ask
+/// first; None if either is NULL
+fn compare_typed(
+    left: &Value,
+    left_type: ColumnType,
+    right: &Value,
+    right_type: ColumnType,
+    collation: Collation,
+    operator: &ComparisonOperator,
+    encoding: TextEncoding,
+) -> Option<bool> {
+    if left.is_null() || right.is_null() {
+        return None;
+    }
+    if *operator == ComparisonOperator::Like {
+        return Some(like(&value_text(left), &value_text(right)));
+    }
+
+    let numeric = |affinity: Affinity| matches!(affinity, Affinity::Integer | Affinity::Real | Affinity::Numeric);
+    fn to_numeric(value: &Value) -> Cow<'_, Value> {
+        match value {
+            Value::Text(_) => Cow::Owned(Affinity::Numeric.apply(value.clone())),
+            _ => Cow::Borrowed(value),
+        }
+    }
+    fn to_text(value: &Value) -> Cow<'_, Value> {
+        match value {
+            Value::Integer(_) | Value::Real(_) => Cow::Owned(Affinity::Text.apply(value.clone())),
+            _ => Cow::Borrowed(value),
+        }
+    }
+    let (left_affinity, right_affinity) = (left_type.affinity, right_type.affinity);
+    let (left, right) = if numeric(left_affinity) && !numeric(right_affinity) {
+        (Cow::Borrowed(left), to_numeric(right))
+    } else if numeric(right_affinity) && !numeric(left_affinity) {
+        (to_numeric(left), Cow::Borrowed(right))
+    } else if left_affinity == Affinity::Text && right_affinity == Affinity::Blob {
+        (Cow::Borrowed(left), to_text(right))
+    } else if right_affinity == Affinity::Text && left_affinity == Affinity::Blob {
+        (to_text(left), Cow::Borrowed(right))
+    } else {
+        (Cow::Borrowed(left), Cow::Borrowed(right))
+    };
+
+    let ordering = collation.compare(&left, &right, encoding);
+    Some(match operator {
+        ComparisonOperator::Equal => ordering.is_eq(),
+        ComparisonOperator::NotEqual => ordering.is_ne(),
+        ComparisonOperator::LessThan => ordering.is_lt(),
+        ComparisonOperator::LessThanOrEqual => ordering.is_le(),
+        ComparisonOperator::GreaterThan => ordering.is_gt(),
+        ComparisonOperator::GreaterThanOrEqual => ordering.is_ge(),
+        ComparisonOperator::Like => unreachable!("LIKE was matched above"),
+    })
+}
+
+/// Collation an expression names with COLLATE, which carries through
+/// operators to the comparisons above them
+fn explicit_collation(expr: &ScalarExpr) -> Option<Collation> {
+    match expr {
+        ScalarExpr::Collate { collation, .. } => Some(Collation::from_name(Some(collation))),
+        ScalarExpr::Column(_) | ScalarExpr::Literal(_) | ScalarExpr::Aggregate(_) => None,
+        ScalarExpr::Unary { expr, .. } | ScalarExpr::Cast { expr, .. } => explicit_collation(expr),
+        ScalarExpr::Binary { left, right, .. } | ScalarExpr::Compare { left, right, .. } | ScalarExpr::Is { left, right, .. } => {
+            explicit_collation(left).or_else(|| explicit_collation(right))
+        }
+        ScalarExpr::Case { .. } => None,
+        ScalarExpr::Function { args, .. } => args.iter().find_map(explicit_collation),
+    }
+}
+
+/// Collation of the column an expression reads as it is, or through CAST
+fn column_collation(expr: &ScalarExpr, operands: &dyn Operands) -> Option<Collation> {
+    match expr {
+        ScalarExpr::Column(name) => operands.column(name).map(|(_, column_type)| column_type.collation),
+        ScalarExpr::Cast { expr, .. } => column_collation(expr, operands),
+        _ => None,
+    }
+}
+
+/// Collation an expression defines, by COLLATE or as a column, if any
+pub(crate) fn defined_collation(expr: &ScalarExpr, operands: &dyn Operands) -> Option<Collation> {
+    explicit_collation(expr).or_else(|| column_collation(expr, operands))
+}
+
+/// The collation an ORDER BY or GROUP BY term compares TEXT with
+pub(crate) fn collation_of(expr: &ScalarExpr, operands: &dyn Operands) -> Collation {
+    defined_collation(expr, operands).unwrap_or(Collation::Binary)
+}
+
+/// Affinity an expression has in comparisons: that of a column, or of the
+/// type a CAST names; anything else has none, like a BLOB column
+fn expr_affinity(expr: &ScalarExpr, operands: &dyn Operands) -> Affinity {
+    match expr {
+        ScalarExpr::Column(name) => operands.column(name).map_or(Affinity::Blob, |(_, column_type)| column_type.affinity),
+        ScalarExpr::Cast { affinity, .. } => *affinity,
+        ScalarExpr::Collate { expr, .. } => expr_affinity(expr, operands),
+        _ => Affinity::Blob,
+    }
+}
+
+fn unary(operator: UnaryOperator, value: &Value) -> Value {
+    match (operator, value) {
+        (_, Value::Null) => Value::Null,
+        (UnaryOperator::Not, value) => Value::Integer(i64::from(truth(value) == Some(false))),
+        (UnaryOperator::Negate, value) => match number(value) {
+            Value::Integer(v) => v.checked_neg().map_or(Value::Real(-(v as f64)), Value::Integer),
+            Value::Real(v) => Value::Real(-v),
+            _ => Value::Null,
+        },
+    }
+}
+
+fn binary(operator: BinaryOperator, left: &Value, right: &Value) -> Value {
+    if left.is_null() || right.is_null() {
+        return Value::Null;
+    }
+    match operator {
+        BinaryOperator::Concat => {
+            let mut text = value_text(left);
+            text.push_str(&value_text(right));
+            Value::Text(text)
+        }
+        BinaryOperator::BitAnd => Value::Integer(integer(left) & integer(right)),
+        BinaryOperator::BitOr => Value::Integer(integer(left) | integer(right)),
+        BinaryOperator::Remainder => {
+            let divisor = match integer(right) {
+                0 => return Value::Null,
+                // Avoids overflowing on i64::MIN % -1, which is 0 either way
+                -1 => 1,
+                divisor => divisor,
+            };
+            let remainder = integer(left) % divisor;
+            match (number(left), number(right)) {
+                (Value::Integer(_), Value::Integer(_)) => Value::Integer(remainder),
+                _ => Value::Real(remainder as f64),
+            }
+        }
+        BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide => {
+            arithmetic(operator, number(left), number(right))
+        }
+        BinaryOperator::And | BinaryOperator::Or => unreachable!("logical operators short-circuit"),
+    }
+}
+
+/// `+`, `-`, `*` or `/` on two numbers: INTEGER unless a REAL is involved or
+/// the result overflows, and NULL for division by zero
+fn arithmetic(operator: BinaryOperator, left: Value, right: Value) -> Value {
+    if let (Value::Integer(a), Value::Integer(b)) = (&left, &right) {
+        let (a, b) = (*a, *b);
+        let result = match operator {
+            BinaryOperator::Add => a.checked_add(b),
+            BinaryOperator::Subtract => a.checked_sub(b),
+            BinaryOperator::Multiply => a.checked_mul(b),
+            _ if b == 0 => return Value::Null,
+            _ => a.checked_div(b),
+        };
+        if let Some(result) = result {
+            return Value::Integer(result);
+        }
+    }
+    let (a, b) = (left.as_real().unwrap_or(0.0), right.as_real().unwrap_or(0.0));
+    let result = match operator {
+        BinaryOperator::Add => a + b,
+        BinaryOperator::Subtract => a - b,
+        BinaryOperator::Multiply => a * b,
+        _ if b == 0.0 => return Value::Null,
+        _ => a / b,
+    };
+    // Infinity minus infinity and the like have no value
+    if result.is_nan() { Value::Null } else { Value::Real(result) }
+}
+
+/// A value as a number for arithmetic, reading TEXT and BLOB up to the end of
+/// the number they start with
+fn number(value: &Value) -> Value {
+    match value {
+        Value::Integer(_) | Value::Real(_) => value.clone(),
+        Value::Text(text) => number_prefix(text.as_bytes()),
+        Value::Blob(bytes) => number_prefix(bytes),
+        Value::Null => Value::Null,
+    }
+}
+
+/// A value as an INTEGER for bitwise operators and `%`, with REALs truncated
+fn integer(value: &Value) -> i64 {
+    match number(value) {
+        Value::Integer(v) => v,
+        Value::Real(v) => v as i64,
+        _ => 0,
+    }
+}
+
+/// The number `bytes` start with after any spaces, or 0: an INTEGER unless it
+/// has a decimal point or an exponent, or does not fit in 64 bits
+fn number_prefix(bytes: &[u8]) -> Value {
+    let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let start = bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let mut end = start + usize::from(matches!(bytes.get(start), Some(b'+' | b'-')));
+    let whole = digits(end);
+    end += whole;
+    let mut real = false;
+    if bytes.get(end) == Some(&b'.') && (whole > 0 || digits(end + 1) > 0) {
+        real = true;
+        end += 1 + digits(end + 1);
+    }
+    if whole == 0 && !real {
+        return Value::Integer(0);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent = digits(end + 1 + sign);
+        if exponent > 0 {
+            real = true;
+            end += 1 + sign + exponent;
+        }
+    }
+
+    // The prefix is ASCII, so it is valid UTF-8
+    let text = core::str::from_utf8(&bytes[start..end]).unwrap_or_default();
+    if !real {
+        if let Ok(v) = text.parse::<i64>() {
+            return Value::Integer(v);
+        }
+    }
+    Value::Real(text.parse::<f64>().unwrap_or(0.0))
+}
+
+/// The integer `bytes` start with after any spaces, saturating at the
+/// bounds of 64 bits, as `CAST(text AS INTEGER)` reads it
+fn integer_prefix(bytes: &[u8]) -> i64 {
+    let start = bytes.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let mut digits = bytes[start..].iter().peekable();
+    let negative = match digits.peek() {
+        Some(b'-') => {
+            digits.next();
+            true
+        }
+        Some(b'+') => {
+            digits.next();
+            false
+        }
+        _ => false,
+    };
+    let mut value: i128 = 0;
+    for digit in digits.take_while(|b| b.is_ascii_digit()) {
+        value = (value * 10 + i128::from(digit - b'0')).min(i128::from(i64::MAX) + 1);
+    }
+    let value = if negative { -value } else { value };
+    value.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64
+}
+
+/// Convert a value as `CAST(value AS type)` does for a type of this affinity
+pub(crate) fn cast(value: Value, affinity: Affinity) -> Value {
+    let bytes = |value: &Value| -> Vec<u8> {
+        match value {
+            Value::Blob(bytes) => bytes.clone(),
+            value => value_text(value).into_bytes(),
+        }
+    };
+    match (affinity, value) {
+        (_, Value::Null) => Value::Null,
+        (Affinity::Integer, Value::Integer(v)) => Value::Integer(v),
+        // Conversion saturates, and NaN becomes 0
+        (Affinity::Integer, Value::Real(v)) => Value::Integer(v as i64),
+        (Affinity::Integer, value) => Value::Integer(integer_prefix(&bytes(&value))),
+        (Affinity::Real, value) => match number(&value) {
+            Value::Integer(v) => Value::Real(v as f64),
+            number => number,
+        },
+        // Text that reads as a whole number becomes an INTEGER, while REALs stay REAL
+        (Affinity::Numeric, value @ (Value::Integer(_) | Value::Real(_))) => value,
+        (Affinity::Numeric, value) => match number(&value) {
+            Value::Real(v) if (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&v) && (v as i64) as f64 == v => {
+                Value::Integer(v as i64)
+            }
+            number => number,
+        },
+        (Affinity::Text, value @ Value::Text(_)) => value,
+        (Affinity::Text, value) => Value::Text(value_text(&value)),
+        (Affinity::Blob, value) => Value::Blob(bytes(&value)),
+    }
+}
+
+/// Text form of a value, as `||`, LIKE and group_concat read it
+pub(crate) fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(v) => v.to_string(),
+        Value::Real(v) => format_real(*v),
+        Value::Text(text) => text.clone(),
+        Value::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Whether `text` matches a LIKE pattern, where `%` matches any run of
+/// characters and `_` any single character. As in SQLite, upper and lower
+/// case ASCII letters match each other, while other characters match exactly
+pub(crate) fn like(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    // Greedy match, backtracking to the most recent `%`
+    let (mut t, mut p) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '_' || c.eq_ignore_ascii_case(&text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `%` absorb one more character
+                Some((bp, bt)) => {
+                    p = bp;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+/// Sort rows by the value of `key`, NULLs first, comparing TEXT with the
+/// collation of the column or COLLATE clause the key names
+pub(crate) fn sort_rows(rows: &mut Vec<Row>, key: &ScalarExpr, ascending: bool, context: &Context) -> Result<()> {
+    let Some(first) = rows.first() else {
+        return Ok(());
+    };
+    let collation = collation_of(key, &RowOperands { row: first, context });
+    let mut keyed = Vec::with_capacity(rows.len());
+    for row in rows.drain(..) {
+        let value = evaluate(key, &RowOperands { row: &row, context }, context.encoding)?.into_owned();
+        keyed.push((value, row));
+    }
+    keyed.sort_by(|(a, _), (b, _)| {
+        let ordering = collation.compare(a, b, context.encoding);
+        if ascending { ordering } else { ordering.reverse() }
+    });
+    rows.extend(keyed.into_iter().map(|(_, row)| row));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_prefix() {
+        let cases: &[(&str, Value)] = &[
+            ("12abc", Value::Integer(12)),
+            ("  3 ", Value::Integer(3)),
+            ("3.0", Value::Real(3.0)),
+            ("0x10", Value::Integer(0)),
+            ("1e2", Value::Real(100.0)),
+            ("1e", Value::Integer(1)),
+            (".5", Value::Real(0.5)),
+            ("5.", Value::Real(5.0)),
+            ("-", Value::Integer(0)),
+            ("+7", Value::Integer(7)),
+            ("abc", Value::Integer(0)),
+            ("9223372036854775808", Value::Real(9_223_372_036_854_775_808.0)),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(&number_prefix(text.as_bytes()), expected, "{}", text);
+        }
+        assert_eq!(integer_prefix(b" -12.9"), -12);
+        assert_eq!(integer_prefix(b"99999999999999999999"), i64::MAX);
+        assert_eq!(integer_prefix(b"-99999999999999999999"), i64::MIN);
+    }
+
+    #[test]
+    fn test_compare_integer_real() {
+        assert_eq!(compare_integer_real(9_007_199_254_740_993, 9_007_199_254_740_992.0), Ordering::Greater);
+        assert_eq!(compare_integer_real(1, 1.0), Ordering::Equal);
+        assert_eq!(compare_integer_real(1, 1.5), Ordering::Less);
+        assert_eq!(compare_integer_real(-1, -1.5), Ordering::Greater);
+        assert_eq!(compare_integer_real(i64::MAX, 1e19), Ordering::Less);
+    }
+}
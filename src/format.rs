@@ -1,5 +1,11 @@
 //! SQLite file format constants and structures
 
+use crate::{Error, Result, Value};
+use core::cmp::Ordering;
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
 /// SQLite file header magic string
 pub const SQLITE_HEADER_MAGIC: &[u8; 16] = b"SQLite format 3\0";
 
@@ -43,7 +49,7 @@ pub struct FileHeader {
     pub default_cache_size: u32,
     /// Largest root page number
     pub largest_root_page: u32,
-    /// Text encoding
+    /// Text encoding (1 = UTF-8, 2 = UTF-16le, 3 = UTF-16be); see [`FileHeader::encoding`]
     pub text_encoding: u32,
     /// User version
     pub user_version: u32,
@@ -57,6 +63,148 @@ pub struct FileHeader {
     pub sqlite_version: u32,
 }
 
+impl FileHeader {
+    /// Encoding of every TEXT value in the database
+    ///
+    /// Databases that have never had a schema written may store 0, which
+    /// SQLite treats as UTF-8.
+    pub fn encoding(&self) -> TextEncoding {
+        TextEncoding::from_header_value(self.text_encoding).unwrap_or_default()
+    }
+
+    /// Number of usable bytes on each page (page size minus reserved space)
+    pub fn usable_size(&self) -> usize {
+        self.page_size as usize - self.reserved_space as usize
+    }
+
+    /// Number of payload bytes stored on the b-tree page itself for a cell
+    /// whose total payload is `payload_size` bytes. Anything beyond this is
+    /// stored in a chain of overflow pages.
+    ///
+    /// `table_leaf` selects the thresholds for table b-tree leaf cells; index
+    /// cells (leaf and interior) use the embedded payload fractions.
+    pub fn local_payload_size(&self, payload_size: usize, table_leaf: bool) -> usize {
+        let usable = self.usable_size();
+        let (max_local, min_local) = if table_leaf {
+            (
+                usable - 35,
+                ((usable - 12) * self.leaf_payload_fraction as usize / 255).saturating_sub(23),
+            )
+        } else {
+            (
+                ((usable - 12) * self.max_payload_fraction as usize / 255).saturating_sub(23),
+                ((usable - 12) * self.min_payload_fraction as usize / 255).saturating_sub(23),
+            )
+        };
+
+        if payload_size <= max_local {
+            return payload_size;
+        }
+
+        let surplus = min_local + (payload_size - min_local) % (usable - 4);
+        if surplus <= max_local {
+            surplus
+        } else {
+            min_local
+        }
+    }
+}
+
+/// Text encoding of a database, stored at offset 56 of the header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Utf16le,
+    Utf16be,
+}
+
+impl TextEncoding {
+    /// Encoding for a header value, or None if it is not 1, 2 or 3
+    pub fn from_header_value(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(TextEncoding::Utf8),
+            2 => Some(TextEncoding::Utf16le),
+            3 => Some(TextEncoding::Utf16be),
+            _ => None,
+        }
+    }
+
+    /// Decode TEXT content, failing on invalid UTF-8 or unpaired surrogates
+    pub fn decode(self, bytes: &[u8]) -> Result<String> {
+        match self {
+            TextEncoding::Utf8 => Ok(core::str::from_utf8(bytes)?.into()),
+            _ if !bytes.len().is_multiple_of(2) => Err(Error::InvalidRecord),
+            _ => char::decode_utf16(self.code_units(bytes))
+                .collect::<core::result::Result<String, _>>()
+                .map_err(|_| Error::InvalidRecord),
+        }
+    }
+
+    /// Decode TEXT content, replacing anything invalid with U+FFFD
+    pub fn decode_lossy(self, bytes: &[u8]) -> String {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            _ => {
+                let mut text: String = char::decode_utf16(self.code_units(bytes))
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect();
+                if !bytes.len().is_multiple_of(2) {
+                    text.push(char::REPLACEMENT_CHARACTER);
+                }
+                text
+            }
+        }
+    }
+
+    /// Encode text as it is stored in a database of this encoding
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            TextEncoding::Utf8 => text.as_bytes().to_vec(),
+            TextEncoding::Utf16le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            TextEncoding::Utf16be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+        }
+    }
+
+    /// UTF-16 code units of `bytes`, ignoring a trailing odd byte
+    fn code_units(self, bytes: &[u8]) -> impl Iterator<Item = u16> + '_ {
+        bytes.chunks_exact(2).map(move |pair| match self {
+            TextEncoding::Utf16be => u16::from_be_bytes([pair[0], pair[1]]),
+            _ => u16::from_le_bytes([pair[0], pair[1]]),
+        })
+    }
+
+    /// Compare two values the way SQLite orders them in an index of this database
+    ///
+    /// The BINARY collation compares the stored bytes of TEXT values, so its
+    /// order depends on the encoding: UTF-16be sorts surrogate pairs before
+    /// U+E000..U+FFFF, and UTF-16le effectively compares low bytes first.
+    pub fn compare(self, a: &Value, b: &Value) -> Ordering {
+        match (self, a, b) {
+            (TextEncoding::Utf8, _, _) => a.cmp(b),
+            (TextEncoding::Utf16be, Value::Text(a), Value::Text(b)) => a.encode_utf16().cmp(b.encode_utf16()),
+            (TextEncoding::Utf16le, Value::Text(a), Value::Text(b)) => a
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .cmp(b.encode_utf16().flat_map(u16::to_le_bytes)),
+            _ => a.cmp(b),
+        }
+    }
+
+    /// Compare index keys column by column, up to the shorter of the two
+    pub fn compare_keys<'a, 'b>(
+        self,
+        a: impl IntoIterator<Item = &'a Value>,
+        b: impl IntoIterator<Item = &'b Value>,
+    ) -> Ordering {
+        a.into_iter()
+            .zip(b)
+            .map(|(a, b)| self.compare(a, b))
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
 /// Page types in SQLite
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PageType {
@@ -89,6 +237,8 @@ impl PageType {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::format;
 
     #[test]
     fn test_sqlite_header_magic() {
@@ -122,6 +272,27 @@ mod tests {
         assert_eq!(format!("{:?}", PageType::LeafIndex), "LeafIndex");
     }
 
+    #[test]
+    fn test_text_encoding() {
+        assert_eq!(TextEncoding::from_header_value(2), Some(TextEncoding::Utf16le));
+        assert_eq!(TextEncoding::from_header_value(4), None);
+
+        assert_eq!(TextEncoding::Utf16le.decode(&[0x68, 0x00, 0xe9, 0x00]).unwrap(), "hé");
+        assert_eq!(TextEncoding::Utf16be.decode(&[0xd8, 0x3d, 0xde, 0x00]).unwrap(), "\u{1f600}");
+        assert!(TextEncoding::Utf16be.decode(&[0xd8, 0x3d]).is_err());
+        assert!(TextEncoding::Utf16le.decode(&[0x68]).is_err());
+        assert_eq!(TextEncoding::Utf16le.decode_lossy(&[0x68, 0x00, 0x69]), "h\u{fffd}");
+
+        // U+FF21 sorts after U+1F600 in UTF-8 but before it in UTF-16be
+        let (fullwidth, emoji) = (Value::Text("\u{ff21}".into()), Value::Text("\u{1f600}".into()));
+        assert_eq!(TextEncoding::Utf8.compare(&fullwidth, &emoji), Ordering::Less);
+        assert_eq!(TextEncoding::Utf16be.compare(&fullwidth, &emoji), Ordering::Greater);
+        // Little-endian compares the low byte first, so U+0200 (00 02) sorts before U+0101 (01 01)
+        let (a, b) = (Value::Text("\u{0200}".into()), Value::Text("\u{0101}".into()));
+        assert_eq!(TextEncoding::Utf16le.compare(&a, &b), Ordering::Less);
+        assert_eq!(TextEncoding::Utf16le.compare_keys(&[Value::Integer(1), a], &[Value::Integer(1)]), Ordering::Equal);
+    }
+
     #[test]
     fn test_file_header_creation() {
         let header = FileHeader {
@@ -157,6 +328,49 @@ mod tests {
         assert_eq!(header.sqlite_version, 3039000);
     }
 
+    #[test]
+    fn test_local_payload_size() {
+        let header = FileHeader {
+            page_size: 4096,
+            write_version: 1,
+            read_version: 1,
+            reserved_space: 0,
+            max_payload_fraction: 64,
+            min_payload_fraction: 32,
+            leaf_payload_fraction: 32,
+            file_change_counter: 1,
+            database_size: 10,
+            first_freelist_page: 0,
+            freelist_pages: 0,
+            schema_cookie: 1,
+            schema_format: 4,
+            default_cache_size: 0,
+            largest_root_page: 0,
+            text_encoding: 1,
+            user_version: 0,
+            incremental_vacuum: 0,
+            application_id: 0,
+            version_valid_for: 1,
+            sqlite_version: 3039000,
+        };
+
+        assert_eq!(header.usable_size(), 4096);
+
+        // Table leaf cells keep up to U-35 bytes locally
+        assert_eq!(header.local_payload_size(100, true), 100);
+        assert_eq!(header.local_payload_size(4061, true), 4061);
+        assert_eq!(header.local_payload_size(5000, true), 908);
+
+        // Index cells spill much earlier
+        assert_eq!(header.local_payload_size(1002, false), 1002);
+        assert_eq!(header.local_payload_size(2000, false), 489);
+
+        let reserved = FileHeader { reserved_space: 32, ..header };
+        assert_eq!(reserved.usable_size(), 4064);
+        assert_eq!(reserved.local_payload_size(4029, true), 4029);
+        assert!(reserved.local_payload_size(4030, true) < 4030);
+    }
+
     #[test]
     fn test_file_header_debug() {
         let header = FileHeader {
@@ -0,0 +1,884 @@
+//! SQLite's built-in scalar functions, looked up by name when a query is
+//! parsed and called when its expressions are evaluated
+
+use crate::{
+    database::Affinity,
+    eval::{self, value_text, Collation, Operands},
+    format::TextEncoding,
+    query::{ScalarExpr, ScalarFunction},
+    Error, Result, Value,
+};
+use alloc::{borrow::Cow, sync::Arc};
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::{String, ToString}, vec, vec::Vec};
+
+/// Every function with the names it is called by and the numbers of
+/// arguments it takes
+const FUNCTIONS: &[(&str, ScalarFunction, RangeInclusive<usize>)] = &[
+    ("abs", ScalarFunction::Abs, 1..=1),
+    ("char", ScalarFunction::Char, 0..=usize::MAX),
+    ("coalesce", ScalarFunction::Coalesce, 2..=usize::MAX),
+    ("format", ScalarFunction::Printf, 0..=usize::MAX),
+    ("hex", ScalarFunction::Hex, 1..=1),
+    ("ifnull", ScalarFunction::IfNull, 2..=2),
+    ("iif", ScalarFunction::Iif, 2..=3),
+    ("instr", ScalarFunction::Instr, 2..=2),
+    ("length", ScalarFunction::Length, 1..=1),
+    ("lower", ScalarFunction::Lower, 1..=1),
+    ("ltrim", ScalarFunction::Ltrim, 1..=2),
+    ("max", ScalarFunction::Max, 2..=usize::MAX),
+    ("min", ScalarFunction::Min, 2..=usize::MAX),
+    ("nullif", ScalarFunction::NullIf, 2..=2),
+    ("printf", ScalarFunction::Printf, 0..=usize::MAX),
+    ("quote", ScalarFunction::Quote, 1..=1),
+    ("random", ScalarFunction::Random, 0..=0),
+    ("replace", ScalarFunction::Replace, 3..=3),
+    ("round", ScalarFunction::Round, 1..=2),
+    ("rtrim", ScalarFunction::Rtrim, 1..=2),
+    ("substr", ScalarFunction::Substr, 2..=3),
+    ("substring", ScalarFunction::Substr, 2..=3),
+    ("trim", ScalarFunction::Trim, 1..=2),
+    ("typeof", ScalarFunction::TypeOf, 1..=1),
+    ("unicode", ScalarFunction::Unicode, 1..=1),
+    ("upper", ScalarFunction::Upper, 1..=1),
+];
+
+/// The function called `name`, in any case, and the numbers of arguments it takes
+pub(crate) fn lookup(name: &str) -> Option<(ScalarFunction, RangeInclusive<usize>)> {
+    FUNCTIONS
+        .iter()
+        .find(|(candidate, ..)| candidate.eq_ignore_ascii_case(name))
+        .map(|(_, function, arity)| (*function, arity.clone()))
+}
+
+/// The name a function is shown with
+pub(crate) fn name(function: ScalarFunction) -> &'static str {
+    FUNCTIONS
+        .iter()
+        .find(|(_, candidate, _)| *candidate == function)
+        .map_or("", |(name, ..)| name)
+}
+
+/// Generator behind `random()`, shared by the contexts of a database so that
+/// a seeded database draws one sequence across its queries
+#[derive(Debug, Clone)]
+pub(crate) struct Random(Arc<AtomicU64>);
+
+impl Random {
+    /// A generator starting from `seed`, or from a seed of its own
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        Random(Arc::new(AtomicU64::new(seed.unwrap_or_else(fresh_seed))))
+    }
+
+    /// The next number of the sequence (splitmix64)
+    pub(crate) fn draw(&self) -> i64 {
+        const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut z = self.0.fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)) as i64
+    }
+}
+
+/// A seed that differs for every generator: from the process's hash keys with
+/// `std`, and from a count of the generators alone without it
+fn fresh_seed() -> u64 {
+    static CREATED: AtomicU64 = AtomicU64::new(0);
+    let count = CREATED.fetch_add(1, Ordering::Relaxed);
+    #[cfg(feature = "std")]
+    {
+        use std::hash::BuildHasher;
+        std::collections::hash_map::RandomState::new().hash_one(count)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        count.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// Call `function` on `args`, evaluating only the arguments it needs
+pub(crate) fn call<'a>(
+    function: ScalarFunction,
+    args: &'a [ScalarExpr],
+    operands: &'a dyn Operands,
+    encoding: TextEncoding,
+) -> Result<Cow<'a, Value>> {
+    let value = |index: usize| eval::evaluate(&args[index], operands, encoding);
+
+    // Functions that choose between their arguments only evaluate those they return
+    match function {
+        ScalarFunction::Coalesce | ScalarFunction::IfNull => {
+            for (index, arg) in args.iter().enumerate() {
+                let value = eval::evaluate(arg, operands, encoding)?;
+                if !value.is_null() || index == args.len() - 1 {
+                    return Ok(value);
+                }
+            }
+            return Ok(Cow::Owned(Value::Null));
+        }
+        ScalarFunction::Iif => {
+            return match eval::truth(&*value(0)?) {
+                Some(true) => value(1),
+                _ if args.len() > 2 => value(2),
+                _ => Ok(Cow::Owned(Value::Null)),
+            };
+        }
+        ScalarFunction::Random => return Ok(Cow::Owned(Value::Integer(operands.context().random()))),
+        _ => {}
+    }
+
+    let values = (0..args.len()).map(value).collect::<Result<Vec<_>>>()?;
+    let values: Vec<&Value> = values.iter().map(|value| &**value).collect();
+    let text = |index: usize| value_text(values[index]);
+    let any_null = values.iter().any(|value| value.is_null());
+
+    Ok(Cow::Owned(match function {
+        ScalarFunction::Coalesce | ScalarFunction::IfNull | ScalarFunction::Iif | ScalarFunction::Random => {
+            unreachable!("called above")
+        }
+        // The rest of the functions, other than these, are NULL for a NULL argument
+        ScalarFunction::Char => Value::Text(
+            values
+                .iter()
+                .flat_map(|value| match u32::try_from(int_arg(value)) {
+                    Ok(code @ 0..=0x10ffff) => match char::from_u32(code) {
+                        Some(c) => vec![c],
+                        // SQLite writes a surrogate as three bytes that are not UTF-8
+                        None => vec![char::REPLACEMENT_CHARACTER; 3],
+                    },
+                    _ => vec![char::REPLACEMENT_CHARACTER],
+                })
+                .collect(),
+        ),
+        ScalarFunction::Hex => Value::Text(
+            bytes(values[0], encoding).iter().map(|byte| format!("{:02X}", byte)).collect(),
+        ),
+        ScalarFunction::Printf => match values.first() {
+            Some(Value::Null) | None => Value::Null,
+            Some(format) => printf(&value_text(format), &values[1..]).map_or(Value::Null, Value::Text),
+        },
+        ScalarFunction::Quote => Value::Text(quote(values[0])),
+        ScalarFunction::TypeOf => Value::Text(values[0].type_name().to_ascii_lowercase()),
+        ScalarFunction::NullIf => {
+            let collation = leftmost_collation(args, operands);
+            if !any_null && collation.compare(values[0], values[1], encoding).is_eq() {
+                Value::Null
+            } else {
+                values[0].clone()
+            }
+        }
+        // An empty pattern leaves the text as it is, even for a NULL replacement
+        ScalarFunction::Replace if !values[0].is_null() && !values[1].is_null() && text(1).is_empty() => {
+            Value::Text(text(0))
+        }
+        _ if any_null => Value::Null,
+        ScalarFunction::Replace => Value::Text(text(0).replace(text(1).as_str(), &text(2))),
+        ScalarFunction::Abs => match values[0] {
+            Value::Integer(v) => Value::Integer(v.checked_abs().ok_or_else(|| Error::QueryError("integer overflow".to_string()))?),
+            value => Value::Real(real_arg(value).abs()),
+        },
+        ScalarFunction::Instr => match (values[0], values[1]) {
+            (Value::Blob(haystack), Value::Blob(needle)) => Value::Integer(match find(haystack, needle) {
+                Some(position) => position as i64 + 1,
+                None => 0,
+            }),
+            _ => {
+                let (haystack, needle) = (text(0), text(1));
+                Value::Integer(match haystack.find(needle.as_str()) {
+                    Some(position) => haystack[..position].chars().count() as i64 + 1,
+                    None => 0,
+                })
+            }
+        },
+        ScalarFunction::Length => Value::Integer(match values[0] {
+            Value::Blob(bytes) => bytes.len(),
+            // SQLite counts the characters up to the first NUL
+            value => value_text(value).chars().take_while(|&c| c != '\0').count(),
+        } as i64),
+        ScalarFunction::Lower => Value::Text(text(0).to_ascii_lowercase()),
+        ScalarFunction::Upper => Value::Text(text(0).to_ascii_uppercase()),
+        ScalarFunction::Ltrim | ScalarFunction::Rtrim | ScalarFunction::Trim => {
+            let characters: Vec<char> = if values.len() > 1 { text(1).chars().collect() } else { vec![' '] };
+            let subject = text(0);
+            let trimmed = match function {
+                ScalarFunction::Ltrim => subject.trim_start_matches(characters.as_slice()),
+                ScalarFunction::Rtrim => subject.trim_end_matches(characters.as_slice()),
+                _ => subject.trim_matches(characters.as_slice()),
+            };
+            Value::Text(trimmed.to_string())
+        }
+        ScalarFunction::Max | ScalarFunction::Min => {
+            let collation = leftmost_collation(args, operands);
+            // Of equal values max() keeps the first and min() takes the last
+            let chosen = values.iter().copied().reduce(|chosen, value| {
+                let ordering = collation.compare(value, chosen, encoding);
+                let better = if function == ScalarFunction::Max { ordering.is_gt() } else { ordering.is_le() };
+                if better { value } else { chosen }
+            });
+            chosen.cloned().unwrap_or(Value::Null)
+        }
+        ScalarFunction::Round => {
+            let digits = values.get(1).map_or(0, |digits| int_arg(digits).clamp(0, 30));
+            Value::Real(round(real_arg(values[0]), digits as usize))
+        }
+        ScalarFunction::Substr => {
+            let start = int_arg(values[1]);
+            let length = values.get(2).map(|length| int_arg(length));
+            match values[0] {
+                // SQLite has no bytes to take from an empty blob, and returns NULL
+                Value::Blob(bytes) if bytes.is_empty() => Value::Null,
+                Value::Blob(bytes) => {
+                    let (from, to) = substr_range(bytes.len(), start, length);
+                    Value::Blob(bytes[from..to].to_vec())
+                }
+                value => {
+                    let chars: Vec<char> = value_text(value).chars().collect();
+                    let (from, to) = substr_range(chars.len(), start, length);
+                    Value::Text(chars[from..to].iter().collect())
+                }
+            }
+        }
+        ScalarFunction::Unicode => match text(0).chars().next() {
+            Some(c) => Value::Integer(i64::from(u32::from(c))),
+            None => Value::Null,
+        },
+    }))
+}
+
+/// Collation `min()`, `max()` and `nullif()` compare TEXT with: that of their
+/// leftmost argument that defines one
+fn leftmost_collation(args: &[ScalarExpr], operands: &dyn Operands) -> Collation {
+    args.iter().find_map(|arg| eval::defined_collation(arg, operands)).unwrap_or(Collation::Binary)
+}
+
+/// A value as an INTEGER argument, as `sqlite3_value_int64()` reads it
+fn int_arg(value: &Value) -> i64 {
+    match eval::cast(value.clone(), Affinity::Integer) {
+        Value::Integer(v) => v,
+        _ => 0,
+    }
+}
+
+/// A value as a REAL argument, as `sqlite3_value_double()` reads it
+fn real_arg(value: &Value) -> f64 {
+    match eval::cast(value.clone(), Affinity::Real) {
+        Value::Real(v) => v,
+        _ => 0.0,
+    }
+}
+
+/// The bytes of a value, with TEXT in the database's encoding
+fn bytes(value: &Value, encoding: TextEncoding) -> Vec<u8> {
+    match value {
+        Value::Blob(bytes) => bytes.clone(),
+        Value::Null => Vec::new(),
+        value => encoding.encode(&value_text(value)),
+    }
+}
+
+/// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// The range `substr()` takes from `len` characters or bytes, for a 1-based
+/// `start` that counts from the end when negative and a `length` that
+/// reaches back before `start` when negative
+fn substr_range(len: usize, start: i64, length: Option<i64>) -> (usize, usize) {
+    let len = len as i64;
+    let (mut p1, mut p2) = (start, length.map_or(i64::MAX, i64::saturating_abs));
+    if p1 < 0 {
+        p1 = p1.saturating_add(len);
+        if p1 < 0 {
+            p2 = p2.saturating_add(p1).max(0);
+            p1 = 0;
+        }
+    } else if p1 > 0 {
+        p1 -= 1;
+    } else if p2 > 0 {
+        // Position 0 is just before the first character
+        p2 -= 1;
+    }
+    if length.is_some_and(|length| length < 0) {
+        p1 -= p2;
+        if p1 < 0 {
+            p2 += p1;
+            p1 = 0;
+        }
+    }
+    let from = p1.min(len);
+    (from as usize, p1.saturating_add(p2).min(len) as usize)
+}
+
+/// `round(value, digits)`, rounding half away from zero
+fn round(value: f64, digits: usize) -> f64 {
+    // Beyond 2^52 a REAL has no fractional part
+    if !(-4_503_599_627_370_496.0..=4_503_599_627_370_496.0).contains(&value) {
+        value
+    } else if digits == 0 {
+        (value + if value < 0.0 { -0.5 } else { 0.5 }) as i64 as f64
+    } else {
+        let text = format_real(value, Conversion::Float, 'e', &Spec { alternate2: true, precision: Some(digits), ..Spec::default() });
+        text.parse().unwrap_or(value)
+    }
+}
+
+/// `quote(value)`: the value as an SQL literal
+fn quote(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(v) => v.to_string(),
+        Value::Real(v) => {
+            // Fifteen digits, unless it takes twenty to read back the same value
+            let text = format_real(*v, Conversion::Generic, 'e', &Spec { alternate2: true, precision: Some(15), ..Spec::default() });
+            if text.parse::<f64>().ok() == Some(*v) {
+                text
+            } else {
+                format_real(*v, Conversion::Exponent, 'e', &Spec { alternate2: true, precision: Some(20), ..Spec::default() })
+            }
+        }
+        Value::Text(text) => format!("'{}'", text.replace('\'', "''")),
+        Value::Blob(bytes) => {
+            let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("X'{}'", hex)
+        }
+    }
+}
+
+/// Flags, width and precision of a `printf()` conversion
+#[derive(Debug, Default)]
+struct Spec {
+    /// `-`: pad on the right
+    left: bool,
+    /// `+` or ` `: what positive numbers start with
+    sign: Option<u8>,
+    /// `#`: `0x` before hexadecimal, trailing zeros kept by `%g`
+    alternate: bool,
+    /// `!`: characters rather than bytes for strings, and more digits for REALs
+    alternate2: bool,
+    /// `0`: pad numbers with zeros
+    zero: bool,
+    /// `,`: separate thousands
+    thousands: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// How `printf()` writes a REAL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conversion {
+    /// `%f`
+    Float,
+    /// `%e` and `%E`
+    Exponent,
+    /// `%g` and `%G`
+    Generic,
+}
+
+/// `printf(format, args...)` as SQLite formats it, reading each argument as
+/// the conversion asks and a missing one as NULL; None when it writes nothing
+/// at all, as for an empty format, which SQLite returns as NULL
+fn printf(format: &str, args: &[&Value]) -> Option<String> {
+    let mut args = args.iter().copied();
+    let mut next = || args.next().unwrap_or(&Value::Null);
+    let format = format.as_bytes();
+    let mut out: Vec<u8> = Vec::new();
+    // Whether anything was written, even an empty string
+    let mut written = false;
+    let mut i = 0;
+    while i < format.len() {
+        if format[i] != b'%' {
+            let end = format[i..].iter().position(|&b| b == b'%').map_or(format.len(), |n| i + n);
+            out.extend_from_slice(&format[i..end]);
+            written = true;
+            i = end;
+            continue;
+        }
+        i += 1;
+        if i == format.len() {
+            out.push(b'%');
+            written = true;
+            break;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(&flag) = format.get(i) {
+            match flag {
+                b'-' => spec.left = true,
+                b'+' => spec.sign = Some(b'+'),
+                b' ' => spec.sign = spec.sign.or(Some(b' ')),
+                b'#' => spec.alternate = true,
+                b'!' => spec.alternate2 = true,
+                b'0' => spec.zero = true,
+                b',' => spec.thousands = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        if format.get(i) == Some(&b'*') {
+            let width = int_arg(next());
+            spec.left |= width < 0;
+            spec.width = width.unsigned_abs().min(i32::MAX as u64) as usize;
+            i += 1;
+        } else {
+            while let Some(digit) = format.get(i).filter(|b| b.is_ascii_digit()) {
+                spec.width = (spec.width * 10 + usize::from(digit - b'0')).min(i32::MAX as usize);
+                i += 1;
+            }
+        }
+        if format.get(i) == Some(&b'.') {
+            i += 1;
+            let mut precision = 0;
+            if format.get(i) == Some(&b'*') {
+                precision = int_arg(next()).unsigned_abs().min(i32::MAX as u64) as usize;
+                i += 1;
+            } else {
+                while let Some(digit) = format.get(i).filter(|b| b.is_ascii_digit()) {
+                    precision = (precision * 10 + usize::from(digit - b'0')).min(i32::MAX as usize);
+                    i += 1;
+                }
+            }
+            spec.precision = Some(precision);
+        }
+        // Length modifiers mean nothing to SQL values
+        while format.get(i) == Some(&b'l') {
+            i += 1;
+        }
+
+        let Some(&conversion) = format.get(i) else {
+            break;
+        };
+        i += 1;
+        let (text, counts_chars) = match conversion {
+            b'd' | b'i' | b'u' | b'x' | b'X' | b'o' | b'p' | b'r' => (integer(int_arg(next()), conversion, &spec), false),
+            b'f' => (format_real(real_arg(next()), Conversion::Float, 'e', &spec).into_bytes(), false),
+            b'e' | b'E' => (format_real(real_arg(next()), Conversion::Exponent, char::from(conversion), &spec).into_bytes(), false),
+            b'g' => (format_real(real_arg(next()), Conversion::Generic, 'e', &spec).into_bytes(), false),
+            b'G' => (format_real(real_arg(next()), Conversion::Generic, 'E', &spec).into_bytes(), false),
+            b's' | b'z' => {
+                let arg = next();
+                let bytes = if arg.is_null() { Vec::new() } else { text_bytes(arg) };
+                let end = match spec.precision {
+                    Some(precision) if spec.alternate2 => char_boundary(&bytes, precision),
+                    Some(precision) => precision.min(bytes.len()),
+                    None => bytes.len(),
+                };
+                (bytes[..end].to_vec(), spec.alternate2)
+            }
+            b'q' | b'Q' | b'w' => (escape(next(), conversion, &spec), spec.alternate2),
+            b'c' => {
+                let arg = next();
+                let bytes = if arg.is_null() { Vec::new() } else { text_bytes(arg) };
+                let mut character = bytes[..char_boundary(&bytes, 1)].to_vec();
+                if character.is_empty() {
+                    character.push(0);
+                }
+                let copies = spec.precision.unwrap_or(1).max(1);
+                (character.repeat(copies), true)
+            }
+            b'%' => (vec![b'%'], false),
+            // `%n` writes nothing and takes no argument
+            b'n' => {
+                written = true;
+                continue;
+            }
+            // SQLite stops at a conversion it does not know
+            _ => break,
+        };
+
+        let length = if counts_chars { String::from_utf8_lossy(&text).chars().count() } else { text.len() };
+        let padding = spec.width.saturating_sub(length);
+        if !spec.left {
+            out.resize(out.len() + padding, b' ');
+        }
+        out.extend_from_slice(&text);
+        if spec.left {
+            out.resize(out.len() + padding, b' ');
+        }
+        written = true;
+    }
+    written.then(|| String::from_utf8_lossy(&out).into_owned())
+}
+
+/// A value's text, as bytes
+fn text_bytes(value: &Value) -> Vec<u8> {
+    match value {
+        Value::Blob(bytes) => bytes.clone(),
+        value => value_text(value).into_bytes(),
+    }
+}
+
+/// Bytes taken by the first `count` UTF-8 characters of `bytes`
+fn char_boundary(bytes: &[u8], count: usize) -> usize {
+    let mut seen = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        if byte & 0xc0 != 0x80 {
+            if seen == count {
+                return index;
+            }
+            seen += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// `%q`, `%Q` and `%w`: text with its quotes doubled, `%Q` quoting it too
+fn escape(value: &Value, conversion: u8, spec: &Spec) -> Vec<u8> {
+    let quote = if conversion == b'w' { b'"' } else { b'\'' };
+    let text = match value {
+        Value::Null if conversion == b'Q' => return b"NULL".to_vec(),
+        Value::Null => b"(NULL)".to_vec(),
+        value => text_bytes(value),
+    };
+    let end = match spec.precision {
+        Some(precision) if spec.alternate2 => char_boundary(&text, precision),
+        Some(precision) => precision.min(text.len()),
+        None => text.len(),
+    };
+    let mut out = Vec::with_capacity(end + 2);
+    let quoted = conversion == b'Q' && !value.is_null();
+    if quoted {
+        out.push(quote);
+    }
+    for &byte in &text[..end] {
+        out.push(byte);
+        if byte == quote {
+            out.push(quote);
+        }
+    }
+    if quoted {
+        out.push(quote);
+    }
+    out
+}
+
+/// `%d` and `%i`, `%u`, `%x` and `%X`, `%o`, `%p` (`%X` with `0x`), and `%r`
+/// (an ordinal such as `2nd`)
+fn integer(value: i64, conversion: u8, spec: &Spec) -> Vec<u8> {
+    let (base, digits, prefix): (u64, &[u8], &[u8]) = match conversion {
+        b'x' => (16, b"0123456789abcdef", b"x0"),
+        b'X' => (16, b"0123456789ABCDEF", b"X0"),
+        b'p' => (16, b"0123456789ABCDEF", b"x0"),
+        b'o' => (8, b"01234567", b"0"),
+        _ => (10, b"0123456789", b""),
+    };
+    let (mut magnitude, sign) = match conversion {
+        b'd' | b'i' | b'r' if value < 0 => (value.unsigned_abs(), Some(b'-')),
+        b'd' | b'i' | b'r' => (value as u64, spec.sign),
+        _ => (value as u64, None),
+    };
+    let alternate = spec.alternate && magnitude != 0;
+
+    // Built from the last character back
+    let mut text = Vec::new();
+    if conversion == b'r' {
+        let last = magnitude % 10;
+        let suffix: &[u8] = match last {
+            _ if (magnitude / 10) % 10 == 1 => b"ht",
+            1 => b"ts",
+            2 => b"dn",
+            3 => b"dr",
+            _ => b"ht",
+        };
+        text.extend_from_slice(suffix);
+    }
+    loop {
+        text.push(digits[(magnitude % base) as usize]);
+        magnitude /= base;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    let mut precision = spec.precision.unwrap_or(0);
+    if spec.zero {
+        precision = precision.max(spec.width.saturating_sub(usize::from(sign.is_some())));
+    }
+    text.resize(text.len().max(precision), b'0');
+    if spec.thousands {
+        let mut grouped = Vec::with_capacity(text.len() * 4 / 3);
+        for (index, &digit) in text.iter().enumerate() {
+            if index > 0 && index % 3 == 0 {
+                grouped.push(b',');
+            }
+            grouped.push(digit);
+        }
+        text = grouped;
+    }
+    text.extend(sign);
+    if alternate {
+        text.extend_from_slice(prefix);
+    }
+    text.reverse();
+    text
+}
+
+/// `%f`, `%e` and `%g` of a REAL, rounding half away from zero at the 16th
+/// significant digit (26th with `!`) or at the precision, whichever is first
+fn format_real(value: f64, conversion: Conversion, e: char, spec: &Spec) -> String {
+    let sign = if value.is_sign_negative() && value != 0.0 { Some('-') } else { spec.sign.map(char::from) };
+    if value.is_nan() {
+        return (if spec.zero { "null" } else { "NaN" }).to_string();
+    }
+    let (mut digits, mut point) = if value.is_infinite() {
+        if !spec.zero {
+            let mut text: String = sign.into_iter().collect();
+            text.push_str("Inf");
+            return text;
+        }
+        // Zero-padded infinity is written as a huge number of nines
+        (vec![b'9'], 1000)
+    } else {
+        decimal_digits(value.abs())
+    };
+
+    let mut precision = spec.precision.unwrap_or(6);
+    let round_at = match conversion {
+        Conversion::Float => point.saturating_add(precision as i64),
+        Conversion::Generic => {
+            precision = precision.max(1);
+            precision as i64
+        }
+        Conversion::Exponent => precision as i64 + 1,
+    };
+    let max_digits = if spec.alternate2 { 26 } else { 16 };
+    round_digits(&mut digits, &mut point, round_at, max_digits);
+
+    let exponent = point - 1;
+    let mut conversion = conversion;
+    let mut trim_zeros = spec.alternate2;
+    if conversion == Conversion::Generic {
+        precision -= 1;
+        trim_zeros = !spec.alternate;
+        if exponent < -4 || exponent > precision as i64 {
+            conversion = Conversion::Exponent;
+        } else {
+            precision = (precision as i64 - exponent) as usize;
+            conversion = Conversion::Float;
+        }
+    }
+
+    let mut text: String = sign.into_iter().collect();
+    let mut digits = digits.into_iter().map(char::from);
+    // Digits before the point, which a number below 1 has none of
+    let mut whole = if conversion == Conversion::Exponent { 0 } else { exponent };
+    if whole < 0 {
+        text.push('0');
+    }
+    while whole >= 0 {
+        text.push(digits.next().unwrap_or('0'));
+        if spec.thousands && whole % 3 == 0 && whole > 1 {
+            text.push(',');
+        }
+        whole -= 1;
+    }
+    let has_point = precision > 0 || spec.alternate || spec.alternate2;
+    if has_point {
+        text.push('.');
+    }
+    // Zeros between the point and the first digit
+    whole += 1;
+    while whole < 0 && precision > 0 {
+        text.push('0');
+        whole += 1;
+        precision -= 1;
+    }
+    for _ in 0..precision {
+        text.push(digits.next().unwrap_or('0'));
+    }
+    if trim_zeros && has_point {
+        let trimmed = text.trim_end_matches('0').len();
+        text.truncate(trimmed);
+        if text.ends_with('.') {
+            if spec.alternate2 {
+                text.push('0');
+            } else {
+                text.pop();
+            }
+        }
+    }
+    if conversion == Conversion::Exponent {
+        text.push_str(&format!("{}{}{:02}", e, if exponent < 0 { '-' } else { '+' }, exponent.abs()));
+    }
+
+    if spec.zero && !spec.left && text.len() < spec.width {
+        let at = usize::from(sign.is_some());
+        text.insert_str(at, &"0".repeat(spec.width - text.len()));
+    }
+    text
+}
+
+/// Significant decimal digits of a positive REAL, and where the decimal point
+/// falls among them, found as SQLite finds them: scaling the value to 18 or
+/// 19 digits with double-double arithmetic, whose last digits may be inexact
+// The constants are written as in SQLite's source
+#[allow(clippy::excessive_precision)]
+fn decimal_digits(value: f64) -> (Vec<u8>, i64) {
+    if value == 0.0 {
+        return (vec![b'0'], 1);
+    }
+    let mut rr = [value, 0.0];
+    let mut exponent: i64 = 0;
+    if rr[0] > 9.223372036854774784e+18 {
+        while rr[0] > 9.223372036854774784e+118 {
+            exponent += 100;
+            dekker_mul2(&mut rr, 1.0e-100, -1.99918998026028836196e-117);
+        }
+        while rr[0] > 9.223372036854774784e+28 {
+            exponent += 10;
+            dekker_mul2(&mut rr, 1.0e-10, -3.6432197315497741579e-27);
+        }
+        while rr[0] > 9.223372036854774784e+18 {
+            exponent += 1;
+            dekker_mul2(&mut rr, 1.0e-01, -5.5511151231257827021e-18);
+        }
+    } else {
+        while rr[0] < 9.223372036854774784e-83 {
+            exponent -= 100;
+            dekker_mul2(&mut rr, 1.0e+100, -1.5902891109759918046e+83);
+        }
+        while rr[0] < 9.223372036854774784e+07 {
+            exponent -= 10;
+            dekker_mul2(&mut rr, 1.0e+10, 0.0);
+        }
+        while rr[0] < 9.22337203685477478e+17 {
+            exponent -= 1;
+            dekker_mul2(&mut rr, 1.0e+01, 0.0);
+        }
+    }
+    let v = if rr[1] < 0.0 {
+        (rr[0] as u64).wrapping_sub((-rr[1]) as u64)
+    } else {
+        (rr[0] as u64).wrapping_add(rr[1] as u64)
+    };
+    let digits = v.to_string().into_bytes();
+    let point = digits.len() as i64 + exponent;
+    (digits, point)
+}
+
+/// Multiply the double-double `x` by `y + yy` (Dekker, 1971)
+fn dekker_mul2(x: &mut [f64; 2], y: f64, yy: f64) {
+    let split = |v: f64| f64::from_bits(v.to_bits() & 0xffff_ffff_fc00_0000);
+    let (hx, hy) = (split(x[0]), split(y));
+    let (tx, ty) = (x[0] - hx, y - hy);
+    let p = hx * hy;
+    let q = hx * ty + tx * hy;
+    let c = p + q;
+    let cc = p - c + q + tx * ty;
+    let cc = x[0] * yy + x[1] * y + cc;
+    x[0] = c + cc;
+    x[1] = c - x[0] + cc;
+}
+
+/// Round `digits` half away from zero to the first `round_at` of them, and to
+/// no more than `max_digits`, dropping trailing zeros
+fn round_digits(digits: &mut Vec<u8>, point: &mut i64, round_at: i64, max_digits: usize) {
+    let mut round_at = round_at;
+    if round_at == 0 && digits[0] >= b'5' {
+        // Rounds up to a digit before the first one
+        digits.insert(0, b'0');
+        *point += 1;
+        round_at = 1;
+    }
+    if round_at > 0 && (round_at < digits.len() as i64 || digits.len() > max_digits) {
+        let keep = (round_at as usize).min(max_digits);
+        let up = digits.get(keep).is_some_and(|&digit| digit >= b'5');
+        digits.truncate(keep);
+        if up {
+            let mut index = keep;
+            loop {
+                if index == 0 {
+                    digits.insert(0, b'1');
+                    *point += 1;
+                    break;
+                }
+                index -= 1;
+                if digits[index] == b'9' {
+                    digits[index] = b'0';
+                } else {
+                    digits[index] += 1;
+                    break;
+                }
+            }
+        }
+    }
+    while digits.len() > 1 && digits.last() == Some(&b'0') {
+        digits.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format: &str, args: &[Value]) -> Option<String> {
+        printf(format, &args.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_printf_reals() {
+        // Digits as SQLite 3.43 and later decode them, before rounding half up
+        let cases: &[(&str, f64, &str)] = &[
+            ("%.2f", 2.675, "2.67"),
+            ("%!.30f", 1.0 / 3.0, "0.3333333333333333148"),
+            ("%.20f", 0.1, "0.10000000000000000000"),
+            ("%!.20g", 0.1, "0.1000000000000000055"),
+            ("%,.2f", 1234567.891, "1,234,567.89"),
+            ("%.3f", -0.0005, "-0.001"),
+            ("%020.3f", -1.23456, "-000000000000001.235"),
+            ("%+.2e", 0.0, "+0.00e+00"),
+            ("%G", 1e-10, "1E-10"),
+            ("%E", f64::INFINITY, "Inf"),
+        ];
+        for (spec, value, expected) in cases {
+            assert_eq!(format(spec, &[Value::Real(*value)]).as_deref(), Some(*expected), "{} of {}", spec, value);
+        }
+        assert_eq!(quote(&Value::Real(core::f64::consts::PI)), "3.141592653589793116e+00");
+        assert_eq!(quote(&Value::Real(123456789012345678.0)), "1.2345678901234568e+17");
+        assert_eq!(quote(&Value::Real(1e20)), "1.0e+20");
+        assert_eq!(round(1.005, 2), 1.0);
+        assert_eq!(round(-2.5, 0), -3.0);
+    }
+
+    #[test]
+    fn test_printf_conversions() {
+        let cases: &[(&str, &[Value], Option<&str>)] = &[
+            ("%-020d|", &[Value::Integer(-5)], Some("-0000000000000000005|")),
+            ("%p %#p", &[Value::Integer(255), Value::Integer(255)], Some("FF 0xFF")),
+            ("%r %r %r %r", &[Value::Integer(1), Value::Integer(2), Value::Integer(13), Value::Integer(-22)], Some("1st 2nd 13th -22nd")),
+            ("%u", &[Value::Integer(-1)], Some("18446744073709551615")),
+            ("%!.3s|%.3s", &[Value::Text("héllo".into()), Value::Text("héllo".into())], Some("hél|hé")),
+            ("%c%c", &[Value::Text("hello".into())], Some("h\0")),
+            ("abc%y def", &[], Some("abc")),
+            ("%y def", &[], None),
+            ("", &[], None),
+            ("%n", &[], Some("")),
+            ("%s", &[Value::Text(String::new())], Some("")),
+        ];
+        for (spec, args, expected) in cases {
+            assert_eq!(format(spec, args).as_deref(), *expected, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_substr_range() {
+        let cases: &[(i64, Option<i64>, (usize, usize))] = &[
+            (2, None, (1, 5)),
+            (0, Some(2), (0, 1)),
+            (-2, Some(1), (3, 4)),
+            (-10, Some(7), (0, 2)),
+            (3, Some(-2), (0, 2)),
+            (10, None, (5, 5)),
+            (i64::MIN, Some(i64::MAX), (0, 4)),
+            (2, Some(i64::MIN), (0, 1)),
+        ];
+        for (start, length, expected) in cases {
+            assert_eq!(substr_range(5, *start, *length), *expected, "substr(.., {}, {:?})", start, length);
+        }
+    }
+}
@@ -0,0 +1,838 @@
+//! Joins: resolving the column references of a query over several tables,
+//! placing its conditions on the tables of the nested loops that join them,
+//! and running those loops
+
+use crate::{
+    aggregate::HashKey,
+    collections::HashMap,
+    database::{
+        apply_column_affinity, collect_and_terms, collect_expr_columns, collect_scalar_columns, is_rowid_pseudo_column, Affinity,
+        Database, IndexInfo, IndexRange, KeyRange, OutputColumn, RowSource, TableInfo,
+    },
+    eval::{Collation, ColumnType, Context},
+    logging::log_debug,
+    query::{ComparisonOperator, Expr, JoinKind, OrderBy, ResultColumn, ScalarExpr, SelectQuery},
+    Error, Result, Row, RowIndex, Value,
+};
+use alloc::sync::Arc;
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::{String, ToString}, vec, vec::Vec};
+
+/// A query over joined tables, with its column references resolved
+///
+/// Joined rows hold the columns of every table side by side, each named
+/// `qualifier.column` after the alias or name of its table.
+pub(crate) struct JoinPlan {
+    /// Tables in join order, the first one driving the nested loops
+    pub(crate) tables: Vec<JoinTable>,
+    /// Names of the columns of joined rows
+    pub(crate) columns: Arc<[String]>,
+    /// Types of the columns of joined rows
+    pub(crate) context: Context,
+    /// The query over joined rows; its WHERE clause also holds the ON
+    /// conditions of inner joins, and an aggregate query lists every output
+    /// column as a result column
+    pub(crate) query: SelectQuery,
+    /// Names of the output columns of a query that does not aggregate, and
+    /// how they are computed from joined rows
+    pub(crate) output: (Arc<[String]>, Vec<OutputColumn>),
+}
+
+/// A table of a join and the conditions the rows it joins must satisfy
+pub(crate) struct JoinTable {
+    pub(crate) info: TableInfo,
+    /// How the table joins the rows before it; `Inner` for the first table
+    pub(crate) kind: JoinKind,
+    /// Columns of the rows read from the table: its declared columns, then
+    /// the rowid pseudo-columns the query refers to
+    pub(crate) columns: Arc<[String]>,
+    /// Columns filled from the cell key: the rowid alias and rowid pseudo-columns
+    pub(crate) rowid_columns: Vec<usize>,
+    /// Types of `columns`
+    pub(crate) context: Context,
+    /// Position of the table's first column in joined rows
+    pub(crate) offset: usize,
+    /// Conditions on the table's own columns, named as in `columns`
+    pub(crate) filter: Option<Expr>,
+    /// Joined columns of the tables before this one, each paired with a
+    /// column of this table that the conditions require to equal it
+    pub(crate) keys: Vec<(usize, usize)>,
+    /// ON condition over joined rows of a LEFT join, whose rows without a
+    /// match are joined with NULLs instead of dropped
+    pub(crate) on: Option<Expr>,
+}
+
+impl JoinTable {
+    /// Query reading the rows of the table that satisfy its filter
+    pub(crate) fn filtered_query(&self) -> SelectQuery {
+        let query = SelectQuery::new(self.info.name.clone());
+        match &self.filter {
+            Some(filter) => query.with_where(filter.clone()),
+            None => query,
+        }
+    }
+}
+
+impl JoinPlan {
+    /// Resolve `query` over `tables`: the table it reads, then each joined
+    /// table, typing their columns in contexts derived from `base`
+    pub(crate) fn new(query: &SelectQuery, tables: Vec<TableInfo>, base: &Context) -> Result<JoinPlan> {
+        let qualifiers: Vec<String> = core::iter::once(query.alias.as_ref().unwrap_or(&query.table))
+            .chain(query.joins.iter().map(|join| join.alias.as_ref().unwrap_or(&join.table)))
+            .cloned()
+            .collect();
+        let referenced = referenced_columns(query);
+
+        let mut joined = Vec::new();
+        let mut columns = Vec::new();
+        let mut types = Vec::new();
+        for (position, (info, qualifier)) in tables.into_iter().zip(&qualifiers).enumerate() {
+            // Rowid pseudo-columns follow the declared columns, unless a declared column shadows them
+            let mut table_columns = info.columns.clone();
+            let mut rowid_columns: Vec<usize> = info
+                .rowid_alias
+                .as_deref()
+                .and_then(|name| table_columns.iter().position(|column| column == name))
+                .into_iter()
+                .collect();
+            if !info.without_rowid {
+                for name in &referenced {
+                    let (table, column) = split_qualifier(name, &qualifiers);
+                    if is_rowid_pseudo_column(column)
+                        && table.is_none_or(|table| table.eq_ignore_ascii_case(qualifier))
+                        && column.index_in(&table_columns).is_none()
+                    {
+                        rowid_columns.push(table_columns.len());
+                        table_columns.push(column.to_string());
+                    }
+                }
+            }
+
+            let offset = columns.len();
+            columns.extend(table_columns.iter().map(|column| format!("{}.{}", qualifier, column)));
+            let table_types = ColumnType::of_table(&info, table_columns.len());
+            types.extend(table_types.iter().copied());
+            let kind = match position {
+                0 => JoinKind::Inner,
+                _ => query.joins[position - 1].kind,
+            };
+            joined.push(JoinTable {
+                info,
+                kind,
+                columns: table_columns.into(),
+                rowid_columns,
+                context: base.with_columns(table_types),
+                offset,
+                filter: None,
+                keys: Vec::new(),
+                on: None,
+            });
+        }
+        let columns: Arc<[String]> = columns.into();
+
+        let scope = Scope { qualifiers: &qualifiers, tables: &joined, columns: &columns };
+        let (resolved, output) = scope.resolve(query)?;
+        let (filters, keys, ons, where_expr) = scope.place_conditions(&resolved)?;
+        for (table, ((filter, keys), on)) in joined.iter_mut().zip(filters.into_iter().zip(keys).zip(ons)) {
+            table.filter = filter.map(|mut filter| {
+                apply_column_affinity(&mut filter, &table.columns, &table.context);
+                filter
+            });
+            table.keys = keys;
+            table.on = on;
+        }
+
+        Ok(JoinPlan {
+            tables: joined,
+            columns,
+            context: base.with_columns(types),
+            query: SelectQuery { where_expr, ..resolved },
+            output,
+        })
+    }
+}
+
+/// The tables of a join, for resolving the column names a query uses
+struct Scope<'a> {
+    qualifiers: &'a [String],
+    tables: &'a [JoinTable],
+    /// Names of the columns of joined rows
+    columns: &'a [String],
+}
+
+/// Where the conditions of a join are checked: the filter, join keys and
+/// ON condition of each table, then the WHERE clause over joined rows
+type Placement = (Vec<Option<Expr>>, Vec<Vec<(usize, usize)>>, Vec<Option<Expr>>, Option<Expr>);
+
+impl Scope<'_> {
+    /// Position in joined rows of the column `name` refers to, if it names one
+    ///
+    /// A name without a qualifier must belong to a single table.
+    fn find(&self, name: &str) -> Result<Option<usize>> {
+        let (qualifier, column) = split_qualifier(name, self.qualifiers);
+        let mut found = None;
+        for (table, table_qualifier) in self.tables.iter().zip(self.qualifiers) {
+            if qualifier.is_some_and(|qualifier| !qualifier.eq_ignore_ascii_case(table_qualifier)) {
+                continue;
+            }
+            if let Some(index) = column.index_in(&table.columns) {
+                if found.is_some() {
+                    return Err(Error::QueryError(format!("ambiguous column name: {}", name)));
+                }
+                found = Some(table.offset + index);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Joined column name of a column reference that must name a column
+    fn column(&self, name: &str) -> Result<String> {
+        match self.find(name)? {
+            Some(index) => Ok(self.columns[index].clone()),
+            None => Err(Error::ColumnNotFound(name.to_string())),
+        }
+    }
+
+    /// Joined column name of a reference in GROUP BY, HAVING or the ORDER BY
+    /// of an aggregate query, which may also name an output column or an
+    /// expression after its SQL text
+    fn output(&self, name: &str) -> Result<String> {
+        if let Some(index) = self.find(name)? {
+            return Ok(self.columns[index].clone());
+        }
+        // Names of no joined column are left for the aggregate plan to
+        // resolve as output columns
+        let renamed = SelectQuery::rename_expression_text(name, &mut |column| match self.find(column)? {
+            Some(index) => Ok(self.columns[index].clone()),
+            None => Ok(column.to_string()),
+        })?;
+        Ok(renamed.unwrap_or_else(|| name.to_string()))
+    }
+
+    /// Type of a joined column
+    fn column_type(&self, index: usize) -> ColumnType {
+        let table = &self.tables[self.table_of(index)];
+        table.context.column_type(index - table.offset)
+    }
+
+    /// Table a joined column belongs to
+    fn table_of(&self, index: usize) -> usize {
+        self.tables.iter().rposition(|table| table.offset <= index).unwrap_or(0)
+    }
+
+    /// Tables the columns of a condition over joined rows belong to
+    fn tables_of(&self, expr: &Expr) -> Vec<usize> {
+        let mut columns = Vec::new();
+        collect_expr_columns(expr, &mut columns);
+        let mut tables: Vec<usize> = columns
+            .into_iter()
+            .filter_map(|column| column.index_in(self.columns))
+            .map(|index| self.table_of(index))
+            .collect();
+        tables.sort_unstable();
+        tables.dedup();
+        tables
+    }
+
+    /// Indexes of the tables whose qualifier is `qualifier`
+    fn tables_named(&self, qualifier: &str) -> Result<Vec<usize>> {
+        let tables: Vec<usize> = (0..self.tables.len())
+            .filter(|&table| self.qualifiers[table].eq_ignore_ascii_case(qualifier))
+            .collect();
+        if tables.is_empty() {
+            return Err(Error::TableNotFound(qualifier.to_string()));
+        }
+        Ok(tables)
+    }
+
+    /// Expand a SELECT list entry into output names and joined columns: `*`
+    /// into the declared columns of every table, `q.*` into those of table
+    /// `q`, and a column into itself, named without its qualifier
+    fn expand(&self, entry: &str) -> Result<Vec<(String, usize)>> {
+        let tables = if entry == "*" {
+            (0..self.tables.len()).collect()
+        } else if let Some(qualifier) = entry.strip_suffix(".*") {
+            self.tables_named(qualifier)?
+        } else {
+            let index = self.find(entry)?.ok_or_else(|| Error::ColumnNotFound(entry.to_string()))?;
+            return Ok(vec![(split_qualifier(entry, self.qualifiers).1.to_string(), index)]);
+        };
+        Ok(tables
+            .into_iter()
+            .flat_map(|table| {
+                let table = &self.tables[table];
+                table.info.columns.iter().enumerate().map(|(index, column)| (column.clone(), table.offset + index))
+            })
+            .collect())
+    }
+
+    /// Rewrite `query` over joined columns, returning it with the output
+    /// columns of a query that does not aggregate
+    #[allow(clippy::type_complexity)]
+    fn resolve(&self, query: &SelectQuery) -> Result<(SelectQuery, (Arc<[String]>, Vec<OutputColumn>))> {
+        let column = &mut |name: &str| self.column(name);
+        let output = &mut |name: &str| self.output(name);
+        let selected = query.columns.clone().unwrap_or_else(|| vec!["*".to_string()]);
+
+        let mut names = Vec::new();
+        let mut sources = Vec::new();
+        let mut result_columns = None;
+        if query.is_aggregate() {
+            // Every output column is computed, with bare columns read from joined columns
+            let computed = match &query.result_columns {
+                Some(result_columns) => result_columns
+                    .iter()
+                    .map(|result| Ok(ResultColumn::new(result.name.clone(), result.expr.rename_columns(column)?)))
+                    .collect::<Result<Vec<_>>>()?,
+                None => {
+                    let mut computed = Vec::new();
+                    for entry in &selected {
+                        for (name, index) in self.expand(entry)? {
+                            computed.push(ResultColumn::new(name, ScalarExpr::Column(self.columns[index].clone())));
+                        }
+                    }
+                    computed
+                }
+            };
+            result_columns = Some(computed);
+        } else if let Some(result_columns) = &query.result_columns {
+            for result in result_columns {
+                names.push(result.name.clone());
+                sources.push(match result.expr.rename_columns(column)? {
+                    ScalarExpr::Column(name) => OutputColumn::Column(name.index_in(self.columns).unwrap_or_default()),
+                    expr => OutputColumn::Computed(expr),
+                });
+            }
+        } else {
+            for entry in &selected {
+                for (name, index) in self.expand(entry)? {
+                    names.push(name);
+                    sources.push(OutputColumn::Column(index));
+                }
+            }
+        }
+
+        let group_by = match &query.group_by {
+            Some(keys) => Some(keys.iter().map(|key| key.rename_columns(output)).collect::<Result<Vec<_>>>()?),
+            None => None,
+        };
+        let order_by = match &query.order_by {
+            Some(order_by) => Some(OrderBy {
+                column: if query.is_aggregate() { output(&order_by.column)? } else { self.order_key(query, &order_by.column)? },
+                ascending: order_by.ascending,
+            }),
+            None => None,
+        };
+        let mut joins = query.joins.clone();
+        for join in &mut joins {
+            join.on = join.on.as_ref().map(|on| on.rename_columns(column)).transpose()?;
+        }
+
+        let resolved = SelectQuery {
+            columns: None,
+            result_columns,
+            table: query.table.clone(),
+            alias: query.alias.clone(),
+            joins,
+            where_expr: query.where_with_aliases(&|name| self.find(name).ok().flatten().is_some())?.map(|expr| expr.rename_columns(column)).transpose()?,
+            group_by,
+            having: query.having.as_ref().map(|expr| expr.rename_columns(output)).transpose()?,
+            order_by,
+            limit: query.limit,
+        };
+        Ok((resolved, (names.into(), sources)))
+    }
+
+    /// The ORDER BY of a query that does not aggregate as a name over joined
+    /// rows: the SQL text of the output column it names by alias, a joined
+    /// column, or the SQL text of an expression over joined columns
+    fn order_key(&self, query: &SelectQuery, name: &str) -> Result<String> {
+        let column = &mut |name: &str| self.column(name);
+        if let Some(result) = query.result_columns.iter().flatten().find(|result| result.name.eq_ignore_ascii_case(name)) {
+            return Ok(match result.expr.rename_columns(column)? {
+                ScalarExpr::Column(name) => name,
+                expr => expr.to_string(),
+            });
+        }
+        if let Some(index) = self.find(name)? {
+            return Ok(self.columns[index].clone());
+        }
+        let Some(mut expr) = SelectQuery::parse_expression_text(name) else {
+            return Err(Error::ColumnNotFound(name.to_string()));
+        };
+        query.expand_aliases(&mut expr, &|name| self.find(name).ok().flatten().is_some());
+        Ok(expr.rename_columns(column)?.to_string())
+    }
+
+    /// Split the conditions of a resolved query between the tables of the join
+    ///
+    /// The ON conditions of inner and cross joins mean the same as the WHERE
+    /// clause, so they join it. Each table is then filtered by the conditions
+    /// on its own columns alone, and looked up by equalities with the columns
+    /// of the tables before it. The conditions of a LEFT join come from its ON
+    /// condition only, since WHERE also sees the rows it adds with NULLs.
+    fn place_conditions(&self, query: &SelectQuery) -> Result<Placement> {
+        let mut pool: Vec<Expr> = query.where_expr.iter().flat_map(collect_and_terms).cloned().collect();
+        let mut ons = vec![None];
+        for (position, join) in query.joins.iter().enumerate() {
+            let table = position + 1;
+            match (join.kind, &join.on) {
+                (JoinKind::Left, Some(on)) => {
+                    if self.tables_of(on).iter().any(|&other| other > table) {
+                        return Err(Error::QueryError(format!("ON clause references tables to its right: {}", join.table)));
+                    }
+                    ons.push(Some(on.clone()));
+                }
+                (_, Some(on)) => {
+                    pool.extend(collect_and_terms(on).into_iter().cloned());
+                    ons.push(None);
+                }
+                (_, None) => ons.push(None),
+            }
+        }
+
+        let mut filters = Vec::new();
+        let mut keys = Vec::new();
+        for (position, table) in self.tables.iter().enumerate() {
+            let terms: Vec<&Expr> = match (table.kind, &ons[position]) {
+                (JoinKind::Left, Some(on)) => collect_and_terms(on),
+                (JoinKind::Left, None) => Vec::new(),
+                _ => pool.iter().collect(),
+            };
+
+            let own_columns = &mut |name: &str| {
+                let index = name.index_in(self.columns).ok_or_else(|| Error::ColumnNotFound(name.to_string()))?;
+                Ok(table.columns[index - table.offset].clone())
+            };
+            let mut filter: Option<Expr> = None;
+            let mut table_keys = Vec::new();
+            for term in terms {
+                let tables = self.tables_of(term);
+                if tables == [position] {
+                    let term = term.rename_columns(own_columns)?;
+                    filter = Some(match filter {
+                        Some(filter) => filter.and(term),
+                        None => term,
+                    });
+                } else if let Expr::ColumnComparison { left, operator: ComparisonOperator::Equal, right } = term {
+                    let (Some(left), Some(right)) = (left.index_in(self.columns), right.index_in(self.columns)) else {
+                        continue;
+                    };
+                    // Lookups match values as stored, so the columns must compare
+                    // them under BINARY without converting either one
+                    let (left_type, right_type) = (self.column_type(left), self.column_type(right));
+                    let numeric = |affinity| matches!(affinity, Affinity::Integer | Affinity::Real | Affinity::Numeric);
+                    let converts = left_type.affinity != right_type.affinity
+                        && !(numeric(left_type.affinity) && numeric(right_type.affinity));
+                    if converts || left_type.collation != Collation::Binary || right_type.collation != Collation::Binary {
+                        continue;
+                    }
+                    match (self.table_of(left), self.table_of(right)) {
+                        (l, r) if l == position && r < position => table_keys.push((right, left - table.offset)),
+                        (l, r) if r == position && l < position => table_keys.push((left, right - table.offset)),
+                        _ => {}
+                    }
+                }
+            }
+            filters.push(filter);
+            keys.push(table_keys);
+        }
+
+        let where_expr = pool.into_iter().reduce(Expr::and);
+        Ok((filters, keys, ons, where_expr))
+    }
+}
+
+/// Split a column reference into the qualifier it starts with, when that
+/// names one of `qualifiers`, and the column name
+fn split_qualifier<'a>(name: &'a str, qualifiers: &[String]) -> (Option<&'a str>, &'a str) {
+    match name.split_once('.') {
+        Some((qualifier, column)) if qualifiers.iter().any(|q| q.eq_ignore_ascii_case(qualifier)) => (Some(qualifier), column),
+        _ => (None, name),
+    }
+}
+
+/// Column names a query refers to anywhere, including its ON conditions
+fn referenced_columns(query: &SelectQuery) -> Vec<String> {
+    let mut referenced: Vec<&str> = Vec::new();
+    if let Some(columns) = &query.columns {
+        referenced.extend(columns.iter().map(String::as_str));
+    }
+    let mut scalars: Vec<&ScalarExpr> = query.result_columns.iter().flatten().map(|column| &column.expr).collect();
+    scalars.extend(query.group_by.iter().flatten());
+    for scalar in scalars {
+        collect_scalar_columns(scalar, &mut referenced);
+    }
+    let on = query.joins.iter().filter_map(|join| join.on.as_ref());
+    for expr in [&query.where_expr, &query.having].into_iter().flatten().chain(on) {
+        collect_expr_columns(expr, &mut referenced);
+    }
+    if let Some(order_by) = &query.order_by {
+        referenced.push(&order_by.column);
+    }
+    referenced.into_iter().map(String::from).collect()
+}
+
+/// Drop the table name or alias from the column references of a query over
+/// a single table, such as `u.name` in `SELECT u.name FROM users u`
+///
+/// `columns` are the declared columns of the table, which a name is kept as
+/// whenever it matches one.
+pub(crate) fn unqualify(query: &SelectQuery, columns: &[String]) -> Result<SelectQuery> {
+    let qualifier = query.alias.as_ref().unwrap_or(&query.table);
+    let strip = &mut |name: &str| -> Result<String> {
+        // Expressions named after their SQL text, such as `count(u.id)`, may read qualified columns
+        if name.index_in(columns).is_none() {
+            if let Some(renamed) = SelectQuery::rename_expression_text(name, &mut |column| unqualify_name(column, qualifier, columns))? {
+                return Ok(renamed);
+            }
+        }
+        unqualify_name(name, qualifier, columns)
+    };
+
+    let selected = match &query.columns {
+        Some(selected) => Some(
+            selected
+                .iter()
+                .map(|entry| match entry.strip_suffix(".*") {
+                    Some(table) if table.eq_ignore_ascii_case(qualifier) => Ok("*".to_string()),
+                    Some(table) => Err(Error::TableNotFound(table.to_string())),
+                    None => strip(entry),
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+    let result_columns = match &query.result_columns {
+        Some(result_columns) => Some(
+            result_columns
+                .iter()
+                .map(|result| Ok(ResultColumn::new(result.name.clone(), result.expr.rename_columns(strip)?)))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+    let group_by = match &query.group_by {
+        Some(keys) => Some(keys.iter().map(|key| key.rename_columns(strip)).collect::<Result<Vec<_>>>()?),
+        None => None,
+    };
+    let order_by = match &query.order_by {
+        Some(order_by) => Some(OrderBy { column: strip(&order_by.column)?, ascending: order_by.ascending }),
+        None => None,
+    };
+    Ok(SelectQuery {
+        columns: selected,
+        result_columns,
+        table: query.table.clone(),
+        alias: query.alias.clone(),
+        joins: Vec::new(),
+        where_expr: query.where_expr.as_ref().map(|expr| expr.rename_columns(strip)).transpose()?,
+        group_by,
+        having: query.having.as_ref().map(|expr| expr.rename_columns(strip)).transpose()?,
+        order_by,
+        limit: query.limit,
+    })
+}
+
+/// Drop a table qualifier from a single column reference
+fn unqualify_name(name: &str, qualifier: &str, columns: &[String]) -> Result<String> {
+    if name.index_in(columns).is_some() {
+        return Ok(name.to_string());
+    }
+    match name.split_once('.') {
+        Some((table, column)) if table.eq_ignore_ascii_case(qualifier) => Ok(column.to_string()),
+        Some(_) => Err(Error::ColumnNotFound(name.to_string())),
+        None => Ok(name.to_string()),
+    }
+}
+
+/// Rows of several tables joined by nested loops: each row of the first
+/// table, then for each joined table, the rows its lookup finds for the row
+/// joined so far
+pub(crate) struct JoinSource {
+    /// Query over joined rows, for evaluating conditions
+    query: SelectQuery,
+    first: JoinTable,
+    /// Where the rows of the first table are read from
+    source: RowSource,
+    steps: Vec<JoinStep>,
+    /// Names of the columns of joined rows
+    columns: Arc<[String]>,
+    /// Types of the columns of joined rows
+    context: Context,
+    /// Rows of each step's table still to pair with the row joined so far
+    frames: Vec<JoinFrame>,
+}
+
+/// A joined table and how the rows that may join a row are found in it
+struct JoinStep {
+    table: JoinTable,
+    lookup: Lookup,
+}
+
+/// How a [`JoinStep`] finds the rows of its table that may join a row
+enum Lookup {
+    /// The row whose rowid is the value of a joined column
+    Rowid(usize),
+    /// Rows of a rowid table an index holds under the values of joined columns
+    Index { index: IndexInfo, keys: Vec<usize> },
+    /// Rows of a WITHOUT ROWID table whose PRIMARY KEY starts with the values
+    /// of joined columns
+    PrimaryKey(Vec<usize>),
+    /// Rows whose `columns` equal the values of the joined columns `keys`,
+    /// from a hash table of the filtered rows built on first use
+    Hash {
+        keys: Vec<usize>,
+        columns: Vec<usize>,
+        rows: Option<HashMap<HashKey, Vec<Row>>>,
+    },
+    /// Every row that satisfies the table's filter
+    Scan,
+}
+
+impl Lookup {
+    fn describe(&self) -> String {
+        match self {
+            Lookup::Rowid(_) => "rowid".to_string(),
+            Lookup::Index { index, .. } => format!("index {}", index.name),
+            Lookup::PrimaryKey(_) => "PRIMARY KEY".to_string(),
+            Lookup::Hash { .. } => "hash table".to_string(),
+            Lookup::Scan => "scan".to_string(),
+        }
+    }
+}
+
+/// Rows of a step's table to pair with one joined row
+struct JoinFrame {
+    /// Values of the row joined so far
+    values: Vec<Value>,
+    matches: JoinMatches,
+    /// Whether a row was paired with it, so a LEFT join need not add one with NULLs
+    matched: bool,
+}
+
+enum JoinMatches {
+    Rows(alloc::vec::IntoIter<Row>),
+    /// Rows still to be read, and checked against the table's filter
+    Source(RowSource),
+}
+
+impl JoinSource {
+    /// Join the rows of `tables` in order for `query`, a query over joined
+    /// rows with the columns `columns` typed by `context`
+    pub(crate) fn new(
+        db: &mut Database,
+        mut tables: Vec<JoinTable>,
+        query: SelectQuery,
+        columns: Arc<[String]>,
+        context: Context,
+    ) -> Result<Self> {
+        let first = tables.remove(0);
+        let steps = tables
+            .into_iter()
+            .map(|table| {
+                let lookup = join_lookup(&table);
+                log_debug(&format!("Joining table {} by {}", table.info.name, lookup.describe()));
+                JoinStep { table, lookup }
+            })
+            .collect();
+        let source = db.plan_row_source(&first.filtered_query(), &first.info, first.info.rowid_alias.as_deref())?;
+        Ok(JoinSource { query, first, source, steps, columns, context, frames: Vec::new() })
+    }
+
+    /// Read the next joined row, before WHERE filtering
+    pub(crate) fn next_row(&mut self, db: &mut Database) -> Result<Option<Row>> {
+        let JoinSource { query, first, source, steps, columns, context, frames } = self;
+        loop {
+            let Some(depth) = frames.len().checked_sub(1) else {
+                // Start again from the next row of the first table
+                let row = loop {
+                    match source.next_row(db, &first.info, &first.columns, &first.rowid_columns)? {
+                        Some(row) if !admits(query, first, &row)? => {}
+                        row => break row,
+                    }
+                };
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let frame = join_frame(db, query, &mut steps[0], row.into_values())?;
+                frames.push(frame);
+                continue;
+            };
+
+            let (frame, step) = (&mut frames[depth], &steps[depth]);
+            let row = match &mut frame.matches {
+                JoinMatches::Rows(rows) => rows.next(),
+                JoinMatches::Source(source) => loop {
+                    match source.next_row(db, &step.table.info, &step.table.columns, &step.table.rowid_columns)? {
+                        Some(row) if !admits(query, &step.table, &row)? => {}
+                        row => break row,
+                    }
+                },
+            };
+
+            let mut values = frame.values.clone();
+            match row {
+                Some(row) => {
+                    values.extend(row.into_values());
+                    if let Some(on) = &step.table.on {
+                        // Columns of the tables after this one read as NULL
+                        let len = values.len();
+                        let joined = Row::new(columns.clone(), values);
+                        let satisfied = query.evaluate_expr_in(&joined, on, context)?;
+                        values = joined.into_values();
+                        values.truncate(len);
+                        if !satisfied {
+                            continue;
+                        }
+                    }
+                    frame.matched = true;
+                }
+                None if step.table.kind == JoinKind::Left && !frame.matched => {
+                    frame.matched = true;
+                    values.resize(values.len() + step.table.columns.len(), Value::Null);
+                }
+                None => {
+                    frames.pop();
+                    continue;
+                }
+            }
+
+            if depth + 1 == steps.len() {
+                return Ok(Some(Row::new(columns.clone(), values)));
+            }
+            let frame = join_frame(db, query, &mut steps[depth + 1], values)?;
+            frames.push(frame);
+        }
+    }
+
+    /// Read the joined rows again from the start, keeping any hash tables built
+    pub(crate) fn restart(&mut self, db: &mut Database) -> Result<()> {
+        self.frames.clear();
+        self.source = db.plan_row_source(&self.first.filtered_query(), &self.first.info, self.first.info.rowid_alias.as_deref())?;
+        Ok(())
+    }
+}
+
+/// Whether a row of a joined table satisfies the conditions on its own columns
+fn admits(query: &SelectQuery, table: &JoinTable, row: &Row) -> Result<bool> {
+    match &table.filter {
+        Some(filter) => query.evaluate_expr_in(row, filter, &table.context),
+        None => Ok(true),
+    }
+}
+
+/// Choose how a joined table finds the rows that may join a row: by rowid,
+/// then by the index or PRIMARY KEY with the longest prefix of key columns,
+/// then by hashing its rows on the key columns, or else by scanning it
+fn join_lookup(table: &JoinTable) -> Lookup {
+    let info = &table.info;
+    if !info.without_rowid {
+        if let Some(&(key, _)) = table.keys.iter().find(|(_, column)| table.rowid_columns.contains(column)) {
+            return Lookup::Rowid(key);
+        }
+    }
+
+    let joined = |name: &str| {
+        let column = name.index_in(&info.columns)?;
+        table.keys.iter().find(|(_, key_column)| *key_column == column).map(|(key, _)| *key)
+    };
+    if info.without_rowid {
+        let keys: Vec<usize> = info.seekable_primary_key().map_while(joined).collect();
+        if !keys.is_empty() {
+            return Lookup::PrimaryKey(keys);
+        }
+    } else {
+        let best = info
+            .indexes
+            .iter()
+            .filter(|index| !index.is_partial())
+            .map(|index| {
+                let keys: Vec<usize> = index
+                    .columns
+                    .iter()
+                    .map_while(|part| part.column_name().filter(|_| part.is_seekable()).and_then(joined))
+                    .collect();
+                (index, keys)
+            })
+            .max_by_key(|(_, keys)| keys.len());
+        if let Some((index, keys)) = best.filter(|(_, keys)| !keys.is_empty()) {
+            return Lookup::Index { index: index.clone(), keys };
+        }
+    }
+
+    if table.keys.is_empty() {
+        return Lookup::Scan;
+    }
+    Lookup::Hash {
+        keys: table.keys.iter().map(|(key, _)| *key).collect(),
+        columns: table.keys.iter().map(|(_, column)| *column).collect(),
+        rows: None,
+    }
+}
+
+/// Find the rows of a step's table that may join the row `values`
+fn join_frame(db: &mut Database, query: &SelectQuery, step: &mut JoinStep, values: Vec<Value>) -> Result<JoinFrame> {
+    let table = &step.table;
+    let key_values = |keys: &[usize]| -> Option<Vec<Value>> {
+        // NULL equals nothing, so a NULL key joins no rows
+        keys.iter().map(|&key| Some(values[key].clone()).filter(|value| !value.is_null())).collect()
+    };
+
+    let source = match &mut step.lookup {
+        Lookup::Rowid(key) => {
+            let spans = match &values[*key] {
+                Value::Integer(rowid) => vec![(*rowid, *rowid)],
+                Value::Real(v) if *v >= i64::MIN as f64 && *v < i64::MAX as f64 && (*v as i64) as f64 == *v => {
+                    vec![(*v as i64, *v as i64)]
+                }
+                _ => Vec::new(),
+            };
+            RowSource::rowid_lookups(spans, Vec::new())
+        }
+        Lookup::Index { index, keys } => {
+            let index_ranges = match key_values(keys) {
+                Some(prefix) => vec![IndexRange::new(index, KeyRange::prefix(prefix))],
+                None => Vec::new(),
+            };
+            RowSource::rowid_lookups(Vec::new(), index_ranges)
+        }
+        Lookup::PrimaryKey(keys) => {
+            let ranges = match key_values(keys) {
+                Some(prefix) => vec![KeyRange::prefix(prefix)],
+                None => Vec::new(),
+            };
+            RowSource::KeyRanges {
+                storage_columns: table.info.storage_columns(),
+                ranges: ranges.into_iter(),
+                current: None,
+                seen: None,
+            }
+        }
+        Lookup::Hash { keys, columns, rows } => {
+            let rows = match rows {
+                Some(rows) => rows,
+                None => {
+                    log_debug(&format!("Building a hash table of the rows of {}", table.info.name));
+                    let mut source = db.plan_row_source(&table.filtered_query(), &table.info, table.info.rowid_alias.as_deref())?;
+                    let mut hashed: HashMap<HashKey, Vec<Row>> = HashMap::new();
+                    while let Some(row) = source.next_row(db, &table.info, &table.columns, &table.rowid_columns)? {
+                        let key: Vec<Value> = columns.iter().map(|&column| row.values()[column].clone()).collect();
+                        if admits(query, table, &row)? && !key.iter().any(Value::is_null) {
+                            hashed.entry(HashKey::binary(&key)).or_default().push(row);
+                        }
+                    }
+                    rows.insert(hashed)
+                }
+            };
+            let matches = match key_values(keys) {
+                Some(key) => rows.get(&HashKey::binary(&key)).cloned().unwrap_or_default(),
+                None => Vec::new(),
+            };
+            return Ok(JoinFrame { values, matches: JoinMatches::Rows(matches.into_iter()), matched: false });
+        }
+        Lookup::Scan => db.plan_row_source(&table.filtered_query(), &table.info, table.info.rowid_alias.as_deref())?,
+    };
+    Ok(JoinFrame { values, matches: JoinMatches::Source(source), matched: false })
+}
@@ -10,6 +10,7 @@
 //! ```no_run
 //! use sqlite_wasm_reader::{Database, Error, SelectQuery};
 //!
+//! # #[cfg(feature = "std")]
 //! fn main() -> Result<(), Error> {
 //!     let mut db = Database::open("example.db")?;
 //!     
@@ -28,16 +29,23 @@
 //!     
 //!     Ok(())
 //! }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
 //! ```
 
-// Only use no_std for non-WASI WebAssembly targets
-#![cfg_attr(all(target_arch = "wasm32", not(target_os = "wasi")), no_std)]
+// Without the `std` feature the crate only needs `core` and `alloc`
+#![cfg_attr(not(feature = "std"), no_std)]
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
 extern crate alloc;
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
-use alloc::{string::String, vec::Vec, format};
+/// Map and set types, from `std` when available and `hashbrown` otherwise
+pub(crate) mod collections {
+    #[cfg(feature = "std")]
+    pub use std::collections::{HashMap, HashSet};
+    #[cfg(not(feature = "std"))]
+    pub use hashbrown::{HashMap, HashSet};
+    pub use alloc::collections::{BTreeMap, BTreeSet};
+}
 
 pub mod error;
 pub mod format;
@@ -45,20 +53,41 @@ pub mod page;
 pub mod btree;
 pub mod record;
 pub mod database;
+pub mod datetime;
+pub mod decode;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod row;
 pub mod value;
 pub mod logging;
 pub mod query;
+mod aggregate;
+mod eval;
+mod functions;
+mod join;
+pub mod options;
+pub mod source;
+pub mod wal;
 
 pub use error::{Error, Result};
-pub use database::Database;
+pub use database::{Affinity, ColumnInfo, Database, ForeignKey, GeneratedColumn, RowIter, TableConstraint, TableInfo};
+pub use options::{OpenOptions, ReaderLimits};
 pub use value::Value;
-pub use logging::{Logger, LogLevel, init_default_logger, set_log_level, log_error, log_warn, log_info, log_debug, log_trace};
-pub use query::{SelectQuery, ComparisonOperator, OrderBy};
+pub use logging::{Logger, LogLevel, LogSink, init_default_logger, set_log_level, set_log_sink, clear_log_sink, log_error, log_warn, log_info, log_debug, log_trace};
+pub use query::{Aggregate, AggregateFunction, BinaryOperator, ComparisonOperator, Join, JoinKind, OrderBy, ResultColumn, ScalarExpr, ScalarFunction, SelectQuery, UnaryOperator};
 
 // Re-export commonly used types
-pub use format::{FileHeader, PageType};
+pub use format::{FileHeader, PageType, TextEncoding};
 pub use page::Page;
-pub use btree::{BTreeCursor, Cell};
+pub use btree::{BTreeCursor, Cell, PageReader};
+pub use wal::WalCommit;
+pub use source::{BytesSource, PageSource};
+#[cfg(feature = "std")]
+pub use source::ReadSeekSource;
 
 // Re-export key types
-pub use database::Row;
+pub use row::{ResultSet, Row, RowEntries, RowIndex};
+pub use decode::{FromRow, FromValue, FromValueError};
+pub use datetime::{Date, DateTime, Time};
+#[cfg(feature = "derive")]
+pub use sqlite_wasm_reader_derive::FromRow;
@@ -1,8 +1,30 @@
 //! Logging functionality for sqlite_wasm_reader
 
-use std::sync::Mutex;
+use core::sync::atomic::{AtomicU8, Ordering};
+#[cfg(not(feature = "std"))]
+use core::sync::atomic::AtomicPtr;
+#[cfg(feature = "std")]
+use std::sync::{PoisonError, RwLock};
 
-static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+/// Sentinel for a global logger that has not been initialized yet
+const LEVEL_UNSET: u8 = u8::MAX;
+
+/// Global log level, stored atomically so no lock (and no `std`) is needed
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LEVEL_UNSET);
+
+/// Global log sink installed with [`set_log_sink`], or None for the default output
+#[cfg(feature = "std")]
+static LOG_SINK: RwLock<Option<LogSink>> = RwLock::new(None);
+
+/// Global log sink installed with [`set_log_sink`], or null for the default output.
+///
+/// Without `std` there is no lock to guard an `Option<LogSink>`, so the
+/// function pointer is stored as a data pointer.
+#[cfg(not(feature = "std"))]
+static LOG_SINK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Function that receives every enabled log message
+pub type LogSink = fn(LogLevel, &str);
 
 /// Log levels in order of increasing verbosity
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -21,18 +43,35 @@ pub enum LogLevel {
 
 impl LogLevel {
     /// Get the default log level
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         LogLevel::Info
     }
     
     /// Parse log level from string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
-            "error" => Some(LogLevel::Error),
-            "warn" | "warning" => Some(LogLevel::Warn),
-            "info" => Some(LogLevel::Info),
-            "debug" => Some(LogLevel::Debug),
-            "trace" => Some(LogLevel::Trace),
+        [
+            ("error", LogLevel::Error),
+            ("warn", LogLevel::Warn),
+            ("warning", LogLevel::Warn),
+            ("info", LogLevel::Info),
+            ("debug", LogLevel::Debug),
+            ("trace", LogLevel::Trace),
+        ]
+        .into_iter()
+        .find(|(name, _)| s.eq_ignore_ascii_case(name))
+        .map(|(_, level)| level)
+    }
+    
+    /// Convert a stored level back into a `LogLevel`
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LogLevel::Error),
+            1 => Some(LogLevel::Warn),
+            2 => Some(LogLevel::Info),
+            3 => Some(LogLevel::Debug),
+            4 => Some(LogLevel::Trace),
             _ => None,
         }
     }
@@ -51,6 +90,7 @@ impl Logger {
     }
     
     /// Create a logger with default level (Info)
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Logger::new(LogLevel::default())
     }
@@ -66,16 +106,29 @@ impl Logger {
     }
     
     /// Log a message if the level is enabled
+    ///
+    /// Messages go to the sink installed with [`set_log_sink`]; without one they
+    /// are written to stderr, or dropped when built without `std`.
     pub fn log(&self, level: LogLevel, message: &str) {
-        if level <= self.level {
-            let prefix = match level {
-                LogLevel::Error => "ERROR",
-                LogLevel::Warn => "WARN",
-                LogLevel::Info => "INFO",
-                LogLevel::Debug => "DEBUG",
-                LogLevel::Trace => "TRACE",
-            };
-            eprintln!("[sqlite_wasm_reader] {}: {}", prefix, message);
+        if level > self.level {
+            return;
+        }
+        
+        match log_sink() {
+            Some(sink) => sink(level, message),
+            #[cfg(feature = "std")]
+            None => {
+                let prefix = match level {
+                    LogLevel::Error => "ERROR",
+                    LogLevel::Warn => "WARN",
+                    LogLevel::Info => "INFO",
+                    LogLevel::Debug => "DEBUG",
+                    LogLevel::Trace => "TRACE",
+                };
+                eprintln!("[sqlite_wasm_reader] {}: {}", prefix, message);
+            }
+            #[cfg(not(feature = "std"))]
+            None => {}
         }
     }
     
@@ -107,8 +160,7 @@ impl Logger {
 
 /// Initialize the global logger
 pub fn init_logger(level: LogLevel) {
-    let mut guard = LOGGER.lock().unwrap();
-    *guard = Some(Logger::new(level));
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
 }
 
 /// Initialize the global logger with default level (Info)
@@ -118,24 +170,62 @@ pub fn init_default_logger() {
 
 /// Get the global logger instance
 pub fn get_logger() -> Logger {
-    let guard = LOGGER.lock().unwrap();
-    if let Some(ref logger) = *guard {
-        logger.clone()
-    } else {
-        drop(guard);
-        init_default_logger();
-        let guard = LOGGER.lock().unwrap();
-        guard.as_ref().unwrap().clone()
+    match LogLevel::from_u8(LOG_LEVEL.load(Ordering::Relaxed)) {
+        Some(level) => Logger::new(level),
+        None => {
+            init_default_logger();
+            Logger::default()
+        }
     }
 }
 
 /// Set the global log level
 pub fn set_log_level(level: LogLevel) {
-    let mut guard = LOGGER.lock().unwrap();
-    if let Some(ref mut logger) = *guard {
-        logger.set_level(level);
+    init_logger(level);
+}
+
+/// Route log messages to `sink` instead of stderr
+///
+/// This is the only way to see log output when built without `std`.
+pub fn set_log_sink(sink: LogSink) {
+    #[cfg(feature = "std")]
+    {
+        *LOG_SINK.write().unwrap_or_else(PoisonError::into_inner) = Some(sink);
+    }
+    #[cfg(not(feature = "std"))]
+    LOG_SINK.store(sink as *mut (), Ordering::Release);
+}
+
+/// Restore the default log output
+pub fn clear_log_sink() {
+    #[cfg(feature = "std")]
+    {
+        *LOG_SINK.write().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+    #[cfg(not(feature = "std"))]
+    LOG_SINK.store(core::ptr::null_mut(), Ordering::Release);
+}
+
+/// The currently installed log sink, if any
+#[cfg(feature = "std")]
+fn log_sink() -> Option<LogSink> {
+    // The lock is released before the sink runs, so a sink may log or replace itself
+    *LOG_SINK.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The currently installed log sink, if any
+#[cfg(not(feature = "std"))]
+fn log_sink() -> Option<LogSink> {
+    let ptr = LOG_SINK.load(Ordering::Acquire);
+    if ptr.is_null() {
+        None
     } else {
-        *guard = Some(Logger::new(level));
+        // SAFETY: `LOG_SINK` is private to this module and only ever holds null
+        // or a pointer cast from a `LogSink` in `set_log_sink`, so a non-null
+        // value is a valid function pointer of exactly that type. `transmute`
+        // only compiles because function and data pointers have the same size,
+        // and the cast back restores the original pointer unchanged.
+        Some(unsafe { core::mem::transmute::<*mut (), LogSink>(ptr) })
     }
 }
 
@@ -279,4 +369,23 @@ mod tests {
         assert!(is_enabled(LogLevel::Debug));
         assert!(is_enabled(LogLevel::Trace));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_log_sink() {
+        use core::sync::atomic::AtomicUsize;
+        static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+        fn sink(level: LogLevel, message: &str) {
+            if level == LogLevel::Error && message == "sink test message" {
+                RECEIVED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        set_log_sink(sink);
+        log_error("sink test message");
+        clear_log_sink();
+        log_error("sink test message");
+
+        assert_eq!(RECEIVED.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,157 @@
+//! Options for opening a database
+
+/// Resource limits applied while reading a database
+///
+/// Every limit is optional and `None` disables it, which is the default.
+/// Set limits when reading untrusted files to bound the work a single call
+/// can do. Hitting a limit fails the call with [`Error::LimitExceeded`]
+/// rather than returning truncated results.
+///
+/// [`Error::LimitExceeded`]: crate::Error::LimitExceeded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReaderLimits {
+    /// Most b-tree pages a single cursor may visit
+    pub max_pages_per_traversal: Option<usize>,
+    /// Most rows a query may return, or [`Database::count_table_rows`] may count
+    ///
+    /// [`Database::count_table_rows`]: crate::Database::count_table_rows
+    pub max_rows: Option<usize>,
+    /// Most entries read from `sqlite_master` when loading the schema
+    pub max_schema_objects: Option<usize>,
+}
+
+impl ReaderLimits {
+    /// No limits at all
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Set the most b-tree pages a single cursor may visit
+    pub fn max_pages_per_traversal(mut self, limit: Option<usize>) -> Self {
+        self.max_pages_per_traversal = limit;
+        self
+    }
+
+    /// Set the most rows a query may return or a count may reach
+    pub fn max_rows(mut self, limit: Option<usize>) -> Self {
+        self.max_rows = limit;
+        self
+    }
+
+    /// Set the most schema entries that may be loaded
+    pub fn max_schema_objects(mut self, limit: Option<usize>) -> Self {
+        self.max_schema_objects = limit;
+        self
+    }
+}
+
+/// Number of parsed pages kept in memory by default
+pub const DEFAULT_PAGE_CACHE_SIZE: usize = 5000;
+
+/// Number of groups a GROUP BY query holds in memory at once by default
+pub const DEFAULT_MAX_HASH_GROUPS: usize = 100_000;
+
+/// Number of passes a GROUP BY query may make over its rows by default, once
+/// it has more groups than it may hold at once
+pub const DEFAULT_MAX_GROUP_PASSES: usize = 10;
+
+/// Options used when opening a [`Database`](crate::Database)
+///
+/// ```no_run
+/// # #[cfg(feature = "std")] {
+/// use sqlite_wasm_reader::{Database, OpenOptions, ReaderLimits};
+///
+/// let options = OpenOptions::new()
+///     .limits(ReaderLimits::unlimited().max_rows(Some(10_000_000)))
+///     .page_cache_size(1000);
+/// let db = Database::open_with_options("example.db", &options)?;
+/// # }
+/// # Ok::<(), sqlite_wasm_reader::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenOptions {
+    pub(crate) limits: ReaderLimits,
+    pub(crate) page_cache_size: usize,
+    pub(crate) max_hash_groups: usize,
+    pub(crate) max_group_passes: usize,
+    pub(crate) wal_commit: Option<usize>,
+    pub(crate) random_seed: Option<u64>,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        OpenOptions {
+            limits: ReaderLimits::default(),
+            page_cache_size: DEFAULT_PAGE_CACHE_SIZE,
+            max_hash_groups: DEFAULT_MAX_HASH_GROUPS,
+            max_group_passes: DEFAULT_MAX_GROUP_PASSES,
+            wal_commit: None,
+            random_seed: None,
+        }
+    }
+}
+
+impl OpenOptions {
+    /// Default options: no limits, the default page cache and the latest WAL commit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the resource limits
+    pub fn limits(mut self, limits: ReaderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set how many parsed pages are cached (at least one page is always cached)
+    pub fn page_cache_size(mut self, pages: usize) -> Self {
+        self.page_cache_size = pages;
+        self
+    }
+
+    /// Set how many groups a GROUP BY query may hold in its hash table
+    ///
+    /// Queries with more groups start over and aggregate in passes over the
+    /// rows, each keeping at most this many groups in memory and returning
+    /// them in key order before the next pass. Every pass reads all the rows
+    /// the query matches again, so a query with `n` groups reads them about
+    /// `n / groups + 1` times; [`max_group_passes`](Self::max_group_passes)
+    /// bounds how many times.
+    pub fn max_hash_groups(mut self, groups: usize) -> Self {
+        self.max_hash_groups = groups;
+        self
+    }
+
+    /// Set how many passes over its rows a GROUP BY query may make once it
+    /// has more groups than [`max_hash_groups`](Self::max_hash_groups)
+    ///
+    /// A query that needs more passes fails with [`Error::LimitExceeded`]
+    /// when the last one ends. At least one pass is always allowed.
+    ///
+    /// [`Error::LimitExceeded`]: crate::Error::LimitExceeded
+    pub fn max_group_passes(mut self, passes: usize) -> Self {
+        self.max_group_passes = passes;
+        self
+    }
+
+    /// Read a WAL-mode database as of the commit frame `frame_index`
+    ///
+    /// See [`Database::open_at_wal_commit`](crate::Database::open_at_wal_commit).
+    pub fn wal_commit(mut self, frame_index: usize) -> Self {
+        self.wal_commit = Some(frame_index);
+        self
+    }
+
+    /// Seed `random()` so that queries draw the same sequence on every run
+    ///
+    /// Without a seed each database draws a different sequence.
+    pub fn random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+
+    /// The configured resource limits
+    pub fn reader_limits(&self) -> &ReaderLimits {
+        &self.limits
+    }
+}
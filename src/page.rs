@@ -3,7 +3,7 @@
 use crate::{Error, Result, format::{PageType, PAGE_HEADER_SIZE, CELL_POINTER_SIZE}};
 use byteorder::{BigEndian, ByteOrder};
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+#[cfg(not(feature = "std"))]
 use alloc::{vec::Vec, format};
 
 /// Represents a page in the SQLite database
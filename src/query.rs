@@ -1,31 +1,221 @@
 //! SQL query parsing and execution for SELECT statements
 
-use crate::{Error, Result, Value, Row};
-use std::collections::HashMap;
-use sqlparser::parser::Parser;
-use sqlparser::dialect::SQLiteDialect;
-use sqlparser::ast::{Statement, Query, SetExpr, Select, SelectItem, TableFactor, Expr as SqlExpr, BinaryOperator, Value as SqlValue};
+use crate::{Error, Result, Value, Row, Affinity, RowIndex, aggregate::{AggregatePlan, HashAggregator}, format::TextEncoding};
+use crate::eval::{self, Collation, Context, RowOperands};
+use crate::functions;
+use alloc::sync::Arc;
+use core::any::TypeId;
+use core::fmt;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::Token;
+use sqlparser::dialect::{Dialect, SQLiteDialect};
+use sqlparser::keywords::Keyword;
+use sqlparser::ast::{Statement, Query, SetExpr, Select, SelectItem, TableFactor, Expr as SqlExpr, Value as SqlValue, ValueWithSpan};
+use sqlparser::ast::{BinaryOperator as SqlBinaryOperator, UnaryOperator as SqlUnaryOperator, CastKind};
+use sqlparser::ast::{DuplicateTreatment, Function, FunctionArg, FunctionArgExpr, FunctionArgumentList, FunctionArguments, GroupByExpr, Ident};
+use sqlparser::ast::{JoinConstraint, JoinOperator, ObjectName, SelectItemQualifiedWildcardKind, TableWithJoins};
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
-use alloc::{vec::Vec, string::String, format, boxed::Box};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec, string::{String, ToString}, format, boxed::Box};
 
 /// Represents a parsed SELECT query
 #[derive(Debug, Clone)]
 pub struct SelectQuery {
     /// Columns to select (None means SELECT *)
     pub columns: Option<Vec<String>>,
+    /// SELECT list of a query that computes its columns, such as with
+    /// aggregate functions; when set it takes the place of `columns`
+    pub result_columns: Option<Vec<ResultColumn>>,
     /// Table name
     pub table: String,
+    /// Name the query refers to `table` by, if not its own
+    pub alias: Option<String>,
+    /// Tables joined to `table`, in order; column references may then be
+    /// qualified, as in `c.name`
+    pub joins: Vec<Join>,
     /// WHERE clause root expression
     pub where_expr: Option<Expr>,
+    /// GROUP BY keys; a column that is not in the table names an output
+    /// column of the SELECT list
+    pub group_by: Option<Vec<ScalarExpr>>,
+    /// HAVING condition, over output columns, table columns and aggregate
+    /// calls written as column names, such as `count(*)`
+    pub having: Option<Expr>,
     /// ORDER BY clause
     pub order_by: Option<OrderBy>,
     /// LIMIT clause
     pub limit: Option<usize>,
 }
 
+/// Table joined to the rows of the tables before it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    /// Table name
+    pub table: String,
+    /// Name the query refers to the table by, if not its own
+    pub alias: Option<String>,
+    /// ON condition; without one every pair of rows matches
+    pub on: Option<Expr>,
+}
+
+/// How a [`Join`] pairs its table's rows with the rows before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Pairs of rows that satisfy the ON condition
+    Inner,
+    /// Pairs of rows that satisfy the ON condition, plus each row before the
+    /// join that has none, with NULL in the joined table's columns
+    Left,
+    /// Every pair of rows
+    Cross,
+}
+
+/// Computed column of a SELECT list
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultColumn {
+    /// Name of the output column: its alias, or the expression as written
+    pub name: String,
+    pub expr: ScalarExpr,
+}
+
+impl ResultColumn {
+    /// Create a result column named `name` holding `expr`
+    pub fn new(name: impl Into<String>, expr: ScalarExpr) -> Self {
+        ResultColumn { name: name.into(), expr }
+    }
+}
+
+/// Expression producing a value, evaluated with SQLite's type affinity and
+/// NULL rules: most operators are NULL when an operand is NULL
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScalarExpr {
+    /// Value of a table column; in aggregate queries, from the row picked by
+    /// MIN or MAX, or else from the last row read
+    Column(String),
+    /// A constant
+    Literal(Value),
+    /// Aggregate function over the rows of the query
+    Aggregate(Box<Aggregate>),
+    /// `-expr` or `NOT expr`
+    Unary { operator: UnaryOperator, expr: Box<ScalarExpr> },
+    /// Arithmetic, `||`, bitwise or logical operator
+    Binary { left: Box<ScalarExpr>, operator: BinaryOperator, right: Box<ScalarExpr> },
+    /// Comparison, 1 or 0, with the affinity and collation of the columns compared
+    Compare { left: Box<ScalarExpr>, operator: ComparisonOperator, right: Box<ScalarExpr> },
+    /// `left IS right`, which treats NULL as equal to NULL and is never NULL
+    /// itself, or `IS NOT` when `negated`
+    Is { left: Box<ScalarExpr>, right: Box<ScalarExpr>, negated: bool },
+    /// `CASE [operand] WHEN .. THEN .. [ELSE ..] END`: the result of the first
+    /// branch whose condition is true, or equal to the operand if there is one
+    Case {
+        operand: Option<Box<ScalarExpr>>,
+        branches: Vec<(ScalarExpr, ScalarExpr)>,
+        otherwise: Option<Box<ScalarExpr>>,
+    },
+    /// `CAST(expr AS type)`, converting to the affinity of the type
+    Cast { expr: Box<ScalarExpr>, affinity: Affinity },
+    /// `expr COLLATE name`, comparing TEXT with BINARY, NOCASE or RTRIM
+    Collate { expr: Box<ScalarExpr>, collation: String },
+    /// Call of one of SQLite's built-in scalar functions, such as `lower(name)`
+    Function { function: ScalarFunction, args: Vec<ScalarExpr> },
+}
+
+/// Operators taking one operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    /// `-`, reading TEXT as the number it starts with
+    Negate,
+    /// `NOT`, NULL for NULL
+    Not,
+}
+
+/// Operators taking two operands
+///
+/// Arithmetic stays INTEGER until an operand is REAL or the result overflows;
+/// division by zero is NULL. `And` and `Or` follow three-valued logic, where
+/// `NULL AND 0` is 0 and `NULL OR 1` is 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    /// `||`, joining the text of both operands
+    Concat,
+    BitAnd,
+    BitOr,
+    And,
+    Or,
+}
+
+/// Aggregate function call, such as `COUNT(*)` or `sum(DISTINCT price)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    /// Arguments, empty for `COUNT(*)`
+    pub args: Vec<ScalarExpr>,
+    /// Whether each distinct argument value is only aggregated once
+    pub distinct: bool,
+}
+
+/// Aggregate functions, with SQLite's semantics
+///
+/// All of them skip NULL arguments. `Sum` is an INTEGER while every value is
+/// one, fails with [`Error::IntegerOverflow`] if it overflows, and is NULL
+/// over no values, while `Total` is always a REAL and `0.0` over no values.
+/// `GroupConcat` joins values with its second argument, or `,`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Total,
+    GroupConcat,
+}
+
+/// Scalar functions SQLite has built in, with its semantics
+///
+/// Each reads its arguments as SQLite does, as text or as numbers, and most
+/// are NULL for a NULL argument. `Min` and `Max` take two or more arguments,
+/// since with one they are the aggregates; `Printf` is also called `format`
+/// and `Substr` `substring`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarFunction {
+    Abs,
+    Char,
+    Coalesce,
+    Hex,
+    IfNull,
+    Iif,
+    Instr,
+    Length,
+    Lower,
+    Ltrim,
+    Max,
+    Min,
+    NullIf,
+    Printf,
+    Quote,
+    /// A random INTEGER, from the generator seeded by
+    /// [`OpenOptions::random_seed`](crate::OpenOptions::random_seed)
+    Random,
+    Replace,
+    /// Rounds half away from zero, to a REAL
+    Round,
+    Rtrim,
+    Substr,
+    Trim,
+    TypeOf,
+    Unicode,
+    Upper,
+}
+
 /// Expression for WHERE clause
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     /// Comparison: column op value
     Comparison {
@@ -33,6 +223,12 @@ pub enum Expr {
         operator: ComparisonOperator,
         value: Value,
     },
+    /// Comparison of two columns: left op right, unknown if either is NULL
+    ColumnComparison {
+        left: String,
+        operator: ComparisonOperator,
+        right: String,
+    },
     /// Logical AND
     And(Box<Expr>, Box<Expr>),
     /// Logical OR
@@ -47,6 +243,8 @@ pub enum Expr {
     In { column: String, values: Vec<Value> },
     /// BETWEEN (range check)
     Between { column: String, low: Value, high: Value },
+    /// Any other condition: an expression that holds where its value is true
+    Scalar(ScalarExpr),
 }
 
 /// Comparison operators for WHERE clauses
@@ -135,6 +333,16 @@ impl Expr {
         }
     }
 
+    /// Create `left op right` comparison of two columns, e.g.
+    /// `Expr::compare_columns("o.customer_id", ComparisonOperator::Equal, "c.id")`
+    pub fn compare_columns(left: impl Into<String>, operator: ComparisonOperator, right: impl Into<String>) -> Self {
+        Expr::ColumnComparison {
+            left: left.into(),
+            operator,
+            right: right.into(),
+        }
+    }
+
     /// Create `column IS NULL` expression
     pub fn is_null(column: impl Into<String>) -> Self {
         Expr::IsNull(column.into())
@@ -173,15 +381,359 @@ impl Expr {
     }
 
     /// Logical NOT: `NOT self`
+    #[allow(clippy::should_implement_trait)]
     pub fn not(self) -> Self {
         Expr::Not(Box::new(self))
     }
+
+    /// Copy of the expression with every column name replaced by `rename`
+    pub(crate) fn rename_columns(&self, rename: &mut dyn FnMut(&str) -> Result<String>) -> Result<Expr> {
+        Ok(match self {
+            Expr::Comparison { column, operator, value } => Expr::Comparison {
+                column: rename(column)?,
+                operator: operator.clone(),
+                value: value.clone(),
+            },
+            Expr::ColumnComparison { left, operator, right } => Expr::ColumnComparison {
+                left: rename(left)?,
+                operator: operator.clone(),
+                right: rename(right)?,
+            },
+            Expr::And(left, right) => Expr::And(Box::new(left.rename_columns(rename)?), Box::new(right.rename_columns(rename)?)),
+            Expr::Or(left, right) => Expr::Or(Box::new(left.rename_columns(rename)?), Box::new(right.rename_columns(rename)?)),
+            Expr::Not(expr) => Expr::Not(Box::new(expr.rename_columns(rename)?)),
+            Expr::IsNull(column) => Expr::IsNull(rename(column)?),
+            Expr::IsNotNull(column) => Expr::IsNotNull(rename(column)?),
+            Expr::In { column, values } => Expr::In { column: rename(column)?, values: values.clone() },
+            Expr::Between { column, low, high } => Expr::Between {
+                column: rename(column)?,
+                low: low.clone(),
+                high: high.clone(),
+            },
+            Expr::Scalar(expr) => Expr::Scalar(expr.rename_columns(rename)?),
+        })
+    }
+
+    /// Whether the condition calls an aggregate function anywhere
+    pub(crate) fn contains_aggregate(&self) -> bool {
+        match self {
+            Expr::And(left, right) | Expr::Or(left, right) => left.contains_aggregate() || right.contains_aggregate(),
+            Expr::Not(expr) => expr.contains_aggregate(),
+            Expr::Scalar(expr) => expr.contains_aggregate(),
+            _ => false,
+        }
+    }
+}
+
+impl ScalarExpr {
+    /// Copy of the expression with every column name replaced by `rename`
+    pub(crate) fn rename_columns(&self, rename: &mut dyn FnMut(&str) -> Result<String>) -> Result<ScalarExpr> {
+        fn boxed(expr: &ScalarExpr, rename: &mut dyn FnMut(&str) -> Result<String>) -> Result<Box<ScalarExpr>> {
+            expr.rename_columns(rename).map(Box::new)
+        }
+        Ok(match self {
+            ScalarExpr::Column(name) => ScalarExpr::Column(rename(name)?),
+            ScalarExpr::Literal(value) => ScalarExpr::Literal(value.clone()),
+            ScalarExpr::Aggregate(aggregate) => ScalarExpr::Aggregate(Box::new(Aggregate {
+                function: aggregate.function,
+                args: aggregate.args.iter().map(|arg| arg.rename_columns(rename)).collect::<Result<_>>()?,
+                distinct: aggregate.distinct,
+            })),
+            ScalarExpr::Unary { operator, expr } => ScalarExpr::Unary { operator: *operator, expr: boxed(expr, rename)? },
+            ScalarExpr::Binary { left, operator, right } => {
+                ScalarExpr::Binary { left: boxed(left, rename)?, operator: *operator, right: boxed(right, rename)? }
+            }
+            ScalarExpr::Compare { left, operator, right } => {
+                ScalarExpr::Compare { left: boxed(left, rename)?, operator: operator.clone(), right: boxed(right, rename)? }
+            }
+            ScalarExpr::Is { left, right, negated } => ScalarExpr::Is { left: boxed(left, rename)?, right: boxed(right, rename)?, negated: *negated },
+            ScalarExpr::Case { operand, branches, otherwise } => ScalarExpr::Case {
+                operand: operand.as_deref().map(|operand| boxed(operand, rename)).transpose()?,
+                branches: branches
+                    .iter()
+                    .map(|(when, then)| Ok((when.rename_columns(rename)?, then.rename_columns(rename)?)))
+                    .collect::<Result<_>>()?,
+                otherwise: otherwise.as_deref().map(|otherwise| boxed(otherwise, rename)).transpose()?,
+            },
+            ScalarExpr::Cast { expr, affinity } => ScalarExpr::Cast { expr: boxed(expr, rename)?, affinity: *affinity },
+            ScalarExpr::Collate { expr, collation } => ScalarExpr::Collate { expr: boxed(expr, rename)?, collation: collation.clone() },
+            ScalarExpr::Function { function, args } => ScalarExpr::Function {
+                function: *function,
+                args: args.iter().map(|arg| arg.rename_columns(rename)).collect::<Result<_>>()?,
+            },
+        })
+    }
+
+    /// The expressions this one is computed from
+    pub(crate) fn children(&self) -> Vec<&ScalarExpr> {
+        match self {
+            ScalarExpr::Column(_) | ScalarExpr::Literal(_) => Vec::new(),
+            ScalarExpr::Aggregate(aggregate) => aggregate.args.iter().collect(),
+            ScalarExpr::Function { args, .. } => args.iter().collect(),
+            ScalarExpr::Unary { expr, .. } | ScalarExpr::Cast { expr, .. } | ScalarExpr::Collate { expr, .. } => vec![&**expr],
+            ScalarExpr::Binary { left, right, .. } | ScalarExpr::Compare { left, right, .. } | ScalarExpr::Is { left, right, .. } => {
+                vec![&**left, &**right]
+            }
+            ScalarExpr::Case { operand, branches, otherwise } => operand
+                .as_deref()
+                .into_iter()
+                .chain(branches.iter().flat_map(|(when, then)| [when, then]))
+                .chain(otherwise.as_deref())
+                .collect(),
+        }
+    }
+
+    /// The expressions this one is computed from, to modify in place
+    fn children_mut(&mut self) -> Vec<&mut ScalarExpr> {
+        match self {
+            ScalarExpr::Column(_) | ScalarExpr::Literal(_) => Vec::new(),
+            ScalarExpr::Aggregate(aggregate) => aggregate.args.iter_mut().collect(),
+            ScalarExpr::Function { args, .. } => args.iter_mut().collect(),
+            ScalarExpr::Unary { expr, .. } | ScalarExpr::Cast { expr, .. } | ScalarExpr::Collate { expr, .. } => vec![&mut **expr],
+            ScalarExpr::Binary { left, right, .. } | ScalarExpr::Compare { left, right, .. } | ScalarExpr::Is { left, right, .. } => {
+                vec![&mut **left, &mut **right]
+            }
+            ScalarExpr::Case { operand, branches, otherwise } => operand
+                .as_deref_mut()
+                .into_iter()
+                .chain(branches.iter_mut().flat_map(|(when, then)| [when, then]))
+                .chain(otherwise.as_deref_mut())
+                .collect(),
+        }
+    }
+
+    /// Whether the expression calls an aggregate function anywhere
+    pub(crate) fn contains_aggregate(&self) -> bool {
+        matches!(self, ScalarExpr::Aggregate(_)) || self.children().into_iter().any(ScalarExpr::contains_aggregate)
+    }
+}
+
+/// SQL text of the expression, which parses back into the same expression
+impl fmt::Display for ScalarExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalarExpr::Column(name) => {
+                // Joined columns such as `c.name` read back as qualified names
+                let plain = |part: &str| {
+                    part.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                        && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                };
+                if name.split('.').all(plain) {
+                    f.write_str(name)
+                } else {
+                    write!(f, "\"{}\"", name.replace('"', "\"\""))
+                }
+            }
+            ScalarExpr::Literal(value) => match value {
+                Value::Null => f.write_str("NULL"),
+                Value::Integer(v) => write!(f, "{}", v),
+                Value::Real(v) if v.is_nan() => f.write_str("NULL"),
+                Value::Real(v) if v.is_infinite() => f.write_str(if *v > 0.0 { "1e999" } else { "-1e999" }),
+                Value::Real(v) => write!(f, "{:?}", v),
+                Value::Text(text) => write!(f, "'{}'", text.replace('\'', "''")),
+                Value::Blob(bytes) => {
+                    f.write_str("X'")?;
+                    for byte in bytes {
+                        write!(f, "{:02X}", byte)?;
+                    }
+                    f.write_str("'")
+                }
+            },
+            ScalarExpr::Aggregate(aggregate) => {
+                let name = match aggregate.function {
+                    AggregateFunction::Count => "count",
+                    AggregateFunction::Sum => "sum",
+                    AggregateFunction::Avg => "avg",
+                    AggregateFunction::Min => "min",
+                    AggregateFunction::Max => "max",
+                    AggregateFunction::Total => "total",
+                    AggregateFunction::GroupConcat => "group_concat",
+                };
+                write!(f, "{}(", name)?;
+                if aggregate.distinct {
+                    f.write_str("DISTINCT ")?;
+                }
+                if aggregate.args.is_empty() {
+                    f.write_str("*")?;
+                }
+                for (i, arg) in aggregate.args.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { ", " } else { "" }, arg)?;
+                }
+                f.write_str(")")
+            }
+            ScalarExpr::Unary { operator: UnaryOperator::Negate, expr } => write!(f, "-({})", expr),
+            ScalarExpr::Unary { operator: UnaryOperator::Not, expr } => write!(f, "NOT ({})", expr),
+            ScalarExpr::Binary { left, operator, right } => {
+                let operator = match operator {
+                    BinaryOperator::Add => "+",
+                    BinaryOperator::Subtract => "-",
+                    BinaryOperator::Multiply => "*",
+                    BinaryOperator::Divide => "/",
+                    BinaryOperator::Remainder => "%",
+                    BinaryOperator::Concat => "||",
+                    BinaryOperator::BitAnd => "&",
+                    BinaryOperator::BitOr => "|",
+                    BinaryOperator::And => "AND",
+                    BinaryOperator::Or => "OR",
+                };
+                write!(f, "({} {} {})", left, operator, right)
+            }
+            ScalarExpr::Compare { left, operator, right } => {
+                let operator = match operator {
+                    ComparisonOperator::Equal => "=",
+                    ComparisonOperator::NotEqual => "<>",
+                    ComparisonOperator::LessThan => "<",
+                    ComparisonOperator::LessThanOrEqual => "<=",
+                    ComparisonOperator::GreaterThan => ">",
+                    ComparisonOperator::GreaterThanOrEqual => ">=",
+                    ComparisonOperator::Like => "LIKE",
+                };
+                write!(f, "({} {} {})", left, operator, right)
+            }
+            ScalarExpr::Is { left, right, negated } => {
+                write!(f, "({} IS {}DISTINCT FROM {})", left, if *negated { "" } else { "NOT " }, right)
+            }
+            ScalarExpr::Case { operand, branches, otherwise } => {
+                f.write_str("CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for (when, then) in branches {
+                    write!(f, " WHEN {} THEN {}", when, then)?;
+                }
+                if let Some(otherwise) = otherwise {
+                    write!(f, " ELSE {}", otherwise)?;
+                }
+                f.write_str(" END")
+            }
+            ScalarExpr::Cast { expr, affinity } => {
+                let name = match affinity {
+                    Affinity::Integer => "INTEGER",
+                    Affinity::Text => "TEXT",
+                    Affinity::Blob => "BLOB",
+                    Affinity::Real => "REAL",
+                    Affinity::Numeric => "NUMERIC",
+                };
+                write!(f, "CAST({} AS {})", expr, name)
+            }
+            ScalarExpr::Collate { expr, collation } => write!(f, "({} COLLATE {})", expr, collation),
+            ScalarExpr::Function { function, args } => {
+                write!(f, "{}(", functions::name(*function))?;
+                for (i, arg) in args.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { ", " } else { "" }, arg)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// SQLite's dialect, except that `trim(..)`, `substr(..)` and `substring(..)`
+/// are plain function calls, as SQLite reads them, where sqlparser reads the
+/// SQL standard's forms and rejects `trim(x, characters)`
+#[derive(Debug)]
+struct QueryDialect(SQLiteDialect);
+
+impl Dialect for QueryDialect {
+    // The parser checks for SQLite by type, so the wrapper passes for it
+    fn dialect(&self) -> TypeId {
+        TypeId::of::<SQLiteDialect>()
+    }
+
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        self.0.is_delimited_identifier_start(ch)
+    }
+
+    fn identifier_quote_style(&self, identifier: &str) -> Option<char> {
+        self.0.identifier_quote_style(identifier)
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        self.0.is_identifier_start(ch)
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        self.0.is_identifier_part(ch)
+    }
+
+    fn supports_filter_during_aggregation(&self) -> bool {
+        self.0.supports_filter_during_aggregation()
+    }
+
+    fn supports_start_transaction_modifier(&self) -> bool {
+        self.0.supports_start_transaction_modifier()
+    }
+
+    fn supports_in_empty_list(&self) -> bool {
+        self.0.supports_in_empty_list()
+    }
+
+    fn supports_limit_comma(&self) -> bool {
+        self.0.supports_limit_comma()
+    }
+
+    fn supports_asc_desc_in_column_definition(&self) -> bool {
+        self.0.supports_asc_desc_in_column_definition()
+    }
+
+    fn supports_dollar_placeholder(&self) -> bool {
+        self.0.supports_dollar_placeholder()
+    }
+
+    fn parse_statement(&self, parser: &mut Parser) -> Option<core::result::Result<Statement, ParserError>> {
+        self.0.parse_statement(parser)
+    }
+
+    fn parse_infix(&self, parser: &mut Parser, expr: &SqlExpr, precedence: u8) -> Option<core::result::Result<SqlExpr, ParserError>> {
+        self.0.parse_infix(parser, expr, precedence)
+    }
+
+    fn parse_prefix(&self, parser: &mut Parser) -> Option<core::result::Result<SqlExpr, ParserError>> {
+        let [Token::Word(word), Token::LParen] = parser.peek_tokens() else {
+            return None;
+        };
+        if !matches!(word.keyword, Keyword::TRIM | Keyword::SUBSTR | Keyword::SUBSTRING) {
+            return None;
+        }
+        parser.next_token();
+        Some(parser.parse_function(ObjectName::from(vec![Ident::new(word.value)])))
+    }
+}
+
+impl Join {
+    /// Inner join of `table`, keeping the pairs of rows that satisfy its ON condition
+    pub fn inner(table: impl Into<String>) -> Self {
+        Join { kind: JoinKind::Inner, table: table.into(), alias: None, on: None }
+    }
+
+    /// Left outer join of `table`, which keeps rows without a match
+    pub fn left(table: impl Into<String>) -> Self {
+        Join { kind: JoinKind::Left, table: table.into(), alias: None, on: None }
+    }
+
+    /// Cross join of `table`, pairing every row with every row before it
+    pub fn cross(table: impl Into<String>) -> Self {
+        Join { kind: JoinKind::Cross, table: table.into(), alias: None, on: None }
+    }
+
+    /// Refer to the table as `alias`
+    pub fn with_alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = Some(alias.into());
+        self
+    }
+
+    /// Attach an ON condition, e.g.
+    /// `Expr::compare_columns("o.customer_id", ComparisonOperator::Equal, "c.id")`
+    pub fn with_on(mut self, expr: Expr) -> Self {
+        self.on = Some(expr);
+        self
+    }
 }
 
 impl SelectQuery {
     /// Parse a SELECT SQL statement using sqlparser
     pub fn parse(sql: &str) -> Result<Self> {
-        let dialect = SQLiteDialect {};
+        let dialect = QueryDialect(SQLiteDialect {});
         let statements = Parser::parse_sql(&dialect, sql)
             .map_err(|e| Error::QueryError(format!("SQL parse error: {}", e)))?;
 
@@ -203,211 +755,726 @@ impl SelectQuery {
             return Err(Error::QueryError("Unsupported query type".to_string()));
         };
 
-        let table = Self::parse_table_name(select)?;
-        let columns = Self::parse_columns(&select.projection)?;
+        let (table, alias, joins) = Self::parse_from(select)?;
+        let (columns, result_columns) = Self::parse_columns(&select.projection)?;
         let where_expr = if let Some(expr) = &select.selection {
             Some(Self::parse_where_expr(expr)?)
         } else {
             None
         };
+        if where_expr.as_ref().is_some_and(Expr::contains_aggregate) {
+            return Err(Error::QueryError("misuse of aggregate function in WHERE clause".to_string()));
+        }
+        let group_by = Self::parse_group_by(&select.group_by, columns.as_deref(), result_columns.as_deref())?;
+        let having = select.having.as_ref().map(Self::parse_having).transpose()?;
+        let aggregates = result_columns.iter().flatten().any(|column| column.expr.contains_aggregate());
+        if having.is_some() && group_by.is_none() && !aggregates {
+            return Err(Error::QueryError("HAVING clause on a non-aggregate query".to_string()));
+        }
 
-        let order_by = Self::parse_order_by(query.order_by.as_ref())?;
+        let aggregate = aggregates || group_by.is_some();
+        let order_by = Self::parse_order_by(query.order_by.as_ref(), aggregate, columns.as_deref(), result_columns.as_deref())?;
         let limit = Self::parse_limit(query.limit_clause.as_ref())?;
 
         Ok(SelectQuery {
             columns,
+            result_columns,
             table,
+            alias,
+            joins,
             where_expr,
+            group_by,
+            having,
             order_by,
             limit,
         })
     }
 
-    fn parse_table_name(select: &Select) -> Result<String> {
-        if select.from.len() != 1 {
-            return Err(Error::QueryError("Query must involve exactly one table".to_string()));
+    /// Parse the FROM clause into its first table, that table's alias and the
+    /// tables joined to it; tables listed after a comma are cross joined
+    #[allow(clippy::type_complexity)]
+    fn parse_from(select: &Select) -> Result<(String, Option<String>, Vec<Join>)> {
+        let Some((first, rest)) = select.from.split_first() else {
+            return Err(Error::QueryError("Query must involve at least one table".to_string()));
+        };
+        let (table, alias) = Self::parse_table_factor(&first.relation)?;
+        let mut joins = Vec::new();
+        Self::parse_joins(first, &mut joins)?;
+        for from in rest {
+            let (table, alias) = Self::parse_table_factor(&from.relation)?;
+            joins.push(Join { kind: JoinKind::Cross, table, alias, on: None });
+            Self::parse_joins(from, &mut joins)?;
         }
-        let table = &select.from[0];
-        if !table.joins.is_empty() {
-            return Err(Error::QueryError("JOINs are not supported".to_string()));
+        Ok((table, alias, joins))
+    }
+
+    fn parse_table_factor(relation: &TableFactor) -> Result<(String, Option<String>)> {
+        match relation {
+            TableFactor::Table { name, alias, args: None, .. } if alias.as_ref().is_none_or(|alias| alias.columns.is_empty()) => {
+                Ok((Self::object_name(name), alias.as_ref().map(|alias| alias.name.value.clone())))
+            }
+            _ => Err(Error::QueryError("Unsupported table factor".to_string())),
         }
-        if let TableFactor::Table { name, .. } = &table.relation {
-            Ok(name.0.iter().map(|i| i.to_string()).collect::<Vec<_>>().join("."))
-        } else {
-            Err(Error::QueryError("Unsupported table factor".to_string()))
+    }
+
+    fn parse_joins(from: &TableWithJoins, joins: &mut Vec<Join>) -> Result<()> {
+        for join in &from.joins {
+            let (table, alias) = Self::parse_table_factor(&join.relation)?;
+            let (kind, constraint) = match &join.join_operator {
+                JoinOperator::Join(constraint) | JoinOperator::Inner(constraint) => (JoinKind::Inner, constraint),
+                JoinOperator::Left(constraint) | JoinOperator::LeftOuter(constraint) => (JoinKind::Left, constraint),
+                JoinOperator::CrossJoin => (JoinKind::Cross, &JoinConstraint::None),
+                operator => return Err(Error::QueryError(format!("Unsupported join: {:?}", operator))),
+            };
+            let on = match constraint {
+                JoinConstraint::On(expr) => Some(Self::parse_where_expr(expr)?),
+                JoinConstraint::None => None,
+                JoinConstraint::Using(_) | JoinConstraint::Natural => {
+                    return Err(Error::QueryError("USING and NATURAL joins are not supported".to_string()))
+                }
+            };
+            joins.push(Join { kind, table, alias, on });
+        }
+        Ok(())
+    }
+
+    fn object_name(name: &ObjectName) -> String {
+        name.0.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(".")
+    }
+
+    /// Name of the column an identifier refers to; qualified references such
+    /// as `c.name` keep their qualifier
+    pub(crate) fn column_reference(expr: &SqlExpr) -> Option<String> {
+        match expr {
+            SqlExpr::Identifier(ident) => Some(ident.value.clone()),
+            SqlExpr::CompoundIdentifier(idents) => {
+                Some(idents.iter().map(|ident| ident.value.as_str()).collect::<Vec<_>>().join("."))
+            }
+            _ => None,
         }
     }
 
-    fn parse_columns(projection: &[SelectItem]) -> Result<Option<Vec<String>>> {
+    /// Parse the SELECT list into plain columns, or into result columns when it
+    /// computes anything: aggregates, expressions, or constants
+    #[allow(clippy::type_complexity)]
+    fn parse_columns(projection: &[SelectItem]) -> Result<(Option<Vec<String>>, Option<Vec<ResultColumn>>)> {
         if projection.len() == 1 {
             if let SelectItem::Wildcard(_) = &projection[0] {
-                return Ok(None);
+                return Ok((None, None));
             }
         }
 
-        let mut columns = Vec::new();
-        for item in projection {
-            if let SelectItem::UnnamedExpr(SqlExpr::Identifier(ident)) = item {
-                columns.push(ident.value.clone());
-            } else {
-                return Err(Error::QueryError("Unsupported column expression".to_string()));
+        // Columns, `*` and qualified wildcards such as `c.*`
+        let plain = |item: &SelectItem| match item {
+            SelectItem::UnnamedExpr(expr) => Self::column_reference(expr),
+            SelectItem::Wildcard(_) => Some("*".to_string()),
+            SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                Some(format!("{}.*", Self::object_name(name)))
             }
+            _ => None,
+        };
+        if let Some(columns) = projection.iter().map(plain).collect::<Option<Vec<_>>>() {
+            return Ok((Some(columns), None));
         }
-        Ok(Some(columns))
+
+        let mut result_columns = Vec::new();
+        for item in projection {
+            let (expr, name) = match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    // A qualified column is named without its qualifier, as in SQLite
+                    let name = match expr {
+                        SqlExpr::Identifier(ident) => ident.value.clone(),
+                        SqlExpr::CompoundIdentifier(idents) => idents.last().map(|ident| ident.value.clone()).unwrap_or_default(),
+                        _ => expr.to_string(),
+                    };
+                    (Self::parse_scalar_expr(expr)?, name)
+                }
+                SelectItem::ExprWithAlias { expr, alias } => (Self::parse_scalar_expr(expr)?, alias.value.clone()),
+                _ => return Err(Error::QueryError("Unsupported column expression".to_string())),
+            };
+            result_columns.push(ResultColumn { name, expr });
+        }
+        Ok((None, Some(result_columns)))
     }
 
-    fn parse_where_expr(expr: &SqlExpr) -> Result<Expr> {
-        match expr {
-            SqlExpr::BinaryOp { left, op, right } => match op {
-                BinaryOperator::And => Ok(Expr::And(
-                    Box::new(Self::parse_where_expr(left)?),
-                    Box::new(Self::parse_where_expr(right)?),
-                )),
-                BinaryOperator::Or => Ok(Expr::Or(
-                    Box::new(Self::parse_where_expr(left)?),
-                    Box::new(Self::parse_where_expr(right)?),
-                )),
-                BinaryOperator::Eq
-                | BinaryOperator::NotEq
-                | BinaryOperator::Lt
-                | BinaryOperator::LtEq
-                | BinaryOperator::Gt
-                | BinaryOperator::GtEq => Self::parse_comparison_expr(expr),
-                _ => Err(Error::QueryError(format!("Unsupported operator: {:?}", op))),
+    /// Parse an expression: columns, literals, operators, CASE, CAST, COLLATE
+    /// and function calls
+    fn parse_scalar_expr(expr: &SqlExpr) -> Result<ScalarExpr> {
+        let boxed = |expr: &SqlExpr| Self::parse_scalar_expr(expr).map(Box::new);
+        let unsupported = || Error::QueryError(format!("Unsupported expression: {}", expr));
+        Ok(match expr {
+            SqlExpr::Identifier(_) | SqlExpr::CompoundIdentifier(_) => {
+                ScalarExpr::Column(Self::column_reference(expr).unwrap_or_default())
+            }
+            SqlExpr::Function(function) => Self::parse_function(function)?,
+            SqlExpr::Nested(expr) => Self::parse_scalar_expr(expr)?,
+            SqlExpr::Value(_) => ScalarExpr::Literal(Self::parse_sql_value(expr)?),
+            SqlExpr::UnaryOp { op, expr: operand } => match op {
+                // Negative numbers stay literals
+                SqlUnaryOperator::Minus => match Self::parse_sql_value(expr) {
+                    Ok(value) => ScalarExpr::Literal(value),
+                    Err(_) => ScalarExpr::Unary { operator: UnaryOperator::Negate, expr: boxed(operand)? },
+                },
+                // Unary plus leaves any value as it is, even TEXT
+                SqlUnaryOperator::Plus => Self::parse_scalar_expr(operand)?,
+                SqlUnaryOperator::Not => ScalarExpr::Unary { operator: UnaryOperator::Not, expr: boxed(operand)? },
+                _ => return Err(unsupported()),
             },
-            SqlExpr::IsNull(expr) => {
-                if let SqlExpr::Identifier(ident) = &**expr {
-                    Ok(Expr::IsNull(ident.value.clone()))
-                } else {
-                    Err(Error::QueryError("Expected column name before IS NULL".to_string()))
-                }
+            SqlExpr::BinaryOp { left, op, right } => {
+                let operator = match op {
+                    SqlBinaryOperator::Plus => BinaryOperator::Add,
+                    SqlBinaryOperator::Minus => BinaryOperator::Subtract,
+                    SqlBinaryOperator::Multiply => BinaryOperator::Multiply,
+                    SqlBinaryOperator::Divide => BinaryOperator::Divide,
+                    SqlBinaryOperator::Modulo => BinaryOperator::Remainder,
+                    SqlBinaryOperator::StringConcat => BinaryOperator::Concat,
+                    SqlBinaryOperator::BitwiseAnd => BinaryOperator::BitAnd,
+                    SqlBinaryOperator::BitwiseOr => BinaryOperator::BitOr,
+                    SqlBinaryOperator::And => BinaryOperator::And,
+                    SqlBinaryOperator::Or => BinaryOperator::Or,
+                    op => {
+                        let operator = Self::comparison_operator(op)?;
+                        return Ok(ScalarExpr::Compare { left: boxed(left)?, operator, right: boxed(right)? });
+                    }
+                };
+                ScalarExpr::Binary { left: boxed(left)?, operator, right: boxed(right)? }
+            }
+            SqlExpr::IsNull(operand) | SqlExpr::IsNotNull(operand) => ScalarExpr::Is {
+                left: boxed(operand)?,
+                right: Box::new(ScalarExpr::Literal(Value::Null)),
+                negated: matches!(expr, SqlExpr::IsNotNull(_)),
             },
-            SqlExpr::IsNotNull(expr) => {
-                if let SqlExpr::Identifier(ident) = &**expr {
-                    Ok(Expr::IsNotNull(ident.value.clone()))
-                } else {
-                    Err(Error::QueryError("Expected column name before IS NOT NULL".to_string()))
+            SqlExpr::IsDistinctFrom(left, right) => ScalarExpr::Is { left: boxed(left)?, right: boxed(right)?, negated: true },
+            SqlExpr::IsNotDistinctFrom(left, right) => ScalarExpr::Is { left: boxed(left)?, right: boxed(right)?, negated: false },
+            // `x IS TRUE` is 1 when x is true and 0 otherwise, even for NULL
+            SqlExpr::IsTrue(operand) | SqlExpr::IsNotTrue(operand) | SqlExpr::IsFalse(operand) | SqlExpr::IsNotFalse(operand) => {
+                let operand = Self::parse_scalar_expr(operand)?;
+                let when = match expr {
+                    SqlExpr::IsTrue(_) | SqlExpr::IsNotTrue(_) => operand,
+                    _ => ScalarExpr::Unary { operator: UnaryOperator::Not, expr: Box::new(operand) },
+                };
+                let holds = i64::from(matches!(expr, SqlExpr::IsTrue(_) | SqlExpr::IsFalse(_)));
+                ScalarExpr::Case {
+                    operand: None,
+                    branches: vec![(when, ScalarExpr::Literal(Value::Integer(holds)))],
+                    otherwise: Some(Box::new(ScalarExpr::Literal(Value::Integer(1 - holds)))),
                 }
-            },
-            SqlExpr::Like { negated, expr, pattern, .. } => {
-                if *negated {
-                    return Err(Error::QueryError("NOT LIKE is not supported".to_string()));
-                }
-                if let (SqlExpr::Identifier(ident), value_expr) = (&**expr, &**pattern) {
-                    let value = Self::parse_sql_value(value_expr)?;
-                    Ok(Expr::Comparison {
-                        column: ident.value.clone(),
-                        operator: ComparisonOperator::Like,
-                        value,
+            }
+            SqlExpr::Like { negated, any: false, expr: operand, pattern, escape_char: None } => {
+                let like = ScalarExpr::Compare { left: boxed(operand)?, operator: ComparisonOperator::Like, right: boxed(pattern)? };
+                Self::negate_if(like, *negated)
+            }
+            // `x IN (a, b)` is `x = a OR x = b`, which is also NULL when nothing matches but a NULL
+            SqlExpr::InList { expr: operand, list, negated } => {
+                let operand = Self::parse_scalar_expr(operand)?;
+                let equal = |item: &SqlExpr| -> Result<ScalarExpr> {
+                    Ok(ScalarExpr::Compare { left: Box::new(operand.clone()), operator: ComparisonOperator::Equal, right: boxed(item)? })
+                };
+                let any = list
+                    .iter()
+                    .map(equal)
+                    .reduce(|left, right| {
+                        Ok(ScalarExpr::Binary { left: Box::new(left?), operator: BinaryOperator::Or, right: Box::new(right?) })
                     })
-                } else {
-                    Err(Error::QueryError("Expected column LIKE 'pattern'".to_string()))
-                }
+                    .transpose()?
+                    .unwrap_or(ScalarExpr::Literal(Value::Integer(0)));
+                Self::negate_if(any, *negated)
+            }
+            SqlExpr::Between { expr: operand, negated, low, high } => {
+                let operand = boxed(operand)?;
+                let lower = ScalarExpr::Compare { left: operand.clone(), operator: ComparisonOperator::GreaterThanOrEqual, right: boxed(low)? };
+                let upper = ScalarExpr::Compare { left: operand, operator: ComparisonOperator::LessThanOrEqual, right: boxed(high)? };
+                let between = ScalarExpr::Binary { left: Box::new(lower), operator: BinaryOperator::And, right: Box::new(upper) };
+                Self::negate_if(between, *negated)
+            }
+            SqlExpr::Case { operand, conditions, else_result, .. } => ScalarExpr::Case {
+                operand: operand.as_deref().map(boxed).transpose()?,
+                branches: conditions
+                    .iter()
+                    .map(|when| Ok((Self::parse_scalar_expr(&when.condition)?, Self::parse_scalar_expr(&when.result)?)))
+                    .collect::<Result<_>>()?,
+                otherwise: else_result.as_deref().map(boxed).transpose()?,
             },
-            SqlExpr::InList { expr, list, negated } => {
-                if *negated {
-                    return Err(Error::QueryError("NOT IN is not supported".to_string()));
-                }
-                if let SqlExpr::Identifier(ident) = &**expr {
-                    let mut values = Vec::new();
-                    for item in list {
-                        values.push(Self::parse_sql_value(item)?);
+            SqlExpr::Cast { kind: CastKind::Cast, expr: operand, data_type, format: None } => ScalarExpr::Cast {
+                expr: boxed(operand)?,
+                affinity: Affinity::from_declared_type(&data_type.to_string()),
+            },
+            SqlExpr::Collate { expr: operand, collation } => {
+                let name = Self::object_name(collation);
+                let collation = Collation::parse(&name)
+                    .ok_or_else(|| Error::QueryError(format!("no such collation sequence: {}", name)))?;
+                ScalarExpr::Collate { expr: boxed(operand)?, collation: collation.name().to_string() }
+            }
+            _ => return Err(unsupported()),
+        })
+    }
+
+    /// `NOT expr` if `negated`, else `expr`
+    fn negate_if(expr: ScalarExpr, negated: bool) -> ScalarExpr {
+        if negated {
+            ScalarExpr::Unary { operator: UnaryOperator::Not, expr: Box::new(expr) }
+        } else {
+            expr
+        }
+    }
+
+    /// Whether a call is of an aggregate function, as `min()` and `max()` are
+    /// with one argument; unknown names count, for `parse_aggregate` to reject
+    fn is_aggregate_call(function: &Function) -> bool {
+        match functions::lookup(&function.name.to_string()) {
+            Some((ScalarFunction::Min | ScalarFunction::Max, _)) => {
+                matches!(&function.args, FunctionArguments::List(list) if list.args.len() == 1)
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    /// Parse a function call: an aggregate, or one of SQLite's scalar functions
+    fn parse_function(function: &Function) -> Result<ScalarExpr> {
+        if Self::is_aggregate_call(function) {
+            return Ok(ScalarExpr::Aggregate(Box::new(Self::parse_aggregate(function)?)));
+        }
+        let name = function.name.to_string();
+        let (scalar_function, arity) = functions::lookup(&name).ok_or_else(|| Error::QueryError(format!("no such function: {}", name)))?;
+        let list = Self::argument_list(function)?;
+        if list.duplicate_treatment.is_some() {
+            return Err(Error::QueryError(format!("DISTINCT is only allowed in aggregate functions: {}", function)));
+        }
+        let args = list
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Self::parse_scalar_expr(expr),
+                _ => Err(Error::QueryError(format!("Unsupported function: {}", function))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !arity.contains(&args.len()) {
+            return Err(Error::QueryError(format!("Wrong number of arguments to function {}()", name)));
+        }
+        Ok(ScalarExpr::Function { function: scalar_function, args })
+    }
+
+    /// The arguments of a plain call, without FILTER, OVER or other clauses
+    fn argument_list(function: &Function) -> Result<&FunctionArgumentList> {
+        match &function.args {
+            FunctionArguments::List(list)
+                if list.clauses.is_empty()
+                    && function.filter.is_none()
+                    && function.over.is_none()
+                    && function.within_group.is_empty() =>
+            {
+                Ok(list)
+            }
+            _ => Err(Error::QueryError(format!("Unsupported function: {}", function))),
+        }
+    }
+
+    /// Parse a call of one of the aggregate functions, checking its arguments
+    fn parse_aggregate(function: &Function) -> Result<Aggregate> {
+        let name = function.name.to_string();
+        let unsupported = || Error::QueryError(format!("Unsupported function: {}", function));
+        let list = Self::argument_list(function)?;
+
+        let (aggregate_function, arity) = match name.to_ascii_lowercase().as_str() {
+            "count" => (AggregateFunction::Count, 0..=1),
+            "sum" => (AggregateFunction::Sum, 1..=1),
+            "avg" => (AggregateFunction::Avg, 1..=1),
+            "min" => (AggregateFunction::Min, 1..=1),
+            "max" => (AggregateFunction::Max, 1..=1),
+            "total" => (AggregateFunction::Total, 1..=1),
+            "group_concat" => (AggregateFunction::GroupConcat, 1..=2),
+            "string_agg" => (AggregateFunction::GroupConcat, 2..=2),
+            _ => return Err(unsupported()),
+        };
+        let distinct = matches!(list.duplicate_treatment, Some(DuplicateTreatment::Distinct));
+
+        let mut args = Vec::new();
+        for arg in &list.args {
+            match arg {
+                // COUNT(*) counts rows, so it takes no argument
+                FunctionArg::Unnamed(FunctionArgExpr::Wildcard)
+                    if aggregate_function == AggregateFunction::Count && list.args.len() == 1 && !distinct => {}
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => {
+                    let arg = Self::parse_scalar_expr(expr)?;
+                    if arg.contains_aggregate() {
+                        return Err(Error::QueryError(format!("Misuse of aggregate function {}()", name)));
                     }
-                    Ok(Expr::In {
-                        column: ident.value.clone(),
-                        values,
-                    })
-                } else {
-                    Err(Error::QueryError("Expected column name before IN".to_string()))
+                    args.push(arg);
                 }
-            },
-            SqlExpr::Between { expr, negated, low, high } => {
-                if *negated {
-                    return Err(Error::QueryError("NOT BETWEEN is not supported".to_string()));
-                }
-                if let SqlExpr::Identifier(ident) = &**expr {
-                    let low_value = Self::parse_sql_value(low)?;
-                    let high_value = Self::parse_sql_value(high)?;
-                    Ok(Expr::Between {
-                        column: ident.value.clone(),
-                        low: low_value,
-                        high: high_value,
-                    })
-                } else {
-                    Err(Error::QueryError("Expected column name before BETWEEN".to_string()))
+                _ => return Err(unsupported()),
+            }
+        }
+        let takes_star = aggregate_function == AggregateFunction::Count && list.args.len() == 1;
+        if !arity.contains(&args.len()) || (args.is_empty() && !takes_star) {
+            return Err(Error::QueryError(format!("Wrong number of arguments to function {}()", name)));
+        }
+        if distinct && args.len() != 1 {
+            return Err(Error::QueryError("DISTINCT aggregates must have exactly one argument".to_string()));
+        }
+        Ok(Aggregate { function: aggregate_function, args, distinct })
+    }
+
+    /// Parse the GROUP BY keys, where a number picks a column of the SELECT list
+    fn parse_group_by(
+        group_by: &GroupByExpr,
+        columns: Option<&[String]>,
+        result_columns: Option<&[ResultColumn]>,
+    ) -> Result<Option<Vec<ScalarExpr>>> {
+        let keys = match group_by {
+            GroupByExpr::Expressions(keys, modifiers) if modifiers.is_empty() => keys,
+            _ => return Err(Error::QueryError(format!("Unsupported GROUP BY clause: {}", group_by))),
+        };
+        if keys.is_empty() {
+            return Ok(None);
+        }
+
+        let mut parsed = Vec::new();
+        for key in keys {
+            let key = match Self::parse_scalar_expr(key)? {
+                ScalarExpr::Literal(Value::Integer(position)) => {
+                    let selected = match (result_columns, columns) {
+                        (Some(result_columns), _) => result_columns.len(),
+                        (None, Some(columns)) if !columns.iter().any(|column| column == "*") => columns.len(),
+                        _ => return Err(Error::QueryError("GROUP BY positions need an explicit SELECT list".to_string())),
+                    };
+                    if position < 1 || position as usize > selected {
+                        return Err(Error::QueryError(format!(
+                            "GROUP BY term out of range - should be between 1 and {}",
+                            selected
+                        )));
+                    }
+                    let index = position as usize - 1;
+                    match (result_columns, columns) {
+                        (Some(result_columns), _) => result_columns[index].expr.clone(),
+                        (None, Some(columns)) => ScalarExpr::Column(columns[index].clone()),
+                        (None, None) => unreachable!("positions were checked against the SELECT list"),
+                    }
+                }
+                key => key,
+            };
+            if key.contains_aggregate() {
+                return Err(Error::QueryError("Aggregate functions are not allowed in the GROUP BY clause".to_string()));
+            }
+            parsed.push(key);
+        }
+        Ok(Some(parsed))
+    }
+
+    /// Parse a HAVING clause, naming each aggregate call after its SQL text so
+    /// conditions can refer to it like a column
+    fn parse_having(expr: &SqlExpr) -> Result<Expr> {
+        fn name_aggregates(expr: &mut SqlExpr) -> Result<()> {
+            match expr {
+                // Scalar functions stay calls, over aggregates named in their arguments
+                SqlExpr::Function(function) if !SelectQuery::is_aggregate_call(function) => {
+                    if let FunctionArguments::List(list) = &mut function.args {
+                        for arg in &mut list.args {
+                            if let FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) = arg {
+                                name_aggregates(arg)?;
+                            }
+                        }
+                    }
+                }
+                SqlExpr::Function(function) => {
+                    let name = function.to_string();
+                    SelectQuery::parse_aggregate(function)?;
+                    *expr = SqlExpr::Identifier(Ident::new(name));
+                }
+                SqlExpr::BinaryOp { left, right, .. }
+                | SqlExpr::IsDistinctFrom(left, right)
+                | SqlExpr::IsNotDistinctFrom(left, right)
+                | SqlExpr::Like { expr: left, pattern: right, .. } => {
+                    name_aggregates(left)?;
+                    name_aggregates(right)?;
                 }
+                SqlExpr::Nested(inner)
+                | SqlExpr::UnaryOp { expr: inner, .. }
+                | SqlExpr::IsNull(inner)
+                | SqlExpr::IsNotNull(inner)
+                | SqlExpr::IsTrue(inner)
+                | SqlExpr::IsNotTrue(inner)
+                | SqlExpr::IsFalse(inner)
+                | SqlExpr::IsNotFalse(inner)
+                | SqlExpr::Cast { expr: inner, .. }
+                | SqlExpr::Collate { expr: inner, .. } => name_aggregates(inner)?,
+                SqlExpr::InList { expr: inner, list, .. } => {
+                    name_aggregates(inner)?;
+                    list.iter_mut().try_for_each(name_aggregates)?;
+                }
+                SqlExpr::Between { expr: inner, low, high, .. } => {
+                    name_aggregates(inner)?;
+                    name_aggregates(low)?;
+                    name_aggregates(high)?;
+                }
+                SqlExpr::Case { operand, conditions, else_result, .. } => {
+                    operand.as_deref_mut().map(name_aggregates).transpose()?;
+                    for when in conditions {
+                        name_aggregates(&mut when.condition)?;
+                        name_aggregates(&mut when.result)?;
+                    }
+                    else_result.as_deref_mut().map(name_aggregates).transpose()?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+
+        let mut expr = expr.clone();
+        name_aggregates(&mut expr)?;
+        Self::parse_where_expr(&expr)
+    }
+
+    /// Read a name such as `count(*)` or `price * qty`, as HAVING and ORDER BY
+    /// refer to aggregates and other expressions, back into the expression;
+    /// None if it is not one, or just a column
+    pub(crate) fn parse_expression_text(text: &str) -> Option<ScalarExpr> {
+        let mut parser = Parser::new(&QueryDialect(SQLiteDialect {})).try_with_sql(text).ok()?;
+        let expr = parser.parse_expr().ok()?;
+        if parser.peek_token().token != Token::EOF {
+            return None;
+        }
+        match Self::parse_scalar_expr(&expr).ok()? {
+            ScalarExpr::Column(_) => None,
+            expr => Some(expr),
+        }
+    }
+
+    /// Rename the columns an expression named after its SQL text reads,
+    /// returning None if `text` is not such an expression
+    pub(crate) fn rename_expression_text(text: &str, rename: &mut dyn FnMut(&str) -> Result<String>) -> Result<Option<String>> {
+        match Self::parse_expression_text(text) {
+            Some(expr) => Ok(Some(expr.rename_columns(rename)?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse a stored condition, such as the WHERE clause of a partial index.
+    ///
+    /// Unlike query WHERE clauses, identifiers are never read as text values, so
+    /// conditions comparing two columns are rejected instead of misread.
+    pub(crate) fn parse_predicate(expr: &SqlExpr) -> Result<Expr> {
+        fn has_identifier_operand(expr: &SqlExpr) -> bool {
+            match expr {
+                SqlExpr::BinaryOp { left, op: SqlBinaryOperator::And | SqlBinaryOperator::Or, right } => {
+                    has_identifier_operand(left) || has_identifier_operand(right)
+                }
+                SqlExpr::BinaryOp { left, right, .. } => {
+                    matches!((&**left, &**right), (SqlExpr::Identifier(_), SqlExpr::Identifier(_)))
+                }
+                SqlExpr::Like { pattern, .. } => matches!(&**pattern, SqlExpr::Identifier(_)),
+                SqlExpr::InList { list, .. } => list.iter().any(|item| matches!(item, SqlExpr::Identifier(_))),
+                SqlExpr::Between { low, high, .. } => {
+                    matches!(&**low, SqlExpr::Identifier(_)) || matches!(&**high, SqlExpr::Identifier(_))
+                }
+                SqlExpr::Nested(expr) => has_identifier_operand(expr),
+                _ => false,
+            }
+        }
+        
+        if has_identifier_operand(expr) {
+            return Err(Error::QueryError(format!("Unsupported condition: {}", expr)));
+        }
+        Self::parse_where_expr(expr)
+    }
+
+    /// Parse a WHERE or ON condition, keeping comparisons of a column with a
+    /// value or another column in the simple forms indexes can serve
+    fn parse_where_expr(expr: &SqlExpr) -> Result<Expr> {
+        let scalar = || Self::parse_scalar_expr(expr).map(Expr::Scalar);
+        let negate_if = |expr: Expr, negated: bool| if negated { expr.not() } else { expr };
+        match expr {
+            SqlExpr::BinaryOp { left, op: SqlBinaryOperator::And, right } => Ok(Expr::And(
+                Box::new(Self::parse_where_expr(left)?),
+                Box::new(Self::parse_where_expr(right)?),
+            )),
+            SqlExpr::BinaryOp { left, op: SqlBinaryOperator::Or, right } => Ok(Expr::Or(
+                Box::new(Self::parse_where_expr(left)?),
+                Box::new(Self::parse_where_expr(right)?),
+            )),
+            SqlExpr::BinaryOp { left, op, right } => match Self::comparison_operator(op) {
+                Ok(operator) => match Self::parse_comparison_expr(left, operator, right) {
+                    Some(comparison) => Ok(comparison),
+                    None => scalar(),
+                },
+                Err(_) => scalar(),
             },
+            SqlExpr::UnaryOp { op: SqlUnaryOperator::Not, expr } => Ok(Self::parse_where_expr(expr)?.not()),
+            SqlExpr::IsNull(operand) => match Self::column_reference(operand) {
+                Some(column) => Ok(Expr::IsNull(column)),
+                None => scalar(),
+            },
+            SqlExpr::IsNotNull(operand) => match Self::column_reference(operand) {
+                Some(column) => Ok(Expr::IsNotNull(column)),
+                None => scalar(),
+            },
+            SqlExpr::Like { negated, any: false, expr: operand, pattern, escape_char: None } => {
+                match (Self::column_reference(operand), Self::literal(pattern)) {
+                    (Some(column), Some(value)) => {
+                        Ok(negate_if(Expr::Comparison { column, operator: ComparisonOperator::Like, value }, *negated))
+                    }
+                    _ => scalar(),
+                }
+            }
+            SqlExpr::InList { expr: operand, list, negated } => {
+                match (Self::column_reference(operand), list.iter().map(Self::literal).collect::<Option<Vec<_>>>()) {
+                    (Some(column), Some(values)) => Ok(negate_if(Expr::In { column, values }, *negated)),
+                    _ => scalar(),
+                }
+            }
+            SqlExpr::Between { expr: operand, negated, low, high } => {
+                match (Self::column_reference(operand), Self::literal(low), Self::literal(high)) {
+                    (Some(column), Some(low), Some(high)) => Ok(negate_if(Expr::Between { column, low, high }, *negated)),
+                    _ => scalar(),
+                }
+            }
             SqlExpr::Nested(expr) => Self::parse_where_expr(expr),
-            _ => Err(Error::QueryError(format!("Unsupported expression: {:?}", expr))),
+            _ => scalar(),
         }
     }
 
-    fn parse_comparison_expr(expr: &SqlExpr) -> Result<Expr> {
-        if let SqlExpr::BinaryOp { left, op, right } = expr {
-            let operator = match op {
-                BinaryOperator::Eq => ComparisonOperator::Equal,
-                BinaryOperator::NotEq => ComparisonOperator::NotEqual,
-                BinaryOperator::Lt => ComparisonOperator::LessThan,
-                BinaryOperator::LtEq => ComparisonOperator::LessThanOrEqual,
-                BinaryOperator::Gt => ComparisonOperator::GreaterThan,
-                BinaryOperator::GtEq => ComparisonOperator::GreaterThanOrEqual,
-                _ => return Err(Error::QueryError(format!("Unsupported comparison operator: {:?}", op))),
-            };
+    /// The value of a literal operand of a condition; a double-quoted name
+    /// is read as text, as SQLite does for names that match no column, while
+    /// other names are columns
+    fn literal(expr: &SqlExpr) -> Option<Value> {
+        match expr {
+            SqlExpr::Identifier(ident) if ident.quote_style == Some('"') => Some(Value::Text(ident.value.clone())),
+            SqlExpr::Identifier(_) | SqlExpr::CompoundIdentifier(_) => None,
+            SqlExpr::Nested(expr) => Self::literal(expr),
+            SqlExpr::Value(_) | SqlExpr::UnaryOp { .. } => Self::parse_sql_value(expr).ok(),
+            _ => None,
+        }
+    }
 
-            // Handle both column = value and value = column
-            match (&**left, &**right) {
-                (SqlExpr::Identifier(ident), value) => {
-                    let value = Self::parse_sql_value(value)?;
-                    Ok(Expr::Comparison {
-                        column: ident.value.clone(),
-                        operator,
-                        value,
-                    })
-                },
-                (value, SqlExpr::Identifier(ident)) => {
-                    // For non-equality operators, we need to reverse the operator
-                    let operator = match operator {
-                        ComparisonOperator::LessThan => ComparisonOperator::GreaterThan,
-                        ComparisonOperator::LessThanOrEqual => ComparisonOperator::GreaterThanOrEqual,
-                        ComparisonOperator::GreaterThan => ComparisonOperator::LessThan,
-                        ComparisonOperator::GreaterThanOrEqual => ComparisonOperator::LessThanOrEqual,
-                        _ => operator, // For =, != the order doesn't matter
-                    };
-                    let value = Self::parse_sql_value(value)?;
-                    Ok(Expr::Comparison {
-                        column: ident.value.clone(),
-                        operator,
-                        value,
-                    })
-                },
-                _ => Err(Error::QueryError("Expected column = value comparison".to_string())),
+    fn comparison_operator(op: &SqlBinaryOperator) -> Result<ComparisonOperator> {
+        match op {
+            SqlBinaryOperator::Eq => Ok(ComparisonOperator::Equal),
+            SqlBinaryOperator::NotEq => Ok(ComparisonOperator::NotEqual),
+            SqlBinaryOperator::Lt => Ok(ComparisonOperator::LessThan),
+            SqlBinaryOperator::LtEq => Ok(ComparisonOperator::LessThanOrEqual),
+            SqlBinaryOperator::Gt => Ok(ComparisonOperator::GreaterThan),
+            SqlBinaryOperator::GtEq => Ok(ComparisonOperator::GreaterThanOrEqual),
+            _ => Err(Error::QueryError(format!("Unsupported operator: {}", op))),
+        }
+    }
+
+    /// Parse `left op right` as a comparison of a column with a value or with
+    /// another column, or None if it compares anything else
+    fn parse_comparison_expr(left: &SqlExpr, operator: ComparisonOperator, right: &SqlExpr) -> Option<Expr> {
+        if let Some(column) = Self::column_reference(left) {
+            if let Some(value) = Self::literal(right) {
+                return Some(Expr::Comparison { column, operator, value });
             }
-        } else {
-            Err(Error::QueryError("Expected comparison expression".to_string()))
+            let right = Self::column_reference(right)?;
+            return Some(Expr::ColumnComparison { left: column, operator, right });
         }
+
+        // `value op column` is `column op' value` with the operator reversed
+        let (value, column) = (Self::literal(left)?, Self::column_reference(right)?);
+        let operator = match operator {
+            ComparisonOperator::LessThan => ComparisonOperator::GreaterThan,
+            ComparisonOperator::LessThanOrEqual => ComparisonOperator::GreaterThanOrEqual,
+            ComparisonOperator::GreaterThan => ComparisonOperator::LessThan,
+            ComparisonOperator::GreaterThanOrEqual => ComparisonOperator::LessThanOrEqual,
+            operator => operator,
+        };
+        Some(Expr::Comparison { column, operator, value })
     }
 
-    fn parse_sql_value(sql_value: &SqlExpr) -> Result<Value> {
+    /// Parse a literal: a decimal or `0x` number with an optional sign, a string, a blob, a
+    /// boolean or NULL. Bare identifiers are read as text, as SQLite does for
+    /// names that match no column
+    pub(crate) fn parse_sql_value(sql_value: &SqlExpr) -> Result<Value> {
         match sql_value {
             SqlExpr::Value(value_with_span) => match &value_with_span.value {
                 SqlValue::Number(s, _) => {
-                    if s.contains('.') {
-                        s.parse::<f64>().map(Value::Real).map_err(|_| Error::QueryError("Invalid float value".to_string()))
-                    } else {
-                        s.parse::<i64>().map(Value::Integer).map_err(|_| Error::QueryError("Invalid integer value".to_string()))
+                    // Integers too large for 64 bits are read as REAL, like SQLite does
+                    match s.parse::<i64>() {
+                        Ok(value) => Ok(Value::Integer(value)),
+                        Err(_) => s.parse::<f64>().map(Value::Real).map_err(|_| Error::QueryError("Invalid numeric value".to_string())),
                     }
                 }
                 SqlValue::SingleQuotedString(s) => Ok(Value::Text(s.clone())),
                 SqlValue::DoubleQuotedString(s) => Ok(Value::Text(s.clone())),
+                SqlValue::HexStringLiteral(hex) => {
+                    if let Some(digits) = Self::hex_integer_digits(value_with_span) {
+                        return Self::parse_hex_integer(digits);
+                    }
+                    if !hex.len().is_multiple_of(2) {
+                        return Err(Error::QueryError("Blob literal has an odd number of digits".to_string()));
+                    }
+                    (0..hex.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                        .collect::<core::result::Result<Vec<u8>, _>>()
+                        .map(Value::Blob)
+                        .map_err(|_| Error::QueryError("Invalid blob literal".to_string()))
+                }
+                SqlValue::Boolean(b) => Ok(Value::Integer(i64::from(*b))),
                 SqlValue::Null => Ok(Value::Null),
                 _ => Err(Error::QueryError("Unsupported value type".to_string())),
             },
+            // The magnitude of the smallest integer is too large for 64 bits, so
+            // it is only an integer once negated
+            SqlExpr::UnaryOp { op: SqlUnaryOperator::Minus, expr } if Self::is_i64_min_magnitude(expr) => {
+                Ok(Value::Integer(i64::MIN))
+            }
+            SqlExpr::UnaryOp { op, expr } => match (op, Self::parse_sql_value(expr)?) {
+                (SqlUnaryOperator::Plus, value @ (Value::Integer(_) | Value::Real(_))) => Ok(value),
+                (SqlUnaryOperator::Minus, Value::Integer(value)) => Ok(value
+                    .checked_neg()
+                    .map(Value::Integer)
+                    .unwrap_or(Value::Real(-(value as f64)))),
+                (SqlUnaryOperator::Minus, Value::Real(value)) => Ok(Value::Real(-value)),
+                _ => Err(Error::QueryError(format!("Expected a literal value, found {}", sql_value))),
+            },
+            SqlExpr::Nested(expr) => Self::parse_sql_value(expr),
             SqlExpr::Identifier(ident) => Ok(Value::Text(ident.value.clone())),
             _ => Err(Error::QueryError(format!("Expected a literal value, found {:?}", sql_value))),
         }
     }
 
-    fn parse_order_by(order_by: Option<&sqlparser::ast::OrderBy>) -> Result<Option<OrderBy>> {
+    /// Whether `expr` is the literal `9223372036854775808`, in any parentheses
+    fn is_i64_min_magnitude(expr: &SqlExpr) -> bool {
+        match expr {
+            SqlExpr::Nested(expr) => Self::is_i64_min_magnitude(expr),
+            SqlExpr::Value(value) => matches!(&value.value, SqlValue::Number(digits, _) if digits == "9223372036854775808"),
+            _ => false,
+        }
+    }
+
+    /// The digits of a `0x` integer literal. The parser reads both `0x10` and
+    /// `X'10'` as the same hex string; the integer is the one spanning two
+    /// characters more than its digits
+    pub(crate) fn hex_integer_digits(value: &ValueWithSpan) -> Option<&str> {
+        let SqlValue::HexStringLiteral(hex) = &value.value else {
+            return None;
+        };
+        let span = &value.span;
+        let width = span.end.column.saturating_sub(span.start.column);
+        (span.start.line == span.end.line && width == hex.len() as u64 + 2).then_some(hex.as_str())
+    }
+
+    /// Read the digits of a `0x` literal as the 64-bit two's complement
+    /// integer SQLite makes of them, so `0xFFFFFFFFFFFFFFFF` is -1
+    fn parse_hex_integer(hex: &str) -> Result<Value> {
+        if hex.is_empty() || hex.len() > 16 {
+            return Err(Error::QueryError(format!("hex literal too big: 0x{}", hex)));
+        }
+        u64::from_str_radix(hex, 16)
+            .map(|value| Value::Integer(value as i64))
+            .map_err(|_| Error::QueryError(format!("Invalid hex literal: 0x{}", hex)))
+    }
+
+    /// Parse the ORDER BY clause into the name of a column, the position of
+    /// an output column, or the SQL text of an expression, which `aggregate`
+    /// queries may compute from aggregate calls
+    fn parse_order_by(
+        order_by: Option<&sqlparser::ast::OrderBy>,
+        aggregate: bool,
+        columns: Option<&[String]>,
+        result_columns: Option<&[ResultColumn]>,
+    ) -> Result<Option<OrderBy>> {
         if let Some(order_by) = order_by {
             // In sqlparser 0.57.0, OrderBy has a 'kind' field
             match &order_by.kind {
@@ -416,8 +1483,34 @@ impl SelectQuery {
                     if let Some(order_expr) = expressions.first() {
                         // Extract column name from the expression
                         let column = match &order_expr.expr {
-                            sqlparser::ast::Expr::Identifier(ident) => ident.value.clone(),
-                            _ => return Err(Error::QueryError("Unsupported ORDER BY expression".to_string())),
+                            sqlparser::ast::Expr::Identifier(_) | sqlparser::ast::Expr::CompoundIdentifier(_) => {
+                                Self::column_reference(&order_expr.expr).unwrap_or_default()
+                            }
+                            expr => match Self::parse_scalar_expr(expr)? {
+                                // A number picks an output column by position
+                                ScalarExpr::Literal(Value::Integer(position)) => {
+                                    let names: Vec<&str> = match (result_columns, columns) {
+                                        (Some(result_columns), _) => result_columns.iter().map(|column| column.name.as_str()).collect(),
+                                        (None, Some(columns)) if !columns.iter().any(|column| column.ends_with('*')) => {
+                                            columns.iter().map(String::as_str).collect()
+                                        }
+                                        _ => return Err(Error::QueryError("ORDER BY positions need an explicit SELECT list".to_string())),
+                                    };
+                                    match usize::try_from(position).ok().and_then(|position| names.get(position.wrapping_sub(1))) {
+                                        Some(name) => name.to_string(),
+                                        None => {
+                                            return Err(Error::QueryError(format!(
+                                                "ORDER BY term out of range - should be between 1 and {}",
+                                                names.len()
+                                            )))
+                                        }
+                                    }
+                                }
+                                parsed if parsed.contains_aggregate() && !aggregate => {
+                                    return Err(Error::QueryError("misuse of aggregate function in ORDER BY".to_string()));
+                                }
+                                _ => expr.to_string(),
+                            },
                         };
                         
                         // Extract sort direction
@@ -460,18 +1553,165 @@ impl SelectQuery {
 }
 
 impl SelectQuery {
+    /// Whether the query reduces its rows with aggregate functions or GROUP BY
+    pub(crate) fn is_aggregate(&self) -> bool {
+        self.result_columns.iter().flatten().any(|column| column.expr.contains_aggregate())
+            || self.group_by.is_some()
+            || self.having.is_some()
+    }
+    
+    /// Expression ORDER BY sorts the rows of a query that does not aggregate
+    /// by, over rows with the columns `columns`: an output column named by
+    /// its alias, else a column, else an expression named after its SQL text
+    pub(crate) fn order_key(&self, columns: &[String]) -> Result<Option<ScalarExpr>> {
+        let Some(order_by) = &self.order_by else {
+            return Ok(None);
+        };
+        let name = &order_by.column;
+        if let Some(result) = self.result_columns.iter().flatten().find(|result| result.name.eq_ignore_ascii_case(name)) {
+            return Ok(Some(result.expr.clone()));
+        }
+        if name.index_in(columns).is_some() {
+            return Ok(Some(ScalarExpr::Column(name.clone())));
+        }
+        match Self::parse_expression_text(name) {
+            Some(mut expr) => {
+                self.expand_aliases(&mut expr, &|name| name.index_in(columns).is_some());
+                Ok(Some(expr))
+            }
+            None => Err(Error::ColumnNotFound(name.clone())),
+        }
+    }
+    
+    /// Replace the references in `expr` to output columns by their alias with
+    /// the expressions they name, unless `is_column` says they name a column
+    pub(crate) fn expand_aliases(&self, expr: &mut ScalarExpr, is_column: &dyn Fn(&str) -> bool) {
+        if let ScalarExpr::Column(name) = expr {
+            if let Some(aliased) = self.aliased_expr(name, is_column) {
+                *expr = aliased.clone();
+            }
+            return;
+        }
+        for child in expr.children_mut() {
+            self.expand_aliases(child, is_column);
+        }
+    }
+    
+    /// The expression of the output column `name` is an alias of, unless
+    /// `is_column` says it names a column
+    fn aliased_expr(&self, name: &str, is_column: &dyn Fn(&str) -> bool) -> Option<&ScalarExpr> {
+        let result = self.result_columns.iter().flatten().find(|result| result.name.eq_ignore_ascii_case(name));
+        result.filter(|_| !is_column(name)).map(|result| &result.expr)
+    }
+    
+    /// The WHERE clause with the output columns it names by their alias
+    /// replaced by the expressions they name, as SQLite allows; a condition
+    /// on an alias becomes the same condition on its expression
+    pub(crate) fn where_with_aliases(&self, is_column: &dyn Fn(&str) -> bool) -> Result<Option<Expr>> {
+        let Some(mut where_expr) = self.where_expr.clone() else {
+            return Ok(None);
+        };
+        self.expand_condition_aliases(&mut where_expr, is_column);
+        if where_expr.contains_aggregate() {
+            return Err(Error::QueryError("misuse of aggregate function in WHERE clause".to_string()));
+        }
+        Ok(Some(where_expr))
+    }
+    
+    fn expand_condition_aliases(&self, expr: &mut Expr, is_column: &dyn Fn(&str) -> bool) {
+        let alias = |name: &str| self.aliased_expr(name, is_column).cloned();
+        let operand = |name: &String| alias(name).unwrap_or_else(|| ScalarExpr::Column(name.clone()));
+        let compare = |left: ScalarExpr, operator: ComparisonOperator, right: &Value| {
+            Expr::Scalar(ScalarExpr::Compare { left: Box::new(left), operator, right: Box::new(ScalarExpr::Literal(right.clone())) })
+        };
+        let expanded = match expr {
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                self.expand_condition_aliases(left, is_column);
+                self.expand_condition_aliases(right, is_column);
+                None
+            }
+            Expr::Not(inner) => {
+                self.expand_condition_aliases(inner, is_column);
+                None
+            }
+            Expr::Scalar(inner) => {
+                self.expand_aliases(inner, is_column);
+                None
+            }
+            Expr::Comparison { column, operator, value } => alias(column).map(|left| compare(left, operator.clone(), value)),
+            Expr::ColumnComparison { left, operator, right } => match (alias(left), alias(right)) {
+                (None, None) => None,
+                (_, _) => Some(Expr::Scalar(ScalarExpr::Compare {
+                    left: Box::new(operand(left)),
+                    operator: operator.clone(),
+                    right: Box::new(operand(right)),
+                })),
+            },
+            Expr::IsNull(column) | Expr::IsNotNull(column) => alias(column).map(|left| {
+                let negated = matches!(expr, Expr::IsNotNull(_));
+                Expr::Scalar(ScalarExpr::Is { left: Box::new(left), right: Box::new(ScalarExpr::Literal(Value::Null)), negated })
+            }),
+            // IN is a chain of equalities, unknown rather than false when one of them is
+            Expr::In { column, values } => alias(column).map(|left| {
+                let equalities = values.iter().map(|value| compare(left.clone(), ComparisonOperator::Equal, value));
+                equalities.reduce(Expr::or).unwrap_or(Expr::Scalar(ScalarExpr::Literal(Value::Integer(0))))
+            }),
+            Expr::Between { column, low, high } => alias(column).map(|left| {
+                compare(left.clone(), ComparisonOperator::GreaterThanOrEqual, low)
+                    .and(compare(left, ComparisonOperator::LessThanOrEqual, high))
+            }),
+        };
+        if let Some(expanded) = expanded {
+            *expr = expanded;
+        }
+    }
+    
     /// Execute the query against the provided rows
     pub fn execute(&self, mut rows: Vec<Row>, all_columns: &[String]) -> Result<Vec<Row>> {
+        let context = Context::new(TextEncoding::Utf8);
+        
         // Apply WHERE conditions
-        rows = self.apply_where_conditions(rows)?;
+        rows = self.apply_where_conditions(rows, all_columns, &context)?;
+        
+        // Aggregate queries reduce the matching rows to one per group
+        if self.is_aggregate() {
+            let plan = AggregatePlan::new(self, all_columns, all_columns.len(), &context)?;
+            let mut aggregator = HashAggregator::new(None);
+            for row in &rows {
+                aggregator.push(&plan, row)?;
+            }
+            rows = aggregator.finish(&plan)?;
+            if let Some(having) = &self.having {
+                let mut kept = Vec::new();
+                for row in rows {
+                    if self.evaluate_expr_in(&row, having, plan.group_context())? {
+                        kept.push(row);
+                    }
+                }
+                rows = kept;
+            }
+            if let Some(ref order_by) = self.order_by {
+                let key = ScalarExpr::Column(order_by.column.clone());
+                eval::sort_rows(&mut rows, &key, order_by.ascending, plan.group_context())?;
+            }
+            let columns = plan.visible_columns();
+            rows = rows
+                .into_iter()
+                .map(|row| Row::new(columns.clone(), row.into_values()))
+                .collect();
+            if let Some(limit) = self.limit {
+                rows.truncate(limit);
+            }
+            return Ok(rows);
+        }
         
         // Apply ORDER BY
-        if let Some(ref order_by) = self.order_by {
-            rows = self.apply_order_by(rows, order_by)?;
+        if let (Some(order_by), Some(key)) = (&self.order_by, self.order_key(all_columns)?) {
+            eval::sort_rows(&mut rows, &key, order_by.ascending, &context)?;
         }
         
         // Apply column selection
-        rows = self.apply_column_selection(rows, all_columns)?;
+        rows = self.apply_column_selection(rows, all_columns, &context)?;
         
         // Apply LIMIT
         if let Some(limit) = self.limit {
@@ -482,18 +1722,18 @@ impl SelectQuery {
     }
     
     /// Apply WHERE conditions to filter rows
-    fn apply_where_conditions(&self, rows: Vec<Row>) -> Result<Vec<Row>> {
-        if self.where_expr.is_none() {
+    fn apply_where_conditions(&self, rows: Vec<Row>, all_columns: &[String], context: &Context) -> Result<Vec<Row>> {
+        let Some(where_expr) = self.where_with_aliases(&|name| name.index_in(all_columns).is_some())? else {
             return Ok(rows);
-        }
+        };
         
         let total_rows = rows.len();
-        let filtered_rows: Vec<Row> = rows
-            .into_iter()
-            .filter(|row| {
-                self.evaluate_expr(row, &self.where_expr.as_ref().unwrap())
-            })
-            .collect();
+        let mut filtered_rows = Vec::new();
+        for row in rows {
+            if self.evaluate_expr_in(&row, &where_expr, context)? {
+                filtered_rows.push(row);
+            }
+        }
         
         // Add debug logging for WHERE clause filtering
         crate::logging::log_debug(&format!(
@@ -505,204 +1745,54 @@ impl SelectQuery {
         Ok(filtered_rows)
     }
     
-    /// Evaluate a WHERE expression against a row
+    /// Evaluate a WHERE expression against a row, whose columns are untyped;
+    /// false when it is NULL or cannot be evaluated
     pub fn evaluate_expr(&self, row: &Row, expr: &Expr) -> bool {
-        match expr {
-            Expr::Comparison { column, operator, value } => {
-                let row_value = match row.get(column.as_str()) {
-                    Some(value) => value,
-                    None => return false, // Column doesn't exist
-                };
-                
-                match operator {
-                    ComparisonOperator::Equal => self.values_equal(row_value, value),
-                    ComparisonOperator::NotEqual => !self.values_equal(row_value, value),
-                    ComparisonOperator::LessThan => self.value_less_than(row_value, value),
-                    ComparisonOperator::LessThanOrEqual => {
-                        self.value_less_than(row_value, value) || 
-                        self.values_equal(row_value, value)
-                    },
-                    ComparisonOperator::GreaterThan => {
-                        !self.value_less_than(row_value, value) && 
-                        !self.values_equal(row_value, value)
-                    },
-                    ComparisonOperator::GreaterThanOrEqual => {
-                        !self.value_less_than(row_value, value)
-                    },
-                    ComparisonOperator::Like => self.value_like(row_value, value),
-                }
-            },
-            Expr::And(left, right) => self.evaluate_expr(row, left) && self.evaluate_expr(row, right),
-            Expr::Or(left, right) => self.evaluate_expr(row, left) || self.evaluate_expr(row, right),
-            Expr::Not(expr) => !self.evaluate_expr(row, expr),
-            Expr::IsNull(column) => row.get(column.as_str()).map_or(false, |v| v.is_null()),
-            Expr::IsNotNull(column) => row.get(column.as_str()).map_or(false, |v| !v.is_null()),
-            Expr::In { column, values } => {
-                let row_value = row.get(column.as_str()).cloned().unwrap_or(Value::Null);
-                values.iter().any(|v| self.values_equal(&row_value, v))
-            },
-            Expr::Between { column, low, high } => {
-                let row_value = match row.get(column.as_str()) {
-                    Some(value) => value,
-                    None => return false, // Column doesn't exist
-                };
-                
-                // Check if row_value >= low AND row_value <= high
-                (self.values_equal(row_value, low) || !self.value_less_than(row_value, low)) &&
-                (self.values_equal(row_value, high) || self.value_less_than(row_value, high))
-            },
-        }
+        self.evaluate_expr_in(row, expr, &Context::new(TextEncoding::Utf8)).unwrap_or(false)
     }
     
-    /// Compare two values for equality
-    fn values_equal(&self, a: &Value, b: &Value) -> bool {
-        match (a, b) {
-            (Value::Null, Value::Null) => true,
-            (Value::Integer(a), Value::Integer(b)) => a == b,
-            (Value::Real(a), Value::Real(b)) => (a - b).abs() < f64::EPSILON,
-            (Value::Text(a), Value::Text(b)) => a == b,
-            (Value::Blob(a), Value::Blob(b)) => a == b,
-            // Type coercion
-            (Value::Integer(a), Value::Real(b)) => (*a as f64 - b).abs() < f64::EPSILON,
-            (Value::Real(a), Value::Integer(b)) => (a - *b as f64).abs() < f64::EPSILON,
-            _ => false,
-        }
+    /// Evaluate a WHERE expression over a row typed by `context`: whether it is true
+    pub(crate) fn evaluate_expr_in(&self, row: &Row, expr: &Expr, context: &Context) -> Result<bool> {
+        Ok(eval::condition(expr, &RowOperands { row, context }, context.encoding)? == Some(true))
     }
     
-    /// Check if value a is less than value b
-    fn value_less_than(&self, a: &Value, b: &Value) -> bool {
-        match (a, b) {
-            (Value::Integer(a), Value::Integer(b)) => a < b,
-            (Value::Real(a), Value::Real(b)) => a < b,
-            (Value::Text(a), Value::Text(b)) => a < b,
-            (Value::Integer(a), Value::Real(b)) => (*a as f64) < *b,
-            (Value::Real(a), Value::Integer(b)) => *a < (*b as f64),
-            _ => false,
+    /// Apply column selection (SELECT specific columns or *), computing the
+    /// result columns of a SELECT list with expressions
+    fn apply_column_selection(&self, rows: Vec<Row>, all_columns: &[String], context: &Context) -> Result<Vec<Row>> {
+        if let Some(result_columns) = &self.result_columns {
+            let columns: Arc<[String]> = result_columns.iter().map(|column| column.name.clone()).collect();
+            return rows
+                .into_iter()
+                .map(|row| {
+                    let operands = RowOperands { row: &row, context };
+                    let values = result_columns
+                        .iter()
+                        .map(|column| Ok(eval::evaluate(&column.expr, &operands, context.encoding)?.into_owned()))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(Row::new(columns.clone(), values))
+                })
+                .collect();
         }
-    }
-    
-    /// Check if value matches LIKE pattern (improved implementation)
-    fn value_like(&self, value: &Value, pattern: &Value) -> bool {
-        match (value, pattern) {
-            (Value::Text(text), Value::Text(pattern)) => {
-                // Improved LIKE implementation with % wildcard
-                if pattern.contains('%') {
-                    let pattern_parts: Vec<&str> = pattern.split('%').collect();
-                    
-                    // Handle simple cases like 'prefix%', '%suffix', '%middle%'
-                    match pattern_parts.len() {
-                        2 => {
-                            let prefix = pattern_parts[0];
-                            let suffix = pattern_parts[1];
-                            
-                            // Handle 'prefix%' case (suffix is empty)
-                            if suffix.is_empty() {
-                                return text.starts_with(prefix);
-                            }
-                            // Handle '%suffix' case (prefix is empty)
-                            if prefix.is_empty() {
-                                return text.ends_with(suffix);
-                            }
-                            // Handle 'prefix%suffix' case
-                            return text.starts_with(prefix) && text.ends_with(suffix) && text.len() >= prefix.len() + suffix.len();
-                        },
-                        1 => {
-                            // No % found, exact match
-                            return text == pattern;
-                        },
-                        3 => {
-                            // Handle '%middle%' case
-                            let prefix = pattern_parts[0];
-                            let middle = pattern_parts[1];
-                            let suffix = pattern_parts[2];
-                            
-                            if prefix.is_empty() && suffix.is_empty() {
-                                // Pattern is '%middle%' - check if text contains middle
-                                return text.contains(middle);
-                            }
-                            // More complex patterns - fall back to basic matching
-                            return text.starts_with(prefix) && text.contains(middle) && text.ends_with(suffix);
-                        },
-                        _ => {
-                            // Multiple % wildcards - more complex pattern
-                            // For now, do a simple contains check for each non-empty part
-                            for part in pattern_parts {
-                                if !part.is_empty() && !text.contains(part) {
-                                    return false;
-                                }
-                            }
-                            return true;
-                        }
-                    }
-                } else {
-                    // No wildcards, exact match
-                    text == pattern
-                }
-            },
-            _ => false,
-        }
-    }
-    
-    /// Apply ORDER BY to sort rows
-    fn apply_order_by(&self, mut rows: Vec<Row>, order_by: &OrderBy) -> Result<Vec<Row>> {
-        rows.sort_by(|a, b| {
-            let val_a = a.get(order_by.column.as_str());
-            let val_b = b.get(order_by.column.as_str());
-            
-            let cmp = match (val_a, val_b) {
-                (Some(a), Some(b)) => self.compare_values(a, b),
-                (Some(_), None) => std::cmp::Ordering::Greater,
-                (None, Some(_)) => std::cmp::Ordering::Less,
-                (None, None) => std::cmp::Ordering::Equal,
-            };
-            
-            if order_by.ascending {
-                cmp
-            } else {
-                cmp.reverse()
-            }
-        });
         
-        Ok(rows)
-    }
-    
-    /// Compare two values for ordering
-    fn compare_values(&self, a: &Value, b: &Value) -> std::cmp::Ordering {
-        match (a, b) {
-            (Value::Null, Value::Null) => std::cmp::Ordering::Equal,
-            (Value::Null, _) => std::cmp::Ordering::Less,
-            (_, Value::Null) => std::cmp::Ordering::Greater,
-            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
-            (Value::Real(a), Value::Real(b)) => a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
-            (Value::Text(a), Value::Text(b)) => a.cmp(b),
-            (Value::Integer(a), Value::Real(b)) => (*a as f64).partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
-            (Value::Real(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)).unwrap_or(std::cmp::Ordering::Equal),
-            _ => std::cmp::Ordering::Equal,
-        }
-    }
-    
-    /// Apply column selection (SELECT specific columns or *)
-    fn apply_column_selection(&self, rows: Vec<Row>, all_columns: &[String]) -> Result<Vec<Row>> {
         match &self.columns {
             None => Ok(rows), // SELECT * - return all columns
             Some(selected_columns) => {
-                let mut result_rows = Vec::new();
-                
-                for row in rows {
-                    let mut new_row = HashMap::new();
-                    
-                    for column in selected_columns {
-                        if !all_columns.contains(column) {
-                            return Err(Error::ColumnNotFound(column.clone()));
-                        }
-                        
-                        let value = row.get(column.as_str()).cloned().unwrap_or(Value::Null);
-                        new_row.insert(column.clone(), value);
-                    }
-                    
-                    result_rows.push(new_row);
+                if let Some(column) = selected_columns.iter().find(|column| !all_columns.contains(column)) {
+                    return Err(Error::ColumnNotFound(column.clone()));
                 }
                 
+                let columns: Arc<[String]> = selected_columns.iter().cloned().collect();
+                let result_rows = rows
+                    .into_iter()
+                    .map(|row| {
+                        let values = selected_columns
+                            .iter()
+                            .map(|column| row.get(column).cloned().unwrap_or(Value::Null))
+                            .collect();
+                        Row::new(columns.clone(), values)
+                    })
+                    .collect();
+                
                 Ok(result_rows)
             }
         }
@@ -714,8 +1804,13 @@ impl SelectQuery {
     pub fn new(table: impl Into<String>) -> Self {
         Self {
             columns: None,
+            result_columns: None,
             table: table.into(),
+            alias: None,
+            joins: Vec::new(),
             where_expr: None,
+            group_by: None,
+            having: None,
             order_by: None,
             limit: None,
         }
@@ -732,12 +1827,43 @@ impl SelectQuery {
         self
     }
 
+    /// Specify a computed SELECT list, such as aggregates, in place of plain columns
+    pub fn select_results(mut self, columns: Vec<ResultColumn>) -> Self {
+        self.result_columns = Some(columns);
+        self
+    }
+
+    /// Refer to the table as `alias`, as in `FROM orders o`
+    pub fn with_alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = Some(alias.into());
+        self
+    }
+
+    /// Join another table to the rows of the tables before it
+    pub fn with_join(mut self, join: Join) -> Self {
+        self.joins.push(join);
+        self
+    }
+
     /// Attach a WHERE expression to the query.
     pub fn with_where(mut self, expr: Expr) -> Self {
         self.where_expr = Some(expr);
         self
     }
 
+    /// Group the rows by `keys`, making this an aggregate query
+    pub fn with_group_by(mut self, keys: Vec<ScalarExpr>) -> Self {
+        self.group_by = if keys.is_empty() { None } else { Some(keys) };
+        self
+    }
+
+    /// Attach a HAVING condition, which may compare aggregates named like
+    /// `count(*)` as columns, e.g. `Expr::gt("count(*)", Value::Integer(10))`
+    pub fn with_having(mut self, expr: Expr) -> Self {
+        self.having = Some(expr);
+        self
+    }
+
     /// Attach an ORDER BY clause to the query.
     pub fn with_order_by(mut self, column: impl Into<String>, ascending: bool) -> Self {
         self.order_by = Some(OrderBy { column: column.into(), ascending });
@@ -784,6 +1910,132 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_literals() {
+        let query = SelectQuery::parse("SELECT * FROM t WHERE a > -5 AND b IN (+1.5, 1e3, X'CAFE', TRUE)").unwrap();
+        assert_eq!(
+            query.where_expr,
+            Some(Expr::gt("a", Value::Integer(-5)).and(Expr::in_values(
+                "b",
+                vec![Value::Real(1.5), Value::Real(1000.0), Value::Blob(vec![0xCA, 0xFE]), Value::Integer(1)]
+            )))
+        );
+    }
+
+    #[test]
+    fn test_parse_aggregates() {
+        let query = SelectQuery::parse("SELECT name, COUNT(*), sum(DISTINCT qty) AS total_qty, group_concat(tag, '; ') FROM items").unwrap();
+        assert!(query.columns.is_none());
+        let aggregate = |function, args, distinct| ScalarExpr::Aggregate(Box::new(Aggregate { function, args, distinct }));
+        assert_eq!(
+            query.result_columns.unwrap(),
+            [
+                ResultColumn::new("name", ScalarExpr::Column("name".into())),
+                ResultColumn::new("COUNT(*)", aggregate(AggregateFunction::Count, vec![], false)),
+                ResultColumn::new("total_qty", aggregate(AggregateFunction::Sum, vec![ScalarExpr::Column("qty".into())], true)),
+                ResultColumn::new(
+                    "group_concat(tag, '; ')",
+                    aggregate(
+                        AggregateFunction::GroupConcat,
+                        vec![ScalarExpr::Column("tag".into()), ScalarExpr::Literal(Value::Text("; ".into()))],
+                        false
+                    )
+                ),
+            ]
+        );
+
+        // Plain column lists keep their simple form
+        assert!(SelectQuery::parse("SELECT name FROM items").unwrap().result_columns.is_none());
+        // Expressions without aggregates compute a column of every row
+        let query = SelectQuery::parse("SELECT name, 1 FROM items").unwrap();
+        assert!(!query.is_aggregate());
+        assert_eq!(query.result_columns.unwrap()[1], ResultColumn::new("1", ScalarExpr::Literal(Value::Integer(1))));
+    }
+
+    #[test]
+    fn test_parse_scalar_expressions() {
+        let query = SelectQuery::parse(
+            "SELECT qty * -price AS cost, name || '!', CASE WHEN qty > 1 THEN 'many' END, CAST(qty AS TEXT) FROM items \
+             WHERE qty + 1 > price AND name COLLATE NOCASE = 'a' ORDER BY 2",
+        )
+        .unwrap();
+        let column = |name: &str| Box::new(ScalarExpr::Column(name.into()));
+        let columns = query.result_columns.unwrap();
+        assert_eq!(
+            columns[0].expr,
+            ScalarExpr::Binary {
+                left: column("qty"),
+                operator: BinaryOperator::Multiply,
+                right: Box::new(ScalarExpr::Unary { operator: UnaryOperator::Negate, expr: column("price") }),
+            }
+        );
+        assert_eq!(columns[3].expr, ScalarExpr::Cast { expr: column("qty"), affinity: Affinity::Text });
+        // Expressions print as SQL that parses back into them
+        for column in &columns {
+            assert_eq!(SelectQuery::parse_expression_text(&column.expr.to_string()).as_ref(), Some(&column.expr));
+        }
+        assert_eq!(columns[2].expr.to_string(), "CASE WHEN (qty > 1) THEN 'many' END");
+        assert_eq!(query.order_by.unwrap().column, "name || '!'");
+
+        let where_expr = query.where_expr.unwrap();
+        assert!(matches!(&where_expr, Expr::And(left, _) if matches!(**left, Expr::Scalar(ScalarExpr::Compare { .. }))));
+        assert!(SelectQuery::parse("SELECT name FROM items WHERE count(*) > 1").is_err());
+        assert!(SelectQuery::parse("SELECT name COLLATE nope FROM items").is_err());
+        assert!(SelectQuery::parse("SELECT name FROM items ORDER BY 3").is_err());
+    }
+
+    #[test]
+    fn test_parse_group_by_and_having() {
+        let query = SelectQuery::parse("SELECT tag, name AS n, count(*) FROM items GROUP BY 1, n, qty HAVING count(*) > 2").unwrap();
+        assert!(query.is_aggregate());
+        assert_eq!(
+            query.group_by.unwrap(),
+            [ScalarExpr::Column("tag".into()), ScalarExpr::Column("n".into()), ScalarExpr::Column("qty".into())]
+        );
+        // Aggregates in HAVING become references to the column of the same name
+        assert_eq!(query.having.unwrap(), Expr::gt("count(*)", Value::Integer(2)));
+        assert!(matches!(
+            SelectQuery::parse_expression_text("count(*)"),
+            Some(ScalarExpr::Aggregate(aggregate)) if aggregate.function == AggregateFunction::Count
+        ));
+        assert!(SelectQuery::parse_expression_text("qty").is_none());
+
+        let query = SelectQuery::parse("SELECT count(*) FROM items HAVING max(qty) < 10 ORDER BY sum(qty)").unwrap();
+        assert!(query.group_by.is_none());
+        assert_eq!(query.order_by.unwrap().column, "sum(qty)");
+    }
+
+    #[test]
+    fn test_parse_joins() {
+        let query = SelectQuery::parse(
+            "SELECT o.id, c.name, p.* FROM orders o JOIN customers AS c ON o.customer_id = c.id AND c.tier > 1 \
+             LEFT JOIN products p ON code = product_code, tiers WHERE o.qty >= c.tier ORDER BY c.name",
+        )
+        .unwrap();
+        assert_eq!((query.table.as_str(), query.alias.as_deref()), ("orders", Some("o")));
+        assert_eq!(query.columns.unwrap(), ["o.id", "c.name", "p.*"]);
+        assert_eq!(
+            query.joins,
+            [
+                Join::inner("customers")
+                    .with_alias("c")
+                    .with_on(Expr::compare_columns("o.customer_id", ComparisonOperator::Equal, "c.id").and(Expr::gt("c.tier", Value::Integer(1)))),
+                // Bare identifiers in ON conditions are columns, never text
+                Join::left("products")
+                    .with_alias("p")
+                    .with_on(Expr::compare_columns("code", ComparisonOperator::Equal, "product_code")),
+                Join::cross("tiers"),
+            ]
+        );
+        assert_eq!(query.where_expr.unwrap(), Expr::compare_columns("o.qty", ComparisonOperator::GreaterThanOrEqual, "c.tier"));
+        assert_eq!(query.order_by.unwrap().column, "c.name");
+
+        let query = SelectQuery::parse("SELECT c.city, count(o.id) FROM orders o JOIN customers c ON o.customer_id = c.id GROUP BY c.city").unwrap();
+        assert_eq!(query.result_columns.unwrap()[0], ResultColumn::new("city", ScalarExpr::Column("c.city".into())));
+        assert!(SelectQuery::parse("SELECT * FROM a RIGHT JOIN b ON a.id = b.id").is_err());
+        assert!(SelectQuery::parse("SELECT * FROM a JOIN b USING (id)").is_err());
+    }
+
     #[test]
     fn test_parse_select_with_order_by() {
         let query = SelectQuery::parse("SELECT * FROM users ORDER BY name ASC").unwrap();
@@ -803,25 +2055,34 @@ mod tests {
 
     #[test]
     fn test_like_pattern_matching() {
-        let query = SelectQuery::parse("SELECT * FROM users").unwrap();
-        
         // Test prefix pattern 'f%'
-        assert!(query.value_like(&Value::Text("foo".to_string()), &Value::Text("f%".to_string())));
-        assert!(query.value_like(&Value::Text("ff736190-1479-4681-b9b2-78757cd55821".to_string()), &Value::Text("f%".to_string())));
-        assert!(query.value_like(&Value::Text("fa18fc4d-11dc-466b-84cd-d6793ff93774".to_string()), &Value::Text("f%".to_string())));
-        assert!(!query.value_like(&Value::Text("bar".to_string()), &Value::Text("f%".to_string())));
+        assert!(eval::like("foo", "f%"));
+        assert!(eval::like("ff736190-1479-4681-b9b2-78757cd55821", "f%"));
+        assert!(eval::like("fa18fc4d-11dc-466b-84cd-d6793ff93774", "f%"));
+        assert!(!eval::like("bar", "f%"));
         
         // Test suffix pattern '%bar'
-        assert!(query.value_like(&Value::Text("foobar".to_string()), &Value::Text("%bar".to_string())));
-        assert!(!query.value_like(&Value::Text("foo".to_string()), &Value::Text("%bar".to_string())));
+        assert!(eval::like("foobar", "%bar"));
+        assert!(!eval::like("foo", "%bar"));
         
         // Test contains pattern '%middle%'
-        assert!(query.value_like(&Value::Text("foo middle bar".to_string()), &Value::Text("%middle%".to_string())));
-        assert!(!query.value_like(&Value::Text("foo bar".to_string()), &Value::Text("%middle%".to_string())));
+        assert!(eval::like("foo middle bar", "%middle%"));
+        assert!(!eval::like("foo bar", "%middle%"));
         
         // Test exact match (no wildcards)
-        assert!(query.value_like(&Value::Text("exact".to_string()), &Value::Text("exact".to_string())));
-        assert!(!query.value_like(&Value::Text("different".to_string()), &Value::Text("exact".to_string())));
+        assert!(eval::like("exact", "exact"));
+        assert!(!eval::like("different", "exact"));
+        
+        // Several wildcards stay anchored at both ends
+        assert!(eval::like("abxcyd", "ab%c%d"));
+        assert!(!eval::like("xabcd", "ab%c%d%"));
+        assert!(eval::like("a1c", "a_c"));
+        assert!(!eval::like("ac", "a_c"));
+        
+        // ASCII letters match either case, other characters only themselves
+        assert!(eval::like("ABC", "a%"));
+        assert!(eval::like("Straße", "STRA%E"));
+        assert!(!eval::like("É", "é"));
     }
 
     #[test]
@@ -843,44 +2104,37 @@ mod tests {
     #[test]
     fn test_between_evaluation() {
         let query = SelectQuery::parse("SELECT * FROM users").unwrap();
-        let mut row = std::collections::HashMap::new();
+        let columns: Arc<[String]> = vec!["age".to_string(), "score".to_string()].into();
+        let row = |age: Value, score: Value| Row::new(columns.clone(), vec![age, score]);
         
         // Test integer BETWEEN
-        row.insert("age".to_string(), Value::Integer(25));
         let expr = Expr::Between {
             column: "age".to_string(),
             low: Value::Integer(18),
             high: Value::Integer(65),
         };
-        assert!(query.evaluate_expr(&row, &expr));
+        assert!(query.evaluate_expr(&row(Value::Integer(25), Value::Null), &expr));
         
         // Test value outside range (too low)
-        row.insert("age".to_string(), Value::Integer(15));
-        assert!(!query.evaluate_expr(&row, &expr));
+        assert!(!query.evaluate_expr(&row(Value::Integer(15), Value::Null), &expr));
         
         // Test value outside range (too high)
-        row.insert("age".to_string(), Value::Integer(70));
-        assert!(!query.evaluate_expr(&row, &expr));
+        assert!(!query.evaluate_expr(&row(Value::Integer(70), Value::Null), &expr));
         
         // Test boundary values
-        row.insert("age".to_string(), Value::Integer(18));
-        assert!(query.evaluate_expr(&row, &expr));
-        
-        row.insert("age".to_string(), Value::Integer(65));
-        assert!(query.evaluate_expr(&row, &expr));
+        assert!(query.evaluate_expr(&row(Value::Integer(18), Value::Null), &expr));
+        assert!(query.evaluate_expr(&row(Value::Integer(65), Value::Null), &expr));
         
         // Test float BETWEEN
-        row.insert("score".to_string(), Value::Real(85.5));
         let expr = Expr::Between {
             column: "score".to_string(),
             low: Value::Real(80.0),
             high: Value::Real(90.0),
         };
-        assert!(query.evaluate_expr(&row, &expr));
+        assert!(query.evaluate_expr(&row(Value::Integer(65), Value::Real(85.5)), &expr));
         
         // Test mixed types (integer value, real bounds)
-        row.insert("score".to_string(), Value::Integer(85));
-        assert!(query.evaluate_expr(&row, &expr));
+        assert!(query.evaluate_expr(&row(Value::Integer(65), Value::Integer(85)), &expr));
     }
 
     #[test]
@@ -1,13 +1,18 @@
 //! SQLite record parsing
 
-use crate::{Error, Result, Value, btree::read_varint};
+use crate::{Error, Result, Value, btree::read_varint, format::TextEncoding};
 use byteorder::{BigEndian, ByteOrder};
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
-use alloc::{vec::Vec, string::String};
+#[cfg(not(feature = "std"))]
+use alloc::{vec::Vec, string::String, format};
 
 /// Parse a record from payload data with optimized allocations
 pub fn parse_record_optimized(payload: &[u8]) -> Result<Vec<Value>> {
+    parse_record_encoded(payload, TextEncoding::Utf8)
+}
+
+/// Parse a record whose TEXT values are stored in `encoding`
+pub fn parse_record_encoded(payload: &[u8], encoding: TextEncoding) -> Result<Vec<Value>> {
     if payload.is_empty() {
         return Ok(Vec::new());
     }
@@ -45,12 +50,15 @@ pub fn parse_record_optimized(payload: &[u8]) -> Result<Vec<Value>> {
     
     // Parse values with minimal allocations
     for &serial_type in &serial_types {
-        if data_offset >= payload.len() {
+        // Serial types 8 and 9 (and empty strings/blobs) occupy no content bytes,
+        // so they are valid even at the very end of the payload
+        let has_content = !matches!(serial_type, 0 | 8 | 9 | 12 | 13);
+        if has_content && data_offset >= payload.len() {
             values.push(Value::Null);
             continue;
         }
         
-        let (value, bytes_consumed) = parse_value_optimized(&payload[data_offset..], serial_type)?;
+        let (value, bytes_consumed) = parse_value_optimized(&payload[data_offset..], serial_type, encoding)?;
         values.push(value);
         data_offset += bytes_consumed;
     }
@@ -59,7 +67,7 @@ pub fn parse_record_optimized(payload: &[u8]) -> Result<Vec<Value>> {
 }
 
 /// Parse a single value with optimized allocations
-fn parse_value_optimized(data: &[u8], serial_type: i64) -> Result<(Value, usize)> {
+fn parse_value_optimized(data: &[u8], serial_type: i64, encoding: TextEncoding) -> Result<(Value, usize)> {
     match serial_type {
         0 => Ok((Value::Null, 0)),
         1 => {
@@ -82,7 +90,7 @@ fn parse_value_optimized(data: &[u8], serial_type: i64) -> Result<(Value, usize)
                 Ok((Value::Integer(0), 0))
             } else {
                 let mut bytes = [0u8; 4];
-                bytes[1..4].copy_from_slice(&data[0..3]);
+                bytes[0..3].copy_from_slice(&data[0..3]);
                 let value = BigEndian::read_i32(&bytes) >> 8; // Sign extend
                 Ok((Value::Integer(value as i64), 3))
             }
@@ -100,7 +108,7 @@ fn parse_value_optimized(data: &[u8], serial_type: i64) -> Result<(Value, usize)
                 Ok((Value::Integer(0), 0))
             } else {
                 let mut bytes = [0u8; 8];
-                bytes[2..8].copy_from_slice(&data[0..6]);
+                bytes[0..6].copy_from_slice(&data[0..6]);
                 let value = BigEndian::read_i64(&bytes) >> 16; // Sign extend
                 Ok((Value::Integer(value), 6))
             }
@@ -141,8 +149,8 @@ fn parse_value_optimized(data: &[u8], serial_type: i64) -> Result<(Value, usize)
                     if data.len() < length {
                         Ok((Value::Text(String::new()), 0))
                     } else {
-                        // Use from_utf8_lossy to handle invalid UTF-8 gracefully
-                        let text = String::from_utf8_lossy(&data[0..length]).into_owned();
+                        // Decode lossily to handle invalid text gracefully
+                        let text = encoding.decode_lossy(&data[0..length]);
                         Ok((Value::Text(text), length))
                     }
                 }
@@ -155,13 +163,18 @@ fn parse_value_optimized(data: &[u8], serial_type: i64) -> Result<(Value, usize)
 
 /// Parse a value based on its serial type
 pub fn parse_value(serial_type: i64, data: &[u8]) -> Result<(Value, usize)> {
+    parse_value_encoded(serial_type, data, TextEncoding::Utf8)
+}
+
+/// Parse a value whose TEXT content is stored in `encoding`
+pub fn parse_value_encoded(serial_type: i64, data: &[u8], encoding: TextEncoding) -> Result<(Value, usize)> {
     match serial_type {
         0 => Ok((Value::Null, 0)),
         1 => {
             if data.is_empty() {
                 return Err(Error::InvalidRecord);
             }
-            Ok((Value::Integer(data[0] as i64), 1))
+            Ok((Value::Integer(data[0] as i8 as i64), 1))
         }
         2 => {
             if data.len() < 2 {
@@ -249,8 +262,7 @@ pub fn parse_value(serial_type: i64, data: &[u8]) -> Result<(Value, usize)> {
             if data.len() < length {
                 return Err(Error::InvalidRecord);
             }
-            let text = core::str::from_utf8(&data[..length])?;
-            Ok((Value::Text(text.to_string()), length))
+            Ok((Value::Text(encoding.decode(&data[..length])?), length))
         }
         _ => Err(Error::InvalidFormat("Invalid serial type".into())),
     }
@@ -0,0 +1,322 @@
+//! Query result rows and result sets
+
+use crate::{collections::HashMap, decode::FromValue, Error, Result, Value};
+use alloc::sync::Arc;
+use core::fmt;
+use core::ops::{Deref, DerefMut, Index};
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::{String, ToString}, vec::Vec};
+
+/// A row of query results
+///
+/// Values are stored in projection order. The column names are shared by
+/// every row of a result set, so rows only own their values.
+#[derive(Clone, PartialEq)]
+pub struct Row {
+    columns: Arc<[String]>,
+    values: Vec<Value>,
+}
+
+/// Something that picks a column out of a [`Row`]: a position or a name
+pub trait RowIndex {
+    /// Position of the column in `row`, if it exists
+    fn index_in(&self, columns: &[String]) -> Option<usize>;
+}
+
+impl RowIndex for usize {
+    fn index_in(&self, columns: &[String]) -> Option<usize> {
+        (*self < columns.len()).then_some(*self)
+    }
+}
+
+impl RowIndex for str {
+    fn index_in(&self, columns: &[String]) -> Option<usize> {
+        // Exact matches win; SQL identifiers are otherwise case-insensitive
+        columns
+            .iter()
+            .position(|column| column == self)
+            .or_else(|| columns.iter().position(|column| column.eq_ignore_ascii_case(self)))
+    }
+}
+
+impl RowIndex for String {
+    fn index_in(&self, columns: &[String]) -> Option<usize> {
+        self.as_str().index_in(columns)
+    }
+}
+
+impl<T: RowIndex + ?Sized> RowIndex for &T {
+    fn index_in(&self, columns: &[String]) -> Option<usize> {
+        (**self).index_in(columns)
+    }
+}
+
+impl Row {
+    /// Create a row from shared column names and the matching values
+    ///
+    /// Missing trailing values read as NULL; extra values are dropped.
+    pub fn new(columns: Arc<[String]>, mut values: Vec<Value>) -> Self {
+        values.resize(columns.len(), Value::Null);
+        Row { columns, values }
+    }
+
+    /// Value of a column, by position (`row.get(0)`) or by name (`row.get("id")`)
+    ///
+    /// When several columns share a name the first one is returned.
+    pub fn get<I: RowIndex>(&self, index: I) -> Option<&Value> {
+        index.index_in(&self.columns).map(|i| &self.values[i])
+    }
+
+    /// Value of a column converted to `T`, e.g. `row.get_as::<i64, _>("id")`
+    ///
+    /// Fails with [`Error::ColumnNotFound`] if there is no such column and with
+    /// [`Error::TypeMismatch`] if the value cannot be converted.
+    pub fn get_as<'a, T, I>(&'a self, index: I) -> Result<T>
+    where
+        T: FromValue<'a>,
+        I: RowIndex + fmt::Display,
+    {
+        let i = index
+            .index_in(&self.columns)
+            .ok_or_else(|| Error::ColumnNotFound(index.to_string()))?;
+        T::from_value(&self.values[i]).map_err(|e| Error::TypeMismatch {
+            column: self.columns[i].clone(),
+            expected: e.expected,
+            found: e.found,
+        })
+    }
+
+    /// Position of the first column called `name`
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        name.index_in(&self.columns)
+    }
+
+    /// Column names, in projection order
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The shared column list
+    pub fn columns_arc(&self) -> &Arc<[String]> {
+        &self.columns
+    }
+
+    /// Values, in projection order
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Values, for filling in columns while the row is built
+    pub(crate) fn values_mut(&mut self) -> &mut [Value] {
+        &mut self.values
+    }
+
+    /// Take the values out of the row
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    /// Number of columns
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the row has no columns
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over `(column name, value)` pairs in projection order
+    pub fn iter(&self) -> RowEntries<'_> {
+        RowEntries {
+            columns: self.columns.iter(),
+            values: self.values.iter(),
+        }
+    }
+
+    /// Convert into a map from column name to value
+    ///
+    /// Of several columns with the same name only the first one is kept, as with [`Row::get`].
+    pub fn into_map(self) -> HashMap<String, Value> {
+        self.columns.iter().cloned().zip(self.values).rev().collect()
+    }
+}
+
+impl<I: RowIndex> Index<I> for Row {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        match index.index_in(&self.columns) {
+            Some(i) => &self.values[i],
+            None => panic!("no such column in row"),
+        }
+    }
+}
+
+impl fmt::Debug for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl From<Row> for HashMap<String, Value> {
+    fn from(row: Row) -> Self {
+        row.into_map()
+    }
+}
+
+impl<'a> IntoIterator for &'a Row {
+    type Item = (&'a str, &'a Value);
+    type IntoIter = RowEntries<'a>;
+
+    fn into_iter(self) -> RowEntries<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the `(column name, value)` pairs of a [`Row`]
+pub struct RowEntries<'a> {
+    columns: core::slice::Iter<'a, String>,
+    values: core::slice::Iter<'a, Value>,
+}
+
+impl<'a> Iterator for RowEntries<'a> {
+    type Item = (&'a str, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.columns.next()?.as_str(), self.values.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl ExactSizeIterator for RowEntries<'_> {}
+
+/// Rows returned by a query, together with their column names
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    columns: Arc<[String]>,
+    rows: Vec<Row>,
+}
+
+impl ResultSet {
+    /// Create a result set from its column names and rows
+    pub fn new(columns: Arc<[String]>, rows: Vec<Row>) -> Self {
+        ResultSet { columns, rows }
+    }
+
+    /// Column names, in projection order
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The rows
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Take the rows out of the result set
+    pub fn into_rows(self) -> Vec<Row> {
+        self.rows
+    }
+
+    /// Convert every row into a map from column name to value
+    pub fn into_maps(self) -> Vec<HashMap<String, Value>> {
+        self.rows.into_iter().map(Row::into_map).collect()
+    }
+}
+
+impl Deref for ResultSet {
+    type Target = [Row];
+
+    fn deref(&self) -> &[Row] {
+        &self.rows
+    }
+}
+
+impl DerefMut for ResultSet {
+    fn deref_mut(&mut self) -> &mut [Row] {
+        &mut self.rows
+    }
+}
+
+impl IntoIterator for ResultSet {
+    type Item = Row;
+    type IntoIter = alloc::vec::IntoIter<Row>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ResultSet {
+    type Item = &'a Row;
+    type IntoIter = core::slice::Iter<'a, Row>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.iter()
+    }
+}
+
+impl From<ResultSet> for Vec<Row> {
+    fn from(results: ResultSet) -> Self {
+        results.rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, vec};
+
+    fn columns(names: &[&str]) -> Arc<[String]> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_row_access() {
+        let row = Row::new(columns(&["id", "name", "id"]), vec![Value::Integer(1), Value::Text("a".into()), Value::Integer(2)]);
+
+        assert_eq!(row.get(1), Some(&Value::Text("a".into())));
+        assert_eq!(row.get(3), None);
+        assert_eq!(row.get("name"), Some(&Value::Text("a".into())));
+        assert_eq!(row.get("NAME"), Some(&Value::Text("a".into())));
+        assert_eq!(row.get("missing"), None);
+
+        // Duplicate names are kept, and lookups by name find the first one
+        assert_eq!(row["id"], Value::Integer(1));
+        assert_eq!(row[2], Value::Integer(2));
+        let names: Vec<_> = row.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["id", "name", "id"]);
+
+        let map = row.clone().into_map();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["id"], Value::Integer(1));
+        assert_eq!(map["name"], Value::Text("a".into()));
+
+        // Short value lists are padded with NULLs
+        let row = Row::new(columns(&["a", "b"]), vec![Value::Integer(1)]);
+        assert_eq!(row.values(), [Value::Integer(1), Value::Null]);
+        assert_eq!(format!("{:?}", row), "{\"a\": Integer(1), \"b\": Null}");
+    }
+
+    #[test]
+    fn test_result_set() {
+        let cols = columns(&["x"]);
+        let results = ResultSet::new(
+            cols.clone(),
+            (0..3).map(|i| Row::new(cols.clone(), vec![Value::Integer(i)])).collect(),
+        );
+
+        assert_eq!(results.columns(), ["x"]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2]["x"], Value::Integer(2));
+        assert!(Arc::ptr_eq(results[0].columns_arc(), results[1].columns_arc()));
+        let values: Vec<_> = results.into_iter().flat_map(Row::into_values).collect();
+        assert_eq!(values, [Value::Integer(0), Value::Integer(1), Value::Integer(2)]);
+    }
+}
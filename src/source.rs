@@ -0,0 +1,179 @@
+//! Page sources: where the bytes of a database (or its WAL) come from
+
+use crate::{Error, Result};
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec, format};
+
+/// Random-access byte storage that database pages are read from
+pub trait PageSource {
+    /// Fill `buf` with the bytes starting at `offset`, failing if the source is too short
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()>;
+
+    /// Total size of the source in bytes
+    fn size(&mut self) -> Result<u64>;
+}
+
+/// Copy `buf.len()` bytes at `offset` out of an in-memory buffer
+fn read_slice_at(data: &[u8], offset: u64, buf: &mut [u8]) -> Result<()> {
+    let start = usize::try_from(offset).map_err(|_| Error::IntegerOverflow)?;
+    let end = start.checked_add(buf.len()).ok_or(Error::IntegerOverflow)?;
+    let bytes = data.get(start..end).ok_or_else(|| {
+        Error::InvalidFormat(format!(
+            "Unexpected end of data reading {} bytes at offset {} ({} bytes available)",
+            buf.len(),
+            offset,
+            data.len()
+        ))
+    })?;
+    buf.copy_from_slice(bytes);
+    Ok(())
+}
+
+impl PageSource for &[u8] {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        read_slice_at(self, offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl PageSource for Vec<u8> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        read_slice_at(self, offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl PageSource for Box<[u8]> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        read_slice_at(self, offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl PageSource for Arc<[u8]> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        read_slice_at(self, offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+/// Page source over any owned byte buffer, e.g. `bytes::Bytes` or a memory map
+pub struct BytesSource<B>(pub B);
+
+impl<B: AsRef<[u8]>> PageSource for BytesSource<B> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        read_slice_at(self.0.as_ref(), offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.0.as_ref().len() as u64)
+    }
+}
+
+/// Page source over any `Read + Seek` implementation
+#[cfg(feature = "std")]
+pub struct ReadSeekSource<R>(pub R);
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> PageSource for ReadSeekSource<R> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.0.seek(SeekFrom::Start(offset))?;
+        self.0.read_exact(buf)?;
+        Ok(())
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.0.seek(SeekFrom::End(0))?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl PageSource for File {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.read_exact(buf)?;
+        Ok(())
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+#[cfg(feature = "std")]
+impl PageSource for BufReader<File> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.read_exact(buf)?;
+        Ok(())
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.get_ref().metadata()?.len())
+    }
+}
+
+impl<S: PageSource + ?Sized> PageSource for Box<S> {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        (**self).read_at(offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        (**self).size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_source<S: PageSource>(mut source: S) {
+        assert_eq!(source.size().unwrap(), 10);
+
+        let mut buf = [0u8; 4];
+        source.read_at(3, &mut buf).unwrap();
+        assert_eq!(buf, [3, 4, 5, 6]);
+
+        source.read_at(6, &mut buf).unwrap();
+        assert_eq!(buf, [6, 7, 8, 9]);
+
+        assert!(source.read_at(7, &mut buf).is_err());
+        assert!(source.read_at(u64::MAX, &mut buf).is_err());
+    }
+
+    #[test]
+    fn test_in_memory_sources() {
+        let data: Vec<u8> = (0..10).collect();
+        check_source(data.as_slice());
+        check_source(data.clone());
+        check_source(data.clone().into_boxed_slice());
+        check_source(Arc::<[u8]>::from(data.clone()));
+        check_source(BytesSource(data.clone()));
+        check_source(Box::new(data.clone()) as Box<dyn PageSource>);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_read_seek_source() {
+        let data: Vec<u8> = (0..10).collect();
+        check_source(ReadSeekSource(std::io::Cursor::new(data)));
+    }
+}
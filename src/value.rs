@@ -1,6 +1,6 @@
 //! SQLite value types
 
-#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+#[cfg(not(feature = "std"))]
 use alloc::{string::String, vec::Vec};
 
 /// Represents a value stored in SQLite
@@ -26,17 +26,18 @@ pub enum Value {
 impl Eq for Value {}
 
 // Manual implementation of PartialOrd to handle NaN values robustly
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for Value {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         match (self, other) {
             (Value::Real(a), Value::Real(b)) => {
                 // Handle NaN values deterministically
                 if a.is_nan() && b.is_nan() {
-                    Some(std::cmp::Ordering::Equal)
+                    Some(core::cmp::Ordering::Equal)
                 } else if a.is_nan() {
-                    Some(std::cmp::Ordering::Less)
+                    Some(core::cmp::Ordering::Less)
                 } else if b.is_nan() {
-                    Some(std::cmp::Ordering::Greater)
+                    Some(core::cmp::Ordering::Greater)
                 } else {
                     a.partial_cmp(b)
                 }
@@ -44,27 +45,29 @@ impl PartialOrd for Value {
             _ => {
                 // For non-Real values, use the standard comparison
                 match (self, other) {
-                    (Value::Null, Value::Null) => Some(std::cmp::Ordering::Equal),
-                    (Value::Null, _) => Some(std::cmp::Ordering::Less),
-                    (_, Value::Null) => Some(std::cmp::Ordering::Greater),
+                    (Value::Null, Value::Null) => Some(core::cmp::Ordering::Equal),
+                    (Value::Null, _) => Some(core::cmp::Ordering::Less),
+                    (_, Value::Null) => Some(core::cmp::Ordering::Greater),
                     (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
                     (Value::Integer(a), Value::Real(b)) => {
                         if b.is_nan() {
-                            Some(std::cmp::Ordering::Greater)
+                            Some(core::cmp::Ordering::Greater)
                         } else {
                             (*a as f64).partial_cmp(b)
                         }
                     }
                     (Value::Real(a), Value::Integer(b)) => {
                         if a.is_nan() {
-                            Some(std::cmp::Ordering::Less)
+                            Some(core::cmp::Ordering::Less)
                         } else {
                             a.partial_cmp(&(*b as f64))
                         }
                     }
                     (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
                     (Value::Blob(a), Value::Blob(b)) => Some(a.cmp(b)),
-                    _ => None, // Different types are not comparable
+                    // Different storage classes sort the way SQLite orders them in b-trees:
+                    // NULL < INTEGER/REAL < TEXT < BLOB
+                    _ => Some(self.storage_class_rank().cmp(&other.storage_class_rank())),
                 }
             }
         }
@@ -72,6 +75,27 @@ impl PartialOrd for Value {
 }
 
 impl Value {
+    /// Rank of the value's storage class in SQLite's sort order
+    fn storage_class_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+
+    /// Name of the value's storage class, e.g. `"INTEGER"`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "NULL",
+            Value::Integer(_) => "INTEGER",
+            Value::Real(_) => "REAL",
+            Value::Text(_) => "TEXT",
+            Value::Blob(_) => "BLOB",
+        }
+    }
+
     /// Returns true if this value is NULL
     pub fn is_null(&self) -> bool {
         matches!(self, Value::Null)
@@ -133,14 +157,17 @@ impl core::fmt::Display for Value {
 
 // Manual implementation of Ord, required for B-tree key comparisons.
 impl Ord for Value {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.partial_cmp(other).unwrap_or(core::cmp::Ordering::Equal)
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, string::ToString, vec};
 
     #[test]
     fn test_value_creation() {
@@ -227,8 +254,6 @@ mod tests {
 
     #[test]
     fn test_nan_handling() {
-        use std::f64;
-        
         let nan = Value::Real(f64::NAN);
         let pos_inf = Value::Real(f64::INFINITY);
         let neg_inf = Value::Real(f64::NEG_INFINITY);
@@ -242,7 +267,7 @@ mod tests {
         assert!(nan < integer);
         
         // NaN should equal NaN
-        assert_eq!(nan.cmp(&nan), std::cmp::Ordering::Equal);
+        assert_eq!(nan.cmp(&nan), core::cmp::Ordering::Equal);
         
         // Other values should be greater than NaN
         assert!(pos_inf > nan);
@@ -255,4 +280,20 @@ mod tests {
         assert!(normal > neg_inf);
         assert!(integer > normal); // 42 > 3.14 in SQLite's numeric comparison
     }
+
+    #[test]
+    fn test_storage_class_ordering() {
+        let null = Value::Null;
+        let int = Value::Integer(1_000);
+        let real = Value::Real(-2.5);
+        let text = Value::Text("0".to_string());
+        let blob = Value::Blob(vec![0]);
+
+        assert!(null < real);
+        assert!(real < int);
+        assert!(int < text);
+        assert!(text < blob);
+        assert_eq!(blob.cmp(&null), core::cmp::Ordering::Greater);
+        assert_eq!(text.partial_cmp(&int), Some(core::cmp::Ordering::Greater));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,336 @@
+//! Write-ahead log (WAL) parsing
+//!
+//! A database in WAL mode keeps its most recent committed pages in a separate
+//! `-wal` file. This module validates the WAL header and frames (salts and
+//! cumulative checksums) and works out which frame holds the newest committed
+//! copy of each page. The `-shm` index file is never consulted.
+
+use crate::{Error, Result, logging::log_debug, source::PageSource};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::collections::HashMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec, format};
+
+/// Size of the WAL file header
+pub const WAL_HEADER_SIZE: usize = 32;
+
+/// Size of each frame header
+pub const WAL_FRAME_HEADER_SIZE: usize = 24;
+
+/// WAL magic number with little-endian checksums
+const WAL_MAGIC_LE: u32 = 0x377f0682;
+
+/// WAL magic number with big-endian checksums
+const WAL_MAGIC_BE: u32 = 0x377f0683;
+
+/// WAL file header
+#[derive(Debug, Clone)]
+pub struct WalHeader {
+    /// Magic number (determines checksum byte order)
+    pub magic: u32,
+    /// File format version (currently 3007000)
+    pub format_version: u32,
+    /// Database page size
+    pub page_size: u32,
+    /// Checkpoint sequence number
+    pub checkpoint_sequence: u32,
+    /// Salt-1, copied into every frame header
+    pub salt1: u32,
+    /// Salt-2, copied into every frame header
+    pub salt2: u32,
+    /// Checksum over the first 24 bytes of the header
+    pub checksum1: u32,
+    /// Checksum over the first 24 bytes of the header
+    pub checksum2: u32,
+}
+
+/// A validated frame in the WAL
+#[derive(Debug, Clone)]
+pub struct WalFrame {
+    /// Page number stored in this frame
+    pub page_number: u32,
+    /// Database size in pages after this frame for commit frames, 0 otherwise
+    pub commit_size: u32,
+    /// Byte offset of the page data within the WAL file
+    pub data_offset: u64,
+}
+
+impl WalFrame {
+    /// Whether this frame is the last frame of a transaction
+    pub fn is_commit(&self) -> bool {
+        self.commit_size != 0
+    }
+}
+
+/// A committed transaction in the WAL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalCommit {
+    /// Zero-based index of the commit frame within the WAL
+    pub frame_index: usize,
+    /// Database size in pages after this commit
+    pub database_size: u32,
+}
+
+/// Parsed write-ahead log containing only committed, checksum-valid frames
+#[derive(Debug, Clone)]
+pub struct Wal {
+    /// WAL header
+    pub header: WalHeader,
+    /// Valid frames in log order, ending with the last commit frame
+    pub frames: Vec<WalFrame>,
+}
+
+impl Wal {
+    /// Parse and validate a WAL file.
+    ///
+    /// Frames are accepted while their salts match the header and the cumulative
+    /// checksum is correct; anything after the last valid commit frame (an
+    /// uncommitted or torn transaction) is discarded.
+    pub fn parse<S: PageSource + ?Sized>(source: &mut S) -> Result<Wal> {
+        let mut header_bytes = [0u8; WAL_HEADER_SIZE];
+        source.read_at(0, &mut header_bytes)?;
+
+        let header = Self::parse_header(&header_bytes)?;
+        let big_endian = header.magic == WAL_MAGIC_BE;
+
+        let (s1, s2) = wal_checksum(&header_bytes[..24], big_endian, 0, 0);
+        if (s1, s2) != (header.checksum1, header.checksum2) {
+            return Err(Error::InvalidFormat("WAL header checksum mismatch".into()));
+        }
+
+        let page_size = header.page_size as usize;
+        let mut frame_header = [0u8; WAL_FRAME_HEADER_SIZE];
+        let mut page_data = vec![0u8; page_size];
+        let mut checksum = (s1, s2);
+        let frame_size = (WAL_FRAME_HEADER_SIZE + page_size) as u64;
+        let size = source.size()?;
+        let mut offset = WAL_HEADER_SIZE as u64;
+        let mut frames = Vec::new();
+        let mut committed_frames = 0;
+
+        // A torn final frame is simply not read
+        while offset + frame_size <= size {
+            source.read_at(offset, &mut frame_header)?;
+            source.read_at(offset + WAL_FRAME_HEADER_SIZE as u64, &mut page_data)?;
+
+            let page_number = BigEndian::read_u32(&frame_header[0..4]);
+            let commit_size = BigEndian::read_u32(&frame_header[4..8]);
+            let salt1 = BigEndian::read_u32(&frame_header[8..12]);
+            let salt2 = BigEndian::read_u32(&frame_header[12..16]);
+            let frame_checksum = (
+                BigEndian::read_u32(&frame_header[16..20]),
+                BigEndian::read_u32(&frame_header[20..24]),
+            );
+
+            if salt1 != header.salt1 || salt2 != header.salt2 || page_number == 0 {
+                break;
+            }
+
+            let (c1, c2) = wal_checksum(&frame_header[..8], big_endian, checksum.0, checksum.1);
+            let (c1, c2) = wal_checksum(&page_data, big_endian, c1, c2);
+            if (c1, c2) != frame_checksum {
+                break;
+            }
+            checksum = (c1, c2);
+
+            frames.push(WalFrame {
+                page_number,
+                commit_size,
+                data_offset: offset + WAL_FRAME_HEADER_SIZE as u64,
+            });
+            if commit_size != 0 {
+                committed_frames = frames.len();
+            }
+
+            offset += frame_size;
+        }
+
+        if frames.len() > committed_frames {
+            log_debug(&format!(
+                "Ignoring {} uncommitted WAL frames",
+                frames.len() - committed_frames
+            ));
+        }
+        frames.truncate(committed_frames);
+
+        log_debug(&format!("Parsed WAL with {} committed frames", frames.len()));
+        Ok(Wal { header, frames })
+    }
+
+    /// Parse the 32-byte WAL header
+    fn parse_header(data: &[u8]) -> Result<WalHeader> {
+        let magic = BigEndian::read_u32(&data[0..4]);
+        if magic != WAL_MAGIC_LE && magic != WAL_MAGIC_BE {
+            return Err(Error::InvalidFormat(format!("Invalid WAL magic number: {:#x}", magic)));
+        }
+
+        let page_size = BigEndian::read_u32(&data[8..12]);
+        if !(512..=65536).contains(&page_size) || !page_size.is_power_of_two() {
+            return Err(Error::InvalidFormat(format!("Invalid WAL page size: {}", page_size)));
+        }
+
+        Ok(WalHeader {
+            magic,
+            format_version: BigEndian::read_u32(&data[4..8]),
+            page_size,
+            checkpoint_sequence: BigEndian::read_u32(&data[12..16]),
+            salt1: BigEndian::read_u32(&data[16..20]),
+            salt2: BigEndian::read_u32(&data[20..24]),
+            checksum1: BigEndian::read_u32(&data[24..28]),
+            checksum2: BigEndian::read_u32(&data[28..32]),
+        })
+    }
+
+    /// Database size in pages as of the last commit, if the WAL has any commits
+    pub fn database_size(&self) -> Option<u32> {
+        self.frames.last().map(|frame| frame.commit_size)
+    }
+
+    /// All commits in the WAL, oldest first
+    pub fn commits(&self) -> Vec<WalCommit> {
+        self.frames
+            .iter()
+            .enumerate()
+            .filter(|(_, frame)| frame.is_commit())
+            .map(|(frame_index, frame)| WalCommit { frame_index, database_size: frame.commit_size })
+            .collect()
+    }
+
+    /// Map each page number to the data offset of its newest committed frame
+    pub fn page_offsets(&self) -> HashMap<u32, u64> {
+        match self.frames.len() {
+            0 => HashMap::new(),
+            len => self.page_offsets_at(len - 1),
+        }
+    }
+
+    /// Map each page number to the data offset of its newest frame at or
+    /// before `frame_index`, i.e. the page versions visible at that commit
+    pub fn page_offsets_at(&self, frame_index: usize) -> HashMap<u32, u64> {
+        let end = (frame_index + 1).min(self.frames.len());
+        let mut offsets = HashMap::with_capacity(end);
+        for frame in &self.frames[..end] {
+            offsets.insert(frame.page_number, frame.data_offset);
+        }
+        offsets
+    }
+}
+
+/// WAL checksum over `data` (a multiple of 8 bytes), continuing from `(s1, s2)`
+fn wal_checksum(data: &[u8], big_endian: bool, mut s1: u32, mut s2: u32) -> (u32, u32) {
+    for chunk in data.chunks_exact(8) {
+        let (x1, x2) = if big_endian {
+            (BigEndian::read_u32(&chunk[0..4]), BigEndian::read_u32(&chunk[4..8]))
+        } else {
+            (LittleEndian::read_u32(&chunk[0..4]), LittleEndian::read_u32(&chunk[4..8]))
+        };
+        s1 = s1.wrapping_add(x1).wrapping_add(s2);
+        s2 = s2.wrapping_add(x2).wrapping_add(s1);
+    }
+    (s1, s2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a WAL file in memory with the given (page_number, commit_size) frames
+    fn build_wal(page_size: u32, frames: &[(u32, u32)]) -> Vec<u8> {
+        let mut wal = vec![0u8; WAL_HEADER_SIZE];
+        BigEndian::write_u32(&mut wal[0..4], WAL_MAGIC_LE);
+        BigEndian::write_u32(&mut wal[4..8], 3007000);
+        BigEndian::write_u32(&mut wal[8..12], page_size);
+        BigEndian::write_u32(&mut wal[16..20], 0x1234);
+        BigEndian::write_u32(&mut wal[20..24], 0x5678);
+        let (mut s1, mut s2) = wal_checksum(&wal[..24], false, 0, 0);
+        BigEndian::write_u32(&mut wal[24..28], s1);
+        BigEndian::write_u32(&mut wal[28..32], s2);
+
+        for (i, &(page_number, commit_size)) in frames.iter().enumerate() {
+            let mut frame = vec![0u8; WAL_FRAME_HEADER_SIZE];
+            BigEndian::write_u32(&mut frame[0..4], page_number);
+            BigEndian::write_u32(&mut frame[4..8], commit_size);
+            BigEndian::write_u32(&mut frame[8..12], 0x1234);
+            BigEndian::write_u32(&mut frame[12..16], 0x5678);
+            let data = vec![i as u8; page_size as usize];
+            (s1, s2) = wal_checksum(&frame[..8], false, s1, s2);
+            (s1, s2) = wal_checksum(&data, false, s1, s2);
+            BigEndian::write_u32(&mut frame[16..20], s1);
+            BigEndian::write_u32(&mut frame[20..24], s2);
+            wal.extend_from_slice(&frame);
+            wal.extend_from_slice(&data);
+        }
+        wal
+    }
+
+    #[test]
+    fn test_parse_committed_frames() {
+        let mut wal = build_wal(512, &[(1, 0), (2, 2), (2, 3)]);
+        let parsed = Wal::parse(&mut wal).unwrap();
+
+        assert_eq!(parsed.header.page_size, 512);
+        assert_eq!(parsed.frames.len(), 3);
+        assert_eq!(parsed.database_size(), Some(3));
+
+        let offsets = parsed.page_offsets();
+        assert_eq!(offsets.len(), 2);
+        // Page 2 resolves to the newest frame
+        assert_eq!(offsets[&2], parsed.frames[2].data_offset);
+    }
+
+    #[test]
+    fn test_commit_snapshots() {
+        let mut wal = build_wal(512, &[(1, 0), (2, 2), (2, 0), (3, 3)]);
+        let parsed = Wal::parse(&mut wal).unwrap();
+
+        assert_eq!(
+            parsed.commits(),
+            vec![
+                WalCommit { frame_index: 1, database_size: 2 },
+                WalCommit { frame_index: 3, database_size: 3 },
+            ]
+        );
+
+        // At the first commit page 2 comes from frame 1 and page 3 is absent
+        let offsets = parsed.page_offsets_at(1);
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(offsets[&2], parsed.frames[1].data_offset);
+
+        let offsets = parsed.page_offsets_at(3);
+        assert_eq!(offsets.len(), 3);
+        assert_eq!(offsets[&2], parsed.frames[2].data_offset);
+    }
+
+    #[test]
+    fn test_uncommitted_tail_is_ignored() {
+        let mut wal = build_wal(512, &[(1, 1), (2, 0), (3, 0)]);
+        let parsed = Wal::parse(&mut wal).unwrap();
+
+        assert_eq!(parsed.frames.len(), 1);
+        assert_eq!(parsed.database_size(), Some(1));
+    }
+
+    #[test]
+    fn test_checksum_mismatch_stops_parsing() {
+        let mut wal = build_wal(512, &[(1, 1), (2, 2)]);
+        // Corrupt one byte of the second frame's page data
+        let second_frame_data = WAL_HEADER_SIZE + 2 * WAL_FRAME_HEADER_SIZE + 512;
+        wal[second_frame_data + 10] ^= 0xff;
+
+        let parsed = Wal::parse(&mut wal).unwrap();
+        assert_eq!(parsed.frames.len(), 1);
+        assert_eq!(parsed.database_size(), Some(1));
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut wal = build_wal(512, &[(1, 1)]);
+        wal[0] = 0;
+        assert!(matches!(Wal::parse(&mut wal), Err(Error::InvalidFormat(_))));
+
+        let mut wal = build_wal(512, &[(1, 1)]);
+        wal[20] ^= 0xff;
+        assert!(matches!(Wal::parse(&mut wal), Err(Error::InvalidFormat(_))));
+    }
+}
@@ -0,0 +1,177 @@
+//! Tests for aggregate queries and their b-tree shortcuts
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::{Error, SelectQuery, Value};
+use std::sync::atomic::Ordering;
+
+const ROWS: i64 = 20_000;
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(&format!(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, qty INTEGER, price REAL, tag TEXT, note);
+         CREATE INDEX idx_items_qty ON items (qty);
+         CREATE INDEX idx_items_name ON items (name);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO items (id, name, qty, price, tag, note)
+         SELECT i, 'item ' || i,
+                CASE WHEN i % 10 = 0 THEN NULL ELSE i % 97 END,
+                CASE WHEN i % 7 = 0 THEN NULL ELSE i * 0.1 END,
+                'tag' || (i % 5),
+                CASE i % 4 WHEN 0 THEN i WHEN 1 THEN i * 1.5 WHEN 2 THEN 'text ' || i ELSE NULL END
+         FROM n;
+         CREATE TABLE empty (x INTEGER, y TEXT);
+         CREATE TABLE numbers (v);
+         INSERT INTO numbers (v) VALUES (1), (2.5), ('3'), ('abc'), (X'34'), (NULL), ('4.0'), (' 7 '), ('12abc');
+         CREATE TABLE big (v INTEGER);
+         INSERT INTO big (v) VALUES (9223372036854775807), (1);
+         CREATE TABLE recovered (v);
+         INSERT INTO recovered (v) VALUES (9223372036854775807), (1), (0.5);
+         CREATE TABLE kv (k TEXT PRIMARY KEY, v INTEGER) WITHOUT ROWID;
+         INSERT INTO kv (k, v) VALUES ('b', 2), ('a', 1), ('c', NULL);
+         CREATE TABLE docs (id INTEGER PRIMARY KEY, body BLOB);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 300)
+         INSERT INTO docs (id, body) SELECT i, zeroblob(6000) FROM n;
+         CREATE TABLE letters (v TEXT COLLATE NOCASE, r TEXT COLLATE RTRIM, n);
+         INSERT INTO letters (v, r, n) VALUES ('a', 'x', 1), ('A', 'x  ', 1.0), ('b', 'y', 2), ('B', 'y ', 2.5);",
+        rows = ROWS
+    ))
+    .unwrap();
+});
+
+/// Compare result rows, allowing REAL sums to differ in their last digits
+/// from SQLite versions that add without compensation
+fn assert_rows_match(actual: &[Vec<Value>], expected: &[Vec<Value>], sql: &str) {
+    let same = |a: &Value, b: &Value| match (a, b) {
+        (Value::Real(a), Value::Real(b)) => (a - b).abs() <= 1e-9 * a.abs().max(1.0),
+        _ => a == b,
+    };
+    assert!(
+        actual.len() == expected.len()
+            && actual.iter().zip(expected).all(|(a, b)| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))),
+        "{}: {:?} != {:?}",
+        sql,
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in [
+        "SELECT COUNT(*) FROM items",
+        "SELECT count(*), count(qty), count(DISTINCT qty), count(price), count(note) FROM items",
+        "SELECT sum(qty), avg(qty), total(qty), min(qty), max(qty) FROM items",
+        "SELECT sum(price), avg(price), total(price), min(price), max(price) FROM items",
+        "SELECT sum(DISTINCT qty), avg(DISTINCT qty), total(DISTINCT tag) FROM items",
+        "SELECT min(name), max(name), min(tag), max(tag) FROM items",
+        "SELECT sum(note), total(note), min(note), max(note), count(DISTINCT note) FROM items",
+        "SELECT count(*), sum(qty), max(price) FROM items WHERE qty > 50",
+        "SELECT count(*), min(name) FROM items WHERE id BETWEEN 100 AND 200 OR qty = 3",
+        "SELECT group_concat(qty) FROM items WHERE id < 30",
+        "SELECT group_concat(DISTINCT tag) FROM items WHERE id < 30",
+        "SELECT group_concat(name, ' | ') FROM items WHERE id BETWEEN 5 AND 9",
+        "SELECT group_concat(tag, ';'), group_concat(price) FROM items WHERE id < 20",
+        "SELECT group_concat(note, tag) FROM items WHERE id < 12",
+        // Bare columns come from the row MIN or MAX picked
+        "SELECT name, max(price) FROM items",
+        "SELECT min(price), name, id FROM items WHERE id > 100",
+        "SELECT max(id), min(id), count(rowid), max(oid) FROM items",
+        "SELECT max(rowid) FROM items",
+        "SELECT min(_rowid_) FROM items WHERE qty = 5",
+        "SELECT count(*), 'rows', 42 FROM items WHERE tag = 'tag3'",
+        "SELECT count(*), count(x), sum(x), avg(x), total(x), min(x), max(x), group_concat(y) FROM empty",
+        "SELECT x, count(*) FROM empty",
+        "SELECT max(x) FROM empty",
+        "SELECT sum(v), total(v), avg(v), count(v), min(v), max(v) FROM numbers",
+        "SELECT group_concat(v) FROM numbers",
+        "SELECT total(v), avg(v), count(v) FROM big",
+        "SELECT count(*), max(k), min(v), sum(v) FROM kv",
+        "SELECT count(*) FROM docs",
+        // DISTINCT compares TEXT with the argument's collation
+        "SELECT count(DISTINCT v), group_concat(DISTINCT v), count(DISTINCT r), group_concat(DISTINCT r), count(DISTINCT n) FROM letters",
+        "SELECT count(DISTINCT v COLLATE BINARY), group_concat(DISTINCT r COLLATE BINARY), count(DISTINCT lower(v)) FROM letters",
+        "SELECT count(*) FROM items LIMIT 0",
+    ] {
+        assert_rows_match(&query(&mut db, sql).unwrap(), &DB.expected_rows(sql), sql);
+    }
+
+    // string_agg is group_concat with a required separator
+    assert_eq!(
+        query(&mut db, "SELECT string_agg(tag, ';') FROM items WHERE id < 20").unwrap(),
+        DB.expected_rows("SELECT group_concat(tag, ';') FROM items WHERE id < 20")
+    );
+}
+
+#[test]
+fn test_column_names() {
+    let mut db = DB.open();
+    let rows = db
+        .execute_query(&SelectQuery::parse("SELECT COUNT(*), max(qty) AS most, name FROM items").unwrap())
+        .unwrap();
+    assert_eq!(rows.columns(), ["COUNT(*)", "most", "name"]);
+    assert_eq!(rows[0]["most"], Value::Integer(96));
+}
+
+#[test]
+fn test_sum_overflow_and_invalid_calls() {
+    let mut db = DB.open();
+    assert!(matches!(query(&mut db, "SELECT sum(v) FROM big"), Err(Error::IntegerOverflow)));
+    // A REAL value after the overflow makes the sum approximate instead
+    assert_eq!(query(&mut db, "SELECT sum(v) FROM recovered").unwrap(), [[Value::Real(9223372036854775808.5)]]);
+    assert!(matches!(query(&mut db, "SELECT sum(missing) FROM items"), Err(Error::ColumnNotFound(_))));
+
+    for sql in [
+        "SELECT sum(qty, price) FROM items",
+        "SELECT count(DISTINCT *) FROM items",
+        "SELECT sum(count(*)) FROM items",
+        "SELECT group_concat(DISTINCT name, ',') FROM items",
+        "SELECT median(qty) FROM items",
+        "SELECT sum(*) FROM items",
+    ] {
+        assert!(matches!(SelectQuery::parse(sql), Err(Error::QueryError(_))), "{}", sql);
+    }
+}
+
+#[test]
+fn test_min_and_max_read_one_index_entry() {
+    let mut db = DB.open_seek_only(8);
+    for sql in [
+        "SELECT min(qty) FROM items",
+        "SELECT max(qty) FROM items",
+        "SELECT min(name) FROM items",
+        "SELECT max(name) AS last FROM items",
+        "SELECT max(id) FROM items",
+        "SELECT min(rowid) FROM items",
+    ] {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(rows, DB.expected_rows(sql), "{}", sql);
+    }
+
+    // Columns without an index, and aggregates next to other columns, scan
+    for sql in ["SELECT max(price) FROM items", "SELECT max(qty), count(*) FROM items", "SELECT max(qty) FROM items WHERE id > 5"] {
+        assert!(
+            matches!(query(&mut db, sql), Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. })),
+            "{}",
+            sql
+        );
+    }
+}
+
+#[test]
+fn test_count_star_skips_records() {
+    let (mut db, reads) = DB.open_counting();
+
+    // Every row of docs spills onto an overflow page, which counting never reads
+    let before = reads.load(Ordering::Relaxed);
+    assert_eq!(query(&mut db, "SELECT count(*) FROM docs").unwrap(), [[Value::Integer(300)]]);
+    let counted = reads.load(Ordering::Relaxed) - before;
+    assert_eq!(db.count_table_rows("docs").unwrap(), 300);
+
+    let before = reads.load(Ordering::Relaxed);
+    assert_eq!(query(&mut db, "SELECT count(body) FROM docs").unwrap(), [[Value::Integer(300)]]);
+    let scanned = reads.load(Ordering::Relaxed) - before;
+    assert!(counted < 300 && scanned >= 300, "counted with {} reads, scanned with {}", counted, scanned);
+}
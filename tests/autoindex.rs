@@ -0,0 +1,73 @@
+//! Tests for the automatic indexes behind PRIMARY KEY and UNIQUE constraints
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::{Error, Value};
+
+const ROWS: i64 = 5000;
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(&format!(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE, name TEXT);
+         CREATE TABLE devices (uuid TEXT PRIMARY KEY, owner INTEGER, serial TEXT UNIQUE);
+         CREATE TABLE seats (id INTEGER, hall TEXT, row_no INTEGER, seat INTEGER,
+                             UNIQUE (hall, row_no, seat), PRIMARY KEY (id, hall));
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO users (id, email, name) SELECT i, 'user' || i || '@example.com', 'user ' || i FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO devices (uuid, owner, serial) SELECT printf('%08x-uuid', i * 7919), i, 'SN' || i FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO seats (id, hall, row_no, seat) SELECT i, 'hall' || (i % 3), i / 30, i % 30 FROM n;",
+        rows = ROWS
+    ))
+    .unwrap();
+});
+
+#[test]
+fn test_unique_column_lookups_use_the_autoindex() {
+    let mut db = DB.open_seek_only(4);
+    let rows = query(&mut db, "SELECT id, name FROM users WHERE email = 'user4321@example.com'").unwrap();
+    assert_eq!(rows, [vec![Value::Integer(4321), Value::Text("user 4321".into())]]);
+
+    let rows = query(&mut db, "SELECT id FROM users WHERE email = 'nobody@example.com'").unwrap();
+    assert!(rows.is_empty());
+
+    // Second constraint of the table, after its text PRIMARY KEY
+    let rows = query(&mut db, "SELECT owner FROM devices WHERE serial = 'SN77'").unwrap();
+    assert_eq!(rows, [vec![Value::Integer(77)]]);
+
+    // Columns without a constraint still need a scan
+    assert!(matches!(
+        query(&mut db, "SELECT id FROM users WHERE name = 'user 12'"),
+        Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. })
+    ));
+}
+
+#[test]
+fn test_primary_key_lookups_use_the_autoindex() {
+    let mut db = DB.open_seek_only(4);
+    let uuid = format!("{:08x}-uuid", 1234 * 7919);
+    let rows = query(&mut db, &format!("SELECT owner, serial FROM devices WHERE uuid = '{}'", uuid)).unwrap();
+    assert_eq!(rows, [vec![Value::Integer(1234), Value::Text("SN1234".into())]]);
+
+    // Composite constraints, declared as table constraints
+    let rows = query(&mut db, "SELECT seat FROM seats WHERE id = 100 AND hall = 'hall1'").unwrap();
+    assert_eq!(rows, [vec![Value::Integer(10)]]);
+
+    let rows = query(&mut db, "SELECT id FROM seats WHERE hall = 'hall2' AND row_no = 10 AND seat = 2").unwrap();
+    assert_eq!(rows, [vec![Value::Integer(302)]]);
+}
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in [
+        "SELECT * FROM users WHERE email = 'user1@example.com'",
+        "SELECT * FROM devices WHERE serial = 'SN5000' OR serial = 'SN1' ORDER BY owner",
+        "SELECT * FROM seats WHERE hall = 'hall0' AND row_no = 3 ORDER BY id",
+        "SELECT * FROM seats WHERE id = 7 AND hall = 'hall0'",
+    ] {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+}
@@ -0,0 +1,93 @@
+//! Tests for DEFAULT values of columns added after rows were written
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::{Affinity, Value};
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE pairs (k TEXT PRIMARY KEY, v INTEGER) WITHOUT ROWID;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500)
+         INSERT INTO items (id, name) SELECT i, 'item ' || i FROM n;
+         INSERT INTO pairs (k, v) VALUES ('a', 1), ('b', 2);
+         ALTER TABLE items ADD COLUMN qty INTEGER DEFAULT 7;
+         ALTER TABLE items ADD COLUMN label TEXT DEFAULT 'none';
+         ALTER TABLE items ADD COLUMN ratio REAL DEFAULT -1.5;
+         ALTER TABLE items ADD COLUMN blank DEFAULT NULL;
+         ALTER TABLE items ADD COLUMN unset TEXT;
+         ALTER TABLE items ADD COLUMN from_text INTEGER DEFAULT '42';
+         ALTER TABLE items ADD COLUMN to_text TEXT DEFAULT 12;
+         ALTER TABLE items ADD COLUMN whole REAL DEFAULT 3;
+         ALTER TABLE items ADD COLUMN decimal NUMERIC DEFAULT '2.50';
+         ALTER TABLE items ADD COLUMN signed DEFAULT +5;
+         ALTER TABLE items ADD COLUMN flag DEFAULT TRUE;
+         ALTER TABLE items ADD COLUMN data BLOB DEFAULT X'CAFE';
+         ALTER TABLE items ADD COLUMN h INT DEFAULT 0x10;
+         ALTER TABLE items ADD COLUMN neg DEFAULT -0x1;
+         ALTER TABLE items ADD COLUMN small_hex INTEGER DEFAULT 0x7FFFFFFF;
+         ALTER TABLE items ADD COLUMN large_hex INTEGER DEFAULT 0xFFFFFFFFFFFFFFFF;
+         ALTER TABLE items ADD COLUMN short_blob DEFAULT x'0a';
+         ALTER TABLE items ADD COLUMN smallest INTEGER DEFAULT -9223372036854775808;
+         ALTER TABLE items ADD COLUMN nested_smallest DEFAULT (-(9223372036854775808));
+         ALTER TABLE items ADD COLUMN past_smallest DEFAULT -9223372036854775809;
+         ALTER TABLE pairs ADD COLUMN note TEXT DEFAULT 'fresh';
+         INSERT INTO items (id, name, qty, label) VALUES (501, 'new', 1, 'set');
+         INSERT INTO pairs (k, v, note) VALUES ('c', 3, 'given');
+         CREATE INDEX idx_items_qty ON items (qty);",
+    )
+    .unwrap();
+});
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in [
+        "SELECT * FROM items ORDER BY id",
+        "SELECT * FROM items WHERE id BETWEEN 10 AND 12 ORDER BY id",
+        "SELECT id, label FROM items WHERE qty = 7 ORDER BY id",
+        "SELECT id, qty FROM items WHERE qty = 1",
+        "SELECT id FROM items WHERE label = 'none' AND ratio < 0 ORDER BY id",
+        "SELECT * FROM pairs ORDER BY k",
+        "SELECT k FROM pairs WHERE note = 'fresh' ORDER BY k",
+        "SELECT id FROM items WHERE smallest = -9223372036854775808 ORDER BY id",
+        "SELECT id, nested_smallest FROM items WHERE smallest < -9223372036854775807 AND id < 5 ORDER BY id",
+        "SELECT id FROM items WHERE past_smallest < -9223372036854775808",
+    ] {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+}
+
+#[test]
+fn test_default_values_follow_affinity() {
+    let db = DB.open();
+    let items = db.table_info("items").unwrap();
+    let default = |name: &str| items.column_info.iter().find(|col| col.name == name).unwrap().default_value().cloned();
+
+    assert_eq!(default("qty"), Some(Value::Integer(7)));
+    assert_eq!(default("from_text"), Some(Value::Integer(42)));
+    assert_eq!(default("to_text"), Some(Value::Text("12".into())));
+    assert_eq!(default("whole"), Some(Value::Real(3.0)));
+    assert_eq!(default("decimal"), Some(Value::Real(2.5)));
+    assert_eq!(default("data"), Some(Value::Blob(vec![0xCA, 0xFE])));
+    assert_eq!(default("h"), Some(Value::Integer(16)));
+    assert_eq!(default("neg"), Some(Value::Integer(-1)));
+    // Past 32 bits SQLite keeps the literal as text
+    assert_eq!(default("small_hex"), Some(Value::Integer(0x7FFF_FFFF)));
+    assert_eq!(default("large_hex"), Some(Value::Text("0xFFFFFFFFFFFFFFFF".into())));
+    let text = |name: &str| items.column_info.iter().find(|col| col.name == name).unwrap().default.clone();
+    assert_eq!(text("h").as_deref(), Some("0x10"));
+    assert_eq!(text("neg").as_deref(), Some("-0x1"));
+    assert_eq!(text("data").as_deref(), Some("X'CAFE'"));
+    assert_eq!(default("short_blob"), Some(Value::Blob(vec![0x0A])));
+    assert_eq!(default("smallest"), Some(Value::Integer(i64::MIN)));
+    assert_eq!(default("nested_smallest"), Some(Value::Integer(i64::MIN)));
+    assert_eq!(default("past_smallest"), Some(Value::Real(-9223372036854775809.0)));
+    assert_eq!(default("blank"), Some(Value::Null));
+    assert_eq!(default("unset"), None);
+
+    assert_eq!(Affinity::Numeric.apply(Value::Text("1e3".into())), Value::Integer(1000));
+    assert_eq!(Affinity::Integer.apply(Value::Text("abc".into())), Value::Text("abc".into()));
+    assert_eq!(Affinity::Text.apply(Value::Real(2.0)), Value::Text("2.0".into()));
+}
@@ -0,0 +1,130 @@
+//! Fixture helpers shared by the integration tests that build databases with rusqlite
+#![allow(dead_code)]
+
+use sqlite_wasm_reader::{BytesSource, Database, OpenOptions, PageSource, ReaderLimits, Result, SelectQuery, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// A database built once per test binary by running `setup` through rusqlite
+pub struct Fixture {
+    setup: fn(&rusqlite::Connection),
+    bytes: OnceLock<Vec<u8>>,
+}
+
+impl Fixture {
+    pub const fn new(setup: fn(&rusqlite::Connection)) -> Self {
+        Fixture { setup, bytes: OnceLock::new() }
+    }
+
+    /// Bytes of the database file
+    pub fn bytes(&self) -> &[u8] {
+        self.bytes.get_or_init(|| {
+            let path = scratch_path("fixture");
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            (self.setup)(&conn);
+            drop(conn);
+            let bytes = std::fs::read(&path).unwrap();
+            let _ = std::fs::remove_file(&path);
+            bytes
+        })
+    }
+
+    pub fn open(&'static self) -> Database {
+        Database::from_bytes(self.bytes()).unwrap()
+    }
+
+    /// A reader that fails any traversal of more than `max_pages` pages, so
+    /// queries over large tables only succeed through seeks
+    pub fn open_seek_only(&self, max_pages: usize) -> Database {
+        self.open_with(&OpenOptions::new().limits(ReaderLimits::unlimited().max_pages_per_traversal(Some(max_pages))))
+    }
+
+    pub fn open_with(&self, options: &OpenOptions) -> Database {
+        Database::from_source_with_options(self.bytes().to_vec(), options).unwrap()
+    }
+
+    /// A reader together with the number of reads it made from the bytes
+    pub fn open_counting(&'static self) -> (Database, Arc<AtomicUsize>) {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let source = CountingSource { inner: BytesSource(self.bytes()), reads: reads.clone() };
+        (Database::from_source(source).unwrap(), reads)
+    }
+
+    /// Run `f` on a rusqlite connection to a copy of the database
+    pub fn with_sqlite<T>(&self, f: impl FnOnce(&rusqlite::Connection) -> T) -> T {
+        let path = scratch_path("expected");
+        std::fs::write(&path, self.bytes()).unwrap();
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        let result = f(&conn);
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    /// Root page of the table or index `name`
+    pub fn root_page(&self, name: &str) -> u32 {
+        self.with_sqlite(|conn| {
+            conn.query_row("SELECT rootpage FROM sqlite_master WHERE name = ?1", [name], |row| row.get(0))
+                .unwrap()
+        })
+    }
+
+    /// Column names and rows SQLite returns for `sql`
+    pub fn expected_result(&self, sql: &str) -> (Vec<String>, Vec<Vec<Value>>) {
+        self.with_sqlite(|conn| {
+            let mut stmt = conn.prepare(sql).unwrap();
+            let names: Vec<String> = stmt.column_names().iter().map(|name| name.to_string()).collect();
+            let rows = stmt
+                .query_map([], |r| (0..names.len()).map(|i| Ok(sqlite_value(r.get_ref(i)?))).collect())
+                .unwrap()
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .unwrap();
+            (names, rows)
+        })
+    }
+
+    /// Rows SQLite returns for `sql`
+    pub fn expected_rows(&self, sql: &str) -> Vec<Vec<Value>> {
+        self.expected_result(sql).1
+    }
+}
+
+/// A path in the working directory that no other fixture of this test binary uses
+fn scratch_path(kind: &str) -> String {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    format!("test_{}_{}_{}.sqlite", kind, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Page source that counts the reads it serves
+struct CountingSource {
+    inner: BytesSource<&'static [u8]>,
+    reads: Arc<AtomicUsize>,
+}
+
+impl PageSource for CountingSource {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.inner.read_at(offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        self.inner.size()
+    }
+}
+
+/// Values of the rows the reader returns for `sql`
+pub fn query(db: &mut Database, sql: &str) -> Result<Vec<Vec<Value>>> {
+    let rows = db.execute_query(&SelectQuery::parse(sql)?)?;
+    Ok(rows.iter().map(|row| row.values().to_vec()).collect())
+}
+
+/// Convert a value read by rusqlite
+pub fn sqlite_value(value: rusqlite::types::ValueRef<'_>) -> Value {
+    match value {
+        rusqlite::types::ValueRef::Null => Value::Null,
+        rusqlite::types::ValueRef::Integer(v) => Value::Integer(v),
+        rusqlite::types::ValueRef::Real(v) => Value::Real(v),
+        rusqlite::types::ValueRef::Text(v) => Value::Text(String::from_utf8(v.to_vec()).unwrap()),
+        rusqlite::types::ValueRef::Blob(v) => Value::Blob(v.to_vec()),
+    }
+}
@@ -0,0 +1,158 @@
+//! Tests for expressions in the SELECT list, WHERE, ORDER BY and GROUP BY,
+//! compared against SQLite for its affinity, collation and NULL rules
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::{Error, SelectQuery, Value};
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT COLLATE NOCASE, code TEXT, qty INTEGER, price REAL, misc, padded TEXT COLLATE RTRIM);
+         CREATE INDEX idx_items_code ON items (code);
+         CREATE INDEX idx_items_name ON items (name);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 300)
+         INSERT INTO items (id, name, code, qty, price, misc, padded)
+         SELECT i,
+                CASE i % 4 WHEN 0 THEN 'Apple' WHEN 1 THEN 'apple' WHEN 2 THEN 'Banana' ELSE NULL END,
+                CASE WHEN i % 13 = 0 THEN NULL ELSE (i % 40) END,
+                CASE WHEN i % 7 = 0 THEN NULL ELSE i % 12 END,
+                CASE WHEN i % 11 = 0 THEN NULL ELSE i * 0.5 END,
+                CASE i % 5 WHEN 0 THEN i WHEN 1 THEN 'x' || i WHEN 2 THEN i * 1.5 WHEN 3 THEN NULL ELSE CAST(i AS TEXT) END,
+                CASE i % 3 WHEN 0 THEN 'pad' WHEN 1 THEN 'pad  ' ELSE 'Pad' END
+         FROM n;",
+    )
+    .unwrap();
+});
+
+const QUERIES: &[&str] = &[
+    // Arithmetic, concatenation and unary operators, with NULL propagating
+    "SELECT id, qty + 1, qty - price, qty * price, price / 2, qty / 5, qty % 5, -qty, +name FROM items",
+    "SELECT id, 7 / 2, 7.0 / 2, 7 % 0, 7 / 0, 1 - NULL, 'a' || NULL, 5 & 3, 5 | 3 FROM items WHERE id < 3",
+    "SELECT id, name || '-' || code, code || qty, misc + 1, misc * 2, '12abc' + 1, 9223372036854775807 + 1 FROM items WHERE id < 40",
+    "SELECT id, NOT qty, NOT NULL, qty AND price, qty OR NULL, NULL AND 0 FROM items WHERE id < 30",
+    // Comparisons between columns and with computed values
+    "SELECT id FROM items WHERE qty > price",
+    "SELECT id FROM items WHERE qty * 2 = code",
+    "SELECT id FROM items WHERE code = qty",
+    "SELECT id, qty < price, qty = code, misc > 100, misc = '5' FROM items",
+    "SELECT id FROM items WHERE price - qty BETWEEN 10 AND 20",
+    "SELECT id FROM items WHERE qty + 0 IN (1, 2, NULL)",
+    "SELECT id FROM items WHERE qty NOT IN (1, 2, NULL)",
+    "SELECT id FROM items WHERE qty NOT IN (1, 2)",
+    // Three-valued logic: NULL compares as unknown, and NOT unknown stays unknown
+    "SELECT id FROM items WHERE qty <> 3",
+    "SELECT id FROM items WHERE NOT (qty = 3)",
+    "SELECT id FROM items WHERE NOT (qty = 3 AND price > 10)",
+    "SELECT id FROM items WHERE NOT (qty > 3 OR code = '5')",
+    "SELECT id FROM items WHERE qty IS DISTINCT FROM 3",
+    "SELECT id FROM items WHERE qty IS NULL OR NOT price > 100",
+    "SELECT id, qty IS DISTINCT FROM code, qty IS NOT DISTINCT FROM NULL, qty IS TRUE, price IS NOT FALSE FROM items",
+    // Affinity: TEXT columns compare as text, numeric columns convert text
+    "SELECT id FROM items WHERE code = 5",
+    "SELECT id FROM items WHERE code > 30",
+    "SELECT id FROM items WHERE code IN (1, 2, 3)",
+    "SELECT id FROM items WHERE qty = '4'",
+    "SELECT id FROM items WHERE price BETWEEN '10' AND '12'",
+    "SELECT id FROM items WHERE misc = 10",
+    "SELECT id FROM items WHERE misc = '10'",
+    "SELECT id FROM items WHERE misc > 'x2'",
+    "SELECT id FROM items WHERE CAST(code AS INTEGER) < 4",
+    "SELECT id FROM items WHERE id = '17'",
+    // Collations of columns and COLLATE clauses
+    "SELECT id FROM items WHERE name = 'APPLE'",
+    "SELECT id FROM items WHERE name = 'APPLE' COLLATE BINARY",
+    "SELECT id FROM items WHERE name COLLATE BINARY = 'apple'",
+    "SELECT id FROM items WHERE padded = 'pad'",
+    "SELECT id FROM items WHERE code COLLATE NOCASE = 'X'",
+    "SELECT id FROM items WHERE name IN ('BANANA')",
+    "SELECT id FROM items WHERE name > 'b'",
+    // CASE, CAST and aliases
+    "SELECT id, CASE WHEN qty > 6 THEN 'high' WHEN qty > 2 THEN 'mid' ELSE 'low' END AS level FROM items",
+    "SELECT id, CASE qty WHEN 1 THEN 'one' WHEN '2' THEN 'two' END, CASE name WHEN 'APPLE' THEN 1 ELSE 0 END FROM items",
+    "SELECT id, CAST(price AS INTEGER), CAST(qty AS REAL), CAST(misc AS TEXT), CAST(misc AS NUMERIC), CAST(code AS BLOB) FROM items",
+    "SELECT id, CAST('3.0' AS NUMERIC), CAST(' 12 ' AS INTEGER), CAST('1e3' AS REAL), CAST(NULL AS TEXT) FROM items WHERE id = 1",
+    "SELECT id AS n, qty * price AS total, name AS name FROM items WHERE qty * price > 100",
+    "SELECT id, qty * 2 AS d FROM items WHERE d > 20",
+    "SELECT id, qty * 2 AS d, name AS label FROM items WHERE d BETWEEN 4 AND 8 AND label = 'APPLE' OR d IN (0, NULL) OR label IS NULL",
+    "SELECT id, qty AS d, code AS c FROM items WHERE d = c OR d NOT IN (1, 2, 3, 4, 5, 6, 7, 8, 9)",
+    "SELECT id, price AS qty FROM items WHERE qty = 3",
+    // Expressions over grouped rows and GROUP BY expressions
+    "SELECT qty % 3, count(*), sum(price) * 2 FROM items GROUP BY qty % 3",
+    "SELECT name, count(*) FROM items GROUP BY name",
+    "SELECT padded, count(*) FROM items GROUP BY padded",
+    "SELECT CASE WHEN qty > 5 THEN 'big' ELSE 'small' END AS size, max(price) - min(price) FROM items GROUP BY size",
+    "SELECT code, count(*) + 1 FROM items GROUP BY code HAVING count(*) * 2 > 14",
+    "SELECT count(*) FROM items HAVING sum(qty) > 1000 OR max(price) IS NULL",
+    "SELECT name, min(code), max(code) FROM items GROUP BY name",
+    "SELECT sum(qty * price), avg(qty + 1), count(DISTINCT qty % 4) FROM items",
+    // `0x` literals are integers, `X'..'` literals blobs
+    "SELECT id, 0x10, typeof(0x10), -0x1, 0x10 + 1, 0xFFFFFFFFFFFFFFFF, X'10', typeof(x'0A') FROM items WHERE id < 3",
+    "SELECT id FROM items WHERE qty = 0x4",
+    "SELECT id FROM items WHERE id = 0x10 OR id = -0x1 + 3",
+    "SELECT id FROM items WHERE qty * 0x2 > 0xA",
+];
+
+/// Queries whose ORDER BY leaves no ties, so the order is SQLite's
+const ORDERED: &[&str] = &[
+    "SELECT id, misc FROM items WHERE misc IS NOT NULL ORDER BY misc",
+    "SELECT id, misc FROM items WHERE misc IS NOT NULL ORDER BY 2 DESC LIMIT 20",
+    "SELECT id FROM items ORDER BY -id LIMIT 5",
+    "SELECT id, price FROM items WHERE price IS NOT NULL ORDER BY price * -1 LIMIT 10",
+    "SELECT id * 2 AS double FROM items ORDER BY double DESC LIMIT 3",
+    "SELECT id, code || '' AS text FROM items WHERE id < 30 AND code IS NOT NULL ORDER BY text",
+    "SELECT qty % 5, count(*) FROM items GROUP BY qty % 5 ORDER BY 1",
+    "SELECT qty, sum(price) FROM items WHERE qty IS NOT NULL GROUP BY qty ORDER BY qty * -1",
+    "SELECT code, count(*) AS n FROM items GROUP BY code ORDER BY code COLLATE NOCASE DESC",
+    "SELECT padded || id AS label FROM items WHERE id < 20 ORDER BY label COLLATE NOCASE",
+];
+
+/// SQLite may return rows without ORDER BY in another order, so they are
+/// compared sorted
+fn sorted(mut rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    rows.sort();
+    rows
+}
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in QUERIES {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(sorted(rows), sorted(DB.expected_rows(sql)), "{}", sql);
+    }
+    for sql in ORDERED {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(rows, DB.expected_rows(sql), "{}", sql);
+    }
+}
+
+#[test]
+fn test_expression_names_and_errors() {
+    let mut db = DB.open();
+    let rows = db.execute_query(&SelectQuery::parse("SELECT qty * 2, name AS label, CAST(id AS TEXT) FROM items WHERE id = 2").unwrap()).unwrap();
+    assert_eq!(rows.columns(), ["qty * 2", "label", "CAST(id AS TEXT)"]);
+    assert_eq!(rows[0].values(), [Value::Integer(4), Value::Text("Banana".into()), Value::Text("2".into())]);
+
+    assert!(matches!(query(&mut db, "SELECT qty + missing FROM items"), Err(Error::ColumnNotFound(_))));
+    assert!(matches!(query(&mut db, "SELECT id FROM items WHERE missing * 2 > 1"), Err(Error::ColumnNotFound(_))));
+    for sql in [
+        "SELECT id FROM items WHERE nosuch = 1",
+        "SELECT id FROM items WHERE qty = nosuch",
+        "SELECT id FROM items WHERE nosuch IS NULL",
+        "SELECT id FROM items WHERE nosuch IN (1, 2)",
+        "SELECT id FROM items WHERE id < 0 AND nosuch BETWEEN 1 AND 2",
+    ] {
+        assert!(matches!(query(&mut db, sql), Err(Error::ColumnNotFound(_))), "{}", sql);
+    }
+    assert!(matches!(query(&mut db, "SELECT count(*) AS n FROM items WHERE n > 1"), Err(Error::QueryError(_))));
+    for sql in [
+        "SELECT id FROM items WHERE sum(qty) > 1",
+        "SELECT id FROM items ORDER BY count(*)",
+        "SELECT id FROM items ORDER BY 0",
+        "SELECT name COLLATE unknown FROM items",
+        "SELECT qty << 1 FROM items",
+    ] {
+        assert!(matches!(SelectQuery::parse(sql), Err(Error::QueryError(_))), "{}", sql);
+    }
+}
@@ -0,0 +1,156 @@
+//! Tests for GROUP BY and HAVING, across hash, ordered and multi-pass grouping
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::query::Expr;
+use sqlite_wasm_reader::{Error, OpenOptions, ScalarExpr, SelectQuery, Value};
+use std::sync::atomic::Ordering;
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE sales (id INTEGER PRIMARY KEY, country TEXT, city TEXT COLLATE NOCASE, qty INTEGER, price REAL, note);
+         CREATE INDEX idx_sales_qty ON sales (qty);
+         CREATE INDEX idx_sales_place ON sales (city, country);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 5000)
+         INSERT INTO sales (id, country, city, qty, price, note)
+         SELECT i,
+                CASE i % 4 WHEN 0 THEN 'fr' WHEN 1 THEN 'de' WHEN 2 THEN 'us' ELSE NULL END,
+                CASE i % 6 WHEN 0 THEN 'Paris' WHEN 1 THEN 'PARIS' WHEN 2 THEN 'Berlin' WHEN 3 THEN 'berlin' WHEN 4 THEN 'Austin' ELSE NULL END,
+                CASE WHEN i % 13 = 0 THEN NULL ELSE i % 97 END,
+                i * 0.25,
+                'note ' || (i % 300)
+         FROM n;
+         CREATE TABLE mixed (k, v INTEGER);
+         INSERT INTO mixed (k, v) VALUES (1, 1), (1.0, 2), ('1', 3), (NULL, 4), (NULL, 5), (2.5, 6), (X'01', 7), ('a', 8), (2.5, 9);
+         CREATE TABLE empty (x INTEGER, y TEXT);",
+    )
+    .unwrap();
+});
+
+const QUERIES: &[&str] = &[
+    "SELECT country, COUNT(*), AVG(qty) FROM sales GROUP BY country HAVING COUNT(*) > 10",
+    "SELECT country, city, count(*), sum(qty), max(price) FROM sales GROUP BY country, city",
+    "SELECT city, country, count(qty) FROM sales GROUP BY 2, 1",
+    "SELECT count(*), min(id) FROM sales GROUP BY city",
+    "SELECT country AS c, total(price) FROM sales GROUP BY c",
+    "SELECT qty, count(*) FROM sales GROUP BY qty",
+    "SELECT qty, count(*), group_concat(id) FROM sales WHERE id < 300 GROUP BY qty HAVING count(*) > 3",
+    "SELECT note, count(DISTINCT country), sum(qty) FROM sales GROUP BY note",
+    "SELECT country FROM sales GROUP BY country",
+    "SELECT country FROM sales GROUP BY country HAVING sum(qty) > 60000",
+    "SELECT country FROM sales GROUP BY country HAVING sum(qty) > sum(price)",
+    "SELECT count(*) FROM sales GROUP BY country HAVING country IS NOT NULL AND country <> 'fr'",
+    "SELECT country, count(*) AS n FROM sales WHERE qty > 50 GROUP BY country HAVING n > 600 AND max(qty) = 96",
+    "SELECT country, max(price), id FROM sales GROUP BY country",
+    // Bare columns come from the first row of a group unless MIN or MAX picks another
+    "SELECT country, note, count(*) FROM sales GROUP BY country",
+    "SELECT city, note, max(qty), min(price) FROM sales GROUP BY city",
+    "SELECT id, count(*) FROM sales WHERE id BETWEEN 10 AND 20 GROUP BY id",
+    "SELECT rowid, qty FROM sales WHERE rowid < 5 GROUP BY rowid",
+    "SELECT note, sum(qty) AS total FROM sales GROUP BY note ORDER BY total DESC LIMIT 5",
+    "SELECT country, count(*) FROM sales GROUP BY country ORDER BY count(*)",
+    "SELECT qty, count(*) FROM sales GROUP BY qty ORDER BY qty DESC LIMIT 3",
+    "SELECT qty, 'x', 7 FROM sales WHERE qty < 3 GROUP BY qty",
+    "SELECT count(*) FROM sales HAVING count(*) > 100000",
+    "SELECT count(*) FROM sales HAVING count(*) < 100000",
+    "SELECT k, count(*), sum(v) FROM mixed GROUP BY k",
+    "SELECT count(*), sum(x) FROM empty GROUP BY y",
+    "SELECT y, count(*) FROM empty GROUP BY y HAVING count(*) >= 0",
+];
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in QUERIES {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+}
+
+#[test]
+fn test_aggregating_in_passes_matches_hashing() {
+    // A hash table of three groups forces most queries to aggregate in passes,
+    // up to one per three of the 300 notes
+    let options = OpenOptions::new().max_hash_groups(3).max_group_passes(100);
+    let mut db = DB.open_with(&options);
+    for sql in QUERIES {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+
+    // Each pass returns its groups before the next one starts
+    let options = OpenOptions::new().max_hash_groups(1).max_group_passes(300);
+    let mut db = DB.open_with(&options);
+    let query = SelectQuery::parse("SELECT note, count(*) FROM sales GROUP BY note").unwrap();
+    let first: Vec<_> = db.query_iter(&query).unwrap().take(2).map(|row| row.unwrap().values().to_vec()).collect();
+    assert_eq!(first, DB.expected_rows("SELECT note, count(*) FROM sales GROUP BY note LIMIT 2"));
+
+    // Queries that need more passes than allowed fail rather than read the rows again and again
+    let mut db = DB.open_with(&OpenOptions::new().max_hash_groups(100).max_group_passes(3));
+    assert_eq!(db.execute_query(&query).unwrap().len(), 300);
+    let mut db = DB.open_with(&OpenOptions::new().max_hash_groups(100).max_group_passes(2));
+    let rows: Vec<_> = db.query_iter(&query).unwrap().collect();
+    assert_eq!(rows.iter().take_while(|row| row.is_ok()).count(), 100);
+    assert!(matches!(rows[100], Err(Error::LimitExceeded { limit: "max_group_passes", max: 2 })));
+}
+
+#[test]
+fn test_groups_stream_from_ordered_index() {
+    // Full scans fail, so only queries grouping as they read an index succeed
+    let mut db = DB.open_seek_only(8);
+    for sql in [
+        "SELECT qty, count(*) FROM sales GROUP BY qty LIMIT 3",
+        "SELECT qty, count(*), sum(price) FROM sales GROUP BY qty LIMIT 2",
+        "SELECT city, country, count(*) FROM sales GROUP BY country, city LIMIT 2",
+    ] {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(rows, DB.expected_rows(sql), "{}", sql);
+    }
+    for sql in [
+        "SELECT note, count(*) FROM sales GROUP BY note LIMIT 1",
+        "SELECT country, count(*) FROM sales GROUP BY country LIMIT 1",
+    ] {
+        assert!(
+            matches!(query(&mut db, sql), Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. })),
+            "{}",
+            sql
+        );
+    }
+
+    // Counting the groups of an index reads the index alone
+    let (mut db, reads) = DB.open_counting();
+    let before = reads.load(Ordering::Relaxed);
+    let sql = "SELECT qty, count(*) FROM sales GROUP BY qty";
+    assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql));
+    let covered = reads.load(Ordering::Relaxed) - before;
+    let before = reads.load(Ordering::Relaxed);
+    let sql = "SELECT note, count(*) FROM sales GROUP BY note";
+    assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql));
+    let scanned = reads.load(Ordering::Relaxed) - before;
+    assert!(covered < scanned, "index read with {} reads, table with {}", covered, scanned);
+}
+
+#[test]
+fn test_builder_and_invalid_queries() {
+    let mut db = DB.open();
+    let built = SelectQuery::new("sales")
+        .select_columns(vec!["country".into()])
+        .with_group_by(vec![ScalarExpr::Column("country".into())])
+        .with_having(Expr::gt("count(*)", Value::Integer(1000)))
+        .with_order_by("sum(qty)", false);
+    let rows = db.execute_query(&built).unwrap();
+    assert_eq!(rows.columns(), ["country"]);
+    let rows: Vec<_> = rows.iter().map(|row| row.values().to_vec()).collect();
+    assert_eq!(rows, DB.expected_rows("SELECT country FROM sales GROUP BY country HAVING count(*) > 1000 ORDER BY sum(qty) DESC"));
+
+    assert!(matches!(query(&mut db, "SELECT count(*) FROM sales GROUP BY missing"), Err(Error::ColumnNotFound(_))));
+    assert!(matches!(query(&mut db, "SELECT count(*) FROM sales GROUP BY country HAVING missing > 1"), Err(Error::ColumnNotFound(_))));
+    for sql in [
+        "SELECT country, count(*) FROM sales GROUP BY 3",
+        "SELECT country, count(*) FROM sales GROUP BY count(*)",
+        "SELECT country, count(*) AS n FROM sales GROUP BY 2",
+        "SELECT country FROM sales HAVING country = 'fr'",
+        "SELECT country FROM sales ORDER BY count(*)",
+    ] {
+        assert!(matches!(SelectQuery::parse(sql), Err(Error::QueryError(_))), "{}", sql);
+    }
+}
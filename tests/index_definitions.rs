@@ -0,0 +1,75 @@
+//! Tests for partial, expression, COLLATE and DESC indexes in the query planner
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::{Error, Value};
+
+const ROWS: i64 = 4000;
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(&format!(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, category TEXT, deleted INTEGER, price REAL);
+         CREATE INDEX idx_live_category ON items (category) WHERE deleted = 0;
+         CREATE INDEX idx_cheap_price ON items (price) WHERE price < 10.0 AND price IS NOT NULL;
+         CREATE INDEX idx_lower_name ON items (lower(name));
+         CREATE INDEX idx_name_nocase ON items (name COLLATE NOCASE);
+         CREATE INDEX idx_price_desc ON items (price DESC);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {})
+         INSERT INTO items (id, name, category, deleted, price)
+         SELECT i, CASE WHEN i % 2 THEN 'Item' || i ELSE 'item' || i END, 'cat' || (i % 400), i % 3 = 0, i / 4.0 FROM n;",
+        ROWS
+    ))
+    .unwrap();
+});
+
+fn is_scan(result: Result<Vec<Vec<Value>>, Error>) -> bool {
+    matches!(result, Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. }))
+}
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in [
+        // Deleted rows are missing from the partial index
+        "SELECT id FROM items WHERE category = 'cat7' ORDER BY id",
+        "SELECT id FROM items WHERE category = 'cat7' AND deleted = 0 ORDER BY id",
+        "SELECT id FROM items WHERE category = 'cat7' AND deleted = 1 ORDER BY id",
+        "SELECT id FROM items WHERE price = 3.5 ORDER BY id",
+        "SELECT id FROM items WHERE price = 130.5 ORDER BY id",
+        "SELECT id FROM items WHERE price = 3.5 AND price < 5 ORDER BY id",
+        // Binary comparison, whatever the NOCASE index holds
+        "SELECT id FROM items WHERE name = 'Item41' ORDER BY id",
+        "SELECT id FROM items WHERE name = 'item41' ORDER BY id",
+        // Branches without a usable index still return their rows
+        "SELECT id FROM items WHERE (category = 'cat3' AND deleted = 0) OR name = 'item100' ORDER BY id",
+    ] {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+}
+
+#[test]
+fn test_partial_indexes_need_an_implied_predicate() {
+    let mut db = DB.open_seek_only(4);
+
+    let rows = query(&mut db, "SELECT id FROM items WHERE category = 'cat7' AND deleted = 0").unwrap();
+    assert_eq!(rows.len(), DB.expected_rows("SELECT id FROM items WHERE category = 'cat7' AND deleted = 0").len());
+    assert!(is_scan(query(&mut db, "SELECT id FROM items WHERE category = 'cat7'")));
+    assert!(is_scan(query(&mut db, "SELECT id FROM items WHERE category = 'cat7' AND deleted = 1")));
+
+    // Equality and tighter bounds imply the range predicate
+    assert!(query(&mut db, "SELECT id FROM items WHERE price = 3.5").is_ok());
+    assert!(query(&mut db, "SELECT id FROM items WHERE price = 3.5 AND price <= 9.5").is_ok());
+    assert!(is_scan(query(&mut db, "SELECT id FROM items WHERE price = 130.5")));
+}
+
+#[test]
+fn test_expression_collate_and_desc_keys_are_not_column_seeks() {
+    let mut db = DB.open_seek_only(4);
+    for sql in [
+        "SELECT id FROM items WHERE name = 'Item41'",
+        "SELECT id FROM items WHERE price = 130.5",
+    ] {
+        assert!(is_scan(query(&mut db, sql)), "{}", sql);
+    }
+}
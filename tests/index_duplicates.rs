@@ -0,0 +1,80 @@
+//! Tests for index seeks on keys duplicated across many index pages
+
+mod common;
+
+use common::Fixture;
+use sqlite_wasm_reader::{BTreeCursor, Database, PageReader, SelectQuery, Value};
+
+const ROWS: i64 = 60_000;
+
+// Long tags keep few entries per page, so each key spans many leaves and
+// interior cells
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(&format!(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, bucket INTEGER, tag TEXT, payload TEXT);
+         CREATE INDEX idx_items_bucket ON items (bucket);
+         CREATE INDEX idx_items_bucket_tag ON items (bucket, tag);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {})
+         INSERT INTO items (id, bucket, tag, payload)
+         SELECT i, i % 7, 'tag-' || (i % 3) || '-' || printf('%.60d', 0), 'payload ' || i FROM n;",
+        ROWS
+    ))
+    .unwrap();
+});
+
+fn tag(i: i64) -> String {
+    format!("tag-{}-{}", i, "0".repeat(60))
+}
+
+/// Rowids of the rows matching `bucket` and, if given, `tag`, in rowid order
+fn expected_rowids(bucket: i64, tag_of: Option<i64>) -> Vec<i64> {
+    (1..=ROWS).filter(|i| i % 7 == bucket && tag_of.is_none_or(|t| i % 3 == t)).collect()
+}
+
+fn find(db: &mut Database, root: u32, key: &[Value]) -> Vec<i64> {
+    let page = db.read_page(root).unwrap();
+    let key: Vec<&Value> = key.iter().collect();
+    let mut rowids = BTreeCursor::new(page).find_rowids_by_key(&key, db).unwrap();
+    rowids.sort();
+    rowids
+}
+
+#[test]
+fn test_find_rowids_by_key_finds_every_duplicate() {
+    let mut db = DB.open();
+    let (bucket_root, composite_root) = (DB.root_page("idx_items_bucket"), DB.root_page("idx_items_bucket_tag"));
+
+    for bucket in 0..7 {
+        assert_eq!(find(&mut db, bucket_root, &[Value::Integer(bucket)]), expected_rowids(bucket, None));
+
+        // A prefix of the composite index, and the full key
+        assert_eq!(find(&mut db, composite_root, &[Value::Integer(bucket)]), expected_rowids(bucket, None));
+        for t in 0..3 {
+            assert_eq!(
+                find(&mut db, composite_root, &[Value::Integer(bucket), Value::Text(tag(t))]),
+                expected_rowids(bucket, Some(t))
+            );
+        }
+    }
+
+    assert!(find(&mut db, bucket_root, &[Value::Integer(7)]).is_empty());
+    assert!(find(&mut db, bucket_root, &[Value::Integer(-1)]).is_empty());
+    assert!(find(&mut db, composite_root, &[Value::Integer(3), Value::Text("tag-9".into())]).is_empty());
+}
+
+/// Rowids of the rows `sql` returns, which come in index order, sorted
+fn query_rowids(db: &mut Database, sql: &str) -> Vec<i64> {
+    let query = SelectQuery::parse(sql).unwrap();
+    let mut ids: Vec<i64> = db.execute_query(&query).unwrap().iter().map(|row| row["id"].as_integer().unwrap()).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_queries_return_every_duplicate() {
+    let mut db = DB.open();
+
+    assert_eq!(query_rowids(&mut db, "SELECT id FROM items WHERE bucket = 4"), expected_rowids(4, None));
+    let sql = format!("SELECT id FROM items WHERE bucket = 2 AND tag = '{}'", tag(1));
+    assert_eq!(query_rowids(&mut db, &sql), expected_rowids(2, Some(1)));
+}
@@ -0,0 +1,133 @@
+//! Tests for index range scans: comparisons, BETWEEN and prefix LIKE
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::{Database, Error, SelectQuery};
+use std::sync::atomic::Ordering;
+
+const ROWS: i64 = 20_000;
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(&format!(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, ts INTEGER, kind TEXT, name TEXT, score REAL,
+                              code TEXT, label TEXT COLLATE NOCASE);
+         CREATE INDEX idx_events_ts ON events (ts);
+         CREATE INDEX idx_events_kind_ts ON events (kind, ts);
+         CREATE INDEX idx_events_name ON events (name);
+         CREATE INDEX idx_events_code ON events (code);
+         CREATE INDEX idx_events_label ON events (label);
+         CREATE TABLE tags (id INTEGER PRIMARY KEY, tag TEXT);
+         CREATE INDEX idx_tags_tag ON tags (tag);
+         INSERT INTO tags (id, tag) VALUES (1, 'a'), (2, 'b'), (3, 'c');
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {})
+         INSERT INTO events (id, ts, kind, name, score, code, label)
+         SELECT i,
+                CASE WHEN i % 97 = 0 THEN NULL ELSE 1700000000 + (i * 7919) % {} END,
+                'kind' || (i % 7),
+                CASE WHEN i % 5 = 0 THEN 'Alpha' || i WHEN i % 5 = 1 THEN 'alpha' || i ELSE 'beta_' || i END,
+                i / 8.0,
+                printf('%05d-%s', (i * 37) % 100000, CASE WHEN i % 2 THEN 'x' ELSE 'X' END),
+                CASE i % 3 WHEN 0 THEN 'A' || i WHEN 1 THEN 'a' || i ELSE 'b' || i END
+         FROM n;",
+        ROWS, ROWS
+    ))
+    .unwrap();
+});
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in [
+        "SELECT id, ts FROM events WHERE ts > 1700019950 ORDER BY id",
+        "SELECT id, ts FROM events WHERE ts >= 1700019950 ORDER BY id",
+        "SELECT id, ts FROM events WHERE ts < 1700000040 ORDER BY id",
+        "SELECT id, ts FROM events WHERE ts <= 1700000040 ORDER BY id",
+        "SELECT id, ts FROM events WHERE ts BETWEEN 1700005000 AND 1700005100 ORDER BY id",
+        "SELECT id, ts FROM events WHERE ts > 1700005000 AND ts < 1700005100 ORDER BY id",
+        "SELECT id, ts FROM events WHERE 1700005100 >= ts AND ts > 1700005000 ORDER BY id",
+        "SELECT id, ts FROM events WHERE ts > 1700030000 ORDER BY id",
+        "SELECT id FROM events WHERE kind = 'kind3' AND ts BETWEEN 1700010000 AND 1700010500 ORDER BY id",
+        "SELECT id FROM events WHERE kind = 'kind3' AND ts > 1700019000 ORDER BY id",
+        "SELECT id FROM events WHERE kind = 'kind3' ORDER BY id",
+        "SELECT id, name FROM events WHERE name LIKE 'alpha12%' ORDER BY id",
+        "SELECT id, name FROM events WHERE name LIKE 'Alpha1_0' ORDER BY id",
+        "SELECT id, name FROM events WHERE name LIKE 'zeta%' ORDER BY id",
+        "SELECT id, name FROM events WHERE name LIKE 'ALPHA99%' ORDER BY id",
+        "SELECT id, code FROM events WHERE code LIKE '0012%' ORDER BY id",
+        "SELECT id, code FROM events WHERE code LIKE '00123-x' ORDER BY id",
+        "SELECT id, ts FROM events WHERE ts LIKE '1700001%' ORDER BY id",
+        "SELECT id, label FROM events WHERE label LIKE 'a1%' ORDER BY id",
+        "SELECT id FROM events WHERE ts < 1700000010 OR ts > 1700019990 ORDER BY id",
+        "SELECT id FROM events WHERE ts IS NULL ORDER BY id",
+    ] {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+}
+
+#[test]
+fn test_ranges_use_indexes() {
+    let mut db = DB.open_seek_only(8);
+    for sql in [
+        "SELECT id FROM events WHERE ts BETWEEN 1700005000 AND 1700005100",
+        "SELECT id FROM events WHERE ts > 1700019950",
+        "SELECT id FROM events WHERE ts <= 1700000040",
+        "SELECT id FROM events WHERE kind = 'kind3' AND ts BETWEEN 1700010000 AND 1700010500",
+        "SELECT id FROM events WHERE code LIKE '0012%'",
+        "SELECT id FROM events WHERE code LIKE '001%-X'",
+        "SELECT id FROM events WHERE ts < 1700000010 OR ts > 1700019990",
+    ] {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(rows.len(), DB.expected_rows(sql).len(), "{}", sql);
+    }
+
+    // Unindexed columns, patterns starting with a wildcard or a letter, and
+    // LIKE on columns that hold numbers or use another collation still scan
+    for sql in [
+        "SELECT id FROM events WHERE score > 10",
+        "SELECT id FROM events WHERE name LIKE '%9'",
+        "SELECT id FROM events WHERE name LIKE 'alpha12%'",
+        "SELECT id FROM events WHERE ts LIKE '1700001%'",
+        "SELECT id FROM events WHERE label LIKE 'a1%'",
+    ] {
+        assert!(
+            matches!(query(&mut db, sql), Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. })),
+            "{}",
+            sql
+        );
+    }
+}
+
+#[test]
+fn test_ranges_stream_from_the_index() {
+    // Rows are looked up as the index cursor reaches their entries, so the
+    // first row comes before the rest of the range is read
+    let (mut db, reads) = DB.open_counting();
+    let query = SelectQuery::parse("SELECT id, ts FROM events WHERE ts > 1700000000").unwrap();
+    let mut rows = db.query_iter(&query).unwrap();
+    assert!(rows.next().unwrap().is_ok());
+    let first = reads.load(Ordering::Relaxed);
+    assert_eq!(rows.count(), DB.expected_rows("SELECT id FROM events WHERE ts > 1700000000").len() - 1);
+    let all = reads.load(Ordering::Relaxed);
+    assert!(first * 10 < all, "{} reads for the first row, {} for all", first, all);
+}
+
+#[test]
+fn test_bad_index_entries_are_errors() {
+    let page_size = u16::from_be_bytes([DB.bytes()[16], DB.bytes()[17]]) as usize;
+    let header = (DB.root_page("idx_tags_tag") as usize - 1) * page_size;
+    assert_eq!(DB.bytes()[header], 0x0a, "expected a leaf index page");
+    let cell = header + u16::from_be_bytes([DB.bytes()[header + 8], DB.bytes()[header + 9]]) as usize;
+    // The first cell holds its payload size, then the record header size and
+    // the serial types of the tag 'a' and the rowid 1; a NULL rowid or a
+    // header longer than the record make the entry unreadable
+    assert_eq!(DB.bytes()[cell + 1..cell + 4], [3, 15, 9]);
+
+    for (offset, byte) in [(3, 0), (1, 100)] {
+        let mut bytes = DB.bytes().to_vec();
+        bytes[cell + offset] = byte;
+        let mut db = Database::from_bytes(bytes).unwrap();
+        let result = query(&mut db, "SELECT id FROM tags WHERE tag <= 'c'");
+        assert!(matches!(result, Err(Error::InvalidFormat(_))), "{:?}", result);
+    }
+}
@@ -1,5 +1,7 @@
 //! Integration tests for sqlite_wasm_reader
 
+#![allow(clippy::approx_constant)]
+
 use sqlite_wasm_reader::{Database, Error, Value, SelectQuery};
 use std::fs;
 use std::process::Command;
@@ -0,0 +1,193 @@
+//! Tests for joins, compared against SQLite, and the lookups that serve them
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::query::Expr;
+use sqlite_wasm_reader::{ComparisonOperator, Error, Join, SelectQuery, Value};
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT, city TEXT, tier INTEGER);
+         CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER, product_code TEXT, qty INTEGER, note TEXT);
+         CREATE INDEX idx_orders_customer ON orders (customer_id);
+         CREATE TABLE products (code TEXT PRIMARY KEY, title TEXT, price REAL) WITHOUT ROWID;
+         CREATE TABLE tiers (level INTEGER, label TEXT);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000)
+         INSERT INTO customers (id, name, city, tier)
+         SELECT i, 'customer ' || i,
+                CASE i % 3 WHEN 0 THEN 'Lyon' WHEN 1 THEN 'Oslo' ELSE NULL END,
+                CASE WHEN i % 11 = 0 THEN NULL ELSE i % 5 END
+         FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 3000)
+         INSERT INTO orders (id, customer_id, product_code, qty, note)
+         SELECT i,
+                CASE WHEN i % 17 = 0 THEN NULL ELSE (i * 7) % 2100 END,
+                'p' || (i % 25),
+                i % 9,
+                'order note ' || i
+         FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < 19)
+         INSERT INTO products (code, title, price) SELECT 'p' || i, 'product ' || i, i * 1.25 FROM n;
+         INSERT INTO tiers (level, label) VALUES (0, 'basic'), (1, 'silver'), (2, 'gold'), (3, 'platinum'), (3, 'platinum plus');",
+    )
+    .unwrap();
+});
+
+/// SQLite may reorder the tables of a join, so rows without ORDER BY are
+/// compared in sorted order
+fn sorted(mut rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    rows.sort();
+    rows
+}
+
+const QUERIES: &[&str] = &[
+    // Looked up by INTEGER PRIMARY KEY, index, WITHOUT ROWID PRIMARY KEY and hash table
+    "SELECT o.id, c.name FROM orders o JOIN customers c ON o.customer_id = c.id",
+    "SELECT c.id, o.id, o.qty FROM customers c JOIN orders o ON o.customer_id = c.id WHERE c.id < 40",
+    "SELECT o.id, p.title, p.price FROM orders o INNER JOIN products p ON p.code = o.product_code",
+    "SELECT c.name, t.label FROM customers c JOIN tiers t ON t.level = c.tier WHERE c.city = 'Lyon'",
+    // LEFT joins keep rows without a match
+    "SELECT c.name, t.label FROM customers c LEFT JOIN tiers t ON t.level = c.tier",
+    "SELECT c.id, o.id FROM customers c LEFT OUTER JOIN orders o ON o.customer_id = c.id WHERE c.id > 1900",
+    "SELECT c.id, o.id FROM customers c LEFT JOIN orders o ON o.customer_id = c.id AND o.qty > 5 WHERE o.id IS NULL",
+    "SELECT o.id, c.id FROM orders o LEFT JOIN customers c ON c.id = o.customer_id WHERE c.id IS NULL",
+    "SELECT t.label, c.id FROM tiers t LEFT JOIN customers c ON c.tier = t.level AND c.id < 30",
+    // Cross joins, written out or with commas
+    "SELECT * FROM tiers CROSS JOIN products WHERE price > 20",
+    "SELECT t.label, p.code FROM tiers t, products p WHERE p.code = 'p3'",
+    "SELECT o.id, c.name FROM orders o, customers c WHERE o.customer_id = c.id AND c.tier = 2",
+    "SELECT o.id, o.qty * p.price AS total FROM orders o JOIN products p ON p.code = o.product_code WHERE total > 50",
+    "SELECT t.label, u.label FROM tiers t JOIN tiers u",
+    // More than two tables
+    "SELECT c.name, o.id, p.title FROM customers c JOIN orders o ON o.customer_id = c.id JOIN products p ON p.code = o.product_code
+     WHERE c.city = 'Lyon' AND o.qty >= 3",
+    "SELECT c.id, o.id, p.title FROM customers c LEFT JOIN orders o ON o.customer_id = c.id
+     LEFT JOIN products p ON p.code = o.product_code WHERE c.id BETWEEN 1800 AND 2000",
+    "SELECT o.id, p.code, t.label FROM orders o JOIN customers c ON c.id = o.customer_id
+     LEFT JOIN products p ON p.code = o.product_code JOIN tiers t ON t.level = c.tier WHERE o.id < 200",
+    // Star, qualified stars and unqualified names that belong to one table
+    "SELECT o.*, c.name FROM orders o JOIN customers c ON c.id = o.customer_id WHERE o.id <= 50",
+    "SELECT * FROM orders o JOIN customers c ON c.id = o.customer_id WHERE o.qty = 8 AND c.tier = 1",
+    "SELECT name, qty FROM orders JOIN customers ON customer_id = customers.id WHERE qty = 7",
+    // Comparisons between columns, and a table joined to itself
+    "SELECT o.id, c.id FROM orders o JOIN customers c ON c.id = o.customer_id WHERE o.qty > c.tier",
+    "SELECT a.id, b.id FROM customers a JOIN customers b ON a.tier = b.tier WHERE a.id < 5 AND b.id < 12",
+    "SELECT a.id, b.id FROM customers a JOIN customers b ON b.id > a.id WHERE a.id > 1995",
+    "SELECT o.rowid, c.oid FROM orders o JOIN customers c ON c.rowid = o.customer_id WHERE o.id < 10",
+    // Expressions over the columns of several tables
+    "SELECT o.id, o.qty * p.price AS cost FROM orders o JOIN products p ON p.code = o.product_code WHERE o.qty * p.price > 40",
+    "SELECT c.id, o.id FROM customers c JOIN orders o ON o.customer_id = c.id AND o.qty + c.tier > 10 WHERE c.id < 300",
+    "SELECT c.name || ' in ' || c.city, CASE WHEN t.level IS NULL THEN 'none' ELSE t.label END FROM customers c
+     LEFT JOIN tiers t ON t.level = c.tier WHERE c.id % 100 = 1",
+    "SELECT p.code, sum(o.qty * p.price) FROM orders o JOIN products p ON p.code = o.product_code GROUP BY p.code HAVING sum(o.qty) * 2 > 1000",
+    // Aggregates over joined rows
+    "SELECT c.city, count(*), sum(o.qty) FROM customers c JOIN orders o ON o.customer_id = c.id GROUP BY c.city",
+    "SELECT c.name, count(o.id) FROM customers c LEFT JOIN orders o ON o.customer_id = c.id GROUP BY c.id HAVING count(o.id) = 0",
+    "SELECT count(*), max(p.price) FROM orders o JOIN products p ON p.code = o.product_code",
+    "SELECT t.label, count(c.id), min(name) FROM tiers t LEFT JOIN customers c ON c.tier = t.level GROUP BY t.label",
+    "SELECT o.product_code FROM orders o JOIN products p ON p.code = o.product_code GROUP BY o.product_code HAVING sum(o.qty) > 500",
+    // Single tables named by an alias
+    "SELECT u.name, u.city FROM customers u WHERE u.id = 3 OR u.id = 9",
+    "SELECT c.* FROM customers c WHERE c.id < 4",
+    "SELECT count(c.id), max(c.tier) FROM customers AS c",
+];
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in QUERIES {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(sorted(rows), sorted(DB.expected_rows(sql)), "{}", sql);
+    }
+
+    // ORDER BY and LIMIT apply to the joined rows
+    for sql in [
+        "SELECT o.id, c.name FROM orders o JOIN customers c ON o.customer_id = c.id ORDER BY o.id DESC LIMIT 10",
+        "SELECT c.name, o.note FROM orders o JOIN customers c ON o.customer_id = c.id WHERE c.id = 7 ORDER BY note",
+        "SELECT c.city, count(*) AS n FROM customers c JOIN orders o ON o.customer_id = c.id GROUP BY c.city ORDER BY n",
+        "SELECT o.id, o.id * 10 + o.qty AS score FROM orders o JOIN customers c ON o.customer_id = c.id WHERE c.id < 50 ORDER BY score",
+        "SELECT o.id FROM orders o JOIN customers c ON o.customer_id = c.id WHERE c.tier = 2 ORDER BY o.id * -1 LIMIT 7",
+    ] {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+    let sql = "SELECT o.id FROM orders o JOIN customers c ON o.customer_id = c.id LIMIT 25";
+    assert_eq!(query(&mut db, sql).unwrap().len(), 25);
+}
+
+#[test]
+fn test_column_names_and_builder() {
+    let mut db = DB.open();
+    let rows = db
+        .execute_query(&SelectQuery::parse("SELECT o.id, c.name, c.id FROM orders o JOIN customers c ON o.customer_id = c.id WHERE o.id = 1").unwrap())
+        .unwrap();
+    // Qualified columns are named without their qualifier, as in SQLite
+    assert_eq!(rows.columns(), ["id", "name", "id"]);
+    assert_eq!(rows[0]["name"], Value::Text("customer 7".into()));
+
+    let built = SelectQuery::new("orders")
+        .with_alias("o")
+        .with_join(
+            Join::left("customers")
+                .with_alias("c")
+                .with_on(Expr::compare_columns("o.customer_id", ComparisonOperator::Equal, "c.id")),
+        )
+        .select_columns(vec!["o.id".into(), "c.name".into()])
+        .with_where(Expr::lt("o.id", Value::Integer(40)));
+    let rows: Vec<_> = db.execute_query(&built).unwrap().iter().map(|row| row.values().to_vec()).collect();
+    let sql = "SELECT o.id, c.name FROM orders o LEFT JOIN customers c ON o.customer_id = c.id WHERE o.id < 40";
+    assert_eq!(sorted(rows), sorted(DB.expected_rows(sql)));
+}
+
+#[test]
+fn test_joined_tables_are_sought() {
+    // Full scans fail, so only joins that look up each joined row succeed
+    let mut db = DB.open_seek_only(8);
+    for sql in [
+        "SELECT o.id, c.name FROM orders o JOIN customers c ON o.customer_id = c.id WHERE o.id BETWEEN 10 AND 20",
+        "SELECT c.id, o.qty FROM customers c JOIN orders o ON c.id = o.customer_id WHERE c.id = 7",
+        "SELECT c.id, o.id FROM customers c LEFT JOIN orders o ON o.customer_id = c.id WHERE c.id IN (5, 6, 2050)",
+        "SELECT o.id, p.title FROM orders o JOIN products p ON p.code = o.product_code WHERE o.id < 5",
+    ] {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(sorted(rows), sorted(DB.expected_rows(sql)), "{}", sql);
+    }
+
+    // A key without an index is joined through a hash table of the whole table
+    let sql = "SELECT c.id, o.id FROM customers c JOIN orders o ON o.qty = c.tier WHERE c.id = 1";
+    assert!(matches!(query(&mut db, sql), Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. })));
+    let mut db = DB.open();
+    assert_eq!(sorted(query(&mut db, sql).unwrap()), sorted(DB.expected_rows(sql)));
+}
+
+#[test]
+fn test_invalid_joins() {
+    let mut db = DB.open();
+    match query(&mut db, "SELECT id FROM orders JOIN customers ON customer_id = customers.id") {
+        Err(Error::QueryError(message)) => assert_eq!(message, "ambiguous column name: id"),
+        other => panic!("expected an ambiguous column, got {:?}", other),
+    }
+    assert!(matches!(
+        query(&mut db, "SELECT x.id FROM orders o JOIN customers c ON o.customer_id = c.id"),
+        Err(Error::ColumnNotFound(_))
+    ));
+    assert!(matches!(
+        query(&mut db, "SELECT o.missing FROM orders o JOIN customers c ON o.customer_id = c.id"),
+        Err(Error::ColumnNotFound(_))
+    ));
+    assert!(matches!(query(&mut db, "SELECT * FROM orders JOIN missing ON missing.id = orders.id"), Err(Error::TableNotFound(_))));
+    assert!(matches!(
+        query(&mut db, "SELECT * FROM customers c LEFT JOIN orders o ON o.product_code = p.code JOIN products p ON p.code = o.product_code"),
+        Err(Error::QueryError(_))
+    ));
+    assert!(matches!(query(&mut db, "SELECT v.name FROM customers u"), Err(Error::ColumnNotFound(_))));
+
+    for sql in [
+        "SELECT * FROM orders o RIGHT JOIN customers c ON o.customer_id = c.id",
+        "SELECT * FROM orders o FULL OUTER JOIN customers c ON o.customer_id = c.id",
+        "SELECT * FROM orders JOIN customers USING (id)",
+        "SELECT * FROM orders NATURAL JOIN customers",
+    ] {
+        assert!(matches!(SelectQuery::parse(sql), Err(Error::QueryError(_))), "{}", sql);
+    }
+}
@@ -0,0 +1,146 @@
+//! Tests for rows and index keys that spill onto overflow pages
+
+use sqlite_wasm_reader::{Database, SelectQuery, Value};
+
+/// Deterministic text of the given length
+fn make_text(seed: usize, len: usize) -> String {
+    (0..len)
+        .map(|i| (b'a' + ((i * 7 + seed * 13) % 26) as u8) as char)
+        .collect()
+}
+
+/// Deterministic blob of the given length
+fn make_blob(seed: usize, len: usize) -> Vec<u8> {
+    (0..len).map(|i| ((i * 31 + seed) % 251) as u8).collect()
+}
+
+/// Row sizes covering local payloads, single overflow pages and long chains
+const SIZES: [usize; 6] = [10, 900, 3_000, 5_000, 50_000, 200_000];
+
+fn create_overflow_db(path: &str, page_size: u32) {
+    let _ = std::fs::remove_file(path);
+    let conn = rusqlite::Connection::open(path).unwrap();
+    conn.execute_batch(&format!("PRAGMA page_size = {};", page_size)).unwrap();
+    conn.execute(
+        "CREATE TABLE docs (id INTEGER PRIMARY KEY, title TEXT, body TEXT, data BLOB)",
+        [],
+    )
+    .unwrap();
+    conn.execute("CREATE INDEX idx_title ON docs (title)", []).unwrap();
+
+    for (i, &size) in SIZES.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO docs (id, title, body, data) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                i as i64 + 1,
+                format!("{}-{}", i, make_text(i, size / 10)),
+                make_text(i, size),
+                make_blob(i, size),
+            ],
+        )
+        .unwrap();
+    }
+
+    // Enough long index keys to build interior index pages with overflow cells
+    for i in 0..200usize {
+        let id = 100 + i as i64;
+        conn.execute(
+            "INSERT INTO docs (id, title, body, data) VALUES (?1, ?2, ?3, NULL)",
+            rusqlite::params![id, format!("k{:03}-{}", i, make_text(i, 2_500)), "small"],
+        )
+        .unwrap();
+    }
+}
+
+fn check_large_rows(path: &str) {
+    let mut db = Database::open(path).unwrap();
+
+    assert_eq!(db.count_table_rows("docs").unwrap(), SIZES.len() + 200);
+
+    let query = SelectQuery::parse("SELECT * FROM docs WHERE id < 100").unwrap();
+    let mut rows = db.execute_query(&query).unwrap();
+    assert_eq!(rows.len(), SIZES.len());
+    rows.sort_by_key(|row| row["id"].as_integer().unwrap());
+
+    for (i, &size) in SIZES.iter().enumerate() {
+        let row = &rows[i];
+        assert_eq!(row["id"], Value::Integer(i as i64 + 1));
+        assert_eq!(row["body"], Value::Text(make_text(i, size)));
+        assert_eq!(row["data"], Value::Blob(make_blob(i, size)));
+    }
+}
+
+fn check_index_lookup(path: &str) {
+    let mut db = Database::open(path).unwrap();
+
+    for i in [0usize, 57, 133, 199] {
+        let title = format!("k{:03}-{}", i, make_text(i, 2_500));
+        let query = SelectQuery::parse(&format!("SELECT id, title FROM docs WHERE title = '{}'", title)).unwrap();
+        let rows = db.execute_query(&query).unwrap();
+        assert_eq!(rows.len(), 1, "expected exactly one row for key {}", i);
+        assert_eq!(rows[0]["id"], Value::Integer(100 + i as i64));
+        assert_eq!(rows[0]["title"], Value::Text(title));
+    }
+
+    let title = format!("4-{}", make_text(4, 5_000));
+    let query = SelectQuery::parse(&format!("SELECT id FROM docs WHERE title = '{}'", title)).unwrap();
+    let rows = db.execute_query(&query).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["id"], Value::Integer(5));
+}
+
+#[test]
+fn test_overflow_rows_default_page_size() {
+    let path = "test_overflow_4096.sqlite";
+    create_overflow_db(path, 4096);
+    check_large_rows(path);
+    check_index_lookup(path);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_overflow_rows_small_pages() {
+    let path = "test_overflow_512.sqlite";
+    create_overflow_db(path, 512);
+    check_large_rows(path);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_broken_overflow_chain_is_reported() {
+    let path = "test_overflow_broken.sqlite";
+    let _ = std::fs::remove_file(path);
+    {
+        let conn = rusqlite::Connection::open(path).unwrap();
+        conn.execute_batch(
+            "PRAGMA page_size = 1024;
+             CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT);",
+        )
+        .unwrap();
+        for (id, size) in [(1, 10), (2, 5_000), (3, 10)] {
+            conn.execute(
+                "INSERT INTO docs (id, body) VALUES (?1, ?2)",
+                rusqlite::params![id, make_text(id as usize, size)],
+            )
+            .unwrap();
+        }
+    }
+    let mut bytes = std::fs::read(path).unwrap();
+    let _ = std::fs::remove_file(path);
+
+    // Page 2 is the table's only page and the long row's chain starts at page 3;
+    // ending the chain there cuts the payload short
+    let first_overflow = 2 * 1024;
+    assert_eq!(&bytes[first_overflow..first_overflow + 4], &4u32.to_be_bytes());
+    bytes[first_overflow..first_overflow + 4].fill(0);
+
+    let mut db = Database::from_bytes(bytes).unwrap();
+    assert_eq!(db.count_table_rows("docs").unwrap(), 3);
+
+    let query = SelectQuery::parse("SELECT * FROM docs").unwrap();
+    let err = db.execute_query(&query).unwrap_err();
+    assert!(err.to_string().contains("Overflow chain ended"), "{}", err);
+
+    let query = SelectQuery::parse("SELECT id FROM docs WHERE id = 2").unwrap();
+    assert!(db.execute_query(&query).is_err());
+}
@@ -0,0 +1,130 @@
+//! Tests for opening databases from in-memory buffers and custom page sources
+
+use sqlite_wasm_reader::{BytesSource, Database, Error, OpenOptions, PageSource, ReaderLimits, Result, SelectQuery, Value};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn create_db(path: &str) {
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+    }
+    let conn = rusqlite::Connection::open(path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, bio TEXT);
+         CREATE INDEX idx_users_name ON users (name);",
+    )
+    .unwrap();
+    for i in 0..500 {
+        conn.execute(
+            "INSERT INTO users (id, name, bio) VALUES (?1, ?2, ?3)",
+            rusqlite::params![i, format!("user-{:03}", i), "x".repeat(i * 10)],
+        )
+        .unwrap();
+    }
+}
+
+fn check_db(mut db: Database) {
+    assert_eq!(db.tables().unwrap(), vec!["users"]);
+    assert_eq!(db.count_table_rows("users").unwrap(), 500);
+
+    let query = SelectQuery::parse("SELECT id, bio FROM users WHERE name = 'user-321'").unwrap();
+    let rows = db.execute_query(&query).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["id"], Value::Integer(321));
+    assert_eq!(rows[0]["bio"], Value::Text("x".repeat(3210)));
+}
+
+#[test]
+fn test_open_from_bytes_and_readers() {
+    let path = "test_page_sources.sqlite";
+    create_db(path);
+    let bytes = std::fs::read(path).unwrap();
+
+    check_db(Database::from_bytes(bytes.clone()).unwrap());
+    check_db(Database::from_bytes(Arc::<[u8]>::from(bytes.clone())).unwrap());
+    check_db(Database::from_bytes(Box::leak(bytes.clone().into_boxed_slice()) as &'static [u8]).unwrap());
+    check_db(Database::from_source(bytes.clone().into_boxed_slice()).unwrap());
+    check_db(Database::from_reader(Cursor::new(bytes.clone())).unwrap());
+    check_db(Database::from_reader(std::fs::File::open(path).unwrap()).unwrap());
+    check_db(Database::from_source(std::fs::File::open(path).unwrap()).unwrap());
+
+    // Truncated buffers fail cleanly instead of panicking
+    let mut truncated = bytes.clone();
+    truncated.truncate(50);
+    assert!(Database::from_bytes(truncated).is_err());
+    let mut truncated = bytes.clone();
+    truncated.truncate(bytes.len() / 2);
+    let mut db = Database::from_bytes(truncated).unwrap();
+    assert!(db.count_table_rows("users").is_err());
+
+    assert!(Database::from_bytes(vec![0u8; 4096]).is_err());
+
+    let _ = std::fs::remove_file(path);
+}
+
+/// Page source that counts how many reads it serves
+struct CountingSource {
+    data: Vec<u8>,
+    reads: Arc<AtomicUsize>,
+}
+
+impl PageSource for CountingSource {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        BytesSource(&self.data[..]).read_at(offset, buf)
+    }
+
+    fn size(&mut self) -> Result<u64> {
+        Ok(self.data.len() as u64)
+    }
+}
+
+#[test]
+fn test_custom_page_source() {
+    let path = "test_page_sources_custom.sqlite";
+    create_db(path);
+    let reads = Arc::new(AtomicUsize::new(0));
+    let source = CountingSource { data: std::fs::read(path).unwrap(), reads: reads.clone() };
+
+    check_db(Database::from_source(source).unwrap());
+    assert!(reads.load(Ordering::Relaxed) > 0);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_wal_from_bytes() {
+    let path = "test_page_sources_wal.sqlite";
+    create_db(path);
+    let conn = rusqlite::Connection::open(path).unwrap();
+    conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+    conn.pragma_update(None, "wal_autocheckpoint", 0).unwrap();
+    conn.execute("UPDATE users SET name = 'renamed' WHERE id = 321", []).unwrap();
+    conn.execute("UPDATE users SET name = 'renamed again' WHERE id = 321", []).unwrap();
+
+    let main = std::fs::read(path).unwrap();
+    let wal = std::fs::read(format!("{}-wal", path)).unwrap();
+
+    let mut db = Database::from_source_with_wal(main.clone(), wal.clone()).unwrap();
+    let query = SelectQuery::parse("SELECT id FROM users WHERE name = 'renamed again'").unwrap();
+    let rows = db.execute_query(&query).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["id"], Value::Integer(321));
+
+    // Options pin the WAL to its first commit and limit the reader
+    let first = db.wal_commits()[0].frame_index;
+    let options = OpenOptions::new().wal_commit(first).limits(ReaderLimits::unlimited().max_pages_per_traversal(Some(8)));
+    let mut pinned = Database::from_source_with_wal_and_options(main.clone(), wal, &options).unwrap();
+    let query = SelectQuery::parse("SELECT name FROM users WHERE id = 321").unwrap();
+    assert_eq!(pinned.execute_query(&query).unwrap()[0]["name"], Value::Text("renamed".into()));
+    assert!(matches!(pinned.count_table_rows("users"), Err(Error::LimitExceeded { .. })));
+
+    // Without the WAL the main file still has the old name
+    check_db(Database::from_bytes(main).unwrap());
+
+    drop(conn);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+    }
+}
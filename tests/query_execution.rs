@@ -75,7 +75,7 @@ fn test_or_condition_with_index() {
         ]),
     ];
 
-    assert_eq!(result, expected_rows);
+    assert_eq!(result.into_maps(), expected_rows);
 }
 
 #[test]
@@ -102,7 +102,7 @@ fn test_composite_index_prefix_query() {
         ]),
     ];
 
-    assert_eq!(result, expected_rows);
+    assert_eq!(result.into_maps(), expected_rows);
 }
 
 #[test]
@@ -120,7 +120,7 @@ fn test_composite_index_full_key_query() {
         ("city".to_string(), Value::Text("New York".to_string())),
     ]);
 
-    assert_eq!(result[0], expected_row);
+    assert_eq!(result[0].clone().into_map(), expected_row);
 }
 
 #[test]
@@ -138,5 +138,34 @@ fn test_single_column_index_query() {
         ("city".to_string(), Value::Text("New York".to_string())),
     ]);
 
-    assert_eq!(result[0], expected_row);
+    assert_eq!(result[0].clone().into_map(), expected_row);
+}
+
+#[test]
+fn test_rows_keep_projection_order() {
+    setup();
+    let mut db = Database::open("test_db.sqlite").unwrap();
+    let query = SelectQuery::parse("SELECT city, name, city, id FROM users WHERE age = 30 ORDER BY id").unwrap();
+    let result = db.execute_query(&query).unwrap();
+
+    assert_eq!(result.columns(), ["city", "name", "city", "id"]);
+    assert_eq!(result.len(), 2);
+    assert_eq!(
+        result[0].values(),
+        [
+            Value::Text("New York".to_string()),
+            Value::Text("Alice".to_string()),
+            Value::Text("New York".to_string()),
+            Value::Integer(1),
+        ]
+    );
+    assert_eq!(result[1].get(1), Some(&Value::Text("David".to_string())));
+    assert_eq!(result[1].get("id"), Some(&Value::Integer(4)));
+
+    // SELECT * follows declaration order
+    let query = SelectQuery::parse("SELECT * FROM users LIMIT 1").unwrap();
+    assert_eq!(db.execute_query(&query).unwrap().columns(), ["id", "name", "age", "city"]);
+
+    let query = SelectQuery::parse("SELECT name, missing FROM users").unwrap();
+    assert!(matches!(db.execute_query(&query), Err(sqlite_wasm_reader::Error::ColumnNotFound(c)) if c == "missing"));
 }
@@ -0,0 +1,81 @@
+//! Tests for the lazy row iterator
+
+mod common;
+
+use common::Fixture;
+use sqlite_wasm_reader::{Error, Result, SelectQuery, Value};
+use std::sync::atomic::Ordering;
+
+const ROWS: i64 = 20_000;
+
+/// A database with a large table and an index
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT, payload TEXT);
+         CREATE INDEX idx_events_kind ON events (kind);",
+    )
+    .unwrap();
+    let tx = conn.unchecked_transaction().unwrap();
+    for i in 0..ROWS {
+        tx.execute(
+            "INSERT INTO events (id, kind, payload) VALUES (?1, ?2, ?3)",
+            rusqlite::params![i, format!("kind-{}", i % 10), format!("payload-{:05}", i)],
+        )
+        .unwrap();
+    }
+    tx.commit().unwrap();
+});
+
+#[test]
+fn test_iterator_matches_execute_query() {
+    let mut db = DB.open();
+    for sql in [
+        "SELECT * FROM events",
+        "SELECT id, payload FROM events WHERE id >= 19990",
+        "SELECT * FROM events WHERE kind = 'kind-3' LIMIT 7",
+        "SELECT id FROM events ORDER BY id DESC LIMIT 3",
+    ] {
+        let query = SelectQuery::parse(sql).unwrap();
+        let collected: Vec<_> = db.query_iter(&query).unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(collected, db.execute_query(&query).unwrap().into_rows(), "{}", sql);
+    }
+
+    let query = SelectQuery::parse("SELECT * FROM events").unwrap();
+    assert_eq!(db.query_iter(&query).unwrap().count(), ROWS as usize);
+}
+
+#[test]
+fn test_iterator_reads_pages_on_demand() {
+    let (mut db, reads) = DB.open_counting();
+    let table_pages = {
+        let query = SelectQuery::parse("SELECT * FROM events").unwrap();
+        let before = reads.load(Ordering::Relaxed);
+        db.query_iter(&query).unwrap().for_each(|row| drop(row.unwrap()));
+        reads.load(Ordering::Relaxed) - before
+    };
+    assert!(table_pages > 50, "expected a multi-page table, read {} pages", table_pages);
+
+    // Taking a few rows only touches the first leaf
+    let (mut db, reads) = DB.open_counting();
+    let query = SelectQuery::parse("SELECT * FROM events").unwrap();
+    let before = reads.load(Ordering::Relaxed);
+    let first: Vec<_> = db.query_iter(&query).unwrap().take(3).map(|r| r.unwrap()).collect();
+    assert_eq!(first[2]["id"], Value::Integer(2));
+    assert!(reads.load(Ordering::Relaxed) - before < 5);
+
+    // LIMIT stops the scan as soon as enough rows matched
+    let (mut db, reads) = DB.open_counting();
+    let query = SelectQuery::parse("SELECT id FROM events WHERE payload > 'payload-00100' LIMIT 2").unwrap();
+    let before = reads.load(Ordering::Relaxed);
+    let rows: Vec<_> = db.query_iter(&query).unwrap().map(|r| r.unwrap()).collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["id"], Value::Integer(101));
+    assert!(reads.load(Ordering::Relaxed) - before < table_pages / 4);
+}
+
+#[test]
+fn test_iterator_errors() {
+    let mut db = DB.open();
+    let query = SelectQuery::parse("SELECT * FROM missing").unwrap();
+    assert!(matches!(db.query_iter(&query), Err(Error::TableNotFound(_))));
+}
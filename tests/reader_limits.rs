@@ -0,0 +1,109 @@
+//! Tests for configurable reader limits and b-tree cycle detection
+
+mod common;
+
+use common::Fixture;
+use sqlite_wasm_reader::{Database, Error, OpenOptions, ReaderLimits, SelectQuery, Value};
+
+const ROWS: i64 = 150_000;
+
+/// A database whose main table is larger than the old hard-coded caps
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(&format!(
+        "CREATE TABLE samples (id INTEGER PRIMARY KEY, value INTEGER);
+         CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {})
+         INSERT INTO samples (id, value) SELECT i, i * 2 FROM n;",
+        ROWS
+    ))
+    .unwrap();
+});
+
+fn open_with(limits: ReaderLimits) -> Result<Database, Error> {
+    Database::from_source_with_options(DB.bytes().to_vec(), &OpenOptions::new().limits(limits))
+}
+
+#[test]
+fn test_large_tables_are_read_completely() {
+    let mut db = DB.open();
+    assert_eq!(db.count_table_rows("samples").unwrap(), ROWS as usize);
+
+    let query = SelectQuery::parse("SELECT * FROM samples").unwrap();
+    let mut count = 0;
+    for row in db.query_iter(&query).unwrap() {
+        let row = row.unwrap();
+        count += 1;
+        assert_eq!(row["id"], Value::Integer(count));
+    }
+    assert_eq!(count, ROWS);
+
+    let query = SelectQuery::parse("SELECT value FROM samples WHERE id = 149999").unwrap();
+    let rows = db.execute_query(&query).unwrap();
+    assert_eq!(rows[0]["value"], Value::Integer(299_998));
+}
+
+#[test]
+fn test_row_limit() {
+    let mut db = open_with(ReaderLimits::unlimited().max_rows(Some(1000))).unwrap();
+    assert!(matches!(
+        db.count_table_rows("samples"),
+        Err(Error::LimitExceeded { limit: "max_rows", max: 1000 })
+    ));
+
+    let query = SelectQuery::parse("SELECT * FROM samples").unwrap();
+    let results: Vec<_> = db.query_iter(&query).unwrap().collect();
+    assert_eq!(results.len(), 1001);
+    assert!(results[..1000].iter().all(|r| r.is_ok()));
+    assert!(matches!(results[1000], Err(Error::LimitExceeded { limit: "max_rows", .. })));
+    assert!(db.execute_query(&query).is_err());
+
+    // ORDER BY buffers the matching rows, which counts against the limit as well
+    let query = SelectQuery::parse("SELECT * FROM samples ORDER BY value DESC LIMIT 1").unwrap();
+    assert!(matches!(db.query_iter(&query), Err(Error::LimitExceeded { .. })));
+
+    // Queries staying under the limit are unaffected
+    let query = SelectQuery::parse("SELECT * FROM samples WHERE id <= 1000").unwrap();
+    assert!(matches!(db.query_iter(&query).unwrap().nth(500), Some(Ok(_))));
+    let query = SelectQuery::parse("SELECT * FROM samples LIMIT 1000").unwrap();
+    assert_eq!(db.execute_query(&query).unwrap().len(), 1000);
+}
+
+#[test]
+fn test_page_and_schema_limits() {
+    let mut db = open_with(ReaderLimits::unlimited().max_pages_per_traversal(Some(10))).unwrap();
+    let query = SelectQuery::parse("SELECT * FROM samples").unwrap();
+    let last = db.query_iter(&query).unwrap().last().unwrap();
+    assert!(matches!(last, Err(Error::LimitExceeded { limit: "max_pages_per_traversal", max: 10 })));
+
+    // Scans that stop early only enter a handful of pages
+    let query = SelectQuery::parse("SELECT * FROM samples LIMIT 5").unwrap();
+    assert_eq!(db.execute_query(&query).unwrap().len(), 5);
+
+    assert!(matches!(
+        open_with(ReaderLimits::unlimited().max_schema_objects(Some(1))),
+        Err(Error::LimitExceeded { limit: "max_schema_objects", max: 1 })
+    ));
+    let db = open_with(ReaderLimits::unlimited().max_schema_objects(Some(2))).unwrap();
+    assert_eq!(db.limits().max_schema_objects, Some(2));
+}
+
+#[test]
+fn test_btree_cycle_is_an_error() {
+    let mut bytes = DB.bytes().to_vec();
+    let page_size = u16::from_be_bytes([bytes[16], bytes[17]]) as usize;
+
+    // Point the right-most child of the table's interior root page back at itself
+    let root_page = DB.root_page("samples");
+    let header = (root_page as usize - 1) * page_size;
+    assert_eq!(bytes[header], 0x05, "expected an interior table page");
+    bytes[header + 8..header + 12].copy_from_slice(&root_page.to_be_bytes());
+
+    let mut db = Database::from_bytes(bytes).unwrap();
+    let query = SelectQuery::parse("SELECT * FROM samples").unwrap();
+    let last = db.query_iter(&query).unwrap().last().unwrap();
+    match last {
+        Err(Error::InvalidFormat(message)) => assert!(message.contains("cycle"), "{}", message),
+        other => panic!("expected a cycle error, got {:?}", other.map(|_| ())),
+    }
+    assert!(db.count_table_rows("samples").is_err());
+}
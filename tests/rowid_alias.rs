@@ -0,0 +1,89 @@
+//! Tests for detecting the column that aliases the rowid
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::Error;
+
+const ROWS: i64 = 5000;
+
+static DB: Fixture = Fixture::new(|conn| {
+    // Keys count down from the top so they never coincide with rowids of
+    // tables where they are ordinary columns
+    conn.execute_batch(&format!(
+        "CREATE TABLE single_line (x INTEGER, id INTEGER PRIMARY KEY, y INTEGER);
+         CREATE TABLE table_constraint (id INTEGER, name TEXT, PRIMARY KEY (id));
+         CREATE TABLE descending (id INTEGER PRIMARY KEY DESC, name TEXT);
+         CREATE TABLE int_key (id INT PRIMARY KEY, name TEXT);
+         CREATE TABLE composite (a INTEGER, b INTEGER, name TEXT, PRIMARY KEY (a, b));
+         CREATE TABLE constraint_desc (id INTEGER, name TEXT, PRIMARY KEY (id DESC));
+         CREATE TABLE quoted_type (id \"INTEGER\" PRIMARY KEY, name TEXT);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO single_line (x, id, y) SELECT i * 3, {rows} * 2 - i, i % 10 FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO table_constraint (id, name) SELECT {rows} * 2 - i, 'row ' || i FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO descending (id, name) SELECT {rows} * 2 - i, 'row ' || i FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO int_key (id, name) SELECT {rows} * 2 - i, 'row ' || i FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO composite (a, b, name) SELECT {rows} * 2 - i, i, 'row ' || i FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO constraint_desc (id, name) SELECT {rows} * 2 - i, 'row ' || i FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO quoted_type (id, name) SELECT {rows} * 2 - i, 'row ' || i FROM n;",
+        rows = ROWS
+    ))
+    .unwrap();
+});
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in [
+        // Full scans fill the alias from the cell key
+        "SELECT * FROM single_line ORDER BY x",
+        "SELECT * FROM table_constraint ORDER BY name",
+        "SELECT * FROM descending ORDER BY name",
+        "SELECT * FROM int_key ORDER BY name",
+        "SELECT * FROM composite ORDER BY name",
+        "SELECT x, y FROM single_line WHERE id = 9000",
+        "SELECT name FROM table_constraint WHERE id BETWEEN 9000 AND 9010 ORDER BY id",
+        "SELECT rowid, id, name FROM descending WHERE id = 9000",
+        "SELECT rowid, id, name FROM descending WHERE rowid = 3",
+        "SELECT rowid, id, name FROM int_key WHERE id > 9995 ORDER BY id",
+        "SELECT rowid, a, b FROM composite WHERE a = 9000",
+        "SELECT * FROM constraint_desc ORDER BY name",
+        "SELECT rowid, id, name FROM constraint_desc WHERE id = 9000",
+        "SELECT * FROM quoted_type ORDER BY name",
+        "SELECT rowid, id, name FROM quoted_type WHERE id > 9995 ORDER BY id",
+    ] {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+}
+
+#[test]
+fn test_only_aliases_seek_the_table() {
+    let mut db = DB.open_seek_only(4);
+    for sql in [
+        "SELECT x FROM single_line WHERE id = 9000",
+        "SELECT name FROM table_constraint WHERE id IN (5001, 7000, 9999)",
+        // A DESC table constraint still aliases the rowid, unlike the column constraint
+        "SELECT name FROM constraint_desc WHERE id = 9000",
+        "SELECT name FROM quoted_type WHERE id BETWEEN 9000 AND 9002",
+    ] {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(rows, DB.expected_rows(sql), "{}", sql);
+    }
+
+    // Ordinary keys are found through their automatic index instead
+    let sql = "SELECT name FROM int_key WHERE id = 9000";
+    assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql));
+
+    // `INTEGER PRIMARY KEY DESC` keys a descending automatic index, which seeks
+    // cannot use
+    assert!(matches!(
+        query(&mut db, "SELECT name FROM descending WHERE id = 9000"),
+        Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. })
+    ));
+}
@@ -0,0 +1,98 @@
+//! Tests for rowid seeks and the rowid pseudo-columns
+
+mod common;
+
+use common::{query, Fixture};
+use sqlite_wasm_reader::{Error, SelectQuery};
+
+const ROWS: i64 = 20_000;
+
+static DB: Fixture = Fixture::new(|conn| {
+    // Even ids leave gaps for ranges and points to fall into
+    conn.execute_batch(&format!(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, bucket INTEGER);
+         CREATE INDEX idx_items_bucket ON items (bucket);
+         CREATE TABLE logs (msg TEXT);
+         CREATE TABLE shadow (rowid TEXT, v INTEGER);
+         CREATE TABLE kv (k TEXT PRIMARY KEY, v INTEGER) WITHOUT ROWID;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO items (id, name, bucket) SELECT i * 2, 'item ' || i, i % 7 FROM n;
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {rows})
+         INSERT INTO logs (msg) SELECT 'message ' || i FROM n;
+         INSERT INTO shadow (rowid, v) VALUES ('a', 1), ('b', 2), ('c', 3);
+         INSERT INTO kv (k, v) VALUES ('x', 1), ('y', 2);",
+        rows = ROWS
+    ))
+    .unwrap();
+});
+
+#[test]
+fn test_results_match_sqlite() {
+    let mut db = DB.open();
+    for sql in [
+        "SELECT * FROM items WHERE id = 500",
+        "SELECT * FROM items WHERE id = 501",
+        "SELECT id, name FROM items WHERE id IN (40, 10, 7, 40000, 39998, 40002) ORDER BY id",
+        "SELECT id FROM items WHERE id BETWEEN 1001 AND 1100 ORDER BY id",
+        "SELECT id FROM items WHERE id > 39990 ORDER BY id",
+        "SELECT id FROM items WHERE id <= 10 ORDER BY id",
+        "SELECT id FROM items WHERE id >= 100 AND id < 160 AND bucket = 3 ORDER BY id",
+        "SELECT id FROM items WHERE id > 10.5 AND id <= 20.0 ORDER BY id",
+        "SELECT id FROM items WHERE id = 10.0",
+        "SELECT id FROM items WHERE id = 10.5",
+        "SELECT id FROM items WHERE id > 100 AND id < 50",
+        "SELECT id FROM items WHERE id IN (4, 6, 8) AND id > 4 ORDER BY id",
+        "SELECT id FROM items WHERE id = 8 OR id = 8 OR id BETWEEN 6 AND 10 ORDER BY id",
+        "SELECT id FROM items WHERE id = 5000 OR (bucket = 2 AND id < 100) ORDER BY id",
+        "SELECT rowid, name FROM items WHERE rowid = 42",
+        "SELECT rowid, name FROM items WHERE rowid BETWEEN 5 AND 9 ORDER BY rowid",
+        "SELECT oid, _rowid_, id FROM items WHERE _rowid_ < 7 ORDER BY id",
+        "SELECT rowid, msg FROM logs WHERE rowid IN (1, 2500, 20000) ORDER BY rowid",
+        "SELECT rowid, msg FROM logs WHERE oid > 19990 ORDER BY rowid DESC LIMIT 3",
+        "SELECT * FROM logs WHERE rowid = 3",
+        "SELECT msg FROM logs WHERE ROWID >= 100 AND ROWID < 104 ORDER BY msg",
+        "SELECT rowid, v FROM shadow WHERE oid = 3",
+        "SELECT rowid, v FROM shadow WHERE rowid = 'b'",
+    ] {
+        assert_eq!(query(&mut db, sql).unwrap(), DB.expected_rows(sql), "{}", sql);
+    }
+}
+
+#[test]
+fn test_rowid_predicates_seek() {
+    let mut db = DB.open_seek_only(8);
+    for sql in [
+        "SELECT * FROM items WHERE id = 500",
+        "SELECT id FROM items WHERE id IN (40, 10, 7, 40000, 39998)",
+        "SELECT id FROM items WHERE id BETWEEN 1001 AND 1100",
+        "SELECT id FROM items WHERE id > 39900",
+        "SELECT id FROM items WHERE id < 50 OR id = 30000",
+        "SELECT rowid, msg FROM logs WHERE rowid IN (1, 2500, 20000)",
+        "SELECT msg FROM logs WHERE oid BETWEEN 7000 AND 7040",
+    ] {
+        let rows = query(&mut db, sql).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+        assert_eq!(rows.len(), DB.expected_rows(sql).len(), "{}", sql);
+    }
+
+    // Conditions a seek cannot serve still scan
+    for sql in [
+        "SELECT id FROM items WHERE id != 500",
+        "SELECT id FROM items WHERE id = 500 OR name = 'item 3'",
+        "SELECT rowid FROM logs WHERE msg = 'message 9'",
+    ] {
+        assert!(
+            matches!(query(&mut db, sql), Err(Error::LimitExceeded { limit: "max_pages_per_traversal", .. })),
+            "{}",
+            sql
+        );
+    }
+}
+
+#[test]
+fn test_without_rowid_tables_have_no_rowid() {
+    let mut db = DB.open();
+    assert!(matches!(query(&mut db, "SELECT rowid FROM kv"), Err(Error::ColumnNotFound(_))));
+
+    let rows = db.execute_query(&SelectQuery::parse("SELECT * FROM logs WHERE rowid = 3").unwrap()).unwrap();
+    assert_eq!(rows.columns(), ["msg"]);
+}
@@ -0,0 +1,122 @@
+//! Tests for the serde feature: deserializing rows and serializing results
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::Fixture;
+use serde::Deserialize;
+use sqlite_wasm_reader::{de, Error, SelectQuery, Value};
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT, score REAL,
+                             admin INTEGER, role TEXT, avatar BLOB);
+         INSERT INTO users VALUES (1, 'alice', 'alice@example.com', 9.5, 1, 'owner', x'0102');
+         INSERT INTO users VALUES (2, 'bob', NULL, 7.25, 0, 'guest', NULL);",
+    )
+    .unwrap();
+});
+
+fn query(sql: &str) -> sqlite_wasm_reader::ResultSet {
+    let mut db = DB.open();
+    db.execute_query(&SelectQuery::parse(sql).unwrap()).unwrap()
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Role {
+    Owner,
+    Guest,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct User<'a> {
+    id: u32,
+    #[serde(borrow)]
+    name: &'a str,
+    email: Option<String>,
+    score: f32,
+    admin: bool,
+    role: Role,
+    avatar: Option<Vec<u8>>,
+}
+
+#[test]
+fn test_deserialize_structs() {
+    let rows = query("SELECT * FROM users ORDER BY id");
+    let users: Vec<User> = de::from_rows(&rows).unwrap();
+    assert_eq!(
+        users,
+        [
+            User {
+                id: 1,
+                name: "alice",
+                email: Some("alice@example.com".into()),
+                score: 9.5,
+                admin: true,
+                role: Role::Owner,
+                avatar: Some(vec![1, 2]),
+            },
+            User { id: 2, name: "bob", email: None, score: 7.25, admin: false, role: Role::Guest, avatar: None },
+        ]
+    );
+
+    // Columns the struct does not mention are ignored, missing Option fields are None
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Partial {
+        name: String,
+        nickname: Option<String>,
+    }
+    let partial: Partial = de::from_row(&rows[1]).unwrap();
+    assert_eq!(partial, Partial { name: "bob".into(), nickname: None });
+}
+
+#[test]
+fn test_deserialize_tuples_and_values() {
+    let rows = query("SELECT name, id FROM users ORDER BY id");
+    let pairs: Vec<(String, i64)> = de::from_rows(&rows).unwrap();
+    assert_eq!(pairs, [("alice".to_string(), 1), ("bob".to_string(), 2)]);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Pair(String, u8);
+    assert_eq!(de::from_row::<Pair>(&rows[0]).unwrap(), Pair("alice".into(), 1));
+
+    let values: Vec<Value> = rows.iter().map(|row| row["id"].clone()).collect();
+    assert_eq!(de::from_value::<i16>(&values[1]).unwrap(), 2);
+    assert_eq!(de::from_value::<Option<f64>>(&Value::Null).unwrap(), None);
+}
+
+#[test]
+fn test_deserialize_errors() {
+    let rows = query("SELECT name, id FROM users");
+
+    // Mismatches name the column
+    let err = de::from_row::<(i64, i64)>(&rows[0]).unwrap_err();
+    assert!(matches!(&err, Error::Deserialize(msg) if msg.starts_with("column name: invalid type")), "{}", err);
+
+    // Tuples must consume every column
+    let err = de::from_row::<(String,)>(&rows[0]).unwrap_err();
+    assert_eq!(err.to_string(), "Deserialization error: row has 2 columns but only 1 were read");
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct NeedsEmail {
+        email: String,
+    }
+    let err = de::from_row::<NeedsEmail>(&rows[0]).unwrap_err();
+    assert_eq!(err.to_string(), "Deserialization error: missing field `email`");
+
+    let rows = query("SELECT id FROM users WHERE id = 2");
+    let err = de::from_row::<(u8, Option<u8>)>(&rows[0]).unwrap_err();
+    assert!(err.to_string().contains("invalid length 1"), "{}", err);
+}
+
+#[test]
+fn test_serialize_results() {
+    let rows = query("SELECT id, name, email, score, avatar FROM users ORDER BY id");
+    assert_eq!(
+        serde_json::to_string(&rows).unwrap(),
+        r#"[{"id":1,"name":"alice","email":"alice@example.com","score":9.5,"avatar":[1,2]},{"id":2,"name":"bob","email":null,"score":7.25,"avatar":null}]"#
+    );
+    assert_eq!(serde_json::to_string(&rows[1]["name"]).unwrap(), r#""bob""#);
+}
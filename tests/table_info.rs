@@ -0,0 +1,165 @@
+//! Tests for the column and constraint metadata of `Database::table_info`
+
+mod common;
+
+use common::Fixture;
+use sqlite_wasm_reader::{Affinity, Error, ForeignKey, GeneratedColumn, TableConstraint};
+
+const SCHEMA: &str = "
+    CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, email VARCHAR(255) UNIQUE);
+    CREATE TABLE books (
+        id INTEGER PRIMARY KEY,
+        author_id INTEGER NOT NULL REFERENCES authors (id) ON DELETE CASCADE,
+        title TEXT DEFAULT 'untitled' COLLATE NOCASE,
+        price DOUBLE PRECISION DEFAULT -1,
+        pages int DEFAULT (1 + 2),
+        added DATETIME DEFAULT CURRENT_TIMESTAMP,
+        cover BLOB,
+        rating,
+        flag BOOLEAN,
+        isbn CHAR(13),
+        price_cents INTEGER GENERATED ALWAYS AS (price * 100) STORED,
+        slug AS (lower(title)),
+        CONSTRAINT one_title UNIQUE (author_id, title),
+        UNIQUE (isbn),
+        CHECK (pages > 0),
+        FOREIGN KEY (author_id, isbn) REFERENCES editions (author, code) ON UPDATE SET NULL
+    );
+    CREATE TABLE tags (book INTEGER, tag TEXT, PRIMARY KEY (book, tag)) WITHOUT ROWID;
+";
+
+static DB: Fixture = Fixture::new(|conn| conn.execute_batch(SCHEMA).unwrap());
+
+/// `PRAGMA table_xinfo` rows: name, declared type, NOT NULL, default, in PRIMARY KEY, hidden
+fn sqlite_columns(table: &str) -> Vec<(String, String, bool, Option<String>, bool, bool)> {
+    DB.with_sqlite(|conn| {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_xinfo({})", table)).unwrap();
+        stmt.query_map([], |r| {
+            Ok((
+                r.get(1)?,
+                r.get(2)?,
+                r.get::<_, i64>(3)? != 0,
+                r.get(4)?,
+                r.get::<_, i64>(5)? != 0,
+                r.get::<_, i64>(6)? != 0,
+            ))
+        })
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap()
+    })
+}
+
+#[test]
+fn test_columns_match_sqlite() {
+    let db = DB.open();
+    for table in ["authors", "books", "tags"] {
+        let info = db.table_info(table).unwrap();
+        let columns: Vec<_> = info
+            .column_info
+            .iter()
+            .map(|col| {
+                (
+                    col.name.clone(),
+                    col.declared_type.clone().unwrap_or_default(),
+                    col.not_null,
+                    col.default.clone(),
+                    col.primary_key,
+                    col.is_hidden(),
+                )
+            })
+            .collect();
+        let expected: Vec<_> = sqlite_columns(table)
+            .into_iter()
+            // The parser normalizes the case of type names
+            .map(|(name, ty, not_null, default, pk, hidden)| (name, ty.to_uppercase(), not_null, default, pk, hidden))
+            .collect();
+        assert_eq!(columns, expected, "{}", table);
+    }
+}
+
+#[test]
+fn test_column_details() {
+    let db = DB.open();
+    let books = db.table_info("books").unwrap();
+    let column = |name: &str| books.column_info.iter().find(|col| col.name == name).unwrap();
+
+    let affinities: Vec<_> = books.column_info.iter().map(|col| col.affinity).collect();
+    assert_eq!(
+        affinities,
+        [
+            Affinity::Integer,
+            Affinity::Integer,
+            Affinity::Text,
+            Affinity::Real,
+            Affinity::Integer,
+            Affinity::Numeric,
+            Affinity::Blob,
+            Affinity::Blob,
+            Affinity::Numeric,
+            Affinity::Text,
+            Affinity::Integer,
+            Affinity::Blob,
+        ]
+    );
+    assert_eq!(Affinity::from_declared_type("CHARINT"), Affinity::Integer);
+    // "INT" is checked first, as in SQLite
+    assert_eq!(Affinity::from_declared_type("FLOATING POINT"), Affinity::Integer);
+    assert_eq!(Affinity::from_declared_type("float"), Affinity::Real);
+
+    assert_eq!(column("title").collation.as_deref(), Some("NOCASE"));
+    assert_eq!(column("title").default.as_deref(), Some("'untitled'"));
+    assert_eq!(db.table_info("authors").unwrap().column_info[1].collation.as_deref(), Some("NOCASE"));
+    assert!(column("isbn").unique && !column("title").unique);
+    assert!(db.table_info("authors").unwrap().column_info[2].unique);
+    assert_eq!(
+        column("price_cents").generated,
+        Some(GeneratedColumn { expression: "price * 100".into(), stored: true })
+    );
+    assert_eq!(column("slug").generated, Some(GeneratedColumn { expression: "lower(title)".into(), stored: false }));
+    assert_eq!(column("rating").declared_type, None);
+}
+
+#[test]
+fn test_constraints_and_foreign_keys() {
+    let db = DB.open();
+    let books = db.table_info("books").unwrap();
+    assert_eq!(
+        books.constraints,
+        [
+            TableConstraint::Unique { name: Some("one_title".into()), columns: vec!["author_id".into(), "title".into()] },
+            TableConstraint::Unique { name: None, columns: vec!["isbn".into()] },
+            TableConstraint::Check { name: None, expression: "pages > 0".into() },
+        ]
+    );
+    assert_eq!(
+        books.foreign_keys,
+        [
+            ForeignKey {
+                name: None,
+                columns: vec!["author_id".into()],
+                table: "authors".into(),
+                referred_columns: vec!["id".into()],
+                on_delete: Some("CASCADE".into()),
+                on_update: None,
+            },
+            ForeignKey {
+                name: None,
+                columns: vec!["author_id".into(), "isbn".into()],
+                table: "editions".into(),
+                referred_columns: vec!["author".into(), "code".into()],
+                on_delete: None,
+                on_update: Some("SET NULL".into()),
+            },
+        ]
+    );
+
+    let tags = db.table_info("tags").unwrap();
+    assert_eq!(
+        tags.constraints,
+        [TableConstraint::PrimaryKey { name: None, columns: vec!["book".into(), "tag".into()] }]
+    );
+    assert!(tags.foreign_keys.is_empty());
+
+    assert!(matches!(db.table_info("missing"), Err(Error::TableNotFound(_))));
+}
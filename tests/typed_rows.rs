@@ -0,0 +1,109 @@
+//! Tests for decoding rows into Rust types with FromRow / FromValue
+
+mod common;
+
+use common::Fixture;
+use sqlite_wasm_reader::{Date, DateTime, Error, FromRow, Row, SelectQuery, Time};
+// The `derive` feature re-exports the macro next to the trait
+#[cfg(not(feature = "derive"))]
+use sqlite_wasm_reader_derive::FromRow;
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT, score REAL,
+                             active INTEGER, avatar BLOB, joined TEXT, last_seen TEXT);
+         INSERT INTO users VALUES (1, 'alice', 'alice@example.com', 9.5, 1, x'0102', '2024-02-29', '2024-03-01 08:30:00');
+         INSERT INTO users VALUES (2, 'bob', NULL, 7, 0, NULL, '2023-12-31', '2024-01-02T10:15:30.250Z');",
+    )
+    .unwrap();
+});
+
+#[derive(Debug, PartialEq, FromRow)]
+struct User {
+    id: i64,
+    name: String,
+    email: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Profile<'a> {
+    #[sqlite(rename = "name")]
+    login: &'a str,
+    score: f64,
+    active: bool,
+    avatar: Option<Vec<u8>>,
+    joined: Date,
+    last_seen: DateTime,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct IdAndName(u32, String);
+
+#[test]
+fn test_query_as_tuples() {
+    let mut db = DB.open();
+    let rows = db.query_as::<(i64, String)>("SELECT id, name FROM users ORDER BY id").unwrap();
+    assert_eq!(rows, [(1, "alice".to_string()), (2, "bob".to_string())]);
+
+    let emails = db.query_as::<(Option<String>,)>("SELECT email FROM users ORDER BY id").unwrap();
+    assert_eq!(emails, [(Some("alice@example.com".to_string()),), (None,)]);
+}
+
+#[test]
+fn test_query_as_derived_structs() {
+    let mut db = DB.open();
+    let users = db.query_as::<User>("SELECT id, name, email FROM users").unwrap();
+    assert_eq!(
+        users,
+        [
+            User { id: 1, name: "alice".into(), email: Some("alice@example.com".into()) },
+            User { id: 2, name: "bob".into(), email: None },
+        ]
+    );
+
+    let pairs = db.query_as::<IdAndName>("SELECT id, name FROM users WHERE id = 2").unwrap();
+    assert_eq!(pairs, [IdAndName(2, "bob".into())]);
+
+    // Borrowing structs decode straight from a row
+    let rows = db.execute_query(&SelectQuery::parse("SELECT * FROM users ORDER BY id").unwrap()).unwrap();
+    let bob = Profile::from_row(&rows[1]).unwrap();
+    assert_eq!(bob.login, "bob");
+    assert_eq!(bob.score, 7.0);
+    assert!(!bob.active);
+    assert_eq!(bob.avatar, None);
+    assert_eq!(bob.joined, Date { year: 2023, month: 12, day: 31 });
+    assert_eq!(
+        bob.last_seen.time,
+        Time { hour: 10, minute: 15, second: 30, nanosecond: 250_000_000 }
+    );
+    let alice = Profile::from_row(&rows[0]).unwrap();
+    assert_eq!(alice.avatar, Some(vec![1, 2]));
+    assert!(alice.active);
+}
+
+#[test]
+fn test_decoding_errors() {
+    let mut db = DB.open();
+
+    match db.query_as::<(i64, i64)>("SELECT id, name FROM users") {
+        Err(Error::TypeMismatch { column, expected, found }) => {
+            assert_eq!(column, "name");
+            assert_eq!(expected, "i64");
+            assert_eq!(found, "TEXT");
+        }
+        other => panic!("expected a type mismatch, got {:?}", other),
+    }
+
+    // NULL only decodes into an Option
+    let err = db.query_as::<(String,)>("SELECT email FROM users").unwrap_err();
+    assert_eq!(err.to_string(), "Cannot read column email as String: found NULL");
+
+    // The derive reads columns by name, so they must be selected
+    assert!(matches!(
+        db.query_as::<User>("SELECT id, name FROM users"),
+        Err(Error::ColumnNotFound(column)) if column == "email"
+    ));
+
+    let rows: Vec<Row> = db.query_as("SELECT name FROM users").unwrap();
+    assert_eq!(rows.len(), 2);
+}
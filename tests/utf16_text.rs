@@ -0,0 +1,116 @@
+//! Tests for databases whose text is stored as UTF-16
+
+use sqlite_wasm_reader::{Database, PageReader, SelectQuery, TextEncoding, Value};
+
+/// Words whose UTF-16 byte order differs from their UTF-8 order
+fn word(i: usize) -> String {
+    const STEMS: [&str; 6] = ["\u{0101}", "\u{0200}", "\u{ff21}", "\u{1f600}", "café", "zoë"];
+    format!("{}{:04}", STEMS[i % STEMS.len()], i)
+}
+
+const ROWS: usize = 3000;
+
+/// Bounds whose range holds different words in each encoding
+const LOWER: &str = "\u{0200}";
+const UPPER: &str = "\u{ff21}";
+
+/// Build a database in `encoding` and return its bytes, together with the
+/// words of `dict` between [`LOWER`] and [`UPPER`] in SQLite's order
+fn build(encoding: &str) -> (Vec<u8>, Vec<String>) {
+    let path = format!("test_utf16_{}.sqlite", encoding.to_lowercase().replace('-', ""));
+    let _ = std::fs::remove_file(&path);
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    conn.execute_batch(&format!(
+        "PRAGMA encoding = '{}';
+         CREATE TABLE words (id INTEGER PRIMARY KEY, word TEXT, note TEXT);
+         CREATE INDEX idx_words_word ON words (word);
+         CREATE TABLE dict (word TEXT PRIMARY KEY, len INTEGER) WITHOUT ROWID;",
+        encoding
+    ))
+    .unwrap();
+    let tx = conn.unchecked_transaction().unwrap();
+    for i in 0..ROWS {
+        let w = word(i);
+        tx.execute(
+            "INSERT INTO words (id, word, note) VALUES (?1, ?2, ?3)",
+            rusqlite::params![i as i64, w, format!("note for {}", w)],
+        )
+        .unwrap();
+        tx.execute("INSERT INTO dict (word, len) VALUES (?1, ?2)", rusqlite::params![w, w.chars().count() as i64])
+            .unwrap();
+    }
+    tx.commit().unwrap();
+
+    let range = conn
+        .prepare("SELECT word FROM dict WHERE word >= ?1 AND word < ?2 ORDER BY word")
+        .unwrap()
+        .query_map([LOWER, UPPER], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<Vec<String>, _>>()
+        .unwrap();
+    drop(conn);
+    let bytes = std::fs::read(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    (bytes, range)
+}
+
+fn text(value: &Value) -> &str {
+    value.as_text().unwrap()
+}
+
+fn check(encoding: &str, expected: TextEncoding) {
+    let (bytes, range) = build(encoding);
+    let mut db = Database::from_bytes(bytes).unwrap();
+    assert_eq!(db.header().encoding(), expected);
+
+    // Schema SQL is stored in the database encoding too
+    assert_eq!(db.tables().unwrap().len(), 2);
+
+    let rows = db.execute_query(&SelectQuery::parse("SELECT id, word, note FROM words").unwrap()).unwrap();
+    assert_eq!(rows.len(), ROWS);
+    for row in &rows {
+        let i = row["id"].as_integer().unwrap() as usize;
+        assert_eq!(text(&row["word"]), word(i));
+        assert_eq!(text(&row["note"]), format!("note for {}", word(i)));
+    }
+
+    // Index seeks compare keys in the database encoding
+    for i in [0, 1, 2, 3, 4, 5, 1234, ROWS - 1] {
+        let sql = format!("SELECT id FROM words WHERE word = '{}'", word(i));
+        let rows = db.execute_query(&SelectQuery::parse(&sql).unwrap()).unwrap();
+        assert_eq!(rows.len(), 1, "{}: {}", encoding, sql);
+        assert_eq!(rows[0]["id"], Value::Integer(i as i64));
+
+        let sql = format!("SELECT len FROM dict WHERE word = '{}'", word(i));
+        let rows = db.execute_query(&SelectQuery::parse(&sql).unwrap()).unwrap();
+        assert_eq!(rows.len(), 1, "{}: {}", encoding, sql);
+    }
+
+    // Range seeks on a text primary key select and walk the keys in SQLite's order
+    let sql = format!("SELECT word FROM dict WHERE word >= '{}' AND word < '{}'", LOWER, UPPER);
+    let rows = db.execute_query(&SelectQuery::parse(&sql).unwrap()).unwrap();
+    let found: Vec<&str> = rows.iter().map(|row| text(&row["word"])).collect();
+    assert!(!range.is_empty());
+    assert_eq!(found, range, "{}", encoding);
+
+    // So do row filters and ORDER BY
+    let sql = format!("SELECT word FROM words WHERE word >= '{}' AND word < '{}' ORDER BY word", LOWER, UPPER);
+    let rows = db.execute_query(&SelectQuery::parse(&sql).unwrap()).unwrap();
+    let found: Vec<&str> = rows.iter().map(|row| text(&row["word"])).collect();
+    assert_eq!(found, range, "{}", encoding);
+}
+
+#[test]
+fn test_utf16le() {
+    check("UTF-16le", TextEncoding::Utf16le);
+}
+
+#[test]
+fn test_utf16be() {
+    check("UTF-16be", TextEncoding::Utf16be);
+}
+
+#[test]
+fn test_utf8_is_unchanged() {
+    check("UTF-8", TextEncoding::Utf8);
+}
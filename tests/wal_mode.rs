@@ -0,0 +1,234 @@
+//! Tests for reading databases in WAL journal mode
+
+use sqlite_wasm_reader::{Database, SelectQuery, Value};
+
+/// Remove a database and its WAL side files
+fn remove_db(path: &str) {
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+    }
+}
+
+/// Open a WAL-mode connection that never checkpoints on its own
+fn open_wal_connection(path: &str) -> rusqlite::Connection {
+    remove_db(path);
+    let conn = rusqlite::Connection::open(path).unwrap();
+    conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+    conn.pragma_update(None, "wal_autocheckpoint", 0).unwrap();
+    conn
+}
+
+/// Copy a database and its -wal file (but not the -shm file) to `dest`
+fn copy_db(src: &str, dest: &str) {
+    remove_db(dest);
+    std::fs::copy(src, dest).unwrap();
+    std::fs::copy(format!("{}-wal", src), format!("{}-wal", dest)).unwrap();
+}
+
+fn wal_len(path: &str) -> u64 {
+    std::fs::metadata(format!("{}-wal", path)).unwrap().len()
+}
+
+fn names(db: &mut Database, sql: &str) -> Vec<String> {
+    let rows = db.execute_query(&SelectQuery::parse(sql).unwrap()).unwrap();
+    rows.into_iter()
+        .map(|row| match &row["name"] {
+            Value::Text(name) => name.clone(),
+            other => panic!("unexpected name value {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn test_reads_uncheckpointed_wal() {
+    let path = "test_wal_basic.sqlite";
+    let conn = open_wal_connection(path);
+    conn.execute_batch("CREATE TABLE base (id INTEGER PRIMARY KEY, name TEXT); INSERT INTO base VALUES (1, 'checkpointed');")
+        .unwrap();
+    conn.pragma_update(None, "wal_checkpoint", "TRUNCATE").unwrap();
+
+    // Everything below only exists in the -wal file
+    conn.execute_batch(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, qty INTEGER);
+         CREATE INDEX idx_items_name ON items (name);
+         UPDATE base SET name = 'updated' WHERE id = 1;",
+    )
+    .unwrap();
+    for i in 0..2000 {
+        conn.execute(
+            "INSERT INTO items (id, name, qty) VALUES (?1, ?2, ?3)",
+            rusqlite::params![i, format!("item-{:04}", i), i % 17],
+        )
+        .unwrap();
+    }
+    conn.execute("DELETE FROM items WHERE qty = 0", []).unwrap();
+    assert!(wal_len(path) > 0);
+
+    let expected: i64 = conn.query_row("SELECT count(*) FROM items", [], |r| r.get(0)).unwrap();
+
+    let mut db = Database::open(path).unwrap();
+    let mut tables = db.tables().unwrap();
+    tables.sort();
+    assert_eq!(tables, vec!["base", "items"]);
+    assert_eq!(db.count_table_rows("items").unwrap(), expected as usize);
+    assert_eq!(names(&mut db, "SELECT name FROM base"), vec!["updated"]);
+    assert_eq!(names(&mut db, "SELECT name FROM items WHERE name = 'item-1234'"), vec!["item-1234"]);
+    assert!(names(&mut db, "SELECT name FROM items WHERE name = 'item-0017'").is_empty());
+
+    // The main file alone still holds the checkpointed state
+    let stale = "test_wal_basic_stale.sqlite";
+    remove_db(stale);
+    std::fs::copy(path, stale).unwrap();
+    let mut db = Database::open(stale).unwrap();
+    assert_eq!(db.tables().unwrap(), vec!["base"]);
+    assert_eq!(names(&mut db, "SELECT name FROM base"), vec!["checkpointed"]);
+
+    drop(conn);
+    remove_db(path);
+    remove_db(stale);
+}
+
+#[test]
+fn test_ignores_torn_and_corrupt_transactions() {
+    let path = "test_wal_torn.sqlite";
+    let conn = open_wal_connection(path);
+    conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT); INSERT INTO t VALUES (1, 'first');")
+        .unwrap();
+    let first_commit_len = wal_len(path);
+    conn.execute("INSERT INTO t VALUES (2, 'second')", []).unwrap();
+    assert!(wal_len(path) > first_commit_len);
+
+    // A partially written second transaction is ignored
+    let torn = "test_wal_torn_copy.sqlite";
+    copy_db(path, torn);
+    let wal = std::fs::OpenOptions::new().write(true).open(format!("{}-wal", torn)).unwrap();
+    wal.set_len(first_commit_len + 100).unwrap();
+    drop(wal);
+    let mut db = Database::open(torn).unwrap();
+    assert_eq!(names(&mut db, "SELECT name FROM t"), vec!["first"]);
+
+    // So is a second transaction whose checksum no longer matches
+    let corrupt = "test_wal_corrupt_copy.sqlite";
+    copy_db(path, corrupt);
+    let wal_path = format!("{}-wal", corrupt);
+    let mut bytes = std::fs::read(&wal_path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    std::fs::write(&wal_path, bytes).unwrap();
+    let mut db = Database::open(corrupt).unwrap();
+    assert_eq!(names(&mut db, "SELECT name FROM t"), vec!["first"]);
+
+    // The intact copy sees both
+    let mut db = Database::open(path).unwrap();
+    assert_eq!(names(&mut db, "SELECT name FROM t ORDER BY id"), vec!["first", "second"]);
+
+    drop(conn);
+    remove_db(path);
+    remove_db(torn);
+    remove_db(corrupt);
+}
+
+#[test]
+fn test_wal_mode_without_wal_file() {
+    let path = "test_wal_closed.sqlite";
+    let conn = open_wal_connection(path);
+    conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT); INSERT INTO t VALUES (1, 'kept');")
+        .unwrap();
+    // Closing the last connection checkpoints and removes the -wal file
+    drop(conn);
+    assert!(std::fs::metadata(format!("{}-wal", path)).is_err());
+
+    let mut db = Database::open(path).unwrap();
+    assert_eq!(names(&mut db, "SELECT name FROM t"), vec!["kept"]);
+    remove_db(path);
+}
+
+#[test]
+fn test_time_travel_to_earlier_commits() {
+    let path = "test_wal_time_travel.sqlite";
+    let conn = open_wal_connection(path);
+    let page_size: u64 = conn.query_row("PRAGMA page_size", [], |r| r.get(0)).unwrap();
+    let frames_written = || (wal_len(path) - 32) / (24 + page_size);
+
+    // (frame index of the commit, tables, names in t)
+    let mut snapshots = Vec::new();
+    let mut record = |conn: &rusqlite::Connection| {
+        let tables: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        let names: Vec<String> = conn
+            .prepare("SELECT name FROM t ORDER BY id")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        snapshots.push((frames_written() as usize - 1, tables, names));
+    };
+
+    conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+    record(&conn);
+    conn.execute_batch("INSERT INTO t VALUES (1, 'one'), (2, 'two'), (3, 'three')").unwrap();
+    record(&conn);
+    conn.execute_batch("BEGIN; UPDATE t SET name = 'TWO' WHERE id = 2; DELETE FROM t WHERE id = 1; COMMIT;")
+        .unwrap();
+    record(&conn);
+    conn.execute_batch(
+        "BEGIN;
+         CREATE TABLE later (x);
+         WITH RECURSIVE n(i) AS (SELECT 11 UNION ALL SELECT i + 1 FROM n WHERE i < 510)
+         INSERT INTO t SELECT i, 'bulk' FROM n;
+         COMMIT;",
+    )
+    .unwrap();
+    record(&conn);
+
+    let latest = Database::open(path).unwrap();
+    let commits = latest.wal_commits().to_vec();
+    let last = *commits.last().unwrap();
+    assert_eq!(last.frame_index, snapshots.last().unwrap().0);
+    drop(latest);
+
+    for (frame_index, tables, expected_names) in &snapshots {
+        let commit = commits
+            .iter()
+            .find(|c| c.frame_index == *frame_index)
+            .expect("every transaction should end in a listed commit");
+        assert!(commit.database_size > 0);
+
+        let mut db = Database::open_at_wal_commit(path, *frame_index).unwrap();
+        let mut actual_tables = db.tables().unwrap();
+        actual_tables.sort();
+        assert_eq!(&actual_tables, tables, "tables at frame {}", frame_index);
+        assert_eq!(db.count_table_rows("t").unwrap(), expected_names.len());
+        assert_eq!(&names(&mut db, "SELECT name FROM t ORDER BY id"), expected_names);
+    }
+
+    assert_eq!(commits.len(), snapshots.len());
+
+    // Only commit frames can be used as snapshots
+    let non_commit = (0..last.frame_index).find(|i| commits.iter().all(|c| c.frame_index != *i)).unwrap();
+    assert!(Database::open_at_wal_commit(path, non_commit).is_err());
+    assert!(Database::open_at_wal_commit(path, last.frame_index + 1).is_err());
+
+    drop(conn);
+    remove_db(path);
+}
+
+#[test]
+fn test_time_travel_requires_wal_mode() {
+    let path = "test_wal_rollback_journal.sqlite";
+    remove_db(path);
+    let conn = rusqlite::Connection::open(path).unwrap();
+    conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY)").unwrap();
+    drop(conn);
+
+    let db = Database::open(path).unwrap();
+    assert!(db.wal_commits().is_empty());
+    assert!(Database::open_at_wal_commit(path, 0).is_err());
+    remove_db(path);
+}
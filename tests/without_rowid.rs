@@ -0,0 +1,164 @@
+//! Tests for reading WITHOUT ROWID tables
+
+mod common;
+
+use common::Fixture;
+use sqlite_wasm_reader::{Row, SelectQuery, Value};
+use std::sync::Arc;
+
+static DB: Fixture = Fixture::new(|conn| {
+    conn.execute_batch(
+        "CREATE TABLE kv (k TEXT PRIMARY KEY, v INTEGER, note TEXT) WITHOUT ROWID;
+         CREATE TABLE events (seq INTEGER, payload TEXT, score REAL, source TEXT, PRIMARY KEY (source, seq)) WITHOUT ROWID;
+         CREATE TABLE plain (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE wd (k INT PRIMARY KEY DESC, v) WITHOUT ROWID;
+         CREATE TABLE mixed (a INT, b TEXT, v, PRIMARY KEY (b DESC, a)) WITHOUT ROWID;
+         INSERT INTO kv VALUES ('alpha', 1, 'first'), ('beta', 2, NULL), ('gamma', 3, 'third');
+         INSERT INTO plain VALUES (1, 'one');",
+    )
+    .unwrap();
+
+    for k in 0..3000i64 {
+        conn.execute("INSERT INTO wd VALUES (?1, ?2)", rusqlite::params![k, format!("v{}", k)]).unwrap();
+        conn.execute(
+            "INSERT INTO mixed VALUES (?1, ?2, ?3)",
+            rusqlite::params![k % 100, format!("b{:02}", k / 100), k],
+        )
+        .unwrap();
+    }
+
+    let sources = ["api", "batch", "cron", "web"];
+    let mut stmt = conn
+        .prepare("INSERT INTO events (seq, payload, score, source) VALUES (?1, ?2, ?3, ?4)")
+        .unwrap();
+    for seq in 0..1500i64 {
+        for source in sources {
+            stmt.execute(rusqlite::params![
+                seq,
+                format!("{}-{}-{}", source, seq, "x".repeat((seq % 40) as usize)),
+                seq as f64 / 4.0 + 0.1,
+                source
+            ])
+            .unwrap();
+        }
+    }
+});
+
+/// Run a query through rusqlite and convert the result to reader rows
+fn expected_rows(sql: &str) -> Vec<Row> {
+    let (names, rows) = DB.expected_result(sql);
+    let names: Arc<[String]> = names.into();
+    rows.into_iter().map(|values| Row::new(names.clone(), values)).collect()
+}
+
+fn sort_rows(rows: &mut [Row], key: &[&str]) {
+    rows.sort_by(|a, b| {
+        let ka: Vec<&Value> = key.iter().map(|k| &a[*k]).collect();
+        let kb: Vec<&Value> = key.iter().map(|k| &b[*k]).collect();
+        ka.cmp(&kb)
+    });
+}
+
+fn assert_same(sql: &str, key: &[&str]) {
+    let mut db = DB.open();
+    let mut actual = db.execute_query(&SelectQuery::parse(sql).unwrap()).unwrap().into_rows();
+    let mut expected = expected_rows(sql);
+    sort_rows(&mut actual, key);
+    sort_rows(&mut expected, key);
+    assert_eq!(actual.len(), expected.len(), "row count mismatch for {}", sql);
+    assert_eq!(actual, expected, "rows differ for {}", sql);
+}
+
+#[test]
+fn test_without_rowid_tables_listed() {
+    let mut db = DB.open();
+    let mut tables = db.tables().unwrap();
+    tables.sort();
+    assert_eq!(tables, vec!["events", "kv", "mixed", "plain", "wd"]);
+    assert_eq!(
+        db.get_table_columns("events").unwrap(),
+        vec!["seq", "payload", "score", "source"]
+    );
+}
+
+#[test]
+fn test_without_rowid_full_scan() {
+    assert_same("SELECT * FROM kv", &["k"]);
+    assert_same("SELECT * FROM events", &["source", "seq"]);
+    assert_same("SELECT payload, seq FROM events", &["payload"]);
+}
+
+#[test]
+fn test_without_rowid_count() {
+    let mut db = DB.open();
+    assert_eq!(db.count_table_rows("kv").unwrap(), 3);
+    assert_eq!(db.count_table_rows("events").unwrap(), 6000);
+    assert_eq!(db.count_table_rows("plain").unwrap(), 1);
+}
+
+#[test]
+fn test_without_rowid_primary_key_equality() {
+    assert_same("SELECT * FROM kv WHERE k = 'beta'", &["k"]);
+    assert_same("SELECT * FROM kv WHERE k = 'missing'", &["k"]);
+    assert_same("SELECT * FROM events WHERE source = 'cron' AND seq = 777", &["source", "seq"]);
+    assert_same("SELECT * FROM events WHERE source = 'batch'", &["source", "seq"]);
+    assert_same("SELECT * FROM events WHERE source = 'api' OR source = 'web'", &["source", "seq"]);
+    assert_same(
+        "SELECT * FROM events WHERE (source = 'web' AND seq = 3) OR source = 'web'",
+        &["source", "seq"],
+    );
+}
+
+#[test]
+fn test_without_rowid_primary_key_ranges() {
+    assert_same(
+        "SELECT * FROM events WHERE source = 'cron' AND seq BETWEEN 100 AND 250",
+        &["source", "seq"],
+    );
+    assert_same(
+        "SELECT * FROM events WHERE source = 'api' AND seq > 1400 AND seq <= 1450",
+        &["source", "seq"],
+    );
+    assert_same("SELECT * FROM events WHERE source >= 'c' AND source < 'd'", &["source", "seq"]);
+    assert_same("SELECT * FROM events WHERE source > 'batch'", &["source", "seq"]);
+    assert_same("SELECT * FROM kv WHERE k < 'c'", &["k"]);
+}
+
+#[test]
+fn test_without_rowid_non_key_filters() {
+    assert_same("SELECT * FROM events WHERE score > 370.3", &["source", "seq"]);
+    assert_same("SELECT * FROM kv WHERE note IS NULL", &["k"]);
+    assert_same("SELECT * FROM events WHERE source = 'web' AND score < 2.2", &["source", "seq"]);
+}
+
+#[test]
+fn test_without_rowid_order_and_limit() {
+    let mut db = DB.open();
+    let rows = db
+        .execute_query(&SelectQuery::parse("SELECT k, v FROM kv ORDER BY v DESC LIMIT 2").unwrap())
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["k"], Value::Text("gamma".to_string()));
+    assert_eq!(rows[1]["k"], Value::Text("beta".to_string()));
+
+    let rows = db
+        .execute_query(&SelectQuery::parse("SELECT * FROM events LIMIT 5").unwrap())
+        .unwrap();
+    assert_eq!(rows.len(), 5);
+}
+
+#[test]
+fn test_without_rowid_descending_primary_key() {
+    assert_same("SELECT * FROM wd WHERE k = 5", &["k"]);
+    assert_same("SELECT * FROM wd WHERE k > 2995", &["k"]);
+    assert_same("SELECT * FROM wd WHERE k < 3", &["k"]);
+    assert_same("SELECT * FROM wd WHERE k BETWEEN 1200 AND 1210", &["k"]);
+    assert_same("SELECT * FROM wd WHERE k = 7 OR k >= 2998", &["k"]);
+
+    // The ascending column after a DESC one is filtered, not sought
+    assert_same("SELECT * FROM mixed", &["b", "a"]);
+    assert_same("SELECT * FROM mixed WHERE b = 'b07'", &["b", "a"]);
+    assert_same("SELECT * FROM mixed WHERE b = 'b07' AND a = 42", &["b", "a"]);
+    assert_same("SELECT * FROM mixed WHERE b > 'b27' AND a < 3", &["b", "a"]);
+    assert_same("SELECT * FROM mixed WHERE a = 99", &["b", "a"]);
+}